@@ -1,6 +1,6 @@
 use rs_can::{
     CanDirection, CanError, CanFdFlags, CanFrame, CanId, CanKind, CanResult, 
-    FrameFormat, Timestamp, MAX_FD_FRAME_SIZE, MAX_FRAME_SIZE,
+    FrameFormat, GenericFrame, Timestamp, MAX_FD_FRAME_SIZE, MAX_FRAME_SIZE,
 };
 use std::fmt::{Display, Formatter};
 
@@ -161,3 +161,17 @@ impl Display for NiCanFrame {
         <dyn CanFrame<Channel = String> as Display>::fmt(self, f)
     }
 }
+
+impl From<NiCanFrame> for GenericFrame<String> {
+    fn from(frame: NiCanFrame) -> Self {
+        GenericFrame::from_frame(&frame)
+    }
+}
+
+impl TryFrom<GenericFrame<String>> for NiCanFrame {
+    type Error = CanError;
+
+    fn try_from(frame: GenericFrame<String>) -> Result<Self, Self::Error> {
+        frame.into_frame()
+    }
+}
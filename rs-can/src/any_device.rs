@@ -0,0 +1,474 @@
+//! The object-safe device and the backend-neutral frame for runtime backend selection.
+
+use crate::{
+    device::{Device, DeviceBuilder},
+    error::Error,
+    frame::{
+        generic::GenericFrame,
+        identifier::{CanFdFlags, Id},
+        Direction, Frame, FrameFormat, Kind, Timestamp, XlParams,
+    },
+    utils, CanResult, MAX_FD_FRAME_SIZE, MAX_FRAME_SIZE, MAX_XL_FRAME_SIZE,
+};
+use std::{
+    any::Any,
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+/// An owned frame with the channel name, converted from and into the frame of any device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnyFrame {
+    id: Id,
+    channel: String,
+    kind: Kind,
+    format: FrameFormat,
+    length: usize,
+    data: Vec<u8>,
+    direction: Direction,
+    timestamp: Option<Timestamp>,
+    bitrate_switch: bool,
+    error_state_indicator: bool,
+    xl_params: Option<XlParams>,
+}
+
+impl AnyFrame {
+    fn new(id: Id, kind: Kind, format: FrameFormat, data: &[u8]) -> Self {
+        Self {
+            id,
+            channel: Default::default(),
+            kind,
+            format,
+            length: data.len(),
+            data: data.to_vec(),
+            direction: Default::default(),
+            timestamp: None,
+            bitrate_switch: false,
+            error_state_indicator: false,
+            xl_params: None,
+        }
+    }
+
+    /// Copy the frame of any device.
+    pub fn from_frame<F: Frame>(frame: &F) -> Self {
+        Self {
+            id: frame.id(),
+            channel: frame.channel().to_string(),
+            kind: frame.kind(),
+            format: frame.format(),
+            length: frame.len(),
+            data: match frame.format() {
+                FrameFormat::Remote => Default::default(),
+                _ => frame.data().to_vec(),
+            },
+            direction: frame.direction(),
+            timestamp: frame.timestamp(),
+            bitrate_switch: frame.is_bitrate_switch(),
+            error_state_indicator: frame.is_esi(),
+            xl_params: frame.xl_params(),
+        }
+    }
+
+    /// Convert into the frame of any device, the channel is parsed from the channel name.
+    pub fn to_frame<F: Frame>(&self) -> CanResult<F>
+    where
+        F::Channel: FromStr,
+    {
+        let mut frame = match (self.format, self.kind) {
+            (FrameFormat::Error, _) => F::new_error(self.id, &self.data)?,
+            (FrameFormat::Remote, _) => F::new_remote(
+                self.id,
+                u8::try_from(self.length).map_err(|_| Error::InvalidDLC(self.length))?,
+            )?,
+            (_, Kind::Classical) => F::new_can(self.id, &self.data)?,
+            (_, Kind::FD) => {
+                let mut flags = CanFdFlags::empty();
+                flags.set(CanFdFlags::BRS, self.bitrate_switch);
+                flags.set(CanFdFlags::ESI, self.error_state_indicator);
+                F::new_can_fd(self.id, &self.data, flags)?
+            }
+            (_, Kind::XL) => F::new_can_xl(
+                self.id.as_raw() as u16,
+                &self.data,
+                self.xl_params.unwrap_or_default(),
+            )?,
+        };
+        frame
+            .set_channel(utils::parse_channel(&self.channel)?)
+            .set_direction(self.direction)
+            .set_timestamp(self.timestamp);
+
+        Ok(frame)
+    }
+}
+
+impl Frame for AnyFrame {
+    type Channel = String;
+
+    fn new_can(id: Id, data: &[u8]) -> CanResult<Self> {
+        if data.len() > MAX_FRAME_SIZE {
+            return Err(Error::InvalidDLC(data.len()));
+        }
+
+        Ok(Self::new(id, Kind::Classical, FrameFormat::Data, data))
+    }
+
+    fn new_remote(id: Id, dlc: u8) -> CanResult<Self> {
+        let length = dlc as usize;
+        if length > MAX_FRAME_SIZE {
+            return Err(Error::InvalidDLC(length));
+        }
+
+        let mut frame = Self::new(id, Kind::Classical, FrameFormat::Remote, &[]);
+        frame.length = length;
+        Ok(frame)
+    }
+
+    fn new_can_fd(id: Id, data: &[u8], flags: CanFdFlags) -> CanResult<Self> {
+        if data.len() > MAX_FD_FRAME_SIZE {
+            return Err(Error::InvalidDLC(data.len()));
+        }
+
+        let mut frame = Self::new(id, Kind::FD, FrameFormat::Data, data);
+        frame.bitrate_switch = flags.contains(CanFdFlags::BRS);
+        frame.error_state_indicator = flags.contains(CanFdFlags::ESI);
+        Ok(frame)
+    }
+
+    fn new_can_xl(priority: u16, data: &[u8], params: XlParams) -> CanResult<Self> {
+        if !(1..=MAX_XL_FRAME_SIZE).contains(&data.len()) {
+            return Err(Error::InvalidDLC(data.len()));
+        }
+        let id = Id::from_bits(priority as u32, Some(false))?;
+
+        let mut frame = Self::new(id, Kind::XL, FrameFormat::Data, data);
+        frame.xl_params = Some(params);
+        Ok(frame)
+    }
+
+    fn new_error(id: Id, data: &[u8]) -> CanResult<Self> {
+        if data.len() > MAX_FRAME_SIZE {
+            return Err(Error::InvalidDLC(data.len()));
+        }
+
+        Ok(Self::new(id, Kind::Classical, FrameFormat::Error, data))
+    }
+
+    #[inline]
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    #[inline]
+    fn channel(&self) -> Self::Channel {
+        self.channel.clone()
+    }
+
+    #[inline]
+    fn set_channel(&mut self, v: Self::Channel) -> &mut Self {
+        self.channel = v;
+        self
+    }
+
+    #[inline]
+    fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    #[inline]
+    fn format(&self) -> FrameFormat {
+        self.format
+    }
+
+    #[inline]
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    #[inline]
+    fn xl_params(&self) -> Option<XlParams> {
+        self.xl_params
+    }
+
+    #[inline]
+    fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    #[inline]
+    fn set_direction(&mut self, d: Direction) -> &mut Self {
+        self.direction = d;
+        self
+    }
+
+    #[inline]
+    fn timestamp(&self) -> Option<Timestamp> {
+        self.timestamp
+    }
+
+    #[inline]
+    fn set_timestamp(&mut self, ts: Option<Timestamp>) -> &mut Self {
+        self.timestamp = ts;
+        self
+    }
+
+    #[inline]
+    fn is_bitrate_switch(&self) -> bool {
+        self.bitrate_switch
+    }
+
+    #[inline]
+    fn set_bitrate_switch(&mut self, v: bool) -> &mut Self {
+        self.bitrate_switch = v;
+        self
+    }
+
+    #[inline]
+    fn is_esi(&self) -> bool {
+        self.error_state_indicator
+    }
+
+    #[inline]
+    fn set_esi(&mut self, v: bool) -> &mut Self {
+        self.error_state_indicator = v;
+        self
+    }
+}
+
+impl Display for AnyFrame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        <dyn Frame<Channel = String> as Display>::fmt(self, f)
+    }
+}
+
+impl<C> From<GenericFrame<C>> for AnyFrame
+where
+    C: Display + Clone + Default + Send + Sync,
+{
+    fn from(frame: GenericFrame<C>) -> Self {
+        AnyFrame::from_frame(&frame)
+    }
+}
+
+impl<C> TryFrom<AnyFrame> for GenericFrame<C>
+where
+    C: Display + Clone + Default + Send + Sync + FromStr,
+{
+    type Error = Error;
+
+    fn try_from(frame: AnyFrame) -> Result<Self, Self::Error> {
+        frame.to_frame()
+    }
+}
+
+/// The object-safe version of [`Device`], the channels are named by their `Display`.
+///
+/// It's implemented for all devices whose channel can be parsed from its name.
+#[async_trait::async_trait]
+pub trait DynDevice: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// get all channels that has opened
+    fn opened_channels(&self) -> Vec<String>;
+    /// Transmit a frame, the frame is converted into the frame of device.
+    async fn transmit(&self, msg: AnyFrame, timeout: Option<u32>) -> CanResult<()>;
+    /// Receive frames of the channel.
+    async fn receive(&self, channel: &str, timeout: Option<u32>) -> CanResult<Vec<AnyFrame>>;
+    /// Close the device.
+    fn shutdown(&mut self);
+}
+
+#[async_trait::async_trait]
+impl<D> DynDevice for D
+where
+    D: Device + 'static,
+    D::Channel: FromStr + Send,
+{
+    #[inline]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    #[inline]
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn opened_channels(&self) -> Vec<String> {
+        Device::opened_channels(self)
+            .iter()
+            .map(|v| v.to_string())
+            .collect()
+    }
+
+    async fn transmit(&self, msg: AnyFrame, timeout: Option<u32>) -> CanResult<()> {
+        let msg = msg.to_frame::<D::Frame>()?;
+        Device::transmit(self, msg, timeout).await
+    }
+
+    async fn receive(&self, channel: &str, timeout: Option<u32>) -> CanResult<Vec<AnyFrame>> {
+        let channel = channel
+            .parse::<D::Channel>()
+            .map_err(|_| Error::channel_not_opened(channel))?;
+        let frames = Device::receive(self, channel, timeout).await?;
+
+        Ok(frames.iter().map(AnyFrame::from_frame).collect())
+    }
+
+    #[inline]
+    fn shutdown(&mut self) {
+        Device::shutdown(self)
+    }
+}
+
+/// A device wrapper which erases the backend, the backend can be selected at runtime.
+///
+/// [`Device::new`] is not supported, the device is created by [`AnyDevice::new`]
+/// or [`AnyDevice::build`].
+///
+/// # Example
+/// ```ignore
+/// let mut builder = DeviceBuilder::<String>::new();
+/// builder.add_config("0".into(), ChannelConfig::new(500_000));
+/// let device = match backend {
+///     "socketcan" => AnyDevice::build::<SocketCan>(builder)?,
+///     "zlgcan" => AnyDevice::build::<ZDriver>(builder)?,
+///     _ => return Err(CanError::NotSupportedError),
+/// };
+/// device.transmit(frame, None).await?;
+/// ```
+pub struct AnyDevice {
+    inner: Box<dyn DynDevice>,
+}
+
+impl AnyDevice {
+    pub fn new<D: DynDevice + 'static>(device: D) -> Self {
+        Self {
+            inner: Box::new(device),
+        }
+    }
+
+    /// Build the device with the channel names, which are parsed into the channels of device.
+    pub fn build<D>(builder: DeviceBuilder<String>) -> CanResult<Self>
+    where
+        D: Device + 'static,
+        D::Channel: FromStr + Send + Default,
+    {
+        let device = builder.parse_channels::<D::Channel>()?.build::<D>()?;
+        Ok(Self::new(device))
+    }
+
+    #[inline]
+    pub fn downcast_ref<D: 'static>(&self) -> Option<&D> {
+        self.inner.as_any().downcast_ref()
+    }
+
+    #[inline]
+    pub fn downcast_mut<D: 'static>(&mut self) -> Option<&mut D> {
+        self.inner.as_any_mut().downcast_mut()
+    }
+}
+
+#[async_trait::async_trait]
+impl Device for AnyDevice {
+    type Channel = String;
+    type Frame = AnyFrame;
+
+    fn new(_: DeviceBuilder<Self::Channel>) -> CanResult<Self> {
+        Err(Error::NotSupportedError)
+    }
+
+    #[inline]
+    fn opened_channels(&self) -> Vec<Self::Channel> {
+        self.inner.opened_channels()
+    }
+
+    #[inline]
+    async fn transmit(&self, msg: Self::Frame, timeout: Option<u32>) -> CanResult<()> {
+        self.inner.transmit(msg, timeout).await
+    }
+
+    #[inline]
+    async fn receive(
+        &self,
+        channel: Self::Channel,
+        timeout: Option<u32>,
+    ) -> CanResult<Vec<Self::Frame>> {
+        self.inner.receive(&channel, timeout).await
+    }
+
+    #[inline]
+    fn shutdown(&mut self) {
+        self.inner.shutdown()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        device::ChannelConfig,
+        frame::identifier::{ExtendedId, StandardId},
+        virtual_bus::{tests::TestFrame, VirtualBus},
+    };
+
+    #[test]
+    fn frames_round_trip() {
+        let id = Id::Extended(ExtendedId::new(0x18DA_F110).unwrap());
+        let mut frames = vec![
+            TestFrame::new_can(Id::Standard(StandardId::new(0x123).unwrap()), &[1, 2]).unwrap(),
+            TestFrame::new_remote(id, 4).unwrap(),
+            TestFrame::new_can_fd(id, &[0x55; 12], CanFdFlags::BRS | CanFdFlags::ESI).unwrap(),
+            TestFrame::new_can_xl(0x7FF, &[0xA5; 100], XlParams::default()).unwrap(),
+            TestFrame::new_error(Id::Standard(StandardId::new(0x04).unwrap()), &[0, 4]).unwrap(),
+        ];
+        frames.iter_mut().for_each(|f| {
+            f.set_channel("can0".into())
+                .set_direction(Direction::Receive);
+        });
+
+        for frame in frames {
+            let any = AnyFrame::from_frame(&frame);
+            assert_eq!(any.channel(), "can0");
+            let other = any.to_frame::<TestFrame>().unwrap();
+            assert_eq!(AnyFrame::from_frame(&other), any);
+            let generic = GenericFrame::<String>::try_from(any.clone()).unwrap();
+            assert_eq!(AnyFrame::from(generic), any);
+        }
+    }
+
+    #[tokio::test]
+    async fn devices_are_selected_at_runtime() {
+        let build = || {
+            let mut builder = DeviceBuilder::<String>::new();
+            builder.add_config("any0".into(), ChannelConfig::new(500_000));
+            AnyDevice::build::<VirtualBus<TestFrame>>(builder).unwrap()
+        };
+        let devices: Vec<Box<dyn DynDevice>> = vec![Box::new(build()), Box::new(build())];
+        assert_eq!(devices[0].opened_channels(), ["any0"]);
+
+        let mut frame =
+            AnyFrame::new_can(Id::Standard(StandardId::new(0x7DF).unwrap()), &[2, 1, 0]).unwrap();
+        frame.set_channel("any0".into());
+        devices[0].transmit(frame, None).await.unwrap();
+
+        let frames = devices[1].receive("any0", Some(100)).await.unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].data(), &[2, 1, 0]);
+        assert_eq!(frames[0].direction(), Direction::Receive);
+        assert!(devices[1].receive("any1", Some(10)).await.is_err());
+    }
+
+    #[test]
+    fn device_is_downcast() {
+        let mut device = AnyDevice::new(VirtualBus::<TestFrame>::new());
+        assert!(device.downcast_ref::<VirtualBus<TestFrame>>().is_some());
+        assert!(device.downcast_mut::<VirtualBus<TestFrame>>().is_some());
+        assert!(device.downcast_ref::<AnyDevice>().is_none());
+    }
+}
@@ -0,0 +1,457 @@
+use super::{
+    xml::Element, Arxml, Cluster, ClusterFrame, ContainedPdu, ContainerHeader, E2eProtection, Pdu,
+    PduKind, PduMapping, SecOcProps,
+};
+use crate::{
+    dbc::{ByteOrder, Signal, ValueType},
+    error::Error,
+    frame::identifier::{ExtendedId, Id, StandardId},
+    CanResult,
+};
+use std::{collections::HashMap, str::FromStr};
+
+/// The max depth of nested PDUs.
+const MAX_DEPTH: usize = 8;
+
+/// The linear conversion and texts of compu-method.
+#[derive(Debug)]
+struct CompuMethod {
+    factor: f64,
+    offset: f64,
+    limits: Option<(f64, f64)>,
+    texts: Vec<(i64, String)>,
+    unit: Option<String>,
+}
+
+impl Default for CompuMethod {
+    fn default() -> Self {
+        Self {
+            factor: 1.0,
+            offset: 0.0,
+            limits: None,
+            texts: Default::default(),
+            unit: None,
+        }
+    }
+}
+
+#[inline]
+fn parse<T: FromStr>(element: &Element, names: &[&str]) -> Option<T> {
+    let text = element.text_of(names)?;
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?.to_string().parse().ok(),
+        None => text.parse().ok(),
+    }
+}
+
+#[inline]
+fn short_name(element: &Element) -> String {
+    element
+        .text_of(&["SHORT-NAME"])
+        .unwrap_or_default()
+        .to_owned()
+}
+
+pub(crate) struct Importer<'a> {
+    root: &'a Element,
+    /// The identifiable elements by the path of short names.
+    elements: HashMap<String, &'a Element>,
+    /// The E2E protections by the path of I-signal I-PDU.
+    protections: HashMap<String, E2eProtection>,
+}
+
+impl<'a> Importer<'a> {
+    pub(crate) fn new(root: &'a Element) -> Self {
+        let mut elements = HashMap::new();
+        Self::index(root, String::new(), &mut elements);
+        Self {
+            root,
+            elements,
+            protections: Default::default(),
+        }
+    }
+
+    fn index(element: &'a Element, path: String, elements: &mut HashMap<String, &'a Element>) {
+        for child in &element.children {
+            match child.text_of(&["SHORT-NAME"]) {
+                Some(name) => {
+                    let path = format!("{}/{}", path, name);
+                    elements.insert(path.clone(), child);
+                    Self::index(child, path, elements);
+                }
+                None => Self::index(child, path.clone(), elements),
+            }
+        }
+    }
+
+    /// The element referred by the child `name`, such as `FRAME-REF`.
+    fn resolve(&self, element: &Element, name: &str) -> Option<(&'a Element, String)> {
+        let path = element.text_of(&[name])?;
+        match self.elements.get(path) {
+            Some(e) => Some((*e, path.to_owned())),
+            None => {
+                rsutil::warn!("RUST-CAN - ARXML reference: `{}` is not found", path);
+                None
+            }
+        }
+    }
+
+    /// The referred element of first descendant `name`.
+    fn resolve_in(&self, element: &Element, name: &str) -> Option<&'a Element> {
+        let reference = element.find(name)?;
+        let path = reference.text.trim();
+        self.elements.get(path).copied()
+    }
+
+    fn error(element: &Element, msg: &str) -> Error {
+        Error::other_error(format!(
+            "invalid ARXML `{}` of `{}`: {}",
+            element.name,
+            short_name(element),
+            msg
+        ))
+    }
+
+    pub(crate) fn import(mut self) -> CanResult<Arxml> {
+        let mut protections = Vec::new();
+        self.root
+            .find_all("END-TO-END-PROTECTION", &mut protections);
+        for protection in protections {
+            self.protection(protection);
+        }
+
+        let mut clusters = Vec::new();
+        self.root.find_all("CAN-CLUSTER", &mut clusters);
+        let clusters = clusters
+            .into_iter()
+            .map(|c| self.cluster(c))
+            .collect::<CanResult<Vec<_>>>()?;
+
+        Ok(Arxml { clusters })
+    }
+
+    fn protection(&mut self, element: &Element) {
+        let Some(profile) = element.child("END-TO-END-PROFILE") else {
+            return;
+        };
+        let protection = E2eProtection {
+            name: short_name(element),
+            category: profile
+                .text_of(&["CATEGORY"])
+                .unwrap_or_default()
+                .to_owned(),
+            data_ids: profile
+                .child("DATA-IDS")
+                .map(|ids| {
+                    ids.children("DATA-ID")
+                        .filter_map(|v| v.text.trim().parse().ok())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            data_id_mode: profile.text_of(&["DATA-ID-MODE"]).map(|v| v.to_owned()),
+            data_length: parse(profile, &["DATA-LENGTH"]),
+            crc_offset: parse(profile, &["CRC-OFFSET"]),
+            counter_offset: parse(profile, &["COUNTER-OFFSET"]),
+            max_delta_counter: parse(profile, &["MAX-DELTA-COUNTER-INIT"])
+                .or_else(|| parse(profile, &["MAX-DELTA-COUNTER"])),
+        };
+
+        let pdus = element.child("END-TO-END-PROTECTION-I-SIGNAL-I-PDUS");
+        for pdu in pdus.iter().flat_map(|v| v.children.iter()) {
+            if let Some(path) = pdu.text_of(&["I-SIGNAL-I-PDU-REF"]) {
+                self.protections.insert(path.to_owned(), protection.clone());
+            }
+        }
+    }
+
+    fn cluster(&self, element: &Element) -> CanResult<Cluster> {
+        // the properties are in the conditional since AUTOSAR 4.
+        let conditional = element
+            .path(&["CAN-CLUSTER-VARIANTS", "CAN-CLUSTER-CONDITIONAL"])
+            .unwrap_or(element);
+        let mut cluster = Cluster {
+            name: short_name(element),
+            baudrate: parse(conditional, &["BAUDRATE"]),
+            fd_baudrate: parse(conditional, &["CAN-FD-BAUDRATE"]),
+            frames: vec![],
+        };
+
+        let channels = conditional.child("PHYSICAL-CHANNELS");
+        for channel in channels
+            .iter()
+            .flat_map(|v| v.children("CAN-PHYSICAL-CHANNEL"))
+        {
+            let triggerings = channel.child("FRAME-TRIGGERINGS");
+            for triggering in triggerings
+                .iter()
+                .flat_map(|v| v.children("CAN-FRAME-TRIGGERING"))
+            {
+                if let Some(frame) = self.frame(triggering)? {
+                    cluster.frames.push(frame);
+                }
+            }
+        }
+
+        Ok(cluster)
+    }
+
+    fn frame(&self, triggering: &Element) -> CanResult<Option<ClusterFrame>> {
+        let Some((frame, _)) = self.resolve(triggering, "FRAME-REF") else {
+            return Ok(None);
+        };
+        let raw = parse::<u32>(triggering, &["IDENTIFIER"])
+            .ok_or_else(|| Self::error(triggering, "invalid identifier"))?;
+        let id = match triggering.text_of(&["CAN-ADDRESSING-MODE"]) {
+            Some("EXTENDED") => Id::Extended(ExtendedId::new(raw)?),
+            _ => Id::Standard(StandardId::new(raw as u16)?),
+        };
+        let fd = [
+            triggering.text_of(&["CAN-FRAME-TX-BEHAVIOR"]),
+            triggering.text_of(&["CAN-FRAME-RX-BEHAVIOR"]),
+        ]
+        .contains(&Some("CAN-FD"));
+
+        let mut pdus = Vec::new();
+        let mappings = frame.child("PDU-TO-FRAME-MAPPINGS");
+        for mapping in mappings
+            .iter()
+            .flat_map(|v| v.children("PDU-TO-FRAME-MAPPING"))
+        {
+            let Some((pdu, path)) = self.resolve(mapping, "PDU-REF") else {
+                continue;
+            };
+            let start = parse::<usize>(mapping, &["START-POSITION"]).unwrap_or_default();
+            pdus.push(PduMapping {
+                offset: start / 8,
+                pdu: self.pdu(pdu, &path, 0)?,
+            });
+        }
+
+        Ok(Some(ClusterFrame {
+            name: short_name(frame),
+            id,
+            fd,
+            length: parse(frame, &["FRAME-LENGTH"]).unwrap_or_default(),
+            pdus,
+        }))
+    }
+
+    /// The PDU referred by the PDU triggering, such as the payload and contained PDUs.
+    fn triggered_pdu(&self, triggering: &Element, depth: usize) -> CanResult<Option<Pdu>> {
+        match self.resolve(triggering, "I-PDU-REF") {
+            Some((pdu, path)) => self.pdu(pdu, &path, depth).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn pdu(&self, element: &Element, path: &str, depth: usize) -> CanResult<Pdu> {
+        if depth > MAX_DEPTH {
+            return Err(Self::error(element, "too deep nested PDUs"));
+        }
+        let mut pdu = Pdu {
+            name: short_name(element),
+            length: parse(element, &["LENGTH"]).unwrap_or_default(),
+            e2e: self.protections.get(path).cloned(),
+            ..Default::default()
+        };
+
+        match element.name.as_str() {
+            "I-SIGNAL-I-PDU" => {
+                let mappings = element.child("I-SIGNAL-TO-PDU-MAPPINGS");
+                for mapping in mappings
+                    .iter()
+                    .flat_map(|v| v.children("I-SIGNAL-TO-I-PDU-MAPPING"))
+                {
+                    // the signal groups are described by their signals.
+                    if let Some((signal, _)) = self.resolve(mapping, "I-SIGNAL-REF") {
+                        pdu.signals.push(self.signal(signal, mapping)?);
+                    }
+                }
+            }
+            "CONTAINER-I-PDU" => {
+                let header = match element.text_of(&["HEADER-TYPE"]) {
+                    Some("SHORT-HEADER") => ContainerHeader::Short,
+                    Some("LONG-HEADER") => ContainerHeader::Long,
+                    _ => ContainerHeader::None,
+                };
+                let mut pdus = Vec::new();
+                let refs = element.child("CONTAINED-PDU-TRIGGERING-REFS");
+                for reference in refs.iter().flat_map(|v| v.children.iter()) {
+                    let Some(triggering) = self.elements.get(reference.text.trim()) else {
+                        continue;
+                    };
+                    let Some((contained, path)) = self.resolve(triggering, "I-PDU-REF") else {
+                        continue;
+                    };
+                    let props = contained.child("CONTAINED-I-PDU-PROPS");
+                    let header_id = props.and_then(|p| match header {
+                        ContainerHeader::Long => parse(p, &["HEADER-ID-LONG-HEADER"]),
+                        _ => parse(p, &["HEADER-ID-SHORT-HEADER"]),
+                    });
+                    pdus.push(ContainedPdu {
+                        header_id,
+                        offset: props.and_then(|p| parse(p, &["OFFSET"])),
+                        pdu: self.pdu(contained, &path, depth + 1)?,
+                    });
+                }
+                pdu.kind = PduKind::Container { header, pdus };
+            }
+            "SECURED-I-PDU" => {
+                let payload = match self.resolve(element, "PAYLOAD-REF") {
+                    Some((triggering, _)) => self.triggered_pdu(triggering, depth + 1)?,
+                    None => None,
+                }
+                .ok_or_else(|| Self::error(element, "payload is not found"))?;
+                pdu.kind = PduKind::Secured {
+                    payload: Box::new(payload),
+                    props: self.secoc(element),
+                };
+            }
+            name => pdu.kind = PduKind::Other(name.to_owned()),
+        }
+
+        Ok(pdu)
+    }
+
+    fn secoc(&self, element: &Element) -> SecOcProps {
+        let props = element.child("SECURE-COMMUNICATION-PROPS");
+        let authentication = props
+            .and_then(|p| self.resolve(p, "AUTHENTICATION-PROPS-REF"))
+            .or_else(|| self.resolve(element, "AUTHENTICATION-PROPS-REF"))
+            .map(|(e, _)| e);
+        let freshness = props
+            .and_then(|p| self.resolve(p, "FRESHNESS-PROPS-REF"))
+            .or_else(|| self.resolve(element, "FRESHNESS-PROPS-REF"))
+            .map(|(e, _)| e);
+        // the lengths are in the props before AUTOSAR 4.3.
+        let value = |names: &[&str], referred: Option<&Element>| {
+            props
+                .and_then(|p| parse(p, names))
+                .or_else(|| referred.and_then(|e| parse(e, names)))
+        };
+
+        SecOcProps {
+            data_id: value(&["DATA-ID"], None),
+            algorithm: authentication
+                .and_then(|e| e.text_of(&["AUTH-ALGORITHM"]))
+                .map(|v| v.to_owned()),
+            auth_tx_length: value(&["AUTH-INFO-TX-LENGTH"], authentication),
+            freshness_length: value(&["FRESHNESS-VALUE-LENGTH"], freshness),
+            freshness_tx_length: value(&["FRESHNESS-VALUE-TX-LENGTH"], freshness),
+        }
+    }
+
+    fn signal(&self, element: &Element, mapping: &Element) -> CanResult<Signal> {
+        let size =
+            parse(element, &["LENGTH"]).ok_or_else(|| Self::error(element, "invalid length"))?;
+        let byte_order = match mapping.text_of(&["PACKING-BYTE-ORDER"]) {
+            Some("MOST-SIGNIFICANT-BYTE-FIRST") => ByteOrder::BigEndian,
+            _ => ByteOrder::LittleEndian,
+        };
+
+        // the network representation overrides the physical props of system signal.
+        let representation = element.child("NETWORK-REPRESENTATION-PROPS");
+        let system = self.resolve(element, "SYSTEM-SIGNAL-REF").map(|(e, _)| e);
+        let physical = system.and_then(|e| e.child("PHYSICAL-PROPS"));
+        let props = [representation, physical];
+        let compu = props
+            .iter()
+            .flatten()
+            .find_map(|p| self.resolve_in(p, "COMPU-METHOD-REF"))
+            .map(|e| self.compu_method(e))
+            .unwrap_or_default();
+        let value_type = match props
+            .iter()
+            .flatten()
+            .find_map(|p| self.resolve_in(p, "BASE-TYPE-REF"))
+            .and_then(|e| e.find("BASE-TYPE-ENCODING"))
+            .map(|e| e.text.trim())
+        {
+            Some("2C") => ValueType::Signed,
+            Some("IEEE754") if size == 64 => ValueType::Float64,
+            Some("IEEE754") => ValueType::Float32,
+            _ => ValueType::Unsigned,
+        };
+        let unit = props
+            .iter()
+            .flatten()
+            .find_map(|p| self.resolve_in(p, "UNIT-REF"))
+            .map(|e| {
+                e.text_of(&["DISPLAY-NAME"])
+                    .map_or_else(|| short_name(e), |v| v.to_owned())
+            })
+            .or(compu.unit)
+            .unwrap_or_default();
+
+        let (min, max) = compu.limits.map_or((0.0, 0.0), |(lower, upper)| {
+            let (a, b) = (
+                lower * compu.factor + compu.offset,
+                upper * compu.factor + compu.offset,
+            );
+            (a.min(b), a.max(b))
+        });
+
+        Ok(Signal {
+            name: short_name(element),
+            start_bit: parse(mapping, &["START-POSITION"]).unwrap_or_default(),
+            size,
+            byte_order,
+            value_type,
+            factor: compu.factor,
+            offset: compu.offset,
+            min,
+            max,
+            unit,
+            values: compu.texts.into_iter().collect(),
+            comment: element.text_of(&["DESC", "L-2"]).map(|v| v.to_owned()),
+            ..Default::default()
+        })
+    }
+
+    fn compu_method(&self, element: &Element) -> CompuMethod {
+        let mut compu = CompuMethod {
+            unit: self.resolve(element, "UNIT-REF").map(|(e, _)| {
+                e.text_of(&["DISPLAY-NAME"])
+                    .map_or_else(|| short_name(e), |v| v.to_owned())
+            }),
+            ..Default::default()
+        };
+        let scales = element.path(&["COMPU-INTERNAL-TO-PHYS", "COMPU-SCALES"]);
+        let mut linear = false;
+        for scale in scales.iter().flat_map(|v| v.children("COMPU-SCALE")) {
+            let lower = parse::<f64>(scale, &["LOWER-LIMIT"]);
+            let upper = parse::<f64>(scale, &["UPPER-LIMIT"]);
+            if let Some(text) = scale.text_of(&["COMPU-CONST", "VT"]) {
+                if let Some(lower) = lower {
+                    compu.texts.push((lower as i64, text.to_owned()));
+                }
+                continue;
+            }
+            // the first linear scale is used.
+            let Some(coeffs) = scale.child("COMPU-RATIONAL-COEFFS") else {
+                continue;
+            };
+            if linear {
+                continue;
+            }
+            let values = |name: &str| {
+                coeffs
+                    .child(name)
+                    .map(|e| {
+                        e.children("V")
+                            .filter_map(|v| v.text.trim().parse::<f64>().ok())
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default()
+            };
+            let (numerator, denominator) = (values("COMPU-NUMERATOR"), values("COMPU-DENOMINATOR"));
+            let denominator = denominator.first().copied().unwrap_or(1.0);
+            compu.offset = numerator.first().copied().unwrap_or_default() / denominator;
+            compu.factor = numerator.get(1).copied().unwrap_or(1.0) / denominator;
+            compu.limits = lower.zip(upper);
+            linear = true;
+        }
+
+        compu
+    }
+}
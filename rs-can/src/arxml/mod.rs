@@ -0,0 +1,881 @@
+//! AUTOSAR ARXML communication matrix.
+//!
+//! The CAN clusters, frame triggerings, PDUs(I-signal, container and secured I-PDU),
+//! I-signals with their compu-methods and the E2E/SecOC metadata are imported.
+//! The signals are described by [`dbc::Signal`](crate::dbc::Signal), so the payloads are
+//! encoded and decoded in the same way as DBC.
+//!
+//! ```ignore
+//! let arxml = Arxml::from_file("system.arxml")?;
+//! let cluster = arxml.cluster("PowertrainCAN").unwrap();
+//! for frame in device.receive(channel, Some(10)).await? {
+//!     let (triggering, pdus) = cluster.decode(&frame)?;
+//!     for pdu in pdus {
+//!         for value in pdu.values {
+//!             println!("{}.{} = {}", pdu.pdu.name, value.name(), value.value);
+//!         }
+//!     }
+//! }
+//! ```
+
+mod import;
+mod xml;
+
+use crate::{
+    dbc::{Signal, SignalValue},
+    error::Error,
+    frame::{identifier::Id, Frame},
+    CanFdFlags, CanResult, MAX_FRAME_SIZE,
+};
+use std::path::Path;
+
+/// The signal values of a PDU for encoding.
+pub type PduSignals<'a> = (&'a str, &'a [(&'a str, f64)]);
+
+/// The E2E protection of PDU.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct E2eProtection {
+    /// The name of protection.
+    pub name: String,
+    /// The profile, such as `PROFILE_01` or `PROFILE_05`.
+    pub category: String,
+    pub data_ids: Vec<u32>,
+    pub data_id_mode: Option<String>,
+    /// The length of protected data in bits.
+    pub data_length: Option<u32>,
+    /// The bit offset of CRC.
+    pub crc_offset: Option<u32>,
+    /// The bit offset of counter.
+    pub counter_offset: Option<u32>,
+    pub max_delta_counter: Option<u32>,
+}
+
+/// The SecOC properties of secured PDU.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SecOcProps {
+    pub data_id: Option<u32>,
+    pub algorithm: Option<String>,
+    /// The length of truncated MAC in bits.
+    pub auth_tx_length: Option<u32>,
+    /// The length of freshness value in bits.
+    pub freshness_length: Option<u32>,
+    /// The length of truncated freshness value in bits.
+    pub freshness_tx_length: Option<u32>,
+}
+
+/// The header of container PDU.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContainerHeader {
+    /// The contained PDUs are at the static offsets.
+    #[default]
+    None,
+    /// 3 bytes id and 1 byte length.
+    Short,
+    /// 4 bytes id and 4 bytes length.
+    Long,
+}
+
+impl ContainerHeader {
+    #[inline]
+    pub fn size(&self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::Short => 4,
+            Self::Long => 8,
+        }
+    }
+}
+
+/// A PDU in container PDU.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainedPdu {
+    /// The header id for the dynamic container.
+    pub header_id: Option<u32>,
+    /// The byte offset for the static container.
+    pub offset: Option<usize>,
+    pub pdu: Pdu,
+}
+
+/// The type of PDU.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum PduKind {
+    /// `I-SIGNAL-I-PDU`, the signals are in [`Pdu::signals`].
+    #[default]
+    Signals,
+    /// `CONTAINER-I-PDU`.
+    Container {
+        header: ContainerHeader,
+        pdus: Vec<ContainedPdu>,
+    },
+    /// `SECURED-I-PDU`, the payload is followed by the freshness value and MAC.
+    Secured {
+        payload: Box<Pdu>,
+        props: SecOcProps,
+    },
+    /// The other PDUs such as `N-PDU` and `NM-PDU`, which are not decoded.
+    Other(String),
+}
+
+/// A PDU of frame.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Pdu {
+    pub name: String,
+    /// The length in bytes.
+    pub length: usize,
+    pub kind: PduKind,
+    pub signals: Vec<Signal>,
+    pub e2e: Option<E2eProtection>,
+}
+
+/// The decoded signals of PDU.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PduValues<'a> {
+    pub pdu: &'a Pdu,
+    pub values: Vec<SignalValue<'a>>,
+}
+
+impl Pdu {
+    #[inline]
+    pub fn signal(&self, name: &str) -> Option<&Signal> {
+        self.signals.iter().find(|s| s.name == name)
+    }
+
+    /// Whether the PDU or its contained/payload PDUs is named `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.name == name
+            || match &self.kind {
+                PduKind::Container { pdus, .. } => pdus.iter().any(|c| c.pdu.contains(name)),
+                PduKind::Secured { payload, .. } => payload.contains(name),
+                _ => false,
+            }
+    }
+
+    /// Decode the signals of PDU and its contained/payload PDUs.
+    pub fn decode(&self, data: &[u8]) -> CanResult<Vec<PduValues<'_>>> {
+        match &self.kind {
+            PduKind::Signals => {
+                let mut values = Vec::with_capacity(self.signals.len());
+                for signal in &self.signals {
+                    let raw = signal.raw(data)?;
+                    values.push(SignalValue {
+                        signal,
+                        raw,
+                        value: signal.physical(raw),
+                    });
+                }
+                Ok(vec![PduValues { pdu: self, values }])
+            }
+            PduKind::Container { header, pdus } => {
+                let mut result = Vec::new();
+                if *header == ContainerHeader::None {
+                    for contained in pdus {
+                        let Some(offset) = contained.offset else {
+                            continue;
+                        };
+                        let end = (offset + contained.pdu.length).min(data.len());
+                        if offset < end {
+                            result.extend(contained.pdu.decode(&data[offset..end])?);
+                        }
+                    }
+                    return Ok(result);
+                }
+
+                let size = header.size();
+                let mut pos = 0;
+                while pos + size <= data.len() {
+                    let (id, length) = match header {
+                        ContainerHeader::Short => (
+                            u32::from_be_bytes([0, data[pos], data[pos + 1], data[pos + 2]]),
+                            data[pos + 3] as usize,
+                        ),
+                        _ => (
+                            u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()),
+                            u32::from_be_bytes(data[pos + 4..pos + 8].try_into().unwrap()) as usize,
+                        ),
+                    };
+                    // the header id 0 is the padding.
+                    if id == 0 {
+                        break;
+                    }
+                    let start = pos + size;
+                    if start + length > data.len() {
+                        return Err(Error::InvalidDLC(data.len()));
+                    }
+                    match pdus.iter().find(|c| c.header_id == Some(id)) {
+                        Some(contained) => {
+                            result.extend(contained.pdu.decode(&data[start..start + length])?)
+                        }
+                        None => rsutil::warn!(
+                            "RUST-CAN - unknown contained PDU: {:#X} of `{}`",
+                            id,
+                            self.name
+                        ),
+                    }
+                    pos = start + length;
+                }
+                Ok(result)
+            }
+            PduKind::Secured { payload, .. } => {
+                let end = payload.length.min(data.len());
+                payload.decode(&data[..end])
+            }
+            PduKind::Other(_) => Ok(vec![PduValues {
+                pdu: self,
+                values: vec![],
+            }]),
+        }
+    }
+
+    /// Encode the signals of PDU and its contained/payload PDUs, `None` if none of them
+    /// is in `values`.
+    ///
+    /// The freshness value and MAC of secured PDU are left as `0`.
+    pub fn encode(&self, values: &[PduSignals<'_>]) -> CanResult<Option<Vec<u8>>> {
+        let signals = values.iter().find(|(name, _)| *name == self.name);
+        let mut data = match &self.kind {
+            PduKind::Container { header, pdus } => {
+                let mut data = Vec::with_capacity(self.length);
+                for contained in pdus {
+                    let Some(payload) = contained.pdu.encode(values)? else {
+                        continue;
+                    };
+                    match (header, contained.offset, contained.header_id) {
+                        (ContainerHeader::None, Some(offset), _) => {
+                            let end = offset + payload.len();
+                            if data.len() < end {
+                                data.resize(end, 0);
+                            }
+                            data[offset..end].copy_from_slice(&payload);
+                        }
+                        (ContainerHeader::Short, _, Some(id)) => {
+                            let length = u8::try_from(payload.len()).map_err(|_| {
+                                Error::operation_error(format!(
+                                    "PDU `{}` is too long for the short header of `{}`",
+                                    contained.pdu.name, self.name
+                                ))
+                            })?;
+                            data.extend_from_slice(&id.to_be_bytes()[1..]);
+                            data.push(length);
+                            data.extend_from_slice(&payload);
+                        }
+                        (ContainerHeader::Long, _, Some(id)) => {
+                            data.extend_from_slice(&id.to_be_bytes());
+                            data.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+                            data.extend_from_slice(&payload);
+                        }
+                        _ => {
+                            return Err(Error::operation_error(format!(
+                                "PDU `{}` has no position in `{}`",
+                                contained.pdu.name, self.name
+                            )))
+                        }
+                    }
+                }
+                if data.is_empty() && signals.is_none() {
+                    return Ok(None);
+                }
+                data
+            }
+            PduKind::Secured { payload, .. } => match payload.encode(values)? {
+                Some(data) => data,
+                None if signals.is_some() => vec![],
+                None => return Ok(None),
+            },
+            _ => match signals {
+                Some(_) => vec![],
+                None => return Ok(None),
+            },
+        };
+        if data.len() > self.length {
+            return Err(Error::InvalidDLC(data.len()));
+        }
+        data.resize(self.length, 0);
+
+        for (name, value) in signals.map_or(&[][..], |(_, v)| *v) {
+            let signal = self.signal(name).ok_or_else(|| {
+                Error::operation_error(format!("signal `{}` is not in PDU `{}`", name, self.name))
+            })?;
+            signal.encode(&mut data, *value)?;
+        }
+
+        Ok(Some(data))
+    }
+}
+
+/// A PDU at the offset of frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PduMapping {
+    /// The byte offset in frame.
+    pub offset: usize,
+    pub pdu: Pdu,
+}
+
+/// A frame triggered on the cluster.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterFrame {
+    pub name: String,
+    pub id: Id,
+    /// The frame is transmitted as CAN-FD.
+    pub fd: bool,
+    pub length: usize,
+    pub pdus: Vec<PduMapping>,
+}
+
+impl ClusterFrame {
+    /// Decode the PDUs in frame.
+    pub fn decode(&self, data: &[u8]) -> CanResult<Vec<PduValues<'_>>> {
+        let mut result = Vec::new();
+        for mapping in &self.pdus {
+            let end = (mapping.offset + mapping.pdu.length).min(data.len());
+            if mapping.offset < end {
+                result.extend(mapping.pdu.decode(&data[mapping.offset..end])?);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Encode the PDUs in frame, the PDUs which are not in `values` are `0`.
+    pub fn encode(&self, values: &[PduSignals<'_>]) -> CanResult<Vec<u8>> {
+        if let Some((name, _)) = values
+            .iter()
+            .find(|(name, _)| !self.pdus.iter().any(|m| m.pdu.contains(name)))
+        {
+            return Err(Error::operation_error(format!(
+                "PDU `{}` is not in frame `{}`",
+                name, self.name
+            )));
+        }
+
+        let mut data = vec![0; self.length];
+        for mapping in &self.pdus {
+            if let Some(payload) = mapping.pdu.encode(values)? {
+                let end = mapping.offset + payload.len();
+                if end > data.len() {
+                    return Err(Error::InvalidDLC(end));
+                }
+                data[mapping.offset..end].copy_from_slice(&payload);
+            }
+        }
+        Ok(data)
+    }
+}
+
+/// A CAN cluster(network).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Cluster {
+    pub name: String,
+    pub baudrate: Option<u32>,
+    pub fd_baudrate: Option<u32>,
+    pub frames: Vec<ClusterFrame>,
+}
+
+impl Cluster {
+    #[inline]
+    pub fn frame(&self, id: Id) -> Option<&ClusterFrame> {
+        self.frames.iter().find(|f| f.id == id)
+    }
+
+    #[inline]
+    pub fn frame_by_name(&self, name: &str) -> Option<&ClusterFrame> {
+        self.frames.iter().find(|f| f.name == name)
+    }
+
+    /// Decode the PDUs of frame.
+    pub fn decode<'a, F: Frame>(
+        &'a self,
+        frame: &F,
+    ) -> CanResult<(&'a ClusterFrame, Vec<PduValues<'a>>)> {
+        let id = frame.id();
+        let triggering = self
+            .frame(id)
+            .ok_or(Error::InvalidIdentifier(id.as_raw()))?;
+        Ok((triggering, triggering.decode(frame.data())?))
+    }
+
+    /// Encode the PDUs into frame, the CAN-FD frame is created for the CAN-FD triggering.
+    pub fn encode<F: Frame>(&self, frame: &str, values: &[PduSignals<'_>]) -> CanResult<F> {
+        let triggering = self
+            .frame_by_name(frame)
+            .ok_or_else(|| Error::operation_error(format!("frame `{}` is not found", frame)))?;
+        let data = triggering.encode(values)?;
+        match triggering.fd || data.len() > MAX_FRAME_SIZE {
+            true => F::new_can_fd(triggering.id, &data, CanFdFlags::BRS),
+            false => F::new_can(triggering.id, &data),
+        }
+    }
+}
+
+/// The communication matrix imported from ARXML.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Arxml {
+    pub clusters: Vec<Cluster>,
+}
+
+impl Arxml {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> CanResult<Self> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| Error::OperationError(e.to_string()))?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> CanResult<Self> {
+        let root = xml::Element::parse(content)?;
+        import::Importer::new(&root).import()
+    }
+
+    #[inline]
+    pub fn cluster(&self, name: &str) -> Option<&Cluster> {
+        self.clusters.iter().find(|c| c.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dbc::{ByteOrder, ValueType},
+        frame::identifier::{ExtendedId, StandardId},
+        virtual_bus::tests::TestFrame,
+        CanKind,
+    };
+
+    const SYSTEM_ARXML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- the communication matrix of powertrain -->
+<AUTOSAR xmlns="http://autosar.org/schema/r4.0">
+<AR-PACKAGES>
+<AR-PACKAGE><SHORT-NAME>Units</SHORT-NAME><ELEMENTS>
+  <UNIT><SHORT-NAME>Rpm</SHORT-NAME><DISPLAY-NAME>rpm</DISPLAY-NAME></UNIT>
+  <UNIT><SHORT-NAME>DegC</SHORT-NAME><DISPLAY-NAME>&#176;C</DISPLAY-NAME></UNIT>
+</ELEMENTS></AR-PACKAGE>
+<AR-PACKAGE><SHORT-NAME>CompuMethods</SHORT-NAME><ELEMENTS>
+  <COMPU-METHOD><SHORT-NAME>EngineSpeed</SHORT-NAME><CATEGORY>LINEAR</CATEGORY>
+    <UNIT-REF DEST="UNIT">/Units/Rpm</UNIT-REF>
+    <COMPU-INTERNAL-TO-PHYS><COMPU-SCALES><COMPU-SCALE>
+      <LOWER-LIMIT>0</LOWER-LIMIT><UPPER-LIMIT>65535</UPPER-LIMIT>
+      <COMPU-RATIONAL-COEFFS>
+        <COMPU-NUMERATOR><V>0</V><V>0.25</V></COMPU-NUMERATOR>
+        <COMPU-DENOMINATOR><V>1</V></COMPU-DENOMINATOR>
+      </COMPU-RATIONAL-COEFFS>
+    </COMPU-SCALE></COMPU-SCALES></COMPU-INTERNAL-TO-PHYS>
+  </COMPU-METHOD>
+  <COMPU-METHOD><SHORT-NAME>Temperature</SHORT-NAME><CATEGORY>LINEAR</CATEGORY>
+    <UNIT-REF DEST="UNIT">/Units/DegC</UNIT-REF>
+    <COMPU-INTERNAL-TO-PHYS><COMPU-SCALES><COMPU-SCALE>
+      <LOWER-LIMIT>0</LOWER-LIMIT><UPPER-LIMIT>250</UPPER-LIMIT>
+      <COMPU-RATIONAL-COEFFS>
+        <COMPU-NUMERATOR><V>-80</V><V>1</V></COMPU-NUMERATOR>
+        <COMPU-DENOMINATOR><V>2</V></COMPU-DENOMINATOR>
+      </COMPU-RATIONAL-COEFFS>
+    </COMPU-SCALE></COMPU-SCALES></COMPU-INTERNAL-TO-PHYS>
+  </COMPU-METHOD>
+  <COMPU-METHOD><SHORT-NAME>Gear</SHORT-NAME><CATEGORY>TEXTTABLE</CATEGORY>
+    <COMPU-INTERNAL-TO-PHYS><COMPU-SCALES>
+      <COMPU-SCALE><LOWER-LIMIT>0</LOWER-LIMIT><UPPER-LIMIT>0</UPPER-LIMIT><COMPU-CONST><VT>Neutral</VT></COMPU-CONST></COMPU-SCALE>
+      <COMPU-SCALE><LOWER-LIMIT>1</LOWER-LIMIT><UPPER-LIMIT>1</UPPER-LIMIT><COMPU-CONST><VT>Drive</VT></COMPU-CONST></COMPU-SCALE>
+      <COMPU-SCALE><LOWER-LIMIT>15</LOWER-LIMIT><UPPER-LIMIT>15</UPPER-LIMIT><COMPU-CONST><VT>Reverse</VT></COMPU-CONST></COMPU-SCALE>
+    </COMPU-SCALES></COMPU-INTERNAL-TO-PHYS>
+  </COMPU-METHOD>
+  <COMPU-METHOD><SHORT-NAME>BrakePressure</SHORT-NAME><CATEGORY>LINEAR</CATEGORY>
+    <COMPU-INTERNAL-TO-PHYS><COMPU-SCALES><COMPU-SCALE>
+      <COMPU-RATIONAL-COEFFS>
+        <COMPU-NUMERATOR><V>0</V><V>1</V></COMPU-NUMERATOR>
+        <COMPU-DENOMINATOR><V>10</V></COMPU-DENOMINATOR>
+      </COMPU-RATIONAL-COEFFS>
+    </COMPU-SCALE></COMPU-SCALES></COMPU-INTERNAL-TO-PHYS>
+  </COMPU-METHOD>
+</ELEMENTS></AR-PACKAGE>
+<AR-PACKAGE><SHORT-NAME>BaseTypes</SHORT-NAME><ELEMENTS>
+  <SW-BASE-TYPE><SHORT-NAME>sint16</SHORT-NAME><BASE-TYPE-SIZE>16</BASE-TYPE-SIZE><BASE-TYPE-ENCODING>2C</BASE-TYPE-ENCODING></SW-BASE-TYPE>
+  <SW-BASE-TYPE><SHORT-NAME>float32</SHORT-NAME><BASE-TYPE-SIZE>32</BASE-TYPE-SIZE><BASE-TYPE-ENCODING>IEEE754</BASE-TYPE-ENCODING></SW-BASE-TYPE>
+</ELEMENTS></AR-PACKAGE>
+<AR-PACKAGE><SHORT-NAME>Signals</SHORT-NAME><ELEMENTS>
+  <I-SIGNAL><SHORT-NAME>EngineSpeed</SHORT-NAME><LENGTH>16</LENGTH>
+    <NETWORK-REPRESENTATION-PROPS><SW-DATA-DEF-PROPS-VARIANTS><SW-DATA-DEF-PROPS-CONDITIONAL>
+      <COMPU-METHOD-REF DEST="COMPU-METHOD">/CompuMethods/EngineSpeed</COMPU-METHOD-REF>
+    </SW-DATA-DEF-PROPS-CONDITIONAL></SW-DATA-DEF-PROPS-VARIANTS></NETWORK-REPRESENTATION-PROPS>
+    <SYSTEM-SIGNAL-REF DEST="SYSTEM-SIGNAL">/Signals/EngineSpeedSystem</SYSTEM-SIGNAL-REF>
+  </I-SIGNAL>
+  <I-SIGNAL><SHORT-NAME>Temperature</SHORT-NAME><LENGTH>8</LENGTH>
+    <SYSTEM-SIGNAL-REF DEST="SYSTEM-SIGNAL">/Signals/TemperatureSystem</SYSTEM-SIGNAL-REF>
+  </I-SIGNAL>
+  <I-SIGNAL><SHORT-NAME>Gear</SHORT-NAME><LENGTH>4</LENGTH>
+    <NETWORK-REPRESENTATION-PROPS><SW-DATA-DEF-PROPS-VARIANTS><SW-DATA-DEF-PROPS-CONDITIONAL>
+      <COMPU-METHOD-REF DEST="COMPU-METHOD">/CompuMethods/Gear</COMPU-METHOD-REF>
+    </SW-DATA-DEF-PROPS-CONDITIONAL></SW-DATA-DEF-PROPS-VARIANTS></NETWORK-REPRESENTATION-PROPS>
+  </I-SIGNAL>
+  <I-SIGNAL><SHORT-NAME>Status</SHORT-NAME><LENGTH>8</LENGTH></I-SIGNAL>
+  <I-SIGNAL><SHORT-NAME>BrakePressure</SHORT-NAME><LENGTH>16</LENGTH>
+    <NETWORK-REPRESENTATION-PROPS><SW-DATA-DEF-PROPS-VARIANTS><SW-DATA-DEF-PROPS-CONDITIONAL>
+      <BASE-TYPE-REF DEST="SW-BASE-TYPE">/BaseTypes/sint16</BASE-TYPE-REF>
+      <COMPU-METHOD-REF DEST="COMPU-METHOD">/CompuMethods/BrakePressure</COMPU-METHOD-REF>
+    </SW-DATA-DEF-PROPS-CONDITIONAL></SW-DATA-DEF-PROPS-VARIANTS></NETWORK-REPRESENTATION-PROPS>
+  </I-SIGNAL>
+  <I-SIGNAL><SHORT-NAME>SteeringAngle</SHORT-NAME><LENGTH>32</LENGTH>
+    <NETWORK-REPRESENTATION-PROPS><SW-DATA-DEF-PROPS-VARIANTS><SW-DATA-DEF-PROPS-CONDITIONAL>
+      <BASE-TYPE-REF DEST="SW-BASE-TYPE">/BaseTypes/float32</BASE-TYPE-REF>
+    </SW-DATA-DEF-PROPS-CONDITIONAL></SW-DATA-DEF-PROPS-VARIANTS></NETWORK-REPRESENTATION-PROPS>
+  </I-SIGNAL>
+  <SYSTEM-SIGNAL><SHORT-NAME>EngineSpeedSystem</SHORT-NAME>
+    <DESC><L-2 L="EN">The speed of crank shaft</L-2></DESC>
+  </SYSTEM-SIGNAL>
+  <SYSTEM-SIGNAL><SHORT-NAME>TemperatureSystem</SHORT-NAME>
+    <PHYSICAL-PROPS><SW-DATA-DEF-PROPS-VARIANTS><SW-DATA-DEF-PROPS-CONDITIONAL>
+      <COMPU-METHOD-REF DEST="COMPU-METHOD">/CompuMethods/Temperature</COMPU-METHOD-REF>
+    </SW-DATA-DEF-PROPS-CONDITIONAL></SW-DATA-DEF-PROPS-VARIANTS></PHYSICAL-PROPS>
+  </SYSTEM-SIGNAL>
+</ELEMENTS></AR-PACKAGE>
+<AR-PACKAGE><SHORT-NAME>Pdus</SHORT-NAME><ELEMENTS>
+  <I-SIGNAL-I-PDU><SHORT-NAME>EnginePdu</SHORT-NAME><LENGTH>8</LENGTH>
+    <I-SIGNAL-TO-PDU-MAPPINGS>
+      <I-SIGNAL-TO-I-PDU-MAPPING><SHORT-NAME>EngineSpeed</SHORT-NAME>
+        <I-SIGNAL-REF DEST="I-SIGNAL">/Signals/EngineSpeed</I-SIGNAL-REF>
+        <PACKING-BYTE-ORDER>MOST-SIGNIFICANT-BYTE-LAST</PACKING-BYTE-ORDER><START-POSITION>0</START-POSITION>
+      </I-SIGNAL-TO-I-PDU-MAPPING>
+      <I-SIGNAL-TO-I-PDU-MAPPING><SHORT-NAME>Temperature</SHORT-NAME>
+        <I-SIGNAL-REF DEST="I-SIGNAL">/Signals/Temperature</I-SIGNAL-REF>
+        <PACKING-BYTE-ORDER>MOST-SIGNIFICANT-BYTE-LAST</PACKING-BYTE-ORDER><START-POSITION>16</START-POSITION>
+      </I-SIGNAL-TO-I-PDU-MAPPING>
+      <I-SIGNAL-TO-I-PDU-MAPPING><SHORT-NAME>Gear</SHORT-NAME>
+        <I-SIGNAL-REF DEST="I-SIGNAL">/Signals/Gear</I-SIGNAL-REF>
+        <PACKING-BYTE-ORDER>MOST-SIGNIFICANT-BYTE-FIRST</PACKING-BYTE-ORDER><START-POSITION>31</START-POSITION>
+      </I-SIGNAL-TO-I-PDU-MAPPING>
+    </I-SIGNAL-TO-PDU-MAPPINGS>
+  </I-SIGNAL-I-PDU>
+  <I-SIGNAL-I-PDU><SHORT-NAME>StatusPdu</SHORT-NAME><LENGTH>8</LENGTH>
+    <I-SIGNAL-TO-PDU-MAPPINGS>
+      <I-SIGNAL-TO-I-PDU-MAPPING><SHORT-NAME>Status</SHORT-NAME>
+        <I-SIGNAL-REF DEST="I-SIGNAL">/Signals/Status</I-SIGNAL-REF><START-POSITION>0</START-POSITION>
+      </I-SIGNAL-TO-I-PDU-MAPPING>
+    </I-SIGNAL-TO-PDU-MAPPINGS>
+  </I-SIGNAL-I-PDU>
+  <I-SIGNAL-I-PDU><SHORT-NAME>BrakePdu</SHORT-NAME><LENGTH>4</LENGTH>
+    <CONTAINED-I-PDU-PROPS><HEADER-ID-SHORT-HEADER>16</HEADER-ID-SHORT-HEADER></CONTAINED-I-PDU-PROPS>
+    <I-SIGNAL-TO-PDU-MAPPINGS>
+      <I-SIGNAL-TO-I-PDU-MAPPING><SHORT-NAME>BrakePressure</SHORT-NAME>
+        <I-SIGNAL-REF DEST="I-SIGNAL">/Signals/BrakePressure</I-SIGNAL-REF><START-POSITION>0</START-POSITION>
+      </I-SIGNAL-TO-I-PDU-MAPPING>
+    </I-SIGNAL-TO-PDU-MAPPINGS>
+  </I-SIGNAL-I-PDU>
+  <I-SIGNAL-I-PDU><SHORT-NAME>SteeringPdu</SHORT-NAME><LENGTH>4</LENGTH>
+    <I-SIGNAL-TO-PDU-MAPPINGS>
+      <I-SIGNAL-TO-I-PDU-MAPPING><SHORT-NAME>SteeringAngle</SHORT-NAME>
+        <I-SIGNAL-REF DEST="I-SIGNAL">/Signals/SteeringAngle</I-SIGNAL-REF><START-POSITION>0</START-POSITION>
+      </I-SIGNAL-TO-I-PDU-MAPPING>
+    </I-SIGNAL-TO-PDU-MAPPINGS>
+  </I-SIGNAL-I-PDU>
+  <SECURED-I-PDU><SHORT-NAME>SecuredSteering</SHORT-NAME><LENGTH>12</LENGTH>
+    <CONTAINED-I-PDU-PROPS><HEADER-ID-SHORT-HEADER>0x20</HEADER-ID-SHORT-HEADER></CONTAINED-I-PDU-PROPS>
+    <AUTHENTICATION-PROPS-REF DEST="SECURE-COMMUNICATION-AUTHENTICATION-PROPS">/SecOC/Props/Cmac</AUTHENTICATION-PROPS-REF>
+    <FRESHNESS-PROPS-REF DEST="SECURE-COMMUNICATION-FRESHNESS-PROPS">/SecOC/Props/Counter</FRESHNESS-PROPS-REF>
+    <PAYLOAD-REF DEST="PDU-TRIGGERING">/Cluster/PowertrainCAN/Channel/SteeringTriggering</PAYLOAD-REF>
+    <SECURE-COMMUNICATION-PROPS><DATA-ID>7</DATA-ID></SECURE-COMMUNICATION-PROPS>
+  </SECURED-I-PDU>
+  <CONTAINER-I-PDU><SHORT-NAME>FdContainer</SHORT-NAME><LENGTH>56</LENGTH>
+    <CONTAINED-PDU-TRIGGERING-REFS>
+      <CONTAINED-PDU-TRIGGERING-REF DEST="PDU-TRIGGERING">/Cluster/PowertrainCAN/Channel/BrakeTriggering</CONTAINED-PDU-TRIGGERING-REF>
+      <CONTAINED-PDU-TRIGGERING-REF DEST="PDU-TRIGGERING">/Cluster/PowertrainCAN/Channel/SecuredTriggering</CONTAINED-PDU-TRIGGERING-REF>
+    </CONTAINED-PDU-TRIGGERING-REFS>
+    <HEADER-TYPE>SHORT-HEADER</HEADER-TYPE>
+  </CONTAINER-I-PDU>
+</ELEMENTS></AR-PACKAGE>
+<AR-PACKAGE><SHORT-NAME>SecOC</SHORT-NAME><ELEMENTS>
+  <SECURE-COMMUNICATION-PROPS-SET><SHORT-NAME>Props</SHORT-NAME>
+    <AUTHENTICATION-PROPSS>
+      <SECURE-COMMUNICATION-AUTHENTICATION-PROPS><SHORT-NAME>Cmac</SHORT-NAME>
+        <AUTH-ALGORITHM>CMAC/AES-128</AUTH-ALGORITHM><AUTH-INFO-TX-LENGTH>56</AUTH-INFO-TX-LENGTH>
+      </SECURE-COMMUNICATION-AUTHENTICATION-PROPS>
+    </AUTHENTICATION-PROPSS>
+    <FRESHNESS-PROPSS>
+      <SECURE-COMMUNICATION-FRESHNESS-PROPS><SHORT-NAME>Counter</SHORT-NAME>
+        <FRESHNESS-VALUE-LENGTH>32</FRESHNESS-VALUE-LENGTH><FRESHNESS-VALUE-TX-LENGTH>8</FRESHNESS-VALUE-TX-LENGTH>
+      </SECURE-COMMUNICATION-FRESHNESS-PROPS>
+    </FRESHNESS-PROPSS>
+  </SECURE-COMMUNICATION-PROPS-SET>
+</ELEMENTS></AR-PACKAGE>
+<AR-PACKAGE><SHORT-NAME>Frames</SHORT-NAME><ELEMENTS>
+  <CAN-FRAME><SHORT-NAME>EngineFrame</SHORT-NAME><FRAME-LENGTH>8</FRAME-LENGTH>
+    <PDU-TO-FRAME-MAPPINGS>
+      <PDU-TO-FRAME-MAPPING><SHORT-NAME>EnginePdu</SHORT-NAME>
+        <PACKING-BYTE-ORDER>MOST-SIGNIFICANT-BYTE-LAST</PACKING-BYTE-ORDER>
+        <PDU-REF DEST="I-SIGNAL-I-PDU">/Pdus/EnginePdu</PDU-REF><START-POSITION>0</START-POSITION>
+      </PDU-TO-FRAME-MAPPING>
+    </PDU-TO-FRAME-MAPPINGS>
+  </CAN-FRAME>
+  <CAN-FRAME><SHORT-NAME>FdFrame</SHORT-NAME><FRAME-LENGTH>64</FRAME-LENGTH>
+    <PDU-TO-FRAME-MAPPINGS>
+      <PDU-TO-FRAME-MAPPING><SHORT-NAME>StatusPdu</SHORT-NAME>
+        <PDU-REF DEST="I-SIGNAL-I-PDU">/Pdus/StatusPdu</PDU-REF><START-POSITION>0</START-POSITION>
+      </PDU-TO-FRAME-MAPPING>
+      <PDU-TO-FRAME-MAPPING><SHORT-NAME>FdContainer</SHORT-NAME>
+        <PDU-REF DEST="CONTAINER-I-PDU">/Pdus/FdContainer</PDU-REF><START-POSITION>64</START-POSITION>
+      </PDU-TO-FRAME-MAPPING>
+    </PDU-TO-FRAME-MAPPINGS>
+  </CAN-FRAME>
+</ELEMENTS></AR-PACKAGE>
+<AR-PACKAGE><SHORT-NAME>E2E</SHORT-NAME><ELEMENTS>
+  <END-TO-END-PROTECTION-SET><SHORT-NAME>Protections</SHORT-NAME>
+    <END-TO-END-PROTECTIONS>
+      <END-TO-END-PROTECTION><SHORT-NAME>EngineProtection</SHORT-NAME>
+        <END-TO-END-PROFILE>
+          <CATEGORY>PROFILE_01</CATEGORY>
+          <COUNTER-OFFSET>48</COUNTER-OFFSET><CRC-OFFSET>56</CRC-OFFSET>
+          <DATA-IDS><DATA-ID>291</DATA-ID></DATA-IDS>
+          <DATA-ID-MODE>ALL-16-BIT</DATA-ID-MODE><DATA-LENGTH>64</DATA-LENGTH>
+          <MAX-DELTA-COUNTER-INIT>1</MAX-DELTA-COUNTER-INIT>
+        </END-TO-END-PROFILE>
+        <END-TO-END-PROTECTION-I-SIGNAL-I-PDUS>
+          <END-TO-END-PROTECTION-I-SIGNAL-I-PDU>
+            <I-SIGNAL-I-PDU-REF DEST="I-SIGNAL-I-PDU">/Pdus/EnginePdu</I-SIGNAL-I-PDU-REF>
+          </END-TO-END-PROTECTION-I-SIGNAL-I-PDU>
+        </END-TO-END-PROTECTION-I-SIGNAL-I-PDUS>
+      </END-TO-END-PROTECTION>
+    </END-TO-END-PROTECTIONS>
+  </END-TO-END-PROTECTION-SET>
+</ELEMENTS></AR-PACKAGE>
+<AR-PACKAGE><SHORT-NAME>Cluster</SHORT-NAME><ELEMENTS>
+  <CAN-CLUSTER><SHORT-NAME>PowertrainCAN</SHORT-NAME>
+    <CAN-CLUSTER-VARIANTS><CAN-CLUSTER-CONDITIONAL>
+      <BAUDRATE>500000</BAUDRATE>
+      <PHYSICAL-CHANNELS>
+        <CAN-PHYSICAL-CHANNEL><SHORT-NAME>Channel</SHORT-NAME>
+          <FRAME-TRIGGERINGS>
+            <CAN-FRAME-TRIGGERING><SHORT-NAME>EngineTriggering</SHORT-NAME>
+              <FRAME-REF DEST="CAN-FRAME">/Frames/EngineFrame</FRAME-REF>
+              <CAN-ADDRESSING-MODE>STANDARD</CAN-ADDRESSING-MODE>
+              <CAN-FRAME-TX-BEHAVIOR>CAN-20</CAN-FRAME-TX-BEHAVIOR>
+              <IDENTIFIER>256</IDENTIFIER>
+            </CAN-FRAME-TRIGGERING>
+            <CAN-FRAME-TRIGGERING><SHORT-NAME>FdTriggering</SHORT-NAME>
+              <FRAME-REF DEST="CAN-FRAME">/Frames/FdFrame</FRAME-REF>
+              <CAN-ADDRESSING-MODE>EXTENDED</CAN-ADDRESSING-MODE>
+              <CAN-FRAME-RX-BEHAVIOR>CAN-FD</CAN-FRAME-RX-BEHAVIOR>
+              <CAN-FRAME-TX-BEHAVIOR>CAN-FD</CAN-FRAME-TX-BEHAVIOR>
+              <IDENTIFIER>419364865</IDENTIFIER>
+            </CAN-FRAME-TRIGGERING>
+          </FRAME-TRIGGERINGS>
+          <PDU-TRIGGERINGS>
+            <PDU-TRIGGERING><SHORT-NAME>SteeringTriggering</SHORT-NAME>
+              <I-PDU-REF DEST="I-SIGNAL-I-PDU">/Pdus/SteeringPdu</I-PDU-REF>
+            </PDU-TRIGGERING>
+            <PDU-TRIGGERING><SHORT-NAME>BrakeTriggering</SHORT-NAME>
+              <I-PDU-REF DEST="I-SIGNAL-I-PDU">/Pdus/BrakePdu</I-PDU-REF>
+            </PDU-TRIGGERING>
+            <PDU-TRIGGERING><SHORT-NAME>SecuredTriggering</SHORT-NAME>
+              <I-PDU-REF DEST="SECURED-I-PDU">/Pdus/SecuredSteering</I-PDU-REF>
+            </PDU-TRIGGERING>
+          </PDU-TRIGGERINGS>
+        </CAN-PHYSICAL-CHANNEL>
+      </PHYSICAL-CHANNELS>
+      <CAN-FD-BAUDRATE>2000000</CAN-FD-BAUDRATE>
+    </CAN-CLUSTER-CONDITIONAL></CAN-CLUSTER-VARIANTS>
+  </CAN-CLUSTER>
+</ELEMENTS></AR-PACKAGE>
+</AR-PACKAGES>
+</AUTOSAR>
+"#;
+
+    fn cluster() -> Cluster {
+        let mut arxml = Arxml::parse(SYSTEM_ARXML).unwrap();
+        assert_eq!(arxml.clusters.len(), 1);
+        arxml.clusters.remove(0)
+    }
+
+    #[test]
+    fn arxml_is_imported() {
+        let cluster = cluster();
+        assert_eq!(cluster.name, "PowertrainCAN");
+        assert_eq!(
+            (cluster.baudrate, cluster.fd_baudrate),
+            (Some(500_000), Some(2_000_000))
+        );
+        assert_eq!(cluster.frames.len(), 2);
+
+        let engine = cluster
+            .frame(Id::Standard(StandardId::new(0x100).unwrap()))
+            .unwrap();
+        assert_eq!(
+            (engine.name.as_str(), engine.fd, engine.length),
+            ("EngineFrame", false, 8)
+        );
+        let pdu = &engine.pdus[0].pdu;
+        let e2e = pdu.e2e.as_ref().unwrap();
+        assert_eq!(e2e.category, "PROFILE_01");
+        assert_eq!(e2e.data_ids, [291]);
+        assert_eq!((e2e.crc_offset, e2e.counter_offset), (Some(56), Some(48)));
+        assert_eq!(e2e.max_delta_counter, Some(1));
+
+        let speed = pdu.signal("EngineSpeed").unwrap();
+        assert_eq!(
+            (speed.factor, speed.offset, speed.max),
+            (0.25, 0.0, 16383.75)
+        );
+        assert_eq!(speed.unit, "rpm");
+        assert_eq!(speed.comment, None);
+        let temperature = pdu.signal("Temperature").unwrap();
+        assert_eq!((temperature.factor, temperature.offset), (0.5, -40.0));
+        assert_eq!(temperature.unit, "°C");
+        let gear = pdu.signal("Gear").unwrap();
+        assert_eq!(gear.byte_order, ByteOrder::BigEndian);
+        assert_eq!(gear.values[&15], "Reverse");
+
+        let fd = cluster.frame_by_name("FdFrame").unwrap();
+        assert_eq!(fd.id, Id::Extended(ExtendedId::new(0x18FF0001).unwrap()));
+        assert!(fd.fd);
+        assert_eq!(fd.pdus[1].offset, 8);
+        let PduKind::Container { header, pdus } = &fd.pdus[1].pdu.kind else {
+            panic!("container PDU is expected");
+        };
+        assert_eq!(*header, ContainerHeader::Short);
+        assert_eq!(
+            pdus.iter().map(|c| c.header_id).collect::<Vec<_>>(),
+            [Some(0x10), Some(0x20)]
+        );
+        let PduKind::Secured { payload, props } = &pdus[1].pdu.kind else {
+            panic!("secured PDU is expected");
+        };
+        assert_eq!(payload.name, "SteeringPdu");
+        assert_eq!(payload.signals[0].value_type, ValueType::Float32);
+        assert_eq!(
+            *props,
+            SecOcProps {
+                data_id: Some(7),
+                algorithm: Some("CMAC/AES-128".into()),
+                auth_tx_length: Some(56),
+                freshness_length: Some(32),
+                freshness_tx_length: Some(8),
+            }
+        );
+
+        assert!(Arxml::parse("<AUTOSAR><AR-PACKAGES></AUTOSAR>").is_err());
+        assert!(Arxml::parse("<AUTOSAR/>").unwrap().clusters.is_empty());
+    }
+
+    #[test]
+    fn arxml_with_bom_is_imported() {
+        let arxml = Arxml::parse(&format!("\u{feff}{}", SYSTEM_ARXML)).unwrap();
+        assert_eq!(arxml.clusters, [cluster()]);
+    }
+
+    #[test]
+    fn frames_are_decoded() {
+        let cluster = cluster();
+        let data = [0x70, 0x17, 0x78, 0xF0, 0, 0, 0, 0];
+        let frame =
+            TestFrame::new_can(Id::Standard(StandardId::new(0x100).unwrap()), &data).unwrap();
+        let (triggering, pdus) = cluster.decode(&frame).unwrap();
+        assert_eq!(triggering.name, "EngineFrame");
+        let values = pdus[0]
+            .values
+            .iter()
+            .map(|v| (v.name(), v.value, v.description()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                ("EngineSpeed", 1500.0, None),
+                ("Temperature", 20.0, None),
+                ("Gear", 15.0, Some("Reverse"))
+            ]
+        );
+        let frame: TestFrame = cluster
+            .encode(
+                "EngineFrame",
+                &[(
+                    "EnginePdu",
+                    &[
+                        ("EngineSpeed", 1500.0),
+                        ("Temperature", 20.0),
+                        ("Gear", 15.0),
+                    ],
+                )],
+            )
+            .unwrap();
+        assert_eq!(frame.data(), data);
+    }
+
+    #[test]
+    fn long_pdu_in_short_header_is_rejected() {
+        let pdu = |name: &str, length, kind| Pdu {
+            name: name.into(),
+            length,
+            kind,
+            ..Default::default()
+        };
+        let contained = ContainedPdu {
+            header_id: Some(0x10),
+            offset: None,
+            pdu: pdu("LongPdu", 256, PduKind::Signals),
+        };
+        let container = pdu(
+            "Container",
+            1024,
+            PduKind::Container {
+                header: ContainerHeader::Short,
+                pdus: vec![contained],
+            },
+        );
+
+        assert!(container.encode(&[("LongPdu", &[])]).is_err());
+    }
+
+    #[test]
+    fn container_is_encoded() {
+        let cluster = cluster();
+        let frame: TestFrame = cluster
+            .encode(
+                "FdFrame",
+                &[
+                    ("StatusPdu", &[("Status", 5.0)]),
+                    ("BrakePdu", &[("BrakePressure", -12.5)]),
+                    ("SteeringPdu", &[("SteeringAngle", 1.5)]),
+                ],
+            )
+            .unwrap();
+        assert_eq!(frame.kind(), CanKind::FD);
+        assert_eq!(frame.data().len(), 64);
+        assert_eq!(frame.data()[..8], [5, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            frame.data()[8..32],
+            [
+                0x00, 0x00, 0x10, 4, 0x83, 0xFF, 0, 0, // brake
+                0x00, 0x00, 0x20, 12, 0x00, 0x00, 0xC0, 0x3F, 0, 0, 0, 0, 0, 0, 0,
+                0, // secured
+            ]
+        );
+        assert!(frame.data()[32..].iter().all(|v| *v == 0));
+
+        let (_, pdus) = cluster.decode(&frame).unwrap();
+        let values = pdus
+            .iter()
+            .flat_map(|p| {
+                p.values
+                    .iter()
+                    .map(|v| (p.pdu.name.as_str(), v.name(), v.value))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                ("StatusPdu", "Status", 5.0),
+                ("BrakePdu", "BrakePressure", -12.5),
+                ("SteeringPdu", "SteeringAngle", 1.5)
+            ]
+        );
+
+        // only the brake is contained.
+        let frame: TestFrame = cluster
+            .encode("FdFrame", &[("BrakePdu", &[("BrakePressure", 1.0)])])
+            .unwrap();
+        let (_, pdus) = cluster.decode(&frame).unwrap();
+        assert_eq!(
+            pdus.iter().map(|p| p.pdu.name.as_str()).collect::<Vec<_>>(),
+            ["StatusPdu", "BrakePdu"]
+        );
+        assert!(cluster
+            .encode::<TestFrame>("FdFrame", &[("EnginePdu", &[])])
+            .is_err());
+        assert!(cluster
+            .encode::<TestFrame>("FdFrame", &[("BrakePdu", &[("Unknown", 1.0)])])
+            .is_err());
+    }
+}
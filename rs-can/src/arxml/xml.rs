@@ -0,0 +1,227 @@
+use crate::{error::Error, CanResult};
+
+/// A minimal XML element, the namespaces are kept in the names and the mixed
+/// text is concatenated.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct Element {
+    pub(crate) name: String,
+    pub(crate) attributes: Vec<(String, String)>,
+    pub(crate) children: Vec<Element>,
+    pub(crate) text: String,
+}
+
+impl Element {
+    #[inline]
+    pub(crate) fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|e| e.name == name)
+    }
+
+    #[inline]
+    pub(crate) fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |e| e.name == name)
+    }
+
+    /// The descendant by the names of each level.
+    pub(crate) fn path(&self, names: &[&str]) -> Option<&Element> {
+        names.iter().try_fold(self, |e, name| e.child(name))
+    }
+
+    /// The trimmed text of descendant.
+    pub(crate) fn text_of(&self, names: &[&str]) -> Option<&str> {
+        self.path(names).map(|e| e.text.trim())
+    }
+
+    /// The first descendant with the name in depth-first order.
+    pub(crate) fn find(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find_map(|e| match e.name == name {
+            true => Some(e),
+            false => e.find(name),
+        })
+    }
+
+    /// All descendants with the name in depth-first order.
+    pub(crate) fn find_all<'a>(&'a self, name: &str, result: &mut Vec<&'a Element>) {
+        for child in &self.children {
+            if child.name == name {
+                result.push(child);
+            }
+            child.find_all(name, result);
+        }
+    }
+
+    /// Parse the document and return the root element.
+    pub(crate) fn parse(content: &str) -> CanResult<Self> {
+        // the BOM is written by the tools on windows
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+        Reader { content, pos: 0 }.document()
+    }
+}
+
+struct Reader<'a> {
+    content: &'a str,
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn error(&self, msg: &str) -> Error {
+        let line = self.content[..self.pos].matches('\n').count() + 1;
+        Error::other_error(format!("invalid XML at line {}: {}", line, msg))
+    }
+
+    #[inline]
+    fn rest(&self) -> &str {
+        &self.content[self.pos..]
+    }
+
+    /// Skip to the end of `delimiter`.
+    fn skip_past(&mut self, delimiter: &str) -> CanResult<&str> {
+        match self.rest().find(delimiter) {
+            Some(i) => {
+                let start = self.pos;
+                self.pos += i + delimiter.len();
+                Ok(&self.content[start..start + i])
+            }
+            None => Err(self.error(&format!("`{}` is expected", delimiter))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn name(&mut self) -> CanResult<String> {
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '>' | '/' | '='))
+            .unwrap_or(rest.len());
+        if end == 0 {
+            return Err(self.error("name is expected"));
+        }
+        let name = rest[..end].to_owned();
+        self.pos += end;
+        Ok(name)
+    }
+
+    fn document(mut self) -> CanResult<Element> {
+        let mut stack: Vec<Element> = Vec::new();
+        let mut root = None;
+        while self.pos < self.content.len() {
+            let rest = self.rest();
+            if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += 9;
+                let text = self.skip_past("]]>")?.to_owned();
+                if let Some(e) = stack.last_mut() {
+                    e.text.push_str(&text);
+                }
+            } else if rest.starts_with("<!") {
+                self.skip_past(">")?;
+            } else if rest.starts_with("</") {
+                self.pos += 2;
+                let name = self.name()?;
+                self.skip_whitespace();
+                self.skip_past(">")?;
+                let element = match stack.pop() {
+                    Some(e) if e.name == name => e,
+                    _ => return Err(self.error(&format!("unexpected end tag `{}`", name))),
+                };
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => root = Some(element),
+                }
+            } else if rest.starts_with('<') {
+                self.pos += 1;
+                let mut element = Element {
+                    name: self.name()?,
+                    ..Default::default()
+                };
+                let closed = loop {
+                    self.skip_whitespace();
+                    let rest = self.rest();
+                    if rest.starts_with("/>") {
+                        self.pos += 2;
+                        break true;
+                    } else if rest.starts_with('>') {
+                        self.pos += 1;
+                        break false;
+                    }
+                    let key = self.name()?;
+                    self.skip_whitespace();
+                    if !self.rest().starts_with('=') {
+                        return Err(self.error("`=` is expected"));
+                    }
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    let quote = match self.rest().chars().next() {
+                        Some(c @ ('"' | '\'')) => c,
+                        _ => return Err(self.error("quoted value is expected")),
+                    };
+                    self.pos += 1;
+                    let value = unescape(self.skip_past(&quote.to_string())?);
+                    element.attributes.push((key, value));
+                };
+                match (closed, stack.last_mut()) {
+                    (false, _) => stack.push(element),
+                    (true, Some(parent)) => parent.children.push(element),
+                    (true, None) => root = Some(element),
+                }
+            } else {
+                let end = rest.find('<').unwrap_or(rest.len());
+                let text = unescape(&rest[..end]);
+                self.pos += end;
+                match stack.last_mut() {
+                    Some(e) => e.text.push_str(&text),
+                    None if text.trim().is_empty() => {}
+                    None => return Err(self.error("text is out of element")),
+                }
+            }
+        }
+
+        match (stack.last(), root) {
+            (None, Some(root)) => Ok(root),
+            (Some(e), _) => Err(self.error(&format!("element `{}` is not closed", e.name))),
+            (None, None) => Err(self.error("root element is expected")),
+        }
+    }
+}
+
+/// Replace the predefined entities and character references.
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let c = match &rest[1..end] {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            v => match v.strip_prefix("#x").or_else(|| v.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => v.strip_prefix('#').and_then(|v| v.parse().ok()),
+            }
+            .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
@@ -0,0 +1,802 @@
+//! Vector ASC log file.
+//!
+//! The frame lines are the same as the `Display` of [`Frame`],
+//! the timestamps are relative to the start of measurement.
+//!
+//! The `CANXL` line is an extension of this crate rather than the CAN XL layout of Vector,
+//! so it's only read back by [`AscReader`]:
+//! `<time> CANXL <channel> <dir> <priority> <sdt> <sec> <vcid> <af> <dlc> <length> <data>`.
+//!
+//! The `CANFD` line is written with the message length in bits from SOF to EOF,
+//! which is calculated from the identifier and DLC without the dynamic stuff bits.
+//! The message duration, CRC and bit timings are not supported since they are unknown
+//! by [`Frame`], they are written as `0` and ignored by [`AscReader`].
+//!
+//! ```ignore
+//! let mut writer = AscWriter::create("trace.asc")?;
+//! for frame in device.receive(channel, Some(10)).await? {
+//!     writer.write_frame(&frame)?;
+//! }
+//! writer.finish()?;
+//!
+//! for frame in AscReader::<_, SocketCanFrame>::open("trace.asc")? {
+//!     println!("{}", frame?);
+//! }
+//! ```
+
+use crate::{
+    error::Error,
+    frame::{
+        identifier::{CanFdFlags, Id},
+        Direction, Frame, Kind, Timestamp, TimestampSource, XlParams,
+    },
+    utils, CanResult,
+};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Write as _},
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    marker::PhantomData,
+    path::Path,
+    str::FromStr,
+};
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+const NANOS_PER_DAY: u128 = 86_400 * NANOS_PER_SEC;
+/// The flags of CAN-FD line.
+const FD_FLAG_EDL: u32 = 1 << 12;
+const FD_FLAG_BRS: u32 = 1 << 13;
+const FD_FLAG_ESI: u32 = 1 << 14;
+
+/// Format the frame line without the timestamp.
+///
+/// The message duration, CRC and bit timing fields of CAN-FD line are written as `0`,
+/// see the module documentation.
+pub(crate) fn fmt_frame<C: Display>(
+    f: &mut dyn fmt::Write,
+    frame: &dyn Frame<Channel = C>,
+    channel: &dyn Display,
+) -> fmt::Result {
+    let id = frame.id();
+    let ext = if id.is_extended() { "x" } else { "" };
+    match frame.kind() {
+        _ if frame.is_error_frame() => write!(f, "{} ErrorFrame", channel),
+        Kind::Classical => {
+            write!(
+                f,
+                "{} {: >8x}{: <4} {} {} {: >2}",
+                channel,
+                id.as_raw(),
+                ext,
+                frame.direction(),
+                if frame.is_remote() { "r" } else { "d" },
+                frame.len(),
+            )?;
+            if !frame.is_remote() {
+                fmt_data(f, frame.data())?;
+            }
+            Ok(())
+        }
+        Kind::FD => {
+            let dlc = frame.dlc().map_err(|_| fmt::Error)?;
+            let mut flags = FD_FLAG_EDL;
+            if frame.is_bitrate_switch() {
+                flags |= FD_FLAG_BRS;
+            }
+            if frame.is_esi() {
+                flags |= FD_FLAG_ESI;
+            }
+            write!(
+                f,
+                "CANFD {} {} {: >8x}{} {} {} {:x} {: >2}",
+                channel,
+                frame.direction(),
+                id.as_raw(),
+                ext,
+                frame.is_bitrate_switch() as u8,
+                frame.is_esi() as u8,
+                dlc,
+                frame.len(),
+            )?;
+            fmt_data(f, frame.data())?;
+            // message_duration, message_length, flags, crc and bit timings
+            write!(
+                f,
+                " {: >8} {: >4} {: >8x} {: >8} 0 0 0 0",
+                0,
+                fd_frame_bits(id.is_extended(), dlc),
+                flags,
+                0
+            )
+        }
+        // the extension of this crate, see the module documentation
+        Kind::XL => {
+            let dlc = frame.xl_dlc().ok_or(fmt::Error)?;
+            let params = frame.xl_params().unwrap_or_default();
+            write!(
+                f,
+                "CANXL {} {} {: >8x} {:02x} {} {:02x} {:08x} {: >4} {: >4}",
+                channel,
+                frame.direction(),
+                id.as_raw(),
+                params.sdu_type,
+                params.sec as u8,
+                params.vcid,
+                params.acceptance,
+                dlc,
+                frame.len(),
+            )?;
+            fmt_data(f, frame.data())
+        }
+    }
+}
+
+/// The length of CAN-FD frame in bits from SOF to EOF, the dynamic stuff bits are not counted.
+fn fd_frame_bits(extended: bool, dlc: u8) -> u32 {
+    let length = match dlc {
+        ..=8 => dlc as u32,
+        9..=12 => (dlc as u32 - 6) * 4,
+        13 => 32,
+        14 => 48,
+        _ => 64,
+    };
+    // SOF, identifier, (SRR, IDE, identifier extension,) RRS, IDE/FDF, res, BRS, ESI and DLC
+    let header = if extended { 41 } else { 22 };
+    // stuff count, CRC and the fixed stuff bits
+    let crc = if length <= 16 { 4 + 17 + 6 } else { 4 + 21 + 7 };
+    // CRC delimiter, ACK slot, ACK delimiter and EOF
+    header + length * 8 + crc + 3 + 7
+}
+
+/// Get the channel number in file, the numeric channels are kept
+/// and the others are numbered from 1 in order of appearance.
+pub(crate) fn channel_number(
+    channels: &mut HashMap<String, usize>,
+    channel: impl Display,
+) -> usize {
+    let channel = channel.to_string();
+    match channels.get(&channel) {
+        Some(&v) => v,
+        None => {
+            let v = channel
+                .parse::<usize>()
+                .unwrap_or_else(|_| channels.len() + 1);
+            channels.insert(channel, v);
+            v
+        }
+    }
+}
+
+#[inline]
+fn fmt_data(f: &mut dyn fmt::Write, data: &[u8]) -> fmt::Result {
+    data.iter().try_for_each(|b| write!(f, " {:02x}", b))
+}
+
+/// Format the unix timestamp as the date of ASC header, e.g. `Thu Apr 27 03:14:07.123 pm 2023`.
+///
+/// The date is written in UTC.
+fn fmt_date(nanos: u128) -> String {
+    let days = (nanos / NANOS_PER_DAY) as i64;
+    let (year, month, day) = utils::civil_from_days(days);
+    let day_nanos = nanos % NANOS_PER_DAY;
+    let secs = (day_nanos / NANOS_PER_SEC) as u32;
+    let millis = (day_nanos % NANOS_PER_SEC / 1_000_000) as u32;
+    let (hour, minute, second) = (secs / 3600, secs / 60 % 60, secs % 60);
+
+    format!(
+        "{} {} {:02} {:02}:{:02}:{:02}.{:03} {} {}",
+        WEEKDAYS[(days + 4).rem_euclid(7) as usize],
+        MONTHS[month as usize - 1],
+        day,
+        match hour % 12 {
+            0 => 12,
+            v => v,
+        },
+        minute,
+        second,
+        millis,
+        if hour < 12 { "am" } else { "pm" },
+        year
+    )
+}
+
+/// Parse the date of ASC header to unix timestamp, the `am`/`pm` and milliseconds are optional.
+fn parse_date(tokens: &[&str]) -> Option<u128> {
+    // weekday month day time [am|pm] year
+    let name = *tokens.get(1)?;
+    let month = MONTHS.iter().position(|&m| m == name)? as u32 + 1;
+    let day = tokens.get(2)?.parse::<u32>().ok()?;
+    let mut times = tokens.get(3)?.split([':', '.']);
+    let mut hour = times.next()?.parse::<u128>().ok()?;
+    let minute = times.next()?.parse::<u128>().ok()?;
+    let second = times.next()?.parse::<u128>().ok()?;
+    let millis = times
+        .next()
+        .and_then(|v| v.parse::<u128>().ok())
+        .unwrap_or(0);
+    let year = match *tokens.get(4)? {
+        "am" | "pm" => {
+            hour %= 12;
+            if tokens[4] == "pm" {
+                hour += 12;
+            }
+            tokens.get(5)?
+        }
+        v => v,
+    }
+    .parse::<i64>()
+    .ok()?;
+
+    let days = u128::try_from(utils::days_from_civil(year, month, day)).ok()?;
+    Some(
+        days * NANOS_PER_DAY
+            + ((hour * 60 + minute) * 60 + second) * NANOS_PER_SEC
+            + millis * 1_000_000,
+    )
+}
+
+/// The streaming writer of ASC file.
+///
+/// The header is written with the first frame, the date is the timestamp of the first frame
+/// when it is a system timestamp, otherwise the current system time.
+/// The timestamps in file are relative to the first frame.
+pub struct AscWriter<W: Write> {
+    writer: Option<W>,
+    origin: Option<u128>,
+    channels: HashMap<String, usize>,
+    line: String,
+}
+
+impl AscWriter<BufWriter<File>> {
+    /// Create the ASC file, the file will be truncated if it exists.
+    pub fn create<P: AsRef<Path>>(path: P) -> CanResult<Self> {
+        let file = File::create(path).map_err(|e| Error::OperationError(e.to_string()))?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> AscWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Some(writer),
+            origin: None,
+            channels: Default::default(),
+            line: Default::default(),
+        }
+    }
+
+    /// Map the channel of device to the channel number in file.
+    ///
+    /// The numeric channels are written as they are,
+    /// the others are numbered from 1 in order of appearance if not mapped.
+    pub fn set_channel(&mut self, channel: impl Display, number: usize) -> &mut Self {
+        self.channels.insert(channel.to_string(), number);
+        self
+    }
+
+    pub fn write_frame<F: Frame>(&mut self, frame: &F) -> CanResult<()> {
+        let timestamp = frame.timestamp().unwrap_or_else(utils::system_timestamp);
+        if self.origin.is_none() {
+            let date = match timestamp.source {
+                TimestampSource::System => timestamp.nanos,
+                _ => utils::system_timestamp().nanos,
+            };
+            self.write_header(date)?;
+            self.origin = Some(timestamp.nanos);
+        }
+
+        let number = channel_number(&mut self.channels, frame.channel());
+
+        let offset = timestamp
+            .nanos
+            .saturating_sub(self.origin.unwrap_or_default());
+        self.line.clear();
+        let _ = write!(
+            self.line,
+            "{: >11.6} ",
+            offset as f64 / NANOS_PER_SEC as f64
+        );
+        fmt_frame(&mut self.line, frame, &number)
+            .map_err(|_| Error::InvalidFrame(format!("{:?}", frame.id())))?;
+        self.line.push('\n');
+
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or(Error::operation_error("writer is finished"))?;
+        writer
+            .write_all(self.line.as_bytes())
+            .map_err(|e| Error::OperationError(e.to_string()))
+    }
+
+    pub fn flush(&mut self) -> CanResult<()> {
+        self.writer()?
+            .flush()
+            .map_err(|e| Error::OperationError(e.to_string()))
+    }
+
+    /// Write the end of file and return the inner writer.
+    pub fn finish(mut self) -> CanResult<W> {
+        self.write_footer()?;
+        self.writer
+            .take()
+            .ok_or(Error::operation_error("writer is finished"))
+    }
+
+    fn writer(&mut self) -> CanResult<&mut W> {
+        self.writer
+            .as_mut()
+            .ok_or(Error::operation_error("writer is finished"))
+    }
+
+    fn write_header(&mut self, date: u128) -> CanResult<()> {
+        let date = fmt_date(date);
+        write!(
+            self.writer()?,
+            "date {date}\n\
+             base hex  timestamps absolute\n\
+             internal events logged\n\
+             // version 9.0.0\n\
+             Begin Triggerblock {date}\n\
+             {: >11.6} Start of measurement\n",
+            0.
+        )
+        .map_err(|e| Error::OperationError(e.to_string()))
+    }
+
+    fn write_footer(&mut self) -> CanResult<()> {
+        if self.origin.is_none() {
+            self.write_header(utils::system_timestamp().nanos)?;
+            self.origin = Some(0);
+        }
+
+        let writer = self.writer()?;
+        writer
+            .write_all(b"End TriggerBlock\n")
+            .and_then(|_| writer.flush())
+            .map_err(|e| Error::OperationError(e.to_string()))
+    }
+}
+
+impl<W: Write> Drop for AscWriter<W> {
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            if let Err(e) = self.write_footer() {
+                rsutil::warn!("RUST-CAN - {} when ASC writer dropped", e);
+            }
+        }
+    }
+}
+
+/// The streaming reader of ASC file, yields the frames in file.
+///
+/// The channel of frame is parsed from the channel number in file.
+/// The timestamp of frame is the date in header plus the offset if the date is present,
+/// otherwise the offset only. The error frames and events are skipped.
+pub struct AscReader<R, F> {
+    reader: R,
+    line: String,
+    line_no: usize,
+    hex: bool,
+    relative: bool,
+    start: Option<u128>,
+    elapsed: f64,
+    _frame: PhantomData<fn() -> F>,
+}
+
+impl<F> AscReader<BufReader<File>, F> {
+    pub fn open<P: AsRef<Path>>(path: P) -> CanResult<Self> {
+        let file = File::open(path).map_err(|e| Error::OperationError(e.to_string()))?;
+        Ok(Self::new(BufReader::new(file)))
+    }
+}
+
+impl<R: BufRead, F> AscReader<R, F> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: Default::default(),
+            line_no: 0,
+            hex: true,
+            relative: false,
+            start: None,
+            elapsed: 0.,
+            _frame: PhantomData,
+        }
+    }
+
+    /// The unix timestamp(ns) of the start of measurement, available after the header is read.
+    #[inline]
+    pub fn start_time(&self) -> Option<u128> {
+        self.start
+    }
+}
+
+impl<R: BufRead, F: Frame> AscReader<R, F>
+where
+    F::Channel: FromStr,
+{
+    fn parse_line(&mut self) -> CanResult<Option<F>> {
+        let line = std::mem::take(&mut self.line);
+        let result = self.parse_tokens(&line.split_whitespace().collect::<Vec<_>>());
+        self.line = line;
+
+        result.map_err(|e| match e {
+            Error::InvalidFrame(msg) => Error::InvalidFrame(format!(
+                "{} at line {}: `{}`",
+                msg,
+                self.line_no,
+                self.line.trim_end()
+            )),
+            e => e,
+        })
+    }
+
+    fn parse_tokens(&mut self, tokens: &[&str]) -> CanResult<Option<F>> {
+        match tokens.first() {
+            Some(&"date") => {
+                self.start = parse_date(&tokens[1..]);
+                return Ok(None);
+            }
+            Some(&"base") => {
+                self.hex = tokens.get(1) != Some(&"dec");
+                self.relative = tokens.contains(&"relative");
+                return Ok(None);
+            }
+            _ => {}
+        }
+
+        let Some(secs) = tokens.first().and_then(|v| v.parse::<f64>().ok()) else {
+            return Ok(None);
+        };
+        let offset = if self.relative {
+            self.elapsed += secs;
+            self.elapsed
+        } else {
+            secs
+        };
+
+        let frame = match tokens.get(1) {
+            Some(&"CANFD") => self.parse_fd(&tokens[2..])?,
+            Some(&"CANXL") => self.parse_xl(&tokens[2..])?,
+            // the named channels are written by `Display for dyn Frame`
+            Some(v)
+                if v.parse::<usize>().is_ok()
+                    || tokens
+                        .get(3)
+                        .is_some_and(|v| parse_direction(Some(v)).is_ok()) =>
+            {
+                self.parse_can(&tokens[1..])?
+            }
+            _ => None,
+        };
+
+        Ok(frame.map(|mut frame| {
+            frame.set_timestamp(Some(Timestamp {
+                nanos: self.start.unwrap_or_default() + (offset * 1e9).round() as u128,
+                source: match self.start {
+                    Some(_) => TimestampSource::System,
+                    None => TimestampSource::Unknown,
+                },
+            }));
+            frame
+        }))
+    }
+
+    /// `<channel> <id> <dir> d <dlc> <data>` or `<channel> <id> <dir> r [dlc]`
+    fn parse_can(&self, tokens: &[&str]) -> CanResult<Option<F>> {
+        let Some(id) = tokens.get(1).and_then(|v| self.parse_id(v)) else {
+            // error frames and events
+            return Ok(None);
+        };
+        let direction = parse_direction(tokens.get(2))?;
+
+        let mut frame = match tokens.get(3) {
+            Some(&"d") => {
+                let dlc = parse_hex(tokens.get(4))? as usize;
+                F::new_can(id, &self.parse_data(&tokens[5..], dlc)?)?
+            }
+            Some(&"r") => {
+                let dlc = tokens.get(4).map_or(Ok(0), |v| parse_hex(Some(v)))?;
+                F::new_remote(id, dlc as u8)?
+            }
+            _ => return Err(Error::InvalidFrame("invalid frame type".into())),
+        };
+        frame
+            .set_channel(utils::parse_channel(tokens[0])?)
+            .set_direction(direction);
+
+        Ok(Some(frame))
+    }
+
+    /// `<channel> <dir> <id> [name] <brs> <esi> <dlc> <length> <data> ...`
+    fn parse_fd(&self, tokens: &[&str]) -> CanResult<Option<F>> {
+        let Some(id) = tokens.get(2).and_then(|v| self.parse_id(v)) else {
+            return Ok(None);
+        };
+        let direction = parse_direction(tokens.get(1))?;
+        // skip the symbolic name
+        let flags = match tokens.get(3) {
+            Some(&"0") | Some(&"1") => &tokens[3..],
+            _ => tokens.get(4..).unwrap_or_default(),
+        };
+        if flags.len() < 4 {
+            return Err(Error::InvalidFrame("too few fields".into()));
+        }
+
+        let mut fd_flags = CanFdFlags::empty();
+        fd_flags.set(CanFdFlags::BRS, flags[0] == "1");
+        fd_flags.set(CanFdFlags::ESI, flags[1] == "1");
+        let length = flags[3]
+            .parse::<usize>()
+            .map_err(|_| Error::InvalidFrame("invalid data length".into()))?;
+        let data = self.parse_data(&flags[4..], length)?;
+        // the message duration, message length, flags, CRC and bit timings are ignored,
+        // the flags are carried by <brs> and <esi> already.
+
+        let mut frame = F::new_can_fd(id, &data, fd_flags)?;
+        frame
+            .set_channel(utils::parse_channel(tokens[0])?)
+            .set_direction(direction);
+
+        Ok(Some(frame))
+    }
+
+    /// `<channel> <dir> <priority> <sdt> <sec> <vcid> <af> <dlc> <length> <data>`,
+    /// the line written by this crate only.
+    fn parse_xl(&self, tokens: &[&str]) -> CanResult<Option<F>> {
+        if tokens.len() < 9 {
+            return Err(Error::InvalidFrame("too few fields".into()));
+        }
+        let direction = parse_direction(tokens.get(1))?;
+        let priority = u16::from_str_radix(tokens[2], 16)
+            .map_err(|_| Error::InvalidFrame("invalid priority id".into()))?;
+        let params = XlParams {
+            sdu_type: parse_hex(tokens.get(3))? as u8,
+            sec: tokens[4] == "1",
+            vcid: parse_hex(tokens.get(5))? as u8,
+            acceptance: parse_hex(tokens.get(6))?,
+        };
+        let length = tokens[8]
+            .parse::<usize>()
+            .map_err(|_| Error::InvalidFrame("invalid data length".into()))?;
+        let data = self.parse_data(&tokens[9..], length)?;
+
+        let mut frame = F::new_can_xl(priority, &data, params)?;
+        frame
+            .set_channel(utils::parse_channel(tokens[0])?)
+            .set_direction(direction);
+
+        Ok(Some(frame))
+    }
+
+    fn parse_id(&self, token: &str) -> Option<Id> {
+        let (raw, extended) = match token.strip_suffix('x') {
+            Some(v) => (v, true),
+            None => (token, false),
+        };
+        let raw = u32::from_str_radix(raw, if self.hex { 16 } else { 10 }).ok()?;
+
+        Id::from_bits(raw, Some(extended)).ok()
+    }
+
+    fn parse_data(&self, tokens: &[&str], length: usize) -> CanResult<Vec<u8>> {
+        if tokens.len() < length {
+            return Err(Error::InvalidFrame("too few data bytes".into()));
+        }
+
+        tokens[..length]
+            .iter()
+            .map(|v| {
+                u8::from_str_radix(v, if self.hex { 16 } else { 10 })
+                    .map_err(|_| Error::InvalidFrame(format!("invalid data byte `{}`", v)))
+            })
+            .collect()
+    }
+}
+
+impl<R: BufRead, F: Frame> Iterator for AscReader<R, F>
+where
+    F::Channel: FromStr,
+{
+    type Item = CanResult<F>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => self.line_no += 1,
+                Err(e) => return Some(Err(Error::OperationError(e.to_string()))),
+            }
+
+            match self.parse_line() {
+                Ok(Some(frame)) => return Some(Ok(frame)),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[inline]
+fn parse_hex(token: Option<&&str>) -> CanResult<u32> {
+    token
+        .and_then(|v| u32::from_str_radix(v, 16).ok())
+        .ok_or(Error::InvalidFrame(format!(
+            "invalid hex value `{:?}`",
+            token
+        )))
+}
+
+#[inline]
+fn parse_direction(token: Option<&&str>) -> CanResult<Direction> {
+    match token {
+        Some(&"Rx") => Ok(Direction::Receive),
+        Some(&"Tx") | Some(&"TxRq") => Ok(Direction::Transmit),
+        _ => Err(Error::InvalidFrame("invalid direction".into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        frame::identifier::{ExtendedId, StandardId},
+        virtual_bus::tests::TestFrame,
+    };
+
+    fn timestamp(nanos: u128) -> Option<Timestamp> {
+        Some(Timestamp {
+            nanos,
+            source: TimestampSource::System,
+        })
+    }
+
+    #[test]
+    fn date_round_trip() {
+        // Thu Apr 27 15:14:07.123 2023 UTC
+        let nanos = 1_682_608_447_123_000_000;
+        let date = fmt_date(nanos);
+        assert_eq!(date, "Thu Apr 27 03:14:07.123 pm 2023");
+        let tokens = date.split_whitespace().collect::<Vec<_>>();
+        assert_eq!(parse_date(&tokens), Some(nanos));
+        assert_eq!(
+            parse_date(&["Thu", "Apr", "27", "15:14:07", "2023"]),
+            Some(1_682_608_447_000_000_000)
+        );
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let start = 1_682_608_447_123_000_000;
+        let mut frames = Vec::new();
+
+        let mut frame =
+            TestFrame::new_can(Id::Standard(StandardId::new(0x123).unwrap()), &[0x01, 0x02])
+                .unwrap();
+        frame
+            .set_channel("1".into())
+            .set_direction(Direction::Receive)
+            .set_timestamp(timestamp(start));
+        frames.push(frame);
+
+        let mut frame =
+            TestFrame::new_remote(Id::Extended(ExtendedId::new(0x1234_5678).unwrap()), 4).unwrap();
+        frame
+            .set_channel("2".into())
+            .set_timestamp(timestamp(start + 1_500_000));
+        frames.push(frame);
+
+        let mut frame = TestFrame::new_can_fd(
+            Id::Extended(ExtendedId::new(0x18DA_F110).unwrap()),
+            &[0x55; 12],
+            CanFdFlags::BRS,
+        )
+        .unwrap();
+        frame
+            .set_channel("1".into())
+            .set_direction(Direction::Receive)
+            .set_timestamp(timestamp(start + 2_000_000));
+        frames.push(frame);
+
+        let params = XlParams {
+            sdu_type: 0x03,
+            vcid: 0x12,
+            acceptance: 0xCAFE_BABE,
+            sec: true,
+        };
+        let mut frame = TestFrame::new_can_xl(0x7FF, &[0xA5; 100], params).unwrap();
+        frame
+            .set_channel("3".into())
+            .set_timestamp(timestamp(start + 3_000_001_000));
+        frames.push(frame);
+
+        let mut writer = AscWriter::new(Vec::new());
+        frames.iter().for_each(|f| writer.write_frame(f).unwrap());
+        let content = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert!(content.starts_with("date Thu Apr 27 03:14:07.123 pm 2023\n"));
+        assert!(content.contains("Begin Triggerblock"));
+        assert!(content.contains("   0.001500 2 12345678x    Tx r  4\n"));
+        // the message length of extended CAN-FD frame with 12 bytes
+        assert!(content.contains(" 55        0  174     3000        0 0 0 0 0\n"));
+        assert!(content.ends_with("End TriggerBlock\n"));
+
+        let mut reader = AscReader::<_, TestFrame>::new(content.as_bytes());
+        let results = reader.by_ref().collect::<CanResult<Vec<_>>>().unwrap();
+        assert_eq!(reader.start_time(), Some(start));
+        assert_eq!(results.len(), frames.len());
+        for (expect, frame) in frames.iter().zip(results.iter()) {
+            assert_eq!(frame.id(), expect.id());
+            assert_eq!(frame.kind(), expect.kind());
+            assert_eq!(frame.format(), expect.format());
+            assert_eq!(frame.channel(), expect.channel());
+            assert_eq!(frame.direction(), expect.direction());
+            assert_eq!(frame.data(), expect.data());
+            assert_eq!(frame.is_bitrate_switch(), expect.is_bitrate_switch());
+            assert_eq!(frame.xl_params(), expect.xl_params());
+            assert_eq!(frame.timestamp(), expect.timestamp());
+        }
+    }
+
+    #[test]
+    fn named_channel_round_trip() {
+        let mut frame =
+            TestFrame::new_can(Id::Standard(StandardId::new(0x123).unwrap()), &[0x01, 0x02])
+                .unwrap();
+        frame
+            .set_channel("can0".into())
+            .set_direction(Direction::Receive)
+            .set_timestamp(timestamp(123_000_000));
+        let frame = &frame as &dyn Frame<Channel = String>;
+        let content = format!("{}\n   0.200 can0 Start of measurement\n", frame);
+
+        let mut reader = AscReader::<_, TestFrame>::new(content.as_bytes());
+        let result = reader.next().unwrap().unwrap();
+        assert_eq!(result.channel(), "can0");
+        assert_eq!(result.id(), frame.id());
+        assert_eq!(result.data(), frame.data());
+        assert_eq!(result.direction(), Direction::Receive);
+        assert_eq!(result.timestamp().unwrap().nanos, 123_000_000);
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn reader_accepts_vector_lines() {
+        let content = "\
+date Mon Jan 02 03:04:05.000 am 2023
+base dec  timestamps relative
+Begin Triggerblock Mon Jan 02 03:04:05.000 am 2023
+   0.000000 Start of measurement
+   0.010000 1  291             Rx   d 2 1 255  Length = 0 BitCount = 0 ID = 291
+   0.010000 1  ErrorFrame
+   0.010000 1  Statistic: D 0 R 0 XD 0 XR 0 E 0 O 0 B 0.00%
+   0.010000 CANFD   2 Tx        256x  Name  0 1 9 12 0 1 2 3 4 5 6 7 8 9 10 11        0    0     3000        0 0 0 0 0
+   0.010000 1  291             Rx   d 2 1
+End TriggerBlock
+";
+        let mut reader = AscReader::<_, TestFrame>::new(content.as_bytes());
+
+        let frame = reader.next().unwrap().unwrap();
+        assert_eq!(frame.id(), Id::Standard(StandardId::new(0x123).unwrap()));
+        assert_eq!(frame.data(), &[0x01, 0xFF]);
+        assert_eq!(frame.timestamp().unwrap().nanos % 1_000_000_000, 10_000_000);
+
+        let frame = reader.next().unwrap().unwrap();
+        assert_eq!(frame.kind(), Kind::FD);
+        assert_eq!(frame.id(), Id::Extended(ExtendedId::new(0x100).unwrap()));
+        assert_eq!(frame.channel(), "2");
+        assert_eq!(frame.direction(), Direction::Transmit);
+        assert_eq!(frame.len(), 12);
+        // the timestamps are relative to previous
+        assert_eq!(frame.timestamp().unwrap().nanos % 1_000_000_000, 40_000_000);
+
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+}
@@ -0,0 +1,357 @@
+use crate::{error::Error, CanResult};
+use serde::{Deserialize, Serialize};
+
+/// The maximum bitrate error in one-tenth of a percent.
+const MAX_BITRATE_ERROR: u64 = 5;
+
+/// The limits of bit-timing registers of a CAN controller,
+/// the segments are in time quanta.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitTimingConst {
+    /// The propagation segment plus the phase segment 1.
+    pub tseg1_min: u32,
+    pub tseg1_max: u32,
+    /// The phase segment 2.
+    pub tseg2_min: u32,
+    pub tseg2_max: u32,
+    pub sjw_max: u32,
+    /// The prescaler of controller clock.
+    pub brp_min: u32,
+    pub brp_max: u32,
+    pub brp_inc: u32,
+}
+
+/// The bit-timing of nominal or data phase, the segments are in time quanta.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BitTiming {
+    pub brp: u32,
+    pub tseg1: u32,
+    pub tseg2: u32,
+    pub sjw: u32,
+}
+
+impl BitTiming {
+    /// The sample point recommended by CiA in one-tenth of a percent.
+    pub fn default_sample_point(bitrate: u32) -> u16 {
+        match bitrate {
+            v if v > 800_000 => 750,
+            v if v > 500_000 => 800,
+            _ => 875,
+        }
+    }
+
+    /// Calculate the bit-timing from controller `clock`(Hz), `bitrate`
+    /// and `sample_point`(in one-tenth of a percent, e.g. 875 for 87.5%).
+    ///
+    /// The timing with the minimum bitrate error is preferred,
+    /// then the one which is the nearest to the sample point and has the most time quanta.
+    pub fn calculate(
+        clock: u32,
+        bitrate: u32,
+        sample_point: Option<u16>,
+        limits: &BitTimingConst,
+    ) -> CanResult<Self> {
+        if clock == 0 || bitrate == 0 {
+            return Err(Error::other_error(format!(
+                "invalid clock: {} or bitrate: {}",
+                clock, bitrate
+            )));
+        }
+        let sample_point = sample_point.unwrap_or_else(|| Self::default_sample_point(bitrate));
+        if !(1..1000).contains(&sample_point) {
+            return Err(Error::other_error(format!(
+                "sample point: {} is out of range",
+                sample_point
+            )));
+        }
+
+        let (clock, bitrate) = (clock as u64, bitrate as u64);
+        let tq_min = 1 + limits.tseg1_min + limits.tseg2_min;
+        let tq_max = 1 + limits.tseg1_max + limits.tseg2_max;
+        let brp_inc = limits.brp_inc.max(1);
+        let mut best: Option<(u64, u32, Self)> = None;
+        for tq in (tq_min..=tq_max).rev() {
+            let bit_clock = bitrate * tq as u64;
+            let mut brp = ((clock + bit_clock / 2) / bit_clock) as u32;
+            brp = (brp / brp_inc) * brp_inc;
+            if brp < limits.brp_min || brp > limits.brp_max {
+                continue;
+            }
+            let rate_error = bitrate.abs_diff(clock / (brp as u64 * tq as u64));
+            if best.is_some_and(|(e, _, _)| rate_error > e) {
+                continue;
+            }
+
+            let Some(timing) = Self::split(tq, brp, sample_point, limits) else {
+                continue;
+            };
+            let sp_error = sample_point.abs_diff(timing.sample_point()) as u32;
+            if best.map_or(true, |(e, s, _)| (rate_error, sp_error) < (e, s)) {
+                best = Some((rate_error, sp_error, timing));
+            }
+            if rate_error == 0 && sp_error == 0 {
+                break;
+            }
+        }
+
+        let (rate_error, _, timing) = best.ok_or(Error::other_error(format!(
+            "bitrate: {} is not reachable with clock: {}",
+            bitrate, clock
+        )))?;
+        if rate_error * 1000 > bitrate * MAX_BITRATE_ERROR {
+            return Err(Error::other_error(format!(
+                "bitrate: {} is not reachable with clock: {}, the nearest is {}",
+                bitrate,
+                clock,
+                timing.bitrate(clock as u32)
+            )));
+        }
+        if rate_error > 0 {
+            rsutil::warn!(
+                "RUST-CAN - bitrate: {} is approximated to {}",
+                bitrate,
+                timing.bitrate(clock as u32)
+            );
+        }
+
+        Ok(timing)
+    }
+
+    /// The time quanta of a bit.
+    #[inline]
+    pub fn bit_tq(&self) -> u32 {
+        1 + self.tseg1 + self.tseg2
+    }
+
+    /// The actual bitrate with controller `clock`(Hz).
+    #[inline]
+    pub fn bitrate(&self, clock: u32) -> u32 {
+        clock / (self.brp * self.bit_tq())
+    }
+
+    /// The sample point in one-tenth of a percent.
+    #[inline]
+    pub fn sample_point(&self) -> u16 {
+        ((1 + self.tseg1) * 1000 / self.bit_tq()) as u16
+    }
+
+    /// The transmitter delay compensation offset in controller clock periods,
+    /// it's only applicable when the prescaler is 1 or 2(ISO 11898-1).
+    pub fn tdc_offset(&self, tdco_max: u32) -> Option<u32> {
+        match self.brp {
+            1 | 2 => Some((self.brp * (1 + self.tseg1)).min(tdco_max)),
+            _ => None,
+        }
+    }
+
+    /// Check the timing against the limits of controller.
+    pub fn validate(&self, limits: &BitTimingConst) -> CanResult<()> {
+        fn check(name: &str, value: u32, min: u32, max: u32) -> CanResult<()> {
+            match (min..=max).contains(&value) {
+                true => Ok(()),
+                false => Err(Error::other_error(format!(
+                    "{}: {} is out of range {}..={}",
+                    name, value, min, max
+                ))),
+            }
+        }
+
+        check("brp", self.brp, limits.brp_min, limits.brp_max)?;
+        check("tseg1", self.tseg1, limits.tseg1_min, limits.tseg1_max)?;
+        check("tseg2", self.tseg2, limits.tseg2_min, limits.tseg2_max)?;
+        check("sjw", self.sjw, 1, limits.sjw_max.min(self.tseg2))
+    }
+
+    /// Split the time quanta at the sample position, `None` if the segments are out of limits.
+    fn split(tq: u32, brp: u32, sample_point: u16, limits: &BitTimingConst) -> Option<Self> {
+        let position = tq * sample_point as u32 / 1000;
+        [position, position + 1]
+            .into_iter()
+            .filter_map(|position| {
+                let tseg1 = position
+                    .saturating_sub(1)
+                    .clamp(limits.tseg1_min, limits.tseg1_max);
+                let tseg2 = tq.checked_sub(1 + tseg1)?;
+                (limits.tseg2_min..=limits.tseg2_max)
+                    .contains(&tseg2)
+                    .then_some(Self {
+                        brp,
+                        tseg1,
+                        tseg2,
+                        sjw: tseg2.min(limits.sjw_max),
+                    })
+            })
+            .min_by_key(|timing| sample_point.abs_diff(timing.sample_point()))
+    }
+}
+
+/// The bit-timing configuration of nominal or data phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BitTimingConfig {
+    /// Calculated from the bitrate of phase and the `sample_point`(in one-tenth of a percent),
+    /// the SJW is the phase segment 2 if not set.
+    SamplePoint { sample_point: u16, sjw: Option<u32> },
+    /// The explicit segments, they must match the bitrate of phase.
+    Segments(BitTiming),
+}
+
+impl BitTimingConfig {
+    /// Resolve the bit-timing of phase with `bitrate` and the limits of controller.
+    ///
+    /// The `clock`(Hz) is required by [`BitTimingConfig::SamplePoint`],
+    /// the bitrate of [`BitTimingConfig::Segments`] is not checked without it.
+    pub fn resolve(
+        &self,
+        clock: Option<u32>,
+        bitrate: u32,
+        limits: &BitTimingConst,
+    ) -> CanResult<BitTiming> {
+        match *self {
+            Self::SamplePoint { sample_point, sjw } => {
+                let clock = clock.ok_or(Error::other_error(
+                    "the clock of controller is required by sample point",
+                ))?;
+                let mut timing = BitTiming::calculate(clock, bitrate, Some(sample_point), limits)?;
+                if let Some(sjw) = sjw {
+                    timing.sjw = sjw;
+                    timing.validate(limits)?;
+                }
+                Ok(timing)
+            }
+            Self::Segments(timing) => {
+                timing.validate(limits)?;
+                if let Some(clock) = clock {
+                    let actual = timing.bitrate(clock);
+                    if (bitrate.abs_diff(actual) as u64) * 1000 > bitrate as u64 * MAX_BITRATE_ERROR
+                    {
+                        return Err(Error::other_error(format!(
+                            "the bitrate of {:?} is {} with clock: {}, but {} is expected",
+                            timing, actual, clock, bitrate
+                        )));
+                    }
+                }
+                Ok(timing)
+            }
+        }
+    }
+}
+
+/// The transmitter delay compensation of CAN-FD data phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum TdcConfig {
+    Disabled,
+    /// The delay is measured by controller, and the offset is from the data bit-timing.
+    Auto,
+    /// The delay `value` and `offset` in controller clock periods.
+    Manual {
+        value: u32,
+        offset: u32,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SJA1000: BitTimingConst = BitTimingConst {
+        tseg1_min: 1,
+        tseg1_max: 16,
+        tseg2_min: 1,
+        tseg2_max: 8,
+        sjw_max: 4,
+        brp_min: 1,
+        brp_max: 64,
+        brp_inc: 1,
+    };
+
+    const MCAN_DATA: BitTimingConst = BitTimingConst {
+        tseg1_min: 1,
+        tseg1_max: 32,
+        tseg2_min: 1,
+        tseg2_max: 16,
+        sjw_max: 16,
+        brp_min: 1,
+        brp_max: 32,
+        brp_inc: 1,
+    };
+
+    #[test]
+    fn bit_timing_is_calculated() {
+        let timing = BitTiming::calculate(8_000_000, 500_000, None, &SJA1000).unwrap();
+        assert_eq!(
+            timing,
+            BitTiming {
+                brp: 1,
+                tseg1: 13,
+                tseg2: 2,
+                sjw: 2
+            }
+        );
+        assert_eq!(timing.sample_point(), 875);
+
+        let timing = BitTiming::calculate(8_000_000, 125_000, Some(750), &SJA1000).unwrap();
+        assert_eq!(timing.bitrate(8_000_000), 125_000);
+        assert_eq!(timing.sample_point(), 750);
+        assert!(timing.validate(&SJA1000).is_ok());
+
+        let timing = BitTiming::calculate(60_000_000, 2_000_000, None, &MCAN_DATA).unwrap();
+        assert_eq!(timing.bitrate(60_000_000), 2_000_000);
+        assert_eq!(timing.bit_tq(), 30);
+        assert_eq!(timing.tdc_offset(127), Some(timing.tseg1 + 1));
+    }
+
+    #[test]
+    fn bit_timing_config_is_resolved() {
+        let cfg = BitTimingConfig::SamplePoint {
+            sample_point: 750,
+            sjw: Some(1),
+        };
+        let timing = cfg.resolve(Some(8_000_000), 250_000, &SJA1000).unwrap();
+        assert_eq!((timing.sample_point(), timing.sjw), (750, 1));
+        assert!(cfg.resolve(None, 250_000, &SJA1000).is_err());
+
+        let cfg = BitTimingConfig::SamplePoint {
+            sample_point: 875,
+            sjw: Some(8),
+        };
+        assert!(cfg.resolve(Some(8_000_000), 500_000, &SJA1000).is_err());
+
+        let cfg = BitTimingConfig::Segments(BitTiming {
+            brp: 1,
+            tseg1: 13,
+            tseg2: 2,
+            sjw: 1,
+        });
+        assert!(cfg.resolve(Some(8_000_000), 500_000, &SJA1000).is_ok());
+        assert!(cfg.resolve(None, 250_000, &SJA1000).is_ok());
+        assert!(cfg.resolve(Some(8_000_000), 250_000, &SJA1000).is_err());
+    }
+
+    #[test]
+    fn invalid_bit_timing_is_rejected() {
+        assert!(BitTiming::calculate(8_000_000, 0, None, &SJA1000).is_err());
+        assert!(BitTiming::calculate(8_000_000, 500_000, Some(1000), &SJA1000).is_err());
+        assert!(BitTiming::calculate(8_000_000, 3_000_000, None, &SJA1000).is_err());
+        assert!(BitTiming::calculate(0, 500_000, None, &SJA1000).is_err());
+        assert!(BitTiming::calculate(8_000_000, 500_000, Some(0), &SJA1000).is_err());
+        assert!(BitTiming::calculate(8_000_000, 500_000, Some(1000), &SJA1000).is_err());
+        assert!(BitTiming::calculate(8_000_000, 1_000, None, &SJA1000).is_err());
+
+        // the sample position rounds to the first time quantum
+        for sample_point in [50, 100, 200] {
+            let timing =
+                BitTiming::calculate(8_000_000, 500_000, Some(sample_point), &SJA1000).unwrap();
+            assert!(timing.validate(&SJA1000).is_ok());
+            assert_eq!(timing.bitrate(8_000_000), 500_000);
+            assert_eq!(timing.sample_point(), 250);
+        }
+
+        let timing = BitTiming {
+            brp: 1,
+            tseg1: 17,
+            tseg2: 2,
+            sjw: 1,
+        };
+        assert!(timing.validate(&SJA1000).is_err());
+    }
+}
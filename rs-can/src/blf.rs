@@ -0,0 +1,996 @@
+//! Vector BLF(binary logging format) file.
+//!
+//! Supports the `CAN_MESSAGE2`, `CAN_FD_MESSAGE_64` and `CAN_ERROR_EXT` objects,
+//! the objects are written into zlib compressed `LOG_CONTAINER`s.
+//! The `CAN_MESSAGE` and `CAN_FD_MESSAGE` objects are accepted when reading.
+//!
+//! ```ignore
+//! let mut writer = BlfWriter::create("trace.blf")?;
+//! for frame in device.receive(channel, Some(10)).await? {
+//!     writer.write_frame(&frame)?;
+//! }
+//! writer.finish()?;
+//!
+//! for frame in BlfReader::<_, SocketCanFrame>::open("trace.blf")? {
+//!     println!("{}", frame?);
+//! }
+//! ```
+
+use crate::{
+    asc,
+    error::Error,
+    frame::{
+        identifier::{CanFdFlags, Id},
+        Direction, Frame, Kind, Timestamp, TimestampSource,
+    },
+    utils, CanResult, MAX_FRAME_SIZE,
+};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    ops::Range,
+    path::Path,
+    str::FromStr,
+};
+
+const FILE_SIGNATURE: &[u8; 4] = b"LOGG";
+const OBJ_SIGNATURE: &[u8; 4] = b"LOBJ";
+const FILE_HEADER_SIZE: usize = 144;
+/// The offset of start time(`SYSTEMTIME`) in file header.
+const START_TIME_OFFSET: usize = 40;
+const OBJ_HEADER_BASE_SIZE: usize = 16;
+const OBJ_HEADER_V1_SIZE: usize = 32;
+const LOG_CONTAINER_HEADER_SIZE: usize = 16;
+/// The uncompressed size of objects in a container.
+const MAX_CONTAINER_SIZE: usize = 128 * 1024;
+const APPLICATION_ID: u8 = 5;
+const BIN_LOG_VERSION: [u8; 4] = [2, 6, 8, 1];
+
+/// The object types.
+const CAN_MESSAGE: u32 = 1;
+const LOG_CONTAINER: u32 = 10;
+const CAN_ERROR_EXT: u32 = 73;
+const CAN_MESSAGE2: u32 = 86;
+const CAN_FD_MESSAGE: u32 = 100;
+const CAN_FD_MESSAGE_64: u32 = 101;
+
+/// The compression methods of `LOG_CONTAINER`.
+const NO_COMPRESSION: u16 = 0;
+const ZLIB_DEFLATE: u16 = 2;
+
+/// The object flags of timestamp unit.
+const TIME_TEN_MICS: u32 = 0x01;
+const TIME_ONE_NANS: u32 = 0x02;
+
+const CAN_MSG_EXT: u32 = 0x8000_0000;
+const CAN_MSG_FLAG_TX: u8 = 0x01;
+const CAN_MSG_FLAG_REMOTE: u8 = 0x80;
+/// The flags of `CAN_FD_MESSAGE`.
+const CAN_FD_FLAG_EDL: u8 = 0x01;
+const CAN_FD_FLAG_BRS: u8 = 0x02;
+const CAN_FD_FLAG_ESI: u8 = 0x04;
+/// The flags of `CAN_FD_MESSAGE_64`.
+const CAN_FD64_FLAG_REMOTE: u32 = 0x0010;
+const CAN_FD64_FLAG_EDL: u32 = 0x1000;
+const CAN_FD64_FLAG_BRS: u32 = 0x2000;
+const CAN_FD64_FLAG_ESI: u32 = 0x4000;
+
+const NANOS_PER_MILLI: u128 = 1_000_000;
+const NANOS_PER_DAY: u128 = 86_400_000 * NANOS_PER_MILLI;
+
+#[inline]
+fn u16_at(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+#[inline]
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+#[inline]
+fn u64_at(buf: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap())
+}
+
+/// The objects are padded with `size % 4` bytes except `CAN_FD_MESSAGE_64`.
+#[inline]
+fn padding_size(obj_type: u32, obj_size: usize) -> usize {
+    match obj_type {
+        CAN_FD_MESSAGE_64 => 0,
+        _ => obj_size % 4,
+    }
+}
+
+/// Read `size` bytes, the buffer grows with the bytes read instead of being allocated
+/// by the untrusted size.
+fn read_sized<R: Read>(reader: &mut R, size: usize) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader.take(size as u64).read_to_end(&mut data)?;
+    match data.len() == size {
+        true => Ok(data),
+        false => Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+}
+
+#[inline]
+fn io_error(e: io::Error) -> Error {
+    Error::OperationError(e.to_string())
+}
+
+/// Convert the unix timestamp to `SYSTEMTIME` in UTC.
+fn to_system_time(nanos: u128) -> [u16; 8] {
+    let days = (nanos / NANOS_PER_DAY) as i64;
+    let (year, month, day) = utils::civil_from_days(days);
+    let millis = (nanos % NANOS_PER_DAY / NANOS_PER_MILLI) as u32;
+    let secs = millis / 1000;
+
+    [
+        year as u16,
+        month as u16,
+        (days + 4).rem_euclid(7) as u16,
+        day as u16,
+        (secs / 3600) as u16,
+        (secs / 60 % 60) as u16,
+        (secs % 60) as u16,
+        (millis % 1000) as u16,
+    ]
+}
+
+/// Convert the `SYSTEMTIME` to unix timestamp, `None` if the time is not set.
+fn from_system_time(time: &[u16; 8]) -> Option<u128> {
+    let [year, month, _, day, hour, minute, second, millis] = time.map(u128::from);
+    if year == 0 || !(1..=12).contains(&month) {
+        return None;
+    }
+
+    let days = utils::days_from_civil(year as i64, month as u32, day as u32);
+    let millis = ((hour * 60 + minute) * 60 + second) * 1000 + millis;
+    Some(u128::try_from(days).ok()? * NANOS_PER_DAY + millis * NANOS_PER_MILLI)
+}
+
+#[inline]
+fn raw_id(id: Id) -> u32 {
+    match id {
+        Id::Standard(_) => id.as_raw(),
+        Id::Extended(_) => id.as_raw() | CAN_MSG_EXT,
+    }
+}
+
+#[inline]
+fn parse_id(raw: u32) -> CanResult<Id> {
+    Id::from_bits(raw & !CAN_MSG_EXT, Some(raw & CAN_MSG_EXT != 0))
+}
+
+/// The streaming writer of BLF file.
+///
+/// The start time of file is the timestamp of the first frame when it is a system timestamp,
+/// otherwise the current system time. The timestamps of objects are relative to the first frame.
+/// The file header is updated when the writer is finished.
+pub struct BlfWriter<W: Write + Seek> {
+    writer: Option<W>,
+    header_pos: u64,
+    buffer: Vec<u8>,
+    channels: HashMap<String, usize>,
+    compression: u32,
+    start: u128,
+    origin: Option<u128>,
+    stop: u128,
+    object_count: u32,
+    uncompressed_size: u64,
+}
+
+impl BlfWriter<BufWriter<File>> {
+    /// Create the BLF file, the file will be truncated if it exists.
+    pub fn create<P: AsRef<Path>>(path: P) -> CanResult<Self> {
+        let file = File::create(path).map_err(io_error)?;
+        Self::new(BufWriter::new(file))
+    }
+}
+
+impl<W: Write + Seek> BlfWriter<W> {
+    /// Create the writer and reserve the file header at current position.
+    pub fn new(mut writer: W) -> CanResult<Self> {
+        let header_pos = writer.stream_position().map_err(io_error)?;
+        writer.write_all(&[0; FILE_HEADER_SIZE]).map_err(io_error)?;
+
+        Ok(Self {
+            writer: Some(writer),
+            header_pos,
+            buffer: Vec::with_capacity(MAX_CONTAINER_SIZE),
+            channels: Default::default(),
+            compression: Compression::default().level(),
+            start: 0,
+            origin: None,
+            stop: 0,
+            object_count: 0,
+            uncompressed_size: FILE_HEADER_SIZE as u64,
+        })
+    }
+
+    /// Set the zlib compression level(0-9) of containers, `0` writes uncompressed containers.
+    pub fn set_compression_level(&mut self, level: u32) -> &mut Self {
+        self.compression = level.min(9);
+        self
+    }
+
+    /// Map the channel of device to the channel number in file.
+    ///
+    /// The numeric channels are written as they are,
+    /// the others are numbered from 1 in order of appearance if not mapped.
+    pub fn set_channel(&mut self, channel: impl Display, number: usize) -> &mut Self {
+        self.channels.insert(channel.to_string(), number);
+        self
+    }
+
+    /// Write the frame as `CAN_MESSAGE2`, `CAN_FD_MESSAGE_64` or `CAN_ERROR_EXT` object.
+    ///
+    /// The CAN XL frames are not supported by BLF.
+    pub fn write_frame<F: Frame>(&mut self, frame: &F) -> CanResult<()> {
+        let timestamp = frame.timestamp().unwrap_or_else(utils::system_timestamp);
+        let origin = *self.origin.get_or_insert_with(|| {
+            self.start = match timestamp.source {
+                TimestampSource::System => timestamp.nanos,
+                _ => utils::system_timestamp().nanos,
+            };
+            timestamp.nanos
+        });
+        let offset = timestamp.nanos.saturating_sub(origin);
+        self.stop = self.stop.max(self.start + offset);
+
+        let channel = asc::channel_number(&mut self.channels, frame.channel());
+        let channel = u16::try_from(channel).map_err(|_| {
+            Error::OtherError(format!("channel number {} is out of range", channel))
+        })?;
+
+        let (obj_type, payload) = match frame.kind() {
+            _ if frame.is_error_frame() => (CAN_ERROR_EXT, Self::can_error_ext(frame, channel)),
+            Kind::Classical => (CAN_MESSAGE2, Self::can_message2(frame, channel)),
+            Kind::FD => (CAN_FD_MESSAGE_64, Self::can_fd_message64(frame, channel)?),
+            Kind::XL => return Err(Error::NotSupportedError),
+        };
+        self.add_object(obj_type, offset as u64, &payload);
+
+        // the object is split into the next container if it's full
+        while self.buffer.len() >= MAX_CONTAINER_SIZE {
+            self.write_container()?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the buffered objects into a container and flush the inner writer.
+    pub fn flush(&mut self) -> CanResult<()> {
+        self.write_container()?;
+        self.writer()?.flush().map_err(io_error)
+    }
+
+    /// Write the remaining objects, update the file header and return the inner writer.
+    pub fn finish(mut self) -> CanResult<W> {
+        self.write_end()?;
+        self.writer
+            .take()
+            .ok_or(Error::operation_error("writer is finished"))
+    }
+
+    fn writer(&mut self) -> CanResult<&mut W> {
+        self.writer
+            .as_mut()
+            .ok_or(Error::operation_error("writer is finished"))
+    }
+
+    fn can_message2<F: Frame>(frame: &F, channel: u16) -> Vec<u8> {
+        let mut flags = 0;
+        if frame.direction() == Direction::Transmit {
+            flags |= CAN_MSG_FLAG_TX;
+        }
+        if frame.is_remote() {
+            flags |= CAN_MSG_FLAG_REMOTE;
+        }
+        let mut data = [0; MAX_FRAME_SIZE];
+        if !frame.is_remote() {
+            data[..frame.data().len()].copy_from_slice(frame.data());
+        }
+
+        let mut payload = Vec::with_capacity(24);
+        payload.extend_from_slice(&channel.to_le_bytes());
+        payload.push(flags);
+        payload.push(frame.len() as u8);
+        payload.extend_from_slice(&raw_id(frame.id()).to_le_bytes());
+        payload.extend_from_slice(&data);
+        // frame length, bit count and reserved
+        payload.extend_from_slice(&[0; 8]);
+        payload
+    }
+
+    fn can_fd_message64<F: Frame>(frame: &F, channel: u16) -> CanResult<Vec<u8>> {
+        let mut flags = CAN_FD64_FLAG_EDL;
+        if frame.is_bitrate_switch() {
+            flags |= CAN_FD64_FLAG_BRS;
+        }
+        if frame.is_esi() {
+            flags |= CAN_FD64_FLAG_ESI;
+        }
+        let data = frame.data();
+
+        let mut payload = Vec::with_capacity(40 + data.len());
+        payload.push(channel as u8);
+        payload.push(frame.dlc()?);
+        payload.push(data.len() as u8);
+        // tx count
+        payload.push(0);
+        payload.extend_from_slice(&raw_id(frame.id()).to_le_bytes());
+        // frame length
+        payload.extend_from_slice(&0u32.to_le_bytes());
+        payload.extend_from_slice(&flags.to_le_bytes());
+        // bit timings, bit rate switch and CRC delimiter offsets
+        payload.extend_from_slice(&[0; 16]);
+        // bit count
+        payload.extend_from_slice(&0u16.to_le_bytes());
+        payload.push(match frame.direction() {
+            Direction::Receive => 0,
+            Direction::Transmit => 1,
+        });
+        // extension data offset and CRC
+        payload.extend_from_slice(&[0; 5]);
+        payload.extend_from_slice(data);
+        Ok(payload)
+    }
+
+    fn can_error_ext<F: Frame>(frame: &F, channel: u16) -> Vec<u8> {
+        let length = frame.len().min(MAX_FRAME_SIZE);
+        let mut data = [0; MAX_FRAME_SIZE];
+        data[..length].copy_from_slice(&frame.data()[..length]);
+
+        let mut payload = Vec::with_capacity(32);
+        payload.extend_from_slice(&channel.to_le_bytes());
+        payload.extend_from_slice(&(length as u16).to_le_bytes());
+        // flags, ECC and position
+        payload.extend_from_slice(&[0; 6]);
+        payload.push(length as u8);
+        payload.push(0);
+        // frame length
+        payload.extend_from_slice(&0u32.to_le_bytes());
+        payload.extend_from_slice(&raw_id(frame.id()).to_le_bytes());
+        // extended flags and reserved
+        payload.extend_from_slice(&[0; 4]);
+        payload.extend_from_slice(&data);
+        payload
+    }
+
+    fn add_object(&mut self, obj_type: u32, timestamp: u64, payload: &[u8]) {
+        let obj_size = OBJ_HEADER_V1_SIZE + payload.len();
+        self.buffer.extend_from_slice(OBJ_SIGNATURE);
+        self.buffer
+            .extend_from_slice(&(OBJ_HEADER_V1_SIZE as u16).to_le_bytes());
+        // header version
+        self.buffer.extend_from_slice(&1u16.to_le_bytes());
+        self.buffer
+            .extend_from_slice(&(obj_size as u32).to_le_bytes());
+        self.buffer.extend_from_slice(&obj_type.to_le_bytes());
+        self.buffer.extend_from_slice(&TIME_ONE_NANS.to_le_bytes());
+        // client index and object version
+        self.buffer.extend_from_slice(&[0; 4]);
+        self.buffer.extend_from_slice(&timestamp.to_le_bytes());
+        self.buffer.extend_from_slice(payload);
+        self.buffer
+            .resize(self.buffer.len() + padding_size(obj_type, obj_size), 0);
+
+        self.object_count += 1;
+    }
+
+    fn write_container(&mut self) -> CanResult<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let uncompressed_size = self.buffer.len().min(MAX_CONTAINER_SIZE);
+        let content = &self.buffer[..uncompressed_size];
+        let (method, data) = match self.compression {
+            0 => (NO_COMPRESSION, content.to_vec()),
+            level => {
+                let mut encoder = ZlibEncoder::new(
+                    Vec::with_capacity(uncompressed_size / 2),
+                    Compression::new(level),
+                );
+                encoder.write_all(content).map_err(io_error)?;
+                (ZLIB_DEFLATE, encoder.finish().map_err(io_error)?)
+            }
+        };
+        self.buffer.drain(..uncompressed_size);
+
+        let obj_size = OBJ_HEADER_BASE_SIZE + LOG_CONTAINER_HEADER_SIZE + data.len();
+        let mut header = Vec::with_capacity(OBJ_HEADER_BASE_SIZE + LOG_CONTAINER_HEADER_SIZE);
+        header.extend_from_slice(OBJ_SIGNATURE);
+        header.extend_from_slice(&(OBJ_HEADER_BASE_SIZE as u16).to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&(obj_size as u32).to_le_bytes());
+        header.extend_from_slice(&LOG_CONTAINER.to_le_bytes());
+        header.extend_from_slice(&method.to_le_bytes());
+        header.extend_from_slice(&[0; 6]);
+        header.extend_from_slice(&(uncompressed_size as u32).to_le_bytes());
+        header.extend_from_slice(&[0; 4]);
+
+        let writer = self.writer()?;
+        writer
+            .write_all(&header)
+            .and_then(|_| writer.write_all(&data))
+            .and_then(|_| writer.write_all(&[0; 4][..padding_size(LOG_CONTAINER, obj_size)]))
+            .map_err(io_error)?;
+
+        self.uncompressed_size +=
+            (OBJ_HEADER_BASE_SIZE + LOG_CONTAINER_HEADER_SIZE + uncompressed_size) as u64;
+        Ok(())
+    }
+
+    fn write_end(&mut self) -> CanResult<()> {
+        self.write_container()?;
+        if self.origin.is_none() {
+            self.start = utils::system_timestamp().nanos;
+            self.stop = self.start;
+        }
+
+        let header_pos = self.header_pos;
+        let mut header = Vec::with_capacity(FILE_HEADER_SIZE);
+        header.extend_from_slice(FILE_SIGNATURE);
+        header.extend_from_slice(&(FILE_HEADER_SIZE as u32).to_le_bytes());
+        header.push(APPLICATION_ID);
+        // application version
+        header.extend_from_slice(&[0; 3]);
+        header.extend_from_slice(&BIN_LOG_VERSION);
+        // the file size is filled later
+        header.extend_from_slice(&0u64.to_le_bytes());
+        header.extend_from_slice(&self.uncompressed_size.to_le_bytes());
+        header.extend_from_slice(&self.object_count.to_le_bytes());
+        // objects read
+        header.extend_from_slice(&0u32.to_le_bytes());
+        to_system_time(self.start)
+            .iter()
+            .chain(to_system_time(self.stop).iter())
+            .for_each(|v| header.extend_from_slice(&v.to_le_bytes()));
+        header.resize(FILE_HEADER_SIZE, 0);
+
+        let writer = self.writer()?;
+        let end = writer.stream_position().map_err(io_error)?;
+        header[16..24].copy_from_slice(&(end - header_pos).to_le_bytes());
+        writer
+            .seek(SeekFrom::Start(header_pos))
+            .and_then(|_| writer.write_all(&header))
+            .and_then(|_| writer.seek(SeekFrom::Start(end)))
+            .and_then(|_| writer.flush())
+            .map_err(io_error)
+    }
+}
+
+impl<W: Write + Seek> Drop for BlfWriter<W> {
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            if let Err(e) = self.write_end() {
+                rsutil::warn!("RUST-CAN - {} when BLF writer dropped", e);
+            }
+        }
+    }
+}
+
+/// The streaming reader of BLF file, yields the frames in file.
+///
+/// The channel of frame is parsed from the channel number in file.
+/// The timestamp of frame is the start time of file plus the offset of object.
+/// The unsupported objects are skipped.
+pub struct BlfReader<R, F> {
+    reader: R,
+    start: Option<u128>,
+    object_count: u32,
+    /// The uncompressed objects, an object may span several containers.
+    buffer: Vec<u8>,
+    pos: usize,
+    eof: bool,
+    _frame: PhantomData<fn() -> F>,
+}
+
+impl<F> BlfReader<BufReader<File>, F> {
+    pub fn open<P: AsRef<Path>>(path: P) -> CanResult<Self> {
+        let file = File::open(path).map_err(io_error)?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read, F> BlfReader<R, F> {
+    /// Create the reader and read the file header.
+    pub fn new(mut reader: R) -> CanResult<Self> {
+        let mut header = vec![0; 8];
+        reader.read_exact(&mut header).map_err(io_error)?;
+        if &header[..4] != FILE_SIGNATURE {
+            return Err(Error::OtherError("invalid BLF file signature".into()));
+        }
+        let header_size = u32_at(&header, 4) as usize;
+        if header_size < START_TIME_OFFSET + 16 {
+            return Err(Error::OtherError(format!(
+                "invalid BLF header size: {}",
+                header_size
+            )));
+        }
+        header.extend(read_sized(&mut reader, header_size - 8).map_err(io_error)?);
+
+        let mut start = [0; 8];
+        start
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, v)| *v = u16_at(&header, START_TIME_OFFSET + i * 2));
+
+        Ok(Self {
+            reader,
+            start: from_system_time(&start),
+            object_count: u32_at(&header, 32),
+            buffer: Default::default(),
+            pos: 0,
+            eof: false,
+            _frame: PhantomData,
+        })
+    }
+
+    /// The unix timestamp(ns) of the start of measurement, `None` if it's not set.
+    #[inline]
+    pub fn start_time(&self) -> Option<u128> {
+        self.start
+    }
+
+    /// The count of objects in file header.
+    #[inline]
+    pub fn object_count(&self) -> u32 {
+        self.object_count
+    }
+
+    /// Read the next object in file and append the objects into buffer.
+    ///
+    /// Return `false` at the end of file.
+    fn read_object(&mut self) -> CanResult<bool> {
+        let mut header = [0; OBJ_HEADER_BASE_SIZE];
+        match self.reader.read_exact(&mut header) {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(io_error(e)),
+        }
+        if &header[..4] != OBJ_SIGNATURE {
+            return Err(Error::OtherError("invalid BLF object signature".into()));
+        }
+        let obj_size = u32_at(&header, 8) as usize;
+        let obj_type = u32_at(&header, 12);
+        if obj_size < OBJ_HEADER_BASE_SIZE {
+            return Err(Error::OtherError(format!(
+                "invalid BLF object size: {}",
+                obj_size
+            )));
+        }
+
+        let size = obj_size - OBJ_HEADER_BASE_SIZE;
+        let mut data = read_sized(&mut self.reader, size).map_err(io_error)?;
+        // the padding of last object may be absent, it's filled with zeros
+        let padding = padding_size(obj_type, obj_size);
+        (&mut self.reader)
+            .take(padding as u64)
+            .read_to_end(&mut data)
+            .map_err(io_error)?;
+        data.resize(size + padding, 0);
+
+        if self.pos > 0 {
+            self.buffer.drain(..self.pos);
+            self.pos = 0;
+        }
+        match obj_type {
+            LOG_CONTAINER => {
+                if data.len() < LOG_CONTAINER_HEADER_SIZE {
+                    return Err(Error::OtherError("invalid BLF container".into()));
+                }
+                let method = u16_at(&data, 0);
+                let size = u32_at(&data, 8) as usize;
+                let content = &data[LOG_CONTAINER_HEADER_SIZE..];
+                match method {
+                    NO_COMPRESSION => self.buffer.extend_from_slice(content),
+                    ZLIB_DEFLATE => {
+                        if size > MAX_CONTAINER_SIZE {
+                            return Err(Error::OtherError(format!(
+                                "invalid BLF container size: {}",
+                                size
+                            )));
+                        }
+                        // one more byte is read to find the content larger than declared
+                        self.buffer.reserve(size);
+                        let decompressed = ZlibDecoder::new(content)
+                            .take(size as u64 + 1)
+                            .read_to_end(&mut self.buffer)
+                            .map_err(io_error)?;
+                        if decompressed != size {
+                            return Err(Error::OtherError(format!(
+                                "invalid BLF container size: {}, decompressed: {}",
+                                size, decompressed
+                            )));
+                        }
+                    }
+                    _ => {
+                        return Err(Error::OtherError(format!(
+                            "unsupported BLF compression method: {}",
+                            method
+                        )))
+                    }
+                }
+            }
+            _ => {
+                self.buffer.extend_from_slice(&header);
+                self.buffer.extend_from_slice(&data);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Take the next complete object in buffer, return `(type, flags, timestamp, payload range)`.
+    fn next_object(&mut self) -> Option<(u32, u32, u64, Range<usize>)> {
+        // skip the paddings
+        let Some(offset) = self.buffer[self.pos..]
+            .windows(OBJ_SIGNATURE.len())
+            .position(|v| v == OBJ_SIGNATURE)
+        else {
+            self.pos = self
+                .buffer
+                .len()
+                .saturating_sub(OBJ_SIGNATURE.len() - 1)
+                .max(self.pos);
+            return None;
+        };
+        let pos = self.pos + offset;
+        let rest = &self.buffer[pos..];
+        if rest.len() < OBJ_HEADER_V1_SIZE {
+            self.pos = pos;
+            return None;
+        }
+
+        let header_size = u16_at(rest, 4) as usize;
+        let obj_size = u32_at(rest, 8) as usize;
+        let obj_type = u32_at(rest, 12);
+        if rest.len() < obj_size {
+            self.pos = pos;
+            return None;
+        }
+        if header_size < OBJ_HEADER_V1_SIZE || obj_size < header_size {
+            // skip the invalid object
+            self.pos = pos + OBJ_SIGNATURE.len();
+            return Some((0, 0, 0, 0..0));
+        }
+
+        self.pos = (pos + obj_size + padding_size(obj_type, obj_size)).min(self.buffer.len());
+        let flags = u32_at(rest, 16);
+        let timestamp = u64_at(rest, 24);
+        Some((
+            obj_type,
+            flags,
+            timestamp,
+            pos + header_size..pos + obj_size,
+        ))
+    }
+}
+
+impl<R: Read, F: Frame> BlfReader<R, F>
+where
+    F::Channel: FromStr,
+{
+    fn parse_object(&self, obj_type: u32, payload: &[u8]) -> CanResult<Option<F>> {
+        let invalid = || Error::InvalidFrame(format!("invalid BLF object type {}", obj_type));
+        let (channel, direction, mut frame) = match obj_type {
+            CAN_MESSAGE | CAN_MESSAGE2 => {
+                if payload.len() < 16 {
+                    return Err(invalid());
+                }
+                let flags = payload[2];
+                let frame =
+                    Self::classic_frame(flags, payload[3], u32_at(payload, 4), &payload[8..16])?;
+                (u16_at(payload, 0), flags & CAN_MSG_FLAG_TX != 0, frame)
+            }
+            CAN_FD_MESSAGE => {
+                if payload.len() < 84 {
+                    return Err(invalid());
+                }
+                let (flags, fd_flags) = (payload[2], payload[13]);
+                let id = u32_at(payload, 4);
+                let frame = if fd_flags & CAN_FD_FLAG_EDL != 0 {
+                    let mut flags = CanFdFlags::empty();
+                    flags.set(CanFdFlags::BRS, fd_flags & CAN_FD_FLAG_BRS != 0);
+                    flags.set(CanFdFlags::ESI, fd_flags & CAN_FD_FLAG_ESI != 0);
+                    let length = (payload[14] as usize).min(64);
+                    F::new_can_fd(parse_id(id)?, &payload[20..20 + length], flags)?
+                } else {
+                    Self::classic_frame(flags, payload[3], id, &payload[20..28])?
+                };
+                (u16_at(payload, 0), flags & CAN_MSG_FLAG_TX != 0, frame)
+            }
+            CAN_FD_MESSAGE_64 => {
+                let length = *payload.get(2).ok_or_else(invalid)? as usize;
+                if payload.len() < 40 + length {
+                    return Err(invalid());
+                }
+                let id = u32_at(payload, 4);
+                let flags = u32_at(payload, 12);
+                let data = &payload[40..40 + length];
+                let frame = if flags & CAN_FD64_FLAG_EDL != 0 {
+                    let mut fd_flags = CanFdFlags::empty();
+                    fd_flags.set(CanFdFlags::BRS, flags & CAN_FD64_FLAG_BRS != 0);
+                    fd_flags.set(CanFdFlags::ESI, flags & CAN_FD64_FLAG_ESI != 0);
+                    F::new_can_fd(parse_id(id)?, data, fd_flags)?
+                } else if flags & CAN_FD64_FLAG_REMOTE != 0 {
+                    F::new_remote(parse_id(id)?, payload[1])?
+                } else {
+                    F::new_can(parse_id(id)?, data)?
+                };
+                (payload[0] as u16, payload[34] != 0, frame)
+            }
+            CAN_ERROR_EXT => {
+                if payload.len() < 32 {
+                    return Err(invalid());
+                }
+                let length = (payload[10] as usize).min(MAX_FRAME_SIZE);
+                let frame =
+                    F::new_error(parse_id(u32_at(payload, 16))?, &payload[24..24 + length])?;
+                (u16_at(payload, 0), false, frame)
+            }
+            _ => return Ok(None),
+        };
+
+        frame
+            .set_channel(utils::parse_channel(&channel.to_string())?)
+            .set_direction(if direction {
+                Direction::Transmit
+            } else {
+                Direction::Receive
+            });
+        Ok(Some(frame))
+    }
+
+    fn classic_frame(flags: u8, dlc: u8, id: u32, data: &[u8]) -> CanResult<F> {
+        let id = parse_id(id)?;
+        if flags & CAN_MSG_FLAG_REMOTE != 0 {
+            F::new_remote(id, dlc)
+        } else {
+            F::new_can(id, &data[..(dlc as usize).min(MAX_FRAME_SIZE)])
+        }
+    }
+}
+
+impl<R: Read, F: Frame> Iterator for BlfReader<R, F>
+where
+    F::Channel: FromStr,
+{
+    type Item = CanResult<F>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some((obj_type, flags, timestamp, range)) = self.next_object() else {
+                if self.eof {
+                    return None;
+                }
+                match self.read_object() {
+                    Ok(v) => self.eof = !v,
+                    Err(e) => {
+                        self.eof = true;
+                        return Some(Err(e));
+                    }
+                }
+                continue;
+            };
+
+            let nanos = match flags {
+                TIME_TEN_MICS => timestamp as u128 * 10_000,
+                _ => timestamp as u128,
+            };
+            match self.parse_object(obj_type, &self.buffer[range]) {
+                Ok(Some(mut frame)) => {
+                    frame.set_timestamp(Some(Timestamp {
+                        nanos: self.start.unwrap_or_default() + nanos,
+                        source: match self.start {
+                            Some(_) => TimestampSource::System,
+                            None => TimestampSource::Unknown,
+                        },
+                    }));
+                    return Some(Ok(frame));
+                }
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        frame::{
+            identifier::{ExtendedId, StandardId},
+            FrameFormat, XlParams,
+        },
+        virtual_bus::tests::TestFrame,
+    };
+    use std::io::Cursor;
+
+    fn frames(start: u128) -> Vec<TestFrame> {
+        let timestamp = |offset| {
+            Some(Timestamp {
+                nanos: start + offset,
+                source: TimestampSource::System,
+            })
+        };
+        let mut frames = Vec::new();
+
+        let mut frame =
+            TestFrame::new_can(Id::Standard(StandardId::new(0x123).unwrap()), &[0x01, 0x02])
+                .unwrap();
+        frame
+            .set_channel("1".into())
+            .set_direction(Direction::Receive)
+            .set_timestamp(timestamp(0));
+        frames.push(frame);
+
+        let mut frame =
+            TestFrame::new_remote(Id::Extended(ExtendedId::new(0x1234_5678).unwrap()), 4).unwrap();
+        frame
+            .set_channel("2".into())
+            .set_timestamp(timestamp(1_500));
+        frames.push(frame);
+
+        let mut frame = TestFrame::new_can_fd(
+            Id::Extended(ExtendedId::new(0x18DA_F110).unwrap()),
+            &[0x55; 12],
+            CanFdFlags::BRS | CanFdFlags::ESI,
+        )
+        .unwrap();
+        frame
+            .set_channel("1".into())
+            .set_timestamp(timestamp(2_000_000));
+        frames.push(frame);
+
+        let mut frame =
+            TestFrame::new_error(Id::Standard(StandardId::new(0x004).unwrap()), &[0, 0x08])
+                .unwrap();
+        frame
+            .set_channel("3".into())
+            .set_direction(Direction::Receive)
+            .set_timestamp(timestamp(3_000_000_001));
+        frames.push(frame);
+
+        frames
+    }
+
+    fn assert_frames(expects: &[TestFrame], frames: &[TestFrame]) {
+        assert_eq!(frames.len(), expects.len());
+        for (expect, frame) in expects.iter().zip(frames.iter()) {
+            assert_eq!(frame.id(), expect.id());
+            assert_eq!(frame.kind(), expect.kind());
+            assert_eq!(frame.format(), expect.format());
+            assert_eq!(frame.channel(), expect.channel());
+            assert_eq!(frame.direction(), expect.direction());
+            assert_eq!(frame.len(), expect.len());
+            if !frame.is_remote() {
+                assert_eq!(frame.data(), expect.data());
+            }
+            assert_eq!(frame.is_bitrate_switch(), expect.is_bitrate_switch());
+            assert_eq!(frame.is_esi(), expect.is_esi());
+            assert_eq!(frame.timestamp(), expect.timestamp());
+        }
+    }
+
+    #[test]
+    fn system_time_round_trip() {
+        // Thu Apr 27 15:14:07.123 2023 UTC
+        let nanos = 1_682_608_447_123_000_000;
+        let time = to_system_time(nanos);
+        assert_eq!(time, [2023, 4, 4, 27, 15, 14, 7, 123]);
+        assert_eq!(from_system_time(&time), Some(nanos));
+        assert_eq!(from_system_time(&[0; 8]), None);
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let start = 1_682_608_447_123_000_000;
+        let expects = frames(start);
+
+        for level in [0, 6] {
+            let mut writer = BlfWriter::new(Cursor::new(Vec::new())).unwrap();
+            writer.set_compression_level(level);
+            expects.iter().for_each(|f| writer.write_frame(f).unwrap());
+            let content = writer.finish().unwrap().into_inner();
+            assert_eq!(&content[..4], FILE_SIGNATURE);
+            assert_eq!(u64_at(&content, 16), content.len() as u64);
+
+            let mut reader = BlfReader::<_, TestFrame>::new(content.as_slice()).unwrap();
+            assert_eq!(reader.start_time(), Some(start));
+            assert_eq!(reader.object_count(), expects.len() as u32);
+            let frames = reader.by_ref().collect::<CanResult<Vec<_>>>().unwrap();
+            assert_frames(&expects, &frames);
+            assert!(frames[3].is_error_frame());
+            assert_eq!(frames[1].format(), FrameFormat::Remote);
+        }
+    }
+
+    #[test]
+    fn frames_span_containers() {
+        let start = 1_682_608_447_123_000_000;
+        let expects = (0..10_000u32)
+            .map(|i| {
+                let mut frame =
+                    TestFrame::new_can(Id::Extended(ExtendedId::new(i).unwrap()), &i.to_le_bytes())
+                        .unwrap();
+                frame.set_channel("1".into()).set_timestamp(Some(Timestamp {
+                    nanos: start + i as u128 * 1_000,
+                    source: TimestampSource::System,
+                }));
+                frame
+            })
+            .collect::<Vec<_>>();
+
+        let mut writer = BlfWriter::new(Cursor::new(Vec::new())).unwrap();
+        expects.iter().for_each(|f| writer.write_frame(f).unwrap());
+        let content = writer.finish().unwrap().into_inner();
+
+        let frames = BlfReader::<_, TestFrame>::new(content.as_slice())
+            .unwrap()
+            .collect::<CanResult<Vec<_>>>()
+            .unwrap();
+        assert_frames(&expects, &frames);
+    }
+
+    #[test]
+    fn truncated_object_is_rejected() {
+        let start = 1_682_608_447_123_000_000;
+        let mut writer = BlfWriter::new(Cursor::new(Vec::new())).unwrap();
+        writer.set_compression_level(0);
+        frames(start)
+            .iter()
+            .for_each(|f| writer.write_frame(f).unwrap());
+        let mut content = writer.finish().unwrap().into_inner();
+        content.truncate(content.len() - 10);
+
+        let mut reader = BlfReader::<_, TestFrame>::new(content.as_slice()).unwrap();
+        assert!(reader.any(|f| f.is_err()));
+
+        // the sizes beyond the file are rejected without allocating them
+        let mut invalid = content.clone();
+        invalid[FILE_HEADER_SIZE + 8..FILE_HEADER_SIZE + 12].copy_from_slice(&[0xFF; 4]);
+        let mut reader = BlfReader::<_, TestFrame>::new(invalid.as_slice()).unwrap();
+        assert!(reader.next().is_some_and(|f| f.is_err()));
+        content[4..8].copy_from_slice(&[0xFF; 4]);
+        assert!(BlfReader::<_, TestFrame>::new(content.as_slice()).is_err());
+    }
+
+    #[test]
+    fn decompression_bomb_is_rejected() {
+        let start = 1_682_608_447_123_000_000;
+        let mut writer = BlfWriter::new(Cursor::new(Vec::new())).unwrap();
+        frames(start)
+            .iter()
+            .for_each(|f| writer.write_frame(f).unwrap());
+        let content = writer.finish().unwrap().into_inner();
+        // the uncompressed size of container
+        let offset = FILE_HEADER_SIZE + OBJ_HEADER_BASE_SIZE + 8;
+        let size = u32_at(&content, offset);
+
+        for invalid_size in [size - 1, size + 1, MAX_CONTAINER_SIZE as u32 + 1] {
+            let mut invalid = content.clone();
+            invalid[offset..offset + 4].copy_from_slice(&invalid_size.to_le_bytes());
+            let mut reader = BlfReader::<_, TestFrame>::new(invalid.as_slice()).unwrap();
+            assert!(reader.next().is_some_and(|f| f.is_err()));
+        }
+    }
+
+    #[test]
+    fn xl_frame_is_not_supported() {
+        let frame = TestFrame::new_can_xl(0x7FF, &[0xA5; 100], XlParams::default()).unwrap();
+        let mut writer = BlfWriter::new(Cursor::new(Vec::new())).unwrap();
+        assert!(matches!(
+            writer.write_frame(&frame),
+            Err(Error::NotSupportedError)
+        ));
+    }
+}
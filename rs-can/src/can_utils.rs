@@ -4,7 +4,10 @@ use crate::{
     frame::{Kind, Timestamp, TimestampSource},
     CanResult,
 };
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 /// resize data with default padding.
 #[inline]
@@ -42,7 +45,18 @@ pub fn can_dlc(length: usize, kind: Kind) -> CanResult<u8> {
             49..=MAX_FD_FRAME_SIZE => Ok(15),
             _ => Err(Error::OtherError("length of frame is out of range!".into())),
         },
-        Kind::XL => Err(Error::OtherError("XL is not supported now!".into())),
+        Kind::XL => Err(Error::OtherError(
+            "the DLC of CAN XL is got by can_xl_dlc!".into(),
+        )),
+    }
+}
+
+/// get CAN XL dlc, which is the data length minus one.
+#[inline]
+pub fn can_xl_dlc(length: usize) -> CanResult<u16> {
+    match length {
+        1..=MAX_XL_FRAME_SIZE => Ok(length as u16 - 1),
+        _ => Err(Error::OtherError("length of frame is out of range!".into())),
     }
 }
 
@@ -63,6 +77,43 @@ pub fn system_timestamp() -> Timestamp {
     }
 }
 
+/// Parse the channel from its `Display`.
+#[inline]
+pub(crate) fn parse_channel<C: FromStr>(channel: &str) -> CanResult<C> {
+    channel
+        .parse()
+        .map_err(|_| Error::InvalidFrame(format!("invalid channel `{}`", channel)))
+}
+
+/// Convert the days since 1970-01-01 to the civil date `(year, month, day)`.
+///
+/// ref: <https://howardhinnant.github.io/date_algorithms.html>
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// Convert the civil date to the days since 1970-01-01.
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146_097 + doe - 719_468
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -96,4 +147,24 @@ mod tests {
         assert_eq!(can_dlc(49, Kind::FD).unwrap(), 15);
         assert_eq!(can_dlc(64, Kind::FD).unwrap(), 15);
     }
+
+    #[test]
+    fn can_xl_dlc_is_length_minus_one() {
+        assert_eq!(can_xl_dlc(1).unwrap(), 0);
+        assert_eq!(can_xl_dlc(64).unwrap(), 63);
+        assert_eq!(can_xl_dlc(2048).unwrap(), 2047);
+        assert!(can_xl_dlc(0).is_err());
+        assert!(can_xl_dlc(2049).is_err());
+        assert!(can_dlc(64, Kind::XL).is_err());
+    }
+
+    #[test]
+    fn civil_date_round_trip() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(days_from_civil(2000, 2, 29), 11_016);
+        for days in [-1, 59, 11_016, 20_454, 100_000] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+    }
 }
@@ -0,0 +1,441 @@
+//! Linux can-utils `candump -L` log file.
+//!
+//! Each line is `(<secs>.<usecs>) <interface> <frame>`, the frame is one of:
+//! - `<id>#<data>` for classical frame, the id is 3 hex chars for standard and 8 for extended;
+//! - `<id>#R[len]` for remote frame;
+//! - `<id>##<flags><data>` for CAN-FD frame, the flags is a hex char of [`CanFdFlags`];
+//! - `<id>#<data>` with the `CAN_ERR_FLAG` in 8 chars id for error frame.
+//!
+//! ```ignore
+//! let mut writer = CandumpWriter::create("candump.log")?;
+//! for frame in device.receive(channel, Some(10)).await? {
+//!     writer.write_frame(&frame)?;
+//! }
+//! writer.finish()?;
+//!
+//! for frame in CandumpReader::<_, SocketCanFrame>::open("candump.log")? {
+//!     println!("{}", frame?);
+//! }
+//! ```
+
+use crate::{
+    error::Error,
+    frame::{
+        identifier::{CanFdFlags, Id, IdentifierFlags},
+        Direction, Frame, Kind, Timestamp, TimestampSource,
+    },
+    utils, CanResult, EFF_MASK, ERR_MASK, MAX_FRAME_SIZE, SFF_MASK,
+};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Write as _},
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    marker::PhantomData,
+    path::Path,
+    str::FromStr,
+};
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// The streaming writer of `candump -L` log file.
+///
+/// The interface of frame is the `Display` of channel if not mapped.
+/// The frames without timestamp are written with current system time.
+pub struct CandumpWriter<W: Write> {
+    writer: W,
+    interfaces: HashMap<String, String>,
+    line: String,
+}
+
+impl CandumpWriter<BufWriter<File>> {
+    /// Create the log file, the file will be truncated if it exists.
+    pub fn create<P: AsRef<Path>>(path: P) -> CanResult<Self> {
+        let file = File::create(path).map_err(|e| Error::OperationError(e.to_string()))?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> CandumpWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            interfaces: Default::default(),
+            line: Default::default(),
+        }
+    }
+
+    /// Map the channel of device to the interface name in file, e.g. `0` to `can0`.
+    pub fn set_channel(
+        &mut self,
+        channel: impl Display,
+        interface: impl Into<String>,
+    ) -> &mut Self {
+        self.interfaces
+            .insert(channel.to_string(), interface.into());
+        self
+    }
+
+    /// Write the frame, the CAN XL frames are not supported.
+    pub fn write_frame<F: Frame>(&mut self, frame: &F) -> CanResult<()> {
+        let timestamp = frame.timestamp().unwrap_or_else(utils::system_timestamp);
+        let channel = frame.channel().to_string();
+        let interface = self.interfaces.get(&channel).unwrap_or(&channel);
+
+        self.line.clear();
+        let _ = write!(
+            self.line,
+            "({}.{:06}) {} ",
+            timestamp.nanos / NANOS_PER_SEC,
+            timestamp.nanos % NANOS_PER_SEC / 1_000,
+            interface
+        );
+
+        let id = frame.id();
+        if frame.is_error_frame() {
+            let raw = (id.as_raw() & ERR_MASK) | IdentifierFlags::ERROR.bits();
+            let _ = write!(self.line, "{:08X}#", raw);
+            push_data(&mut self.line, frame.data());
+        } else {
+            match id {
+                Id::Standard(_) => write!(self.line, "{:03X}", id.as_raw()),
+                Id::Extended(_) => write!(self.line, "{:08X}", id.as_raw()),
+            }
+            .map_err(|e| Error::OtherError(e.to_string()))?;
+
+            match frame.kind() {
+                Kind::Classical if frame.is_remote() => match frame.len() {
+                    0 => self.line.push_str("#R"),
+                    len => {
+                        let _ = write!(self.line, "#R{}", len);
+                    }
+                },
+                Kind::Classical => {
+                    self.line.push('#');
+                    push_data(&mut self.line, frame.data());
+                }
+                Kind::FD => {
+                    let mut flags = CanFdFlags::FDF;
+                    flags.set(CanFdFlags::BRS, frame.is_bitrate_switch());
+                    flags.set(CanFdFlags::ESI, frame.is_esi());
+                    let _ = write!(self.line, "##{:X}", flags.bits());
+                    push_data(&mut self.line, frame.data());
+                }
+                Kind::XL => return Err(Error::NotSupportedError),
+            }
+        }
+        self.line.push('\n');
+
+        self.writer
+            .write_all(self.line.as_bytes())
+            .map_err(|e| Error::OperationError(e.to_string()))
+    }
+
+    pub fn flush(&mut self) -> CanResult<()> {
+        self.writer
+            .flush()
+            .map_err(|e| Error::OperationError(e.to_string()))
+    }
+
+    /// Flush and return the inner writer.
+    pub fn finish(mut self) -> CanResult<W> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// The streaming reader of `candump -L` log file, yields the frames in file.
+///
+/// The channel of frame is parsed from the interface name if not mapped.
+/// The optional direction(`R` or `T`) after frame is accepted, the frames are received by default.
+pub struct CandumpReader<R, F: Frame> {
+    reader: R,
+    line: String,
+    line_no: usize,
+    channels: HashMap<String, F::Channel>,
+    _frame: PhantomData<fn() -> F>,
+}
+
+impl<F: Frame> CandumpReader<BufReader<File>, F> {
+    pub fn open<P: AsRef<Path>>(path: P) -> CanResult<Self> {
+        let file = File::open(path).map_err(|e| Error::OperationError(e.to_string()))?;
+        Ok(Self::new(BufReader::new(file)))
+    }
+}
+
+impl<R: BufRead, F: Frame> CandumpReader<R, F> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: Default::default(),
+            line_no: 0,
+            channels: Default::default(),
+            _frame: PhantomData,
+        }
+    }
+
+    /// Map the interface name in file to the channel of device, e.g. `can0` to `0`.
+    pub fn set_channel(&mut self, interface: impl Into<String>, channel: F::Channel) -> &mut Self {
+        self.channels.insert(interface.into(), channel);
+        self
+    }
+}
+
+impl<R: BufRead, F: Frame> CandumpReader<R, F>
+where
+    F::Channel: FromStr + Clone,
+{
+    fn parse_line(&self, line: &str) -> CanResult<Option<F>> {
+        let mut tokens = line.split_whitespace();
+        let Some(timestamp) = tokens.next() else {
+            return Ok(None);
+        };
+        let timestamp = timestamp
+            .strip_prefix('(')
+            .and_then(|v| v.strip_suffix(')'))
+            .and_then(parse_timestamp)
+            .ok_or(Error::InvalidFrame("invalid timestamp".into()))?;
+        let interface = tokens
+            .next()
+            .ok_or(Error::InvalidFrame("missing interface".into()))?;
+        let mut frame = parse_frame::<F>(
+            tokens
+                .next()
+                .ok_or(Error::InvalidFrame("missing frame".into()))?,
+        )?;
+        let direction = match tokens.next() {
+            Some("T") => Direction::Transmit,
+            _ => Direction::Receive,
+        };
+        let channel = match self.channels.get(interface) {
+            Some(v) => v.clone(),
+            None => utils::parse_channel(interface)?,
+        };
+
+        frame
+            .set_channel(channel)
+            .set_direction(direction)
+            .set_timestamp(Some(Timestamp {
+                nanos: timestamp,
+                source: TimestampSource::System,
+            }));
+        Ok(Some(frame))
+    }
+}
+
+impl<R: BufRead, F: Frame> Iterator for CandumpReader<R, F>
+where
+    F::Channel: FromStr + Clone,
+{
+    type Item = CanResult<F>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => self.line_no += 1,
+                Err(e) => return Some(Err(Error::OperationError(e.to_string()))),
+            }
+
+            match self.parse_line(&self.line) {
+                Ok(Some(frame)) => return Some(Ok(frame)),
+                Ok(None) => continue,
+                Err(Error::InvalidFrame(msg)) => {
+                    return Some(Err(Error::InvalidFrame(format!(
+                        "{} at line {}: `{}`",
+                        msg,
+                        self.line_no,
+                        self.line.trim_end()
+                    ))))
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[inline]
+fn push_data(line: &mut String, data: &[u8]) {
+    data.iter().for_each(|b| {
+        let _ = write!(line, "{:02X}", b);
+    });
+}
+
+/// Parse `<secs>.<fraction>` to nanoseconds.
+fn parse_timestamp(token: &str) -> Option<u128> {
+    let (secs, fraction) = token.split_once('.').unwrap_or((token, ""));
+    let secs = secs.parse::<u128>().ok()?;
+    if fraction.len() > 9 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let nanos = format!("{:0<9}", fraction).parse::<u128>().ok()?;
+
+    Some(secs * NANOS_PER_SEC + nanos)
+}
+
+/// Parse the hex data, the `.` separators are allowed.
+fn parse_data(token: &str) -> CanResult<Vec<u8>> {
+    let digits = token.bytes().filter(|&b| b != b'.').collect::<Vec<_>>();
+    if digits.len() % 2 != 0 {
+        return Err(Error::InvalidFrame("odd number of data digits".into()));
+    }
+
+    digits
+        .chunks(2)
+        .map(|v| {
+            std::str::from_utf8(v)
+                .ok()
+                .and_then(|v| u8::from_str_radix(v, 16).ok())
+                .ok_or(Error::InvalidFrame("invalid data".into()))
+        })
+        .collect()
+}
+
+fn parse_frame<F: Frame>(token: &str) -> CanResult<F> {
+    let (id, rest) = token
+        .split_once('#')
+        .ok_or(Error::InvalidFrame("missing `#`".into()))?;
+    let raw = u32::from_str_radix(id, 16).map_err(|_| Error::InvalidFrame("invalid id".into()))?;
+    let id = match id.len() {
+        3 if raw > SFF_MASK => return Err(Error::InvalidFrame("invalid id".into())),
+        3 => Id::from_bits(raw, Some(false))?,
+        8 if raw & IdentifierFlags::ERROR.bits() != 0 => {
+            let class = raw & ERR_MASK;
+            return F::new_error(
+                Id::from_bits(class, Some(class > SFF_MASK))?,
+                &parse_data(rest)?,
+            );
+        }
+        8 => Id::from_bits(raw & EFF_MASK, Some(true))?,
+        _ => return Err(Error::InvalidFrame("invalid id".into())),
+    };
+
+    if let Some(rest) = rest.strip_prefix('#') {
+        if rest.starts_with('#') {
+            return Err(Error::InvalidFrame("CAN XL is not supported".into()));
+        }
+        let mut chars = rest.chars();
+        let flags = chars
+            .next()
+            .and_then(|v| v.to_digit(16))
+            .ok_or(Error::InvalidFrame("invalid flags".into()))?;
+        let data = parse_data(chars.as_str())?;
+        F::new_can_fd(id, &data, CanFdFlags::from_bits_truncate(flags as u8))
+    } else if let Some(len) = rest.strip_prefix('R') {
+        // the optional length and len8_dlc, e.g. `R`, `R4` or `R8_E`
+        let len = len.split('_').next().unwrap_or_default();
+        let len = match len {
+            "" => 0,
+            v => v
+                .parse::<u8>()
+                .map_err(|_| Error::InvalidFrame("invalid remote length".into()))?,
+        };
+        if len as usize > MAX_FRAME_SIZE {
+            return Err(Error::InvalidDLC(len as usize));
+        }
+        F::new_remote(id, len)
+    } else {
+        // the optional len8_dlc, e.g. `1122334455667788_E`
+        let data = rest.split('_').next().unwrap_or_default();
+        F::new_can(id, &parse_data(data)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        frame::identifier::{ExtendedId, StandardId},
+        frame::FrameFormat,
+        virtual_bus::tests::TestFrame,
+    };
+
+    #[test]
+    fn parse_candump_lines() {
+        let content = "\
+(1697371234.123456) can0 123#DEADBEEF
+(1697371234.200000) can0 1ABCDEF0#R
+(1697371234.300000) can1 321#R4
+(1697371234.400000) vcan0 18DAF110##30102030405060708090A0B0C
+(1697371234.500000) can0 20000004#0004000000000000
+(1697371234.6) can0 7FF#11.22.33 T
+";
+        let mut reader = CandumpReader::<_, TestFrame>::new(content.as_bytes());
+        reader.set_channel("vcan0", "2".into());
+        let frames = reader.collect::<CanResult<Vec<_>>>().unwrap();
+        assert_eq!(frames.len(), 6);
+
+        assert_eq!(
+            frames[0].id(),
+            Id::Standard(StandardId::new(0x123).unwrap())
+        );
+        assert_eq!(frames[0].data(), &[0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(frames[0].channel(), "can0");
+        assert_eq!(frames[0].direction(), Direction::Receive);
+        assert_eq!(
+            frames[0].timestamp().unwrap().nanos,
+            1_697_371_234_123_456_000
+        );
+
+        assert_eq!(
+            frames[1].id(),
+            Id::Extended(ExtendedId::new(0x1ABC_DEF0).unwrap())
+        );
+        assert!(frames[1].is_remote());
+        assert_eq!(frames[2].len(), 4);
+        assert!(frames[2].is_remote());
+
+        assert_eq!(frames[3].kind(), Kind::FD);
+        assert_eq!(frames[3].channel(), "2");
+        assert!(frames[3].is_bitrate_switch());
+        assert!(frames[3].is_esi());
+        assert_eq!(frames[3].len(), 12);
+
+        assert_eq!(frames[4].format(), FrameFormat::Error);
+        assert_eq!(frames[4].id().as_raw(), 0x004);
+        assert_eq!(frames[4].data()[1], 0x04);
+
+        assert_eq!(frames[5].data(), &[0x11, 0x22, 0x33]);
+        assert_eq!(frames[5].direction(), Direction::Transmit);
+        assert_eq!(
+            frames[5].timestamp().unwrap().nanos,
+            1_697_371_234_600_000_000
+        );
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let content = "\
+(1697371234.123456) can0 123#DEADBEEF
+(1697371234.200000) can0 1ABCDEF0#R
+(1697371234.300000) can1 321#R4
+(1697371234.400000) can0 18DAF110##5000102030405060708090A0B
+(1697371234.500000) can1 20000004#0004000000000000
+(1697371234.600000) can0 7FF#
+";
+        let frames = CandumpReader::<_, TestFrame>::new(content.as_bytes())
+            .collect::<CanResult<Vec<_>>>()
+            .unwrap();
+
+        let mut writer = CandumpWriter::new(Vec::new());
+        frames.iter().for_each(|f| writer.write_frame(f).unwrap());
+        let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert_eq!(output, content);
+    }
+
+    #[test]
+    fn invalid_lines_are_reported() {
+        for line in [
+            "(1697371234.123456) can0 123DEADBEEF",
+            "(1697371234.123456) can0 1234#00",
+            "(1697371234.123456) can0 FFF#00",
+            "(1697371234.123456) can0 123#ABC",
+            "1697371234.123456 can0 123#00",
+            "(1697371234.123456) can0 123#R9",
+        ] {
+            let mut reader = CandumpReader::<_, TestFrame>::new(line.as_bytes());
+            assert!(reader.next().unwrap().is_err(), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use super::{DataType, MappedObject, PdoKind, PdoMapping};
+use crate::{error::Error, CanResult};
+use std::{collections::BTreeMap, path::Path};
+
+/// The access type of object.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AccessType {
+    #[default]
+    ReadOnly,
+    WriteOnly,
+    ReadWrite,
+    Const,
+}
+
+impl AccessType {
+    #[inline]
+    pub fn readable(&self) -> bool {
+        !matches!(self, Self::WriteOnly)
+    }
+
+    #[inline]
+    pub fn writable(&self) -> bool {
+        matches!(self, Self::WriteOnly | Self::ReadWrite)
+    }
+}
+
+/// An object(or sub-object) of the object dictionary.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ObjectEntry {
+    pub name: String,
+    /// It's `None` when the data type isn't supported, e.g. `INTEGER24` and `TIME_OF_DAY`.
+    pub data_type: Option<DataType>,
+    pub access: AccessType,
+    /// The raw default value, which may include `$NODEID`.
+    pub default: Option<String>,
+    pub pdo_mapping: bool,
+}
+
+/// The electronic data sheet(CiA 306) of a device.
+///
+/// The variables are stored with sub-index `0`, the arrays and records are stored
+/// by their sub-objects.
+#[derive(Debug, Default, Clone)]
+pub struct Eds {
+    device_info: BTreeMap<String, String>,
+    objects: BTreeMap<(u16, u8), ObjectEntry>,
+}
+
+/// Parse the integer in decimal, hex(`0x`) or octal(leading `0`).
+fn parse_int(value: &str) -> Option<u64> {
+    let value = value.trim();
+    if let Some(hex) = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex, 16).ok()
+    } else if value.len() > 1 && value.starts_with('0') {
+        u64::from_str_radix(&value[1..], 8).ok()
+    } else {
+        value.parse().ok()
+    }
+}
+
+/// Parse the object section name, such as `1018` or `1018sub1`.
+fn parse_section(name: &str) -> Option<(u16, Option<u8>)> {
+    let lower = name.to_ascii_lowercase();
+    let (index, sub_index) = match lower.split_once("sub") {
+        Some((index, sub_index)) => (index, Some(u8::from_str_radix(sub_index, 16).ok()?)),
+        None => (lower.as_str(), None),
+    };
+    if index.len() != 4 {
+        return None;
+    }
+
+    Some((u16::from_str_radix(index, 16).ok()?, sub_index))
+}
+
+impl Eds {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> CanResult<Self> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| Error::OperationError(e.to_string()))?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> CanResult<Self> {
+        let mut eds = Self::default();
+        let mut section = String::new();
+        let mut object = None;
+        let mut entry = ObjectEntry::default();
+        let mut sub_number = 0;
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                eds.insert(object, std::mem::take(&mut entry), sub_number);
+                section = name.to_owned();
+                object = parse_section(name);
+                sub_number = 0;
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or_else(|| {
+                Error::other_error(format!("invalid EDS line {}: `{}`", i + 1, line))
+            })?;
+            let (key, value) = (key.trim(), value.trim());
+            let invalid =
+                || Error::other_error(format!("invalid EDS value of `{}` at line {}", key, i + 1));
+            if object.is_none() {
+                if section.eq_ignore_ascii_case("DeviceInfo") {
+                    eds.device_info.insert(key.to_owned(), value.to_owned());
+                }
+                continue;
+            }
+
+            match key.to_ascii_lowercase().as_str() {
+                "parametername" => entry.name = value.to_owned(),
+                "subnumber" => sub_number = parse_int(value).ok_or_else(invalid)? as u8,
+                "datatype" => {
+                    let data_type = parse_int(value).ok_or_else(invalid)? as u16;
+                    entry.data_type = DataType::try_from(data_type).ok();
+                }
+                "accesstype" => {
+                    entry.access = match value.to_ascii_lowercase().as_str() {
+                        "ro" => AccessType::ReadOnly,
+                        "wo" => AccessType::WriteOnly,
+                        "rw" | "rwr" | "rww" => AccessType::ReadWrite,
+                        "const" => AccessType::Const,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "defaultvalue" if !value.is_empty() => entry.default = Some(value.to_owned()),
+                "pdomapping" => entry.pdo_mapping = parse_int(value).ok_or_else(invalid)? != 0,
+                _ => {}
+            }
+        }
+        eds.insert(object, entry, sub_number);
+
+        Ok(eds)
+    }
+
+    /// Insert the variable or sub-object, the array and record are described by sub-objects.
+    fn insert(&mut self, object: Option<(u16, Option<u8>)>, entry: ObjectEntry, sub_number: u8) {
+        match object {
+            Some((index, Some(sub_index))) => {
+                self.objects.insert((index, sub_index), entry);
+            }
+            Some((index, None)) if sub_number == 0 => {
+                self.objects.insert((index, 0), entry);
+            }
+            _ => {}
+        }
+    }
+
+    /// The value of `[DeviceInfo]`, such as `VendorName` and `ProductName`.
+    #[inline]
+    pub fn device_info(&self, key: &str) -> Option<&str> {
+        self.device_info.get(key).map(|v| v.as_str())
+    }
+
+    #[inline]
+    pub fn object(&self, index: u16, sub_index: u8) -> Option<&ObjectEntry> {
+        self.objects.get(&(index, sub_index))
+    }
+
+    #[inline]
+    pub fn objects(&self) -> impl Iterator<Item = (&(u16, u8), &ObjectEntry)> {
+        self.objects.iter()
+    }
+
+    /// The integer default value, the `$NODEID` is replaced by `node_id`.
+    pub fn default_value(&self, index: u16, sub_index: u8, node_id: u8) -> Option<u64> {
+        let default = self.object(index, sub_index)?.default.as_ref()?;
+        default.split('+').try_fold(0u64, |acc, term| {
+            let term = term.trim();
+            let value = match term.eq_ignore_ascii_case("$NODEID") {
+                true => node_id as u64,
+                false => parse_int(term)?,
+            };
+            Some(acc.wrapping_add(value))
+        })
+    }
+
+    /// The default PDO mapping of `number`(1-based), `None` if the PDO is not described.
+    pub fn pdo(&self, kind: PdoKind, number: u16, node_id: u8) -> Option<PdoMapping> {
+        let mut mapping = PdoMapping::new(kind, number, 0);
+        let comm = mapping.communication_index();
+        mapping.cob_id = self.default_value(comm, 1, node_id)? as u32;
+        mapping.transmission_type = self.default_value(comm, 2, node_id).unwrap_or(0xFF) as u8;
+
+        let index = mapping.mapping_index();
+        let count = self.default_value(index, 0, node_id).unwrap_or_default() as u8;
+        for sub_index in 1..=count {
+            let raw = self.default_value(index, sub_index, node_id)?;
+            mapping.objects.push(MappedObject::from(raw as u32));
+        }
+
+        Some(mapping)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) const SERVO_EDS: &str = r#"
+[FileInfo]
+FileName=servo.eds
+; comment
+
+[DeviceInfo]
+VendorName=ZLG
+ProductName=Servo Drive
+
+[1000]
+ParameterName=Device type
+ObjectType=0x7
+DataType=0x0007
+AccessType=ro
+DefaultValue=0x00020192
+PDOMapping=0
+
+[1018]
+ParameterName=Identity object
+ObjectType=0x9
+SubNumber=2
+
+[1018sub0]
+ParameterName=Number of entries
+DataType=0x0005
+AccessType=ro
+DefaultValue=1
+
+[1018sub1]
+ParameterName=Vendor ID
+DataType=0x0007
+AccessType=ro
+DefaultValue=0x12345678
+
+[1800]
+ParameterName=TPDO1 communication parameter
+ObjectType=0x9
+SubNumber=3
+
+[1800sub0]
+ParameterName=Highest sub-index supported
+DataType=0x0005
+AccessType=const
+DefaultValue=5
+
+[1800sub1]
+ParameterName=COB-ID
+DataType=0x0007
+AccessType=rw
+DefaultValue=$NODEID+0x180
+
+[1800sub2]
+ParameterName=Transmission type
+DataType=0x0005
+AccessType=rw
+DefaultValue=1
+
+[1A00]
+ParameterName=TPDO1 mapping parameter
+ObjectType=0x9
+SubNumber=3
+
+[1A00sub0]
+ParameterName=Number of mapped objects
+DataType=0x0005
+AccessType=rw
+DefaultValue=2
+
+[1A00sub1]
+ParameterName=Statusword
+DataType=0x0007
+AccessType=rw
+DefaultValue=0x60410010
+
+[1A00sub2]
+ParameterName=Position actual value
+DataType=0x0007
+AccessType=rw
+DefaultValue=0x60640020
+
+[6041]
+ParameterName=Statusword
+ObjectType=0x7
+DataType=0x0006
+AccessType=ro
+PDOMapping=1
+"#;
+
+    #[test]
+    fn eds_is_parsed() {
+        let eds = Eds::parse(SERVO_EDS).unwrap();
+        assert_eq!(eds.device_info("ProductName"), Some("Servo Drive"));
+        assert_eq!(eds.default_value(0x1000, 0, 5), Some(0x00020192));
+        assert_eq!(eds.default_value(0x1018, 1, 5), Some(0x12345678));
+        assert!(eds.object(0x1018, 0).is_some());
+        assert_eq!(eds.objects().filter(|((i, _), _)| *i == 0x1018).count(), 2);
+
+        let statusword = eds.object(0x6041, 0).unwrap();
+        assert_eq!(statusword.data_type, Some(DataType::Unsigned16));
+        assert!(statusword.pdo_mapping && !statusword.access.writable());
+
+        let tpdo = eds.pdo(PdoKind::Transmit, 1, 5).unwrap();
+        assert_eq!((tpdo.cob_id, tpdo.transmission_type), (0x185, 1));
+        assert_eq!(
+            tpdo.objects,
+            [
+                MappedObject::new(0x6041, 0, 16),
+                MappedObject::new(0x6064, 0, 32)
+            ]
+        );
+        assert!(eds.pdo(PdoKind::Receive, 1, 5).is_none());
+
+        assert!(Eds::parse("[1000]\nDataType=zero").is_err());
+    }
+
+    #[test]
+    fn unknown_data_type_is_kept() {
+        let eds = Eds::parse(
+            "[2000]\nParameterName=Position\nDataType=0x0010\nAccessType=ro\nDefaultValue=5\n\
+             [2001]\nParameterName=Status\nDataType=0x0005\nAccessType=ro",
+        )
+        .unwrap();
+        let position = eds.object(0x2000, 0).unwrap();
+        assert_eq!(
+            (position.name.as_str(), position.data_type),
+            ("Position", None)
+        );
+        assert_eq!(eds.default_value(0x2000, 0, 5), Some(5));
+        assert_eq!(
+            eds.object(0x2001, 0).unwrap().data_type,
+            Some(DataType::Unsigned8)
+        );
+    }
+}
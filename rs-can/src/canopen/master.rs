@@ -0,0 +1,942 @@
+use super::{
+    Emergency, NmtCommand, NmtState, PdoMapping, SdoConfig, COB_EMCY, COB_HEARTBEAT, COB_NMT,
+    COB_SYNC, MAX_NODE_ID, PDO_INVALID,
+};
+use crate::{
+    device::Device,
+    error::Error,
+    frame::{
+        identifier::{Id, StandardId},
+        Direction, Frame, FrameFormat,
+    },
+    CanResult,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+
+/// The max count of frames buffered for later transfers.
+const CANOPEN_QUEUE_SIZE: usize = 256;
+/// The max receive timeout(ms) when the nodes are monitored.
+const MONITOR_INTERVAL: u32 = 10;
+/// The toggle bit of node guarding response.
+const GUARD_TOGGLE: u8 = 0x80;
+
+/// Called when the state of node is changed, `None` means the node is lost
+/// by heartbeat or life guarding timeout.
+pub type StateCallback = Box<dyn Fn(u8, Option<NmtState>) + Send + Sync>;
+
+/// The messages received by master.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CanOpenEvent {
+    /// The TPDO registered by [`CanOpenMaster::add_pdo`] with the values of mapped objects.
+    Pdo {
+        cob_id: u16,
+        values: Vec<u64>,
+    },
+    Emergency(Emergency),
+}
+
+#[derive(Debug)]
+struct Monitor {
+    state: Option<NmtState>,
+    /// The heartbeat consumer time or node life time.
+    timeout: Option<Duration>,
+    last: Instant,
+    /// The expected toggle bit of node guarding.
+    toggle: Option<bool>,
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Self {
+            state: None,
+            timeout: None,
+            last: Instant::now(),
+            toggle: None,
+        }
+    }
+}
+
+/// A CANopen master on a channel of device.
+///
+/// The heartbeat and node guarding responses are processed while receiving, so
+/// [`CanOpenMaster::receive`] should be polled to monitor the nodes. Only one SDO
+/// transfer is in progress at a time, the frames which are not expected by the current
+/// transfer are buffered for the later transfers.
+///
+/// # Example
+/// ```ignore
+/// let eds = Eds::from_file("servo.eds")?;
+/// let mut master = CanOpenMaster::new(device, "can0".into());
+/// master.set_state_callback(|node, state| println!("node {} state: {:?}", node, state));
+/// master.add_heartbeat_consumer(5, Duration::from_millis(300));
+/// let tpdo = eds.pdo(PdoKind::Transmit, 1, 5).unwrap();
+/// master.configure_pdo(5, &tpdo).await?;
+/// master.add_pdo(tpdo);
+/// master.nmt(NmtCommand::Start, 5).await?;
+/// master.start_sync(Duration::from_millis(10));
+/// let event = master.receive(Some(100)).await?;
+/// ```
+pub struct CanOpenMaster<D: Device> {
+    pub(crate) device: Arc<D>,
+    pub(crate) channel: D::Channel,
+    pub(crate) sdo_config: SdoConfig,
+    pub(crate) sdo_lock: tokio::sync::Mutex<()>,
+    pending: Mutex<VecDeque<(u16, Vec<u8>)>>,
+    monitors: Mutex<HashMap<u8, Monitor>>,
+    callback: Option<StateCallback>,
+    pdos: Mutex<HashMap<u16, PdoMapping>>,
+    sync: Mutex<Option<JoinHandle<()>>>,
+    guards: Mutex<HashMap<u8, JoinHandle<()>>>,
+}
+
+impl<D> CanOpenMaster<D>
+where
+    D: Device + 'static,
+    D::Channel: Clone + Send + Sync,
+{
+    pub fn new(device: Arc<D>, channel: D::Channel) -> Self {
+        Self {
+            device,
+            channel,
+            sdo_config: Default::default(),
+            sdo_lock: Default::default(),
+            pending: Default::default(),
+            monitors: Default::default(),
+            callback: None,
+            pdos: Default::default(),
+            sync: Default::default(),
+            guards: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn device(&self) -> &Arc<D> {
+        &self.device
+    }
+
+    #[inline]
+    pub fn channel(&self) -> &D::Channel {
+        &self.channel
+    }
+
+    #[inline]
+    pub fn sdo_config(&self) -> &SdoConfig {
+        &self.sdo_config
+    }
+
+    pub fn set_sdo_config(&mut self, config: SdoConfig) -> &mut Self {
+        self.sdo_config = config;
+        self
+    }
+
+    pub fn set_state_callback(
+        &mut self,
+        callback: impl Fn(u8, Option<NmtState>) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// The last state reported by heartbeat or node guarding.
+    #[inline]
+    pub fn state(&self, node: u8) -> Option<NmtState> {
+        self.monitors.lock().unwrap().get(&node)?.state
+    }
+
+    /// Discard the buffered frames.
+    pub fn clear(&self) {
+        self.pending.lock().unwrap().clear();
+    }
+
+    /// Send the NMT command to `node`, `0` means all nodes.
+    pub async fn nmt(&self, command: NmtCommand, node: u8) -> CanResult<()> {
+        if node > MAX_NODE_ID {
+            return Err(Error::operation_error(format!("invalid node id: {}", node)));
+        }
+        self.write(COB_NMT, &[command as u8, node]).await
+    }
+
+    /// Send a SYNC.
+    #[inline]
+    pub async fn sync(&self) -> CanResult<()> {
+        self.write(COB_SYNC, &[]).await
+    }
+
+    /// Send SYNC every `interval` in background until stopped.
+    pub fn start_sync(&self, interval: Duration) {
+        let (device, channel) = (self.device.clone(), self.channel.clone());
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = transmit(device.as_ref(), &channel, COB_SYNC, &[], false).await {
+                    rsutil::warn!("RUST-CAN - CANopen SYNC error: {}", e);
+                }
+            }
+        });
+
+        if let Some(task) = self.sync.lock().unwrap().replace(task) {
+            task.abort();
+        }
+    }
+
+    pub fn stop_sync(&self) {
+        if let Some(task) = self.sync.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+
+    /// Consume the heartbeat of `node`, the node is lost without heartbeat in `timeout`.
+    pub fn add_heartbeat_consumer(&self, node: u8, timeout: Duration) {
+        let mut monitors = self.monitors.lock().unwrap();
+        let monitor = monitors.entry(node).or_default();
+        monitor.timeout = Some(timeout);
+        monitor.last = Instant::now();
+    }
+
+    /// Stop monitoring the node by heartbeat or node guarding.
+    pub fn remove_monitor(&self, node: u8) {
+        self.monitors.lock().unwrap().remove(&node);
+        if let Some(task) = self.guards.lock().unwrap().remove(&node) {
+            task.abort();
+        }
+    }
+
+    /// Guard `node` every `guard_time`, the node is lost without response in
+    /// `guard_time * life_time_factor`.
+    pub fn start_node_guarding(&self, node: u8, guard_time: Duration, life_time_factor: u8) {
+        {
+            let mut monitors = self.monitors.lock().unwrap();
+            let monitor = monitors.entry(node).or_default();
+            monitor.timeout = Some(guard_time * life_time_factor.max(1) as u32);
+            monitor.last = Instant::now();
+            monitor.toggle = Some(false);
+        }
+
+        let (device, channel) = (self.device.clone(), self.channel.clone());
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(guard_time);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = transmit(
+                    device.as_ref(),
+                    &channel,
+                    COB_HEARTBEAT + node as u16,
+                    &[0],
+                    true,
+                )
+                .await
+                {
+                    rsutil::warn!("RUST-CAN - CANopen node guarding error: {}", e);
+                }
+            }
+        });
+
+        if let Some(task) = self.guards.lock().unwrap().insert(node, task) {
+            task.abort();
+        }
+    }
+
+    /// Register the TPDO which is decoded by [`CanOpenMaster::receive`].
+    pub fn add_pdo(&self, mapping: PdoMapping) {
+        self.pdos.lock().unwrap().insert(mapping.can_id(), mapping);
+    }
+
+    pub fn remove_pdo(&self, cob_id: u16) -> Option<PdoMapping> {
+        self.pdos.lock().unwrap().remove(&cob_id)
+    }
+
+    /// Transmit the RPDO with the values of mapped objects.
+    pub async fn transmit_pdo(&self, mapping: &PdoMapping, values: &[u64]) -> CanResult<()> {
+        if !mapping.is_enabled() {
+            return Err(Error::operation_error(format!(
+                "PDO: {:#X} is disabled",
+                mapping.cob_id
+            )));
+        }
+        let data = mapping.encode(values)?;
+        self.write(mapping.can_id(), &data).await
+    }
+
+    /// Configure the communication and mapping parameters of PDO on `node` by SDO.
+    pub async fn configure_pdo(&self, node: u8, mapping: &PdoMapping) -> CanResult<()> {
+        let (comm, index) = (mapping.communication_index(), mapping.mapping_index());
+        // disable the PDO and clear the mapping before changing.
+        self.download(node, comm, 1, &(mapping.cob_id | PDO_INVALID).to_le_bytes())
+            .await?;
+        self.download(node, index, 0, &[0]).await?;
+        for (i, object) in mapping.objects.iter().enumerate() {
+            self.download(node, index, i as u8 + 1, &u32::from(*object).to_le_bytes())
+                .await?;
+        }
+        self.download(node, index, 0, &[mapping.objects.len() as u8])
+            .await?;
+        self.download(node, comm, 2, &[mapping.transmission_type])
+            .await?;
+        self.download(node, comm, 1, &mapping.cob_id.to_le_bytes())
+            .await
+    }
+
+    /// Receive the registered TPDO or emergency message, the state of nodes are
+    /// updated meanwhile.
+    pub async fn receive(&self, timeout: Option<u32>) -> CanResult<CanOpenEvent> {
+        let deadline = timeout.map(|ms| Instant::now() + Duration::from_millis(ms as u64));
+        let (cob_id, data) = self
+            .read(deadline, "receive", |cob_id, data| {
+                self.pdos.lock().unwrap().contains_key(&cob_id)
+                    || (Self::emergency_node(cob_id).is_some() && data.len() == 8)
+            })
+            .await?;
+
+        if let Some(node) = Self::emergency_node(cob_id) {
+            if data.len() == 8 {
+                let mut manufacturer = [0; 5];
+                manufacturer.copy_from_slice(&data[3..]);
+                return Ok(CanOpenEvent::Emergency(Emergency {
+                    node,
+                    error_code: u16::from_le_bytes([data[0], data[1]]),
+                    error_register: data[2],
+                    data: manufacturer,
+                }));
+            }
+        }
+
+        let values = match self.pdos.lock().unwrap().get(&cob_id) {
+            Some(mapping) => mapping.decode(&data)?,
+            None => return Err(Error::operation_error("PDO is removed")),
+        };
+        Ok(CanOpenEvent::Pdo { cob_id, values })
+    }
+
+    #[inline]
+    fn emergency_node(cob_id: u16) -> Option<u8> {
+        match cob_id.checked_sub(COB_EMCY) {
+            Some(node @ 1..=0x7F) => Some(node as u8),
+            _ => None,
+        }
+    }
+
+    pub(crate) async fn write(&self, cob_id: u16, data: &[u8]) -> CanResult<()> {
+        transmit(self.device.as_ref(), &self.channel, cob_id, data, false).await
+    }
+
+    /// Update the state by heartbeat or node guarding response.
+    fn update_state(&self, node: u8, value: u8) {
+        let mut monitors = self.monitors.lock().unwrap();
+        let monitor = monitors.entry(node).or_default();
+        if let Some(toggle) = monitor.toggle {
+            match value {
+                0x00 => monitor.toggle = Some(false),
+                v if (v & GUARD_TOGGLE != 0) != toggle => {
+                    rsutil::warn!("RUST-CAN - CANopen node: {} guarding toggle error", node);
+                    return;
+                }
+                _ => monitor.toggle = Some(!toggle),
+            }
+        }
+        let Ok(state) = NmtState::try_from(value & !GUARD_TOGGLE) else {
+            rsutil::warn!(
+                "RUST-CAN - CANopen node: {} invalid state: {:#04X}",
+                node,
+                value
+            );
+            return;
+        };
+
+        monitor.last = Instant::now();
+        if monitor.state != Some(state) {
+            monitor.state = Some(state);
+            drop(monitors);
+            if let Some(callback) = &self.callback {
+                callback(node, Some(state));
+            }
+        }
+    }
+
+    /// Report the nodes which are timeout.
+    fn check_monitors(&self) {
+        let lost = {
+            let mut monitors = self.monitors.lock().unwrap();
+            monitors
+                .iter_mut()
+                .filter(|(_, m)| {
+                    m.state.is_some() && m.timeout.is_some_and(|t| m.last.elapsed() > t)
+                })
+                .map(|(node, m)| {
+                    m.state = None;
+                    *node
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for node in lost {
+            rsutil::warn!("RUST-CAN - CANopen node: {} is lost", node);
+            if let Some(callback) = &self.callback {
+                callback(node, None);
+            }
+        }
+    }
+
+    /// Read the first frame matched by `expected`, the others are buffered.
+    pub(crate) async fn read(
+        &self,
+        deadline: Option<Instant>,
+        timer: &str,
+        expected: impl Fn(u16, &[u8]) -> bool,
+    ) -> CanResult<(u16, Vec<u8>)> {
+        loop {
+            {
+                let mut pending = self.pending.lock().unwrap();
+                if let Some(pos) = pending.iter().position(|(id, data)| expected(*id, data)) {
+                    return Ok(pending.remove(pos).unwrap());
+                }
+            }
+
+            let monitored = self
+                .monitors
+                .lock()
+                .unwrap()
+                .values()
+                .any(|m| m.timeout.is_some());
+            let timeout = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(Error::TimeoutError(format!(
+                            "{} at channel: {}",
+                            timer, self.channel
+                        )));
+                    }
+                    Some((deadline - now).as_millis().max(1) as u32)
+                }
+                None => None,
+            };
+            let timeout = match monitored {
+                true => Some(timeout.map_or(MONITOR_INTERVAL, |t| t.min(MONITOR_INTERVAL))),
+                false => timeout,
+            };
+            let frames = match self.device.receive(self.channel.clone(), timeout).await {
+                Ok(frames) => frames,
+                Err(Error::TimeoutError(_)) => Vec::new(),
+                Err(e) => return Err(e),
+            };
+
+            for frame in frames {
+                let Id::Standard(id) = frame.id() else {
+                    continue;
+                };
+                if frame.direction() != Direction::Receive || frame.format() != FrameFormat::Data {
+                    continue;
+                }
+                let cob_id = id.as_raw();
+                match (cob_id.checked_sub(COB_HEARTBEAT), frame.data()) {
+                    (Some(node @ 1..=0x7F), &[value]) => self.update_state(node as u8, value),
+                    _ => {
+                        let mut pending = self.pending.lock().unwrap();
+                        if pending.len() >= CANOPEN_QUEUE_SIZE {
+                            rsutil::warn!(
+                                "RUST-CAN - CANopen queue is full, the oldest frame is dropped"
+                            );
+                            pending.pop_front();
+                        }
+                        pending.push_back((cob_id, frame.data().to_vec()));
+                    }
+                }
+            }
+            self.check_monitors();
+        }
+    }
+}
+
+async fn transmit<D: Device>(
+    device: &D,
+    channel: &D::Channel,
+    cob_id: u16,
+    data: &[u8],
+    remote: bool,
+) -> CanResult<()>
+where
+    D::Channel: Clone,
+{
+    let id = Id::Standard(StandardId::new(cob_id)?);
+    let mut frame = match remote {
+        true => D::Frame::new_remote(id, data.len() as u8)?,
+        false => D::Frame::new_can(id, data)?,
+    };
+    frame.set_channel(channel.clone());
+    device.transmit(frame, None).await
+}
+
+impl<D: Device> Drop for CanOpenMaster<D> {
+    fn drop(&mut self) {
+        if let Some(task) = self.sync.get_mut().unwrap().take() {
+            task.abort();
+        }
+        for (_, task) in self.guards.get_mut().unwrap().drain() {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        canopen::{
+            eds::tests::SERVO_EDS, sdo_crc, CanOpenEvent, Eds, PdoKind, COB_SDO_RX, COB_SDO_TX,
+        },
+        device::ChannelConfig,
+        virtual_bus::{tests::TestFrame, VirtualBus},
+    };
+
+    type Bus = VirtualBus<TestFrame>;
+
+    /// The SDO block size of simulated node.
+    const BLOCK_SIZE: u8 = 4;
+
+    fn device(channel: &str) -> Arc<Bus> {
+        Arc::new(crate::virtual_bus::tests::device(
+            channel,
+            ChannelConfig::new(500_000),
+        ))
+    }
+
+    #[derive(Debug, Default)]
+    enum SdoState {
+        #[default]
+        Idle,
+        Upload(Vec<u8>, usize, u8),
+        Download((u16, u8), Vec<u8>, u8),
+        BlockUpload(Vec<u8>, usize, u8),
+        BlockDownload((u16, u8), Vec<u8>, u8),
+        BlockDownloadEnd((u16, u8), Vec<u8>),
+    }
+
+    /// A simulated node with the object dictionary.
+    struct Slave {
+        device: Arc<Bus>,
+        channel: String,
+        node: u8,
+        objects: HashMap<(u16, u8), Vec<u8>>,
+        heartbeat: Option<Duration>,
+        state: NmtState,
+        toggle: bool,
+        sdo: SdoState,
+    }
+
+    impl Slave {
+        fn new(channel: &str, node: u8) -> Self {
+            Self {
+                device: device(channel),
+                channel: channel.to_owned(),
+                node,
+                objects: Default::default(),
+                heartbeat: None,
+                state: NmtState::PreOperational,
+                toggle: false,
+                sdo: Default::default(),
+            }
+        }
+
+        async fn send(&self, cob_id: u16, data: &[u8]) {
+            transmit(self.device.as_ref(), &self.channel, cob_id, data, false)
+                .await
+                .unwrap();
+        }
+
+        fn spawn(mut self) -> JoinHandle<()> {
+            tokio::spawn(async move {
+                self.send(COB_HEARTBEAT + self.node as u16, &[NmtState::BootUp as u8])
+                    .await;
+                let mut last = Instant::now();
+                loop {
+                    let frames = self
+                        .device
+                        .receive(self.channel.clone(), Some(2))
+                        .await
+                        .unwrap_or_default();
+                    for frame in frames {
+                        if frame.direction() == Direction::Receive {
+                            self.handle(&frame).await;
+                        }
+                    }
+                    if self.heartbeat.is_some_and(|t| last.elapsed() >= t) {
+                        last = Instant::now();
+                        self.send(COB_HEARTBEAT + self.node as u16, &[self.state as u8])
+                            .await;
+                    }
+                }
+            })
+        }
+
+        async fn handle(&mut self, frame: &TestFrame) {
+            let cob_id = frame.id().as_raw() as u16;
+            let data = frame.data();
+            match cob_id {
+                COB_NMT if data.len() == 2 && [0, self.node].contains(&data[1]) => {
+                    self.state = match data[0] {
+                        0x01 => NmtState::Operational,
+                        0x02 => NmtState::Stopped,
+                        _ => NmtState::PreOperational,
+                    }
+                }
+                COB_SYNC if self.state == NmtState::Operational => {
+                    let mapping = self.tpdo();
+                    let values = mapping
+                        .objects
+                        .iter()
+                        .map(|o| {
+                            let mut buf = [0; 8];
+                            let value = &self.objects[&(o.index, o.sub_index)];
+                            buf[..value.len()].copy_from_slice(value);
+                            u64::from_le_bytes(buf)
+                        })
+                        .collect::<Vec<_>>();
+                    let data = mapping.encode(&values).unwrap();
+                    self.send(mapping.can_id(), &data).await;
+                }
+                _ if cob_id == COB_HEARTBEAT + self.node as u16 && frame.is_remote() => {
+                    let value = self.state as u8 | ((self.toggle as u8) << 7);
+                    self.toggle = !self.toggle;
+                    self.send(cob_id, &[value]).await;
+                }
+                _ if cob_id == COB_SDO_RX + self.node as u16 && data.len() == 8 => {
+                    let mut request = [0; 8];
+                    request.copy_from_slice(data);
+                    for response in self.sdo(request) {
+                        self.send(COB_SDO_TX + self.node as u16, &response).await;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        fn tpdo(&self) -> PdoMapping {
+            let value = |index, sub_index| {
+                let mut buf = [0; 4];
+                let value = &self.objects[&(index, sub_index)];
+                buf[..value.len()].copy_from_slice(value);
+                u32::from_le_bytes(buf)
+            };
+            let mut mapping = PdoMapping::new(PdoKind::Transmit, 1, value(0x1800, 1));
+            for sub_index in 1..=value(0x1A00, 0) as u8 {
+                mapping.objects.push(value(0x1A00, sub_index).into());
+            }
+            mapping
+        }
+
+        fn sdo(&mut self, request: [u8; 8]) -> Vec<[u8; 8]> {
+            let key = (u16::from_le_bytes([request[1], request[2]]), request[3]);
+            let response = |command: u8, data: [u8; 4]| {
+                let [d0, d1, d2, d3] = data;
+                [command, request[1], request[2], request[3], d0, d1, d2, d3]
+            };
+            let command = request[0];
+            match std::mem::take(&mut self.sdo) {
+                SdoState::BlockDownload(key, mut data, seqno) => {
+                    let (last, sn) = (command & 0x80 != 0, command & 0x7F);
+                    if sn != seqno + 1 {
+                        self.sdo = SdoState::BlockDownload(key, data, seqno);
+                        return vec![];
+                    }
+                    data.extend_from_slice(&request[1..]);
+                    match (last, sn == BLOCK_SIZE) {
+                        (true, _) => self.sdo = SdoState::BlockDownloadEnd(key, data),
+                        (false, true) => self.sdo = SdoState::BlockDownload(key, data, 0),
+                        (false, false) => {
+                            self.sdo = SdoState::BlockDownload(key, data, sn);
+                            return vec![];
+                        }
+                    }
+                    return vec![[0xA2, sn, BLOCK_SIZE, 0, 0, 0, 0, 0]];
+                }
+                SdoState::BlockDownloadEnd(key, mut data) if command & 0xE3 == 0xC1 => {
+                    data.truncate(data.len() - ((command >> 2) & 0x07) as usize);
+                    if sdo_crc(&data) != u16::from_le_bytes([request[1], request[2]]) {
+                        return vec![response(0x80, 0x0504_0004u32.to_le_bytes())];
+                    }
+                    self.objects.insert(key, data);
+                    return vec![[0xA1, 0, 0, 0, 0, 0, 0, 0]];
+                }
+                state => self.sdo = state,
+            }
+
+            match command >> 5 {
+                // upload initiate
+                2 => match self.objects.get(&key) {
+                    Some(data) if !data.is_empty() && data.len() <= 4 => {
+                        let mut buf = [0; 4];
+                        buf[..data.len()].copy_from_slice(data);
+                        vec![response(0x43 | ((4 - data.len() as u8) << 2), buf)]
+                    }
+                    Some(data) => {
+                        let size = (data.len() as u32).to_le_bytes();
+                        self.sdo = SdoState::Upload(data.clone(), 0, 0);
+                        vec![response(0x41, size)]
+                    }
+                    None => vec![response(0x80, 0x0602_0000u32.to_le_bytes())],
+                },
+                // upload segment
+                3 => match std::mem::take(&mut self.sdo) {
+                    SdoState::Upload(data, pos, toggle) if command & 0x10 == toggle => {
+                        let end = (pos + 7).min(data.len());
+                        let last = (end == data.len()) as u8;
+                        let unused = (7 - (end - pos)) as u8;
+                        let mut segment = [toggle | (unused << 1) | last, 0, 0, 0, 0, 0, 0, 0];
+                        segment[1..1 + end - pos].copy_from_slice(&data[pos..end]);
+                        if last == 0 {
+                            self.sdo = SdoState::Upload(data, end, toggle ^ 0x10);
+                        }
+                        vec![segment]
+                    }
+                    _ => vec![response(0x80, 0x0503_0000u32.to_le_bytes())],
+                },
+                // download initiate
+                1 => {
+                    if command & 0x02 != 0 {
+                        let size = 4 - ((command >> 2) & 0x03) as usize;
+                        self.objects.insert(key, request[4..4 + size].to_vec());
+                    } else {
+                        self.sdo = SdoState::Download(key, vec![], 0);
+                    }
+                    vec![response(0x60, [0; 4])]
+                }
+                // download segment
+                0 => match std::mem::take(&mut self.sdo) {
+                    SdoState::Download(key, mut data, toggle) if command & 0x10 == toggle => {
+                        let unused = ((command >> 1) & 0x07) as usize;
+                        data.extend_from_slice(&request[1..8 - unused]);
+                        match command & 0x01 {
+                            0 => self.sdo = SdoState::Download(key, data, toggle ^ 0x10),
+                            _ => {
+                                self.objects.insert(key, data);
+                            }
+                        }
+                        vec![[0x20 | toggle, 0, 0, 0, 0, 0, 0, 0]]
+                    }
+                    _ => vec![response(0x80, 0x0503_0000u32.to_le_bytes())],
+                },
+                // block upload
+                5 => match (command & 0x03, std::mem::take(&mut self.sdo)) {
+                    (0, _) => match self.objects.get(&key) {
+                        Some(data) => {
+                            let size = (data.len() as u32).to_le_bytes();
+                            self.sdo = SdoState::BlockUpload(data.clone(), 0, request[4]);
+                            vec![response(0xC6, size)]
+                        }
+                        None => vec![response(0x80, 0x0602_0000u32.to_le_bytes())],
+                    },
+                    (3, SdoState::BlockUpload(data, pos, size)) => self.block(data, pos, size),
+                    (2, SdoState::BlockUpload(data, pos, _)) if pos >= data.len() => {
+                        let unused = (7 - (data.len() - 1) % 7 - 1) as u8;
+                        let [lo, hi] = sdo_crc(&data).to_le_bytes();
+                        vec![[0xC1 | (unused << 2), lo, hi, 0, 0, 0, 0, 0]]
+                    }
+                    (2, SdoState::BlockUpload(data, pos, _)) => self.block(data, pos, request[2]),
+                    _ => vec![],
+                },
+                // block download
+                6 if command & 0x01 == 0 => {
+                    self.sdo = SdoState::BlockDownload(key, vec![], 0);
+                    vec![response(0xA4, [BLOCK_SIZE, 0, 0, 0])]
+                }
+                // abort
+                4 => vec![],
+                _ => vec![response(0x80, 0x0504_0001u32.to_le_bytes())],
+            }
+        }
+
+        /// The segments of next block.
+        fn block(&mut self, data: Vec<u8>, pos: usize, block_size: u8) -> Vec<[u8; 8]> {
+            let block_size = if block_size == 0 { 127 } else { block_size };
+            let mut segments = vec![];
+            let mut end = pos;
+            for seqno in 1..=block_size {
+                let start = end;
+                end = (start + 7).min(data.len());
+                let last = ((end == data.len()) as u8) << 7;
+                let mut segment = [last | seqno, 0, 0, 0, 0, 0, 0, 0];
+                segment[1..1 + end - start].copy_from_slice(&data[start..end]);
+                segments.push(segment);
+                if last != 0 {
+                    break;
+                }
+            }
+            self.sdo = SdoState::BlockUpload(data, end, block_size);
+            segments
+        }
+    }
+
+    fn master(channel: &str) -> CanOpenMaster<Bus> {
+        let mut master = CanOpenMaster::new(device(channel), channel.to_owned());
+        master.set_sdo_config(SdoConfig {
+            timeout: Duration::from_millis(100),
+            block_size: 3,
+            ..Default::default()
+        });
+        master
+    }
+
+    #[tokio::test]
+    async fn sdo_is_transferred() {
+        let channel = "canopen-sdo";
+        let master = master(channel);
+        let mut slave = Slave::new(channel, 5);
+        slave
+            .objects
+            .insert((0x1000, 0), vec![0x92, 0x01, 0x02, 0x00]);
+        let slave = slave.spawn();
+
+        assert_eq!(
+            master.upload(5, 0x1000, 0).await.unwrap(),
+            [0x92, 0x01, 0x02, 0x00]
+        );
+        master.download(5, 0x6060, 0, &[0x01]).await.unwrap();
+        assert_eq!(master.upload(5, 0x6060, 0).await.unwrap(), [0x01]);
+        master.download(5, 0x2001, 0, &[]).await.unwrap();
+        assert_eq!(master.upload(5, 0x2001, 0).await.unwrap(), []);
+
+        let name = b"Servo Drive with a long name".to_vec();
+        master.download(5, 0x1008, 0, &name).await.unwrap();
+        assert_eq!(master.upload(5, 0x1008, 0).await.unwrap(), name);
+
+        let firmware = (0..=255).cycle().take(300).collect::<Vec<u8>>();
+        master
+            .block_download(5, 0x1F50, 1, &firmware)
+            .await
+            .unwrap();
+        assert_eq!(master.upload(5, 0x1F50, 1).await.unwrap(), firmware);
+        assert_eq!(master.block_upload(5, 0x1F50, 1).await.unwrap(), firmware);
+        assert_eq!(master.block_upload(5, 0x1008, 0).await.unwrap(), name);
+
+        match master.upload(5, 0x2000, 0).await {
+            Err(Error::SdoAbort {
+                index,
+                sub_index,
+                code,
+            }) => assert_eq!((index, sub_index, code), (0x2000, 0, 0x0602_0000)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(
+            master.upload(6, 0x1000, 0).await,
+            Err(Error::TimeoutError(_))
+        ));
+        assert!(master.upload(0, 0x1000, 0).await.is_err());
+        slave.abort();
+    }
+
+    #[tokio::test]
+    async fn pdo_is_mapped_by_eds() {
+        let channel = "canopen-pdo";
+        let master = master(channel);
+        let mut slave = Slave::new(channel, 5);
+        slave
+            .objects
+            .insert((0x1800, 1), vec![0x85, 0x01, 0x00, 0x80]);
+        slave.objects.insert((0x1A00, 0), vec![0]);
+        slave.objects.insert((0x6041, 0), vec![0x37, 0x02]);
+        slave
+            .objects
+            .insert((0x6064, 0), 1000i32.to_le_bytes().to_vec());
+        let slave = slave.spawn();
+
+        let eds = Eds::parse(SERVO_EDS).unwrap();
+        let tpdo = eds.pdo(PdoKind::Transmit, 1, 5).unwrap();
+        master.configure_pdo(5, &tpdo).await.unwrap();
+        assert_eq!(master.upload(5, 0x1A00, 0).await.unwrap(), [2]);
+        assert_eq!(master.upload(5, 0x1800, 2).await.unwrap(), [1]);
+        master.add_pdo(tpdo);
+
+        // the TPDO is transmitted after SYNC in operational state.
+        master.nmt(NmtCommand::Start, 0).await.unwrap();
+        master.sync().await.unwrap();
+        assert_eq!(
+            master.receive(Some(100)).await.unwrap(),
+            CanOpenEvent::Pdo {
+                cob_id: 0x185,
+                values: vec![0x0237, 1000]
+            }
+        );
+
+        let emcy = [0x10, 0x81, 0x11, 1, 2, 3, 4, 5];
+        transmit(
+            master.device().as_ref(),
+            &channel.to_owned(),
+            COB_EMCY + 5,
+            &emcy,
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            master.receive(Some(100)).await,
+            Err(Error::TimeoutError(_))
+        ));
+        let other = device(channel);
+        transmit(
+            other.as_ref(),
+            &channel.to_owned(),
+            COB_EMCY + 5,
+            &emcy,
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            master.receive(Some(100)).await.unwrap(),
+            CanOpenEvent::Emergency(Emergency {
+                node: 5,
+                error_code: 0x8110,
+                error_register: 0x11,
+                data: [1, 2, 3, 4, 5]
+            })
+        );
+        slave.abort();
+    }
+
+    #[tokio::test]
+    async fn nodes_are_monitored() {
+        let channel = "canopen-monitor";
+        let mut master = master(channel);
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let cloned = states.clone();
+        master.set_state_callback(move |node, state| cloned.lock().unwrap().push((node, state)));
+
+        let mut slave = Slave::new(channel, 3);
+        slave.heartbeat = Some(Duration::from_millis(20));
+        master.add_heartbeat_consumer(3, Duration::from_millis(100));
+        master.start_node_guarding(4, Duration::from_millis(20), 3);
+        let (slave, guarded) = (slave.spawn(), Slave::new(channel, 4).spawn());
+
+        assert!(master.receive(Some(100)).await.is_err());
+        assert_eq!(master.state(3), Some(NmtState::PreOperational));
+        assert_eq!(master.state(4), Some(NmtState::PreOperational));
+        master.nmt(NmtCommand::Start, 3).await.unwrap();
+        assert!(master.receive(Some(100)).await.is_err());
+        assert_eq!(master.state(3), Some(NmtState::Operational));
+        assert_eq!(master.state(4), Some(NmtState::PreOperational));
+
+        slave.abort();
+        guarded.abort();
+        assert!(master.receive(Some(200)).await.is_err());
+        assert_eq!((master.state(3), master.state(4)), (None, None));
+
+        let states = states.lock().unwrap();
+        let node3 = states.iter().filter(|(n, _)| *n == 3).map(|(_, s)| *s);
+        assert_eq!(
+            node3.collect::<Vec<_>>(),
+            [
+                Some(NmtState::BootUp),
+                Some(NmtState::PreOperational),
+                Some(NmtState::Operational),
+                None
+            ]
+        );
+        assert_eq!(states.iter().rfind(|(n, _)| *n == 4), Some(&(4, None)));
+    }
+}
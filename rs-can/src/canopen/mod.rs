@@ -0,0 +1,133 @@
+//! CANopen(CiA 301) master over any [`Device`](crate::CanDevice).
+//!
+//! The [`CanOpenMaster`] sends NMT commands and SYNC, transfers the objects by
+//! expedited/segmented/block SDO, monitors the nodes by heartbeat or node guarding,
+//! and maps the PDOs described by an [`Eds`] file.
+
+mod eds;
+mod master;
+mod pdo;
+mod sdo;
+
+pub use eds::*;
+pub use master::*;
+pub use pdo::*;
+pub use sdo::*;
+
+/// The function codes of the predefined connection set, the COB-ID is function code + node id.
+pub const COB_NMT: u16 = 0x000;
+pub const COB_SYNC: u16 = 0x080;
+pub const COB_EMCY: u16 = 0x080;
+pub const COB_TPDO1: u16 = 0x180;
+pub const COB_RPDO1: u16 = 0x200;
+pub const COB_SDO_TX: u16 = 0x580;
+pub const COB_SDO_RX: u16 = 0x600;
+pub const COB_HEARTBEAT: u16 = 0x700;
+
+/// The max node id.
+pub const MAX_NODE_ID: u8 = 127;
+
+/// The NMT commands.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NmtCommand {
+    Start = 0x01,
+    Stop = 0x02,
+    EnterPreOperational = 0x80,
+    ResetNode = 0x81,
+    ResetCommunication = 0x82,
+}
+
+/// The NMT states reported by heartbeat or node guarding.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NmtState {
+    BootUp = 0x00,
+    Stopped = 0x04,
+    Operational = 0x05,
+    PreOperational = 0x7F,
+}
+
+impl TryFrom<u8> for NmtState {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(Self::BootUp),
+            0x04 => Ok(Self::Stopped),
+            0x05 => Ok(Self::Operational),
+            0x7F => Ok(Self::PreOperational),
+            v => Err(v),
+        }
+    }
+}
+
+/// The data types of object dictionary.
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DataType {
+    Boolean = 0x0001,
+    Integer8 = 0x0002,
+    Integer16 = 0x0003,
+    Integer32 = 0x0004,
+    Unsigned8 = 0x0005,
+    Unsigned16 = 0x0006,
+    Unsigned32 = 0x0007,
+    Real32 = 0x0008,
+    VisibleString = 0x0009,
+    OctetString = 0x000A,
+    UnicodeString = 0x000B,
+    Domain = 0x000F,
+    Real64 = 0x0011,
+    Integer64 = 0x0015,
+    Unsigned64 = 0x001B,
+}
+
+impl DataType {
+    /// The bits of the fixed size types, `None` for the strings and domain.
+    pub fn bits(&self) -> Option<u8> {
+        match self {
+            Self::Boolean => Some(1),
+            Self::Integer8 | Self::Unsigned8 => Some(8),
+            Self::Integer16 | Self::Unsigned16 => Some(16),
+            Self::Integer32 | Self::Unsigned32 | Self::Real32 => Some(32),
+            Self::Integer64 | Self::Unsigned64 | Self::Real64 => Some(64),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<u16> for DataType {
+    type Error = u16;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            0x0001 => Ok(Self::Boolean),
+            0x0002 => Ok(Self::Integer8),
+            0x0003 => Ok(Self::Integer16),
+            0x0004 => Ok(Self::Integer32),
+            0x0005 => Ok(Self::Unsigned8),
+            0x0006 => Ok(Self::Unsigned16),
+            0x0007 => Ok(Self::Unsigned32),
+            0x0008 => Ok(Self::Real32),
+            0x0009 => Ok(Self::VisibleString),
+            0x000A => Ok(Self::OctetString),
+            0x000B => Ok(Self::UnicodeString),
+            0x000F => Ok(Self::Domain),
+            0x0011 => Ok(Self::Real64),
+            0x0015 => Ok(Self::Integer64),
+            0x001B => Ok(Self::Unsigned64),
+            v => Err(v),
+        }
+    }
+}
+
+/// The emergency message of node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Emergency {
+    pub node: u8,
+    pub error_code: u16,
+    pub error_register: u8,
+    /// The manufacturer specific error code.
+    pub data: [u8; 5],
+}
@@ -0,0 +1,188 @@
+use crate::{error::Error, CanResult, MAX_FRAME_SIZE};
+
+/// The bit of PDO COB-ID which disables the PDO.
+pub const PDO_INVALID: u32 = 0x8000_0000;
+
+/// The direction of PDO, which is named from the view of node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PdoKind {
+    /// RPDO, transmitted by master and received by node.
+    Receive,
+    /// TPDO, transmitted by node and received by master.
+    Transmit,
+}
+
+/// An object mapped into PDO.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MappedObject {
+    pub index: u16,
+    pub sub_index: u8,
+    pub bits: u8,
+}
+
+impl MappedObject {
+    #[inline]
+    pub fn new(index: u16, sub_index: u8, bits: u8) -> Self {
+        Self {
+            index,
+            sub_index,
+            bits,
+        }
+    }
+}
+
+impl From<u32> for MappedObject {
+    #[inline]
+    fn from(v: u32) -> Self {
+        Self::new((v >> 16) as u16, (v >> 8) as u8, v as u8)
+    }
+}
+
+impl From<MappedObject> for u32 {
+    #[inline]
+    fn from(v: MappedObject) -> Self {
+        ((v.index as u32) << 16) | ((v.sub_index as u32) << 8) | v.bits as u32
+    }
+}
+
+/// The communication and mapping parameters of a PDO.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdoMapping {
+    pub kind: PdoKind,
+    /// The 1-based PDO number.
+    pub number: u16,
+    /// The COB-ID, the PDO is disabled if [`PDO_INVALID`] is set.
+    pub cob_id: u32,
+    /// `0` acyclic synchronous, `1..=240` every n SYNC, `254/255` event-driven.
+    pub transmission_type: u8,
+    pub objects: Vec<MappedObject>,
+}
+
+impl PdoMapping {
+    pub fn new(kind: PdoKind, number: u16, cob_id: u32) -> Self {
+        Self {
+            kind,
+            number,
+            cob_id,
+            transmission_type: 0xFF,
+            objects: Default::default(),
+        }
+    }
+
+    /// The index of communication parameter, `0x1400` for RPDO1 and `0x1800` for TPDO1.
+    #[inline]
+    pub fn communication_index(&self) -> u16 {
+        let base = match self.kind {
+            PdoKind::Receive => 0x1400,
+            PdoKind::Transmit => 0x1800,
+        };
+        base + self.number.saturating_sub(1)
+    }
+
+    /// The index of mapping parameter, `0x1600` for RPDO1 and `0x1A00` for TPDO1.
+    #[inline]
+    pub fn mapping_index(&self) -> u16 {
+        self.communication_index() + 0x200
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.cob_id & PDO_INVALID == 0
+    }
+
+    /// The 11-bit identifier.
+    #[inline]
+    pub fn can_id(&self) -> u16 {
+        (self.cob_id & 0x7FF) as u16
+    }
+
+    #[inline]
+    pub fn add_object(&mut self, object: MappedObject) -> &mut Self {
+        self.objects.push(object);
+        self
+    }
+
+    /// The total bits of mapped objects.
+    #[inline]
+    pub fn bits(&self) -> usize {
+        self.objects.iter().map(|o| o.bits as usize).sum()
+    }
+
+    /// Pack the values of mapped objects in little-endian.
+    pub fn encode(&self, values: &[u64]) -> CanResult<Vec<u8>> {
+        if values.len() != self.objects.len() {
+            return Err(Error::operation_error(format!(
+                "PDO values count: {}, expected: {}",
+                values.len(),
+                self.objects.len()
+            )));
+        }
+        let bits = self.bits();
+        if bits > MAX_FRAME_SIZE * 8 {
+            return Err(Error::InvalidDLC(bits.div_ceil(8)));
+        }
+
+        let mut raw = 0u64;
+        let mut offset = 0;
+        for (object, value) in self.objects.iter().zip(values) {
+            raw |= (value & mask(object.bits)) << offset;
+            offset += object.bits as usize;
+        }
+
+        Ok(raw.to_le_bytes()[..bits.div_ceil(8)].to_vec())
+    }
+
+    /// Unpack the values of mapped objects, the signed values are not extended.
+    pub fn decode(&self, data: &[u8]) -> CanResult<Vec<u64>> {
+        let bits = self.bits();
+        if data.len() < bits.div_ceil(8) || bits > MAX_FRAME_SIZE * 8 {
+            return Err(Error::InvalidDLC(data.len()));
+        }
+
+        let mut buf = [0; MAX_FRAME_SIZE];
+        let len = data.len().min(MAX_FRAME_SIZE);
+        buf[..len].copy_from_slice(&data[..len]);
+        let raw = u64::from_le_bytes(buf);
+        let mut offset = 0;
+        Ok(self
+            .objects
+            .iter()
+            .map(|object| {
+                let value = (raw >> offset) & mask(object.bits);
+                offset += object.bits as usize;
+                value
+            })
+            .collect())
+    }
+}
+
+#[inline]
+fn mask(bits: u8) -> u64 {
+    match bits {
+        64.. => u64::MAX,
+        bits => (1 << bits) - 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pdo_is_packed() {
+        let mut mapping = PdoMapping::new(PdoKind::Receive, 2, 0x305);
+        mapping
+            .add_object(MappedObject::from(0x60400010))
+            .add_object(MappedObject::new(0x6060, 0, 8))
+            .add_object(MappedObject::new(0x2000, 1, 1));
+        assert_eq!(mapping.communication_index(), 0x1401);
+        assert_eq!(mapping.mapping_index(), 0x1601);
+        assert_eq!(u32::from(mapping.objects[1]), 0x60600008);
+
+        let data = mapping.encode(&[0x000F, 0x01, 1]).unwrap();
+        assert_eq!(data, [0x0F, 0x00, 0x01, 0x01]);
+        assert_eq!(mapping.decode(&data).unwrap(), [0x000F, 0x01, 1]);
+        assert!(mapping.decode(&data[..3]).is_err());
+        assert!(mapping.encode(&[0]).is_err());
+    }
+}
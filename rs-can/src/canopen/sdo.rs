@@ -0,0 +1,403 @@
+use super::{CanOpenMaster, COB_SDO_RX, COB_SDO_TX, MAX_NODE_ID};
+use crate::{device::Device, error::Error, CanResult};
+use std::time::{Duration, Instant};
+
+/// The abort code of SDO protocol timed out.
+pub const SDO_ABORT_TIMEOUT: u32 = 0x0504_0000;
+/// The abort code of toggle bit not alternated.
+pub const SDO_ABORT_TOGGLE: u32 = 0x0503_0000;
+/// The abort code of command specifier not valid or unknown.
+pub const SDO_ABORT_COMMAND: u32 = 0x0504_0001;
+/// The abort code of invalid sequence number.
+pub const SDO_ABORT_SEQUENCE: u32 = 0x0504_0003;
+/// The abort code of CRC error.
+pub const SDO_ABORT_CRC: u32 = 0x0504_0004;
+
+const SDO_ABORT: u8 = 0x80;
+/// The max data size of expedited transfer.
+const EXPEDITED_SIZE: usize = 4;
+/// The data size of each segment.
+const SEGMENT_SIZE: usize = 7;
+/// The max capacity reserved by the size indicated by server, the data grows as it arrives.
+const MAX_RESERVED_SIZE: usize = 0x1000;
+
+/// The parameters of SDO client.
+#[derive(Debug, Clone, Copy)]
+pub struct SdoConfig {
+    /// The timeout of waiting response.
+    pub timeout: Duration,
+    /// The max segments of each block, `1..=127`.
+    pub block_size: u8,
+    /// Whether the CRC is checked in block transfer.
+    pub crc: bool,
+}
+
+impl Default for SdoConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(1000),
+            block_size: 127,
+            crc: true,
+        }
+    }
+}
+
+/// The CRC-16-CCITT(XMODEM) used by SDO block transfer.
+pub fn sdo_crc(data: &[u8]) -> u16 {
+    data.iter().fold(0, |crc, v| {
+        (0..8).fold(crc ^ ((*v as u16) << 8), |crc, _| match crc & 0x8000 {
+            0 => crc << 1,
+            _ => (crc << 1) ^ 0x1021,
+        })
+    })
+}
+
+/// An SDO transfer to the object of node.
+struct Transfer<'a, D: Device> {
+    master: &'a CanOpenMaster<D>,
+    node: u8,
+    index: u16,
+    sub_index: u8,
+}
+
+impl<D> Transfer<'_, D>
+where
+    D: Device + 'static,
+    D::Channel: Clone + Send + Sync,
+{
+    /// The request with the multiplexer.
+    #[inline]
+    fn initiate(&self, command: u8, data: [u8; 4]) -> [u8; 8] {
+        let [lo, hi] = self.index.to_le_bytes();
+        let [d0, d1, d2, d3] = data;
+        [command, lo, hi, self.sub_index, d0, d1, d2, d3]
+    }
+
+    #[inline]
+    async fn send(&self, data: &[u8]) -> CanResult<()> {
+        let mut frame = [0; 8];
+        frame[..data.len()].copy_from_slice(data);
+        self.master
+            .write(COB_SDO_RX + self.node as u16, &frame)
+            .await
+    }
+
+    /// Send the abort and return the error.
+    async fn abort(&self, code: u32, error: Error) -> Error {
+        let request = self.initiate(SDO_ABORT, code.to_le_bytes());
+        if let Err(e) = self.send(&request).await {
+            rsutil::warn!("RUST-CAN - SDO abort error: {}", e);
+        }
+        error
+    }
+
+    async fn abort_invalid(&self, response: &[u8]) -> Error {
+        let error = Error::InvalidFrame(format!("SDO unexpected response: {:02X?}", response));
+        self.abort(SDO_ABORT_COMMAND, error).await
+    }
+
+    /// Receive the response of server, the abort is returned as error.
+    async fn receive(&self) -> CanResult<[u8; 8]> {
+        let deadline = Some(Instant::now() + self.master.sdo_config.timeout);
+        let cob_id = COB_SDO_TX + self.node as u16;
+        let response = match self
+            .master
+            .read(deadline, "SDO", |id, data| id == cob_id && data.len() == 8)
+            .await
+        {
+            Ok((_, data)) => data,
+            Err(e @ Error::TimeoutError(_)) => return Err(self.abort(SDO_ABORT_TIMEOUT, e).await),
+            Err(e) => return Err(e),
+        };
+
+        let mut buf = [0; 8];
+        buf.copy_from_slice(&response);
+        if buf[0] == SDO_ABORT {
+            return Err(Error::SdoAbort {
+                index: u16::from_le_bytes([buf[1], buf[2]]),
+                sub_index: buf[3],
+                code: u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]),
+            });
+        }
+
+        Ok(buf)
+    }
+
+    /// Receive the response with the expected command and multiplexer.
+    async fn receive_initiate(&self, command: u8, mask: u8) -> CanResult<[u8; 8]> {
+        let response = self.receive().await?;
+        let [_, lo, hi, sub_index, ..] = response;
+        if response[0] & mask != command || [lo, hi, sub_index] != self.initiate(0, [0; 4])[1..4] {
+            return Err(self.abort_invalid(&response).await);
+        }
+        Ok(response)
+    }
+
+    async fn upload(&self) -> CanResult<Vec<u8>> {
+        self.send(&self.initiate(0x40, [0; 4])).await?;
+        let response = self.receive_initiate(0x40, 0xE0).await?;
+
+        let command = response[0];
+        let (expedited, size_indicated) = (command & 0x02 != 0, command & 0x01 != 0);
+        if expedited {
+            let size = match size_indicated {
+                true => EXPEDITED_SIZE - ((command >> 2) & 0x03) as usize,
+                false => EXPEDITED_SIZE,
+            };
+            return Ok(response[4..4 + size].to_vec());
+        }
+
+        let size = u32::from_le_bytes([response[4], response[5], response[6], response[7]]);
+        let mut data = Vec::with_capacity((size as usize).min(MAX_RESERVED_SIZE));
+        let mut toggle = 0;
+        loop {
+            self.send(&[0x60 | toggle]).await?;
+            let response = self.receive().await?;
+            if response[0] & 0xE0 != 0x00 {
+                return Err(self.abort_invalid(&response).await);
+            }
+            if response[0] & 0x10 != toggle {
+                let error = Error::InvalidFrame("SDO toggle bit error".into());
+                return Err(self.abort(SDO_ABORT_TOGGLE, error).await);
+            }
+            let unused = ((response[0] >> 1) & 0x07) as usize;
+            data.extend_from_slice(&response[1..8 - unused]);
+            if response[0] & 0x01 != 0 {
+                break;
+            }
+            toggle ^= 0x10;
+        }
+        if size_indicated && data.len() != size as usize {
+            rsutil::warn!(
+                "RUST-CAN - SDO upload size: {}, indicated: {}",
+                data.len(),
+                size
+            );
+        }
+
+        Ok(data)
+    }
+
+    async fn download(&self, data: &[u8]) -> CanResult<()> {
+        // the expedited transfer can't indicate empty data, which is sent by a segment.
+        if !data.is_empty() && data.len() <= EXPEDITED_SIZE {
+            let mut buf = [0; 4];
+            buf[..data.len()].copy_from_slice(data);
+            let unused = (EXPEDITED_SIZE - data.len()) as u8;
+            self.send(&self.initiate(0x23 | (unused << 2), buf)).await?;
+            self.receive_initiate(0x60, 0xFF).await?;
+            return Ok(());
+        }
+
+        let size = (data.len() as u32).to_le_bytes();
+        self.send(&self.initiate(0x21, size)).await?;
+        self.receive_initiate(0x60, 0xFF).await?;
+
+        let mut toggle = 0;
+        let segments = match data.is_empty() {
+            true => vec![data],
+            false => data.chunks(SEGMENT_SIZE).collect::<Vec<_>>(),
+        };
+        let count = segments.len();
+        for (i, chunk) in segments.into_iter().enumerate() {
+            let unused = (SEGMENT_SIZE - chunk.len()) as u8;
+            let last = (i + 1 == count) as u8;
+            let mut segment = vec![toggle | (unused << 1) | last];
+            segment.extend_from_slice(chunk);
+            self.send(&segment).await?;
+
+            let response = self.receive().await?;
+            if response[0] & 0xE0 != 0x20 {
+                return Err(self.abort_invalid(&response).await);
+            }
+            if response[0] & 0x10 != toggle {
+                let error = Error::InvalidFrame("SDO toggle bit error".into());
+                return Err(self.abort(SDO_ABORT_TOGGLE, error).await);
+            }
+            toggle ^= 0x10;
+        }
+
+        Ok(())
+    }
+
+    async fn block_upload(&self) -> CanResult<Vec<u8>> {
+        let config = self.master.sdo_config;
+        let block_size = config.block_size.clamp(1, 127);
+        // the protocol switch threshold is 0, so the server always uses block transfer.
+        let request = self.initiate(0xA0 | ((config.crc as u8) << 2), [block_size, 0, 0, 0]);
+        self.send(&request).await?;
+        let response = self.receive_initiate(0xC0, 0xE1).await?;
+        let crc = config.crc && response[0] & 0x04 != 0;
+        let size = match response[0] & 0x02 {
+            0 => None,
+            _ => Some(u32::from_le_bytes([
+                response[4],
+                response[5],
+                response[6],
+                response[7],
+            ])),
+        };
+
+        self.send(&[0xA3]).await?;
+        let mut data =
+            Vec::with_capacity((size.unwrap_or_default() as usize).min(MAX_RESERVED_SIZE));
+        let mut last = [0; SEGMENT_SIZE];
+        loop {
+            let mut seqno = 0;
+            let finished = loop {
+                let segment = self.receive().await?;
+                let (finished, sn) = (segment[0] & 0x80 != 0, segment[0] & 0x7F);
+                if sn != seqno + 1 {
+                    let error = Error::InvalidFrame(format!(
+                        "SDO wrong sequence number: {}, expected: {}",
+                        sn,
+                        seqno + 1
+                    ));
+                    return Err(self.abort(SDO_ABORT_SEQUENCE, error).await);
+                }
+                seqno = sn;
+                data.extend_from_slice(&segment[1..]);
+                last.copy_from_slice(&segment[1..]);
+                if finished || seqno == block_size {
+                    break finished;
+                }
+            };
+            self.send(&[0xA2, seqno, block_size]).await?;
+            if finished {
+                break;
+            }
+        }
+
+        let response = self.receive().await?;
+        if response[0] & 0xE3 != 0xC1 {
+            return Err(self.abort_invalid(&response).await);
+        }
+        let unused = ((response[0] >> 2) & 0x07) as usize;
+        data.truncate(data.len() - unused);
+        if crc && sdo_crc(&data) != u16::from_le_bytes([response[1], response[2]]) {
+            let error = Error::InvalidFrame("SDO block upload CRC error".into());
+            return Err(self.abort(SDO_ABORT_CRC, error).await);
+        }
+        self.send(&[0xA1]).await?;
+
+        if let Some(size) = size.filter(|v| *v as usize != data.len()) {
+            rsutil::warn!(
+                "RUST-CAN - SDO upload size: {}, indicated: {}",
+                data.len(),
+                size
+            );
+        }
+
+        Ok(data)
+    }
+
+    async fn block_download(&self, data: &[u8]) -> CanResult<()> {
+        let config = self.master.sdo_config;
+        let size = (data.len() as u32).to_le_bytes();
+        self.send(&self.initiate(0xC2 | ((config.crc as u8) << 2), size))
+            .await?;
+        let response = self.receive_initiate(0xA0, 0xE3).await?;
+        let crc = config.crc && response[0] & 0x04 != 0;
+        let mut block_size = response[4];
+
+        let segments = match data.is_empty() {
+            true => vec![data],
+            false => data.chunks(SEGMENT_SIZE).collect::<Vec<_>>(),
+        };
+        let mut start = 0;
+        while start < segments.len() {
+            if !(1..=127).contains(&block_size) {
+                return Err(self.abort_invalid(&response).await);
+            }
+            let end = (start + block_size as usize).min(segments.len());
+            for (i, segment) in segments[start..end].iter().enumerate() {
+                let last = ((start + i + 1 == segments.len()) as u8) << 7;
+                let mut frame = vec![last | (i as u8 + 1)];
+                frame.extend_from_slice(segment);
+                self.send(&frame).await?;
+            }
+
+            let response = self.receive().await?;
+            if response[0] & 0xE3 != 0xA2 || response[1] as usize > end - start {
+                return Err(self.abort_invalid(&response).await);
+            }
+            // the segments after the acknowledged are retransmitted.
+            start += response[1] as usize;
+            block_size = response[2];
+        }
+
+        let unused = (SEGMENT_SIZE - segments.last().map_or(0, |v| v.len())) as u8;
+        let checksum = match crc {
+            true => sdo_crc(data),
+            false => 0,
+        }
+        .to_le_bytes();
+        self.send(&[0xC1 | (unused << 2), checksum[0], checksum[1]])
+            .await?;
+        let response = self.receive().await?;
+        if response[0] & 0xE3 != 0xA1 {
+            return Err(self.abort_invalid(&response).await);
+        }
+
+        Ok(())
+    }
+}
+
+impl<D> CanOpenMaster<D>
+where
+    D: Device + 'static,
+    D::Channel: Clone + Send + Sync,
+{
+    #[inline]
+    fn transfer(&self, node: u8, index: u16, sub_index: u8) -> CanResult<Transfer<'_, D>> {
+        if !(1..=MAX_NODE_ID).contains(&node) {
+            return Err(Error::operation_error(format!("invalid node id: {}", node)));
+        }
+        Ok(Transfer {
+            master: self,
+            node,
+            index,
+            sub_index,
+        })
+    }
+
+    /// Upload(read) the object by expedited or segmented transfer.
+    pub async fn upload(&self, node: u8, index: u16, sub_index: u8) -> CanResult<Vec<u8>> {
+        let transfer = self.transfer(node, index, sub_index)?;
+        let _lock = self.sdo_lock.lock().await;
+        transfer.upload().await
+    }
+
+    /// Download(write) the object, the data longer than 4 bytes is transferred by segments.
+    pub async fn download(
+        &self,
+        node: u8,
+        index: u16,
+        sub_index: u8,
+        data: &[u8],
+    ) -> CanResult<()> {
+        let transfer = self.transfer(node, index, sub_index)?;
+        let _lock = self.sdo_lock.lock().await;
+        transfer.download(data).await
+    }
+
+    /// Upload(read) the large object by block transfer.
+    pub async fn block_upload(&self, node: u8, index: u16, sub_index: u8) -> CanResult<Vec<u8>> {
+        let transfer = self.transfer(node, index, sub_index)?;
+        let _lock = self.sdo_lock.lock().await;
+        transfer.block_upload().await
+    }
+
+    /// Download(write) the large object by block transfer.
+    pub async fn block_download(
+        &self,
+        node: u8,
+        index: u16,
+        sub_index: u8,
+        data: &[u8],
+    ) -> CanResult<()> {
+        let transfer = self.transfer(node, index, sub_index)?;
+        let _lock = self.sdo_lock.lock().await;
+        transfer.block_download(data).await
+    }
+}
@@ -12,3 +12,14 @@ pub const MAX_FD_FRAME_SIZE: usize = 64;
 pub const MAX_XL_FRAME_SIZE: usize = 2048;
 /// Default padding value(0b1010_1010).
 pub const DEFAULT_PADDING: u8 = 0xAA;
+/// The trace file(`String`) played by [`ReplayDevice`](crate::ReplayDevice),
+/// the format is detected by extension: `.asc`, `.blf`, otherwise `candump -L`.
+pub const REPLAY_FILE: &str = "replay-file";
+/// The speed factor(`f64`) of replay, `0` plays as fast as possible. Default is `1.0`.
+pub const REPLAY_SPEED: &str = "replay-speed";
+/// Whether to restart the replay at the end of trace(`bool`).
+pub const REPLAY_LOOP: &str = "replay-loop";
+/// Whether to validate the transmitted frames against the recorded Tx frames(`bool`).
+pub const REPLAY_VALIDATE_TX: &str = "replay-validate-tx";
+/// The channel(`String`) in trace which is played on the configured channel.
+pub const REPLAY_SOURCE: &str = "replay-source";
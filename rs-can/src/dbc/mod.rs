@@ -0,0 +1,443 @@
+//! Vector DBC database.
+//!
+//! The messages, signals, value tables, multiplexing(include the extended multiplexing
+//! by `SG_MUL_VAL_`), comments and attributes are parsed, the other statements are skipped.
+//!
+//! ```ignore
+//! let dbc = Dbc::from_file("vehicle.dbc")?;
+//! for frame in device.receive(channel, Some(10)).await? {
+//!     let (message, values) = dbc.decode(&frame)?;
+//!     for value in values {
+//!         println!("{}.{} = {} {}", message.name, value.name(), value.value, value.signal.unit);
+//!     }
+//! }
+//!
+//! let frame: SocketCanFrame = dbc.encode("EngineData", &[("EngineSpeed", 1500.0)])?;
+//! device.transmit(frame, None).await?;
+//! ```
+
+mod parser;
+mod signal;
+
+pub use signal::*;
+
+use crate::{error::Error, frame::Frame, CanFdFlags, CanResult, MAX_FRAME_SIZE};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    str::FromStr,
+};
+
+/// The descriptions of raw values.
+pub type ValueTable = BTreeMap<i64, String>;
+
+/// The value of attribute, the enum value is the index of enum.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+impl AttributeValue {
+    #[inline]
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Integer(v) => Some(*v),
+            Self::Float(v) => Some(*v as i64),
+            Self::String(_) => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Integer(v) => Some(*v as f64),
+            Self::Float(v) => Some(*v),
+            Self::String(_) => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// The object which the attribute is defined for.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttributeObject {
+    #[default]
+    Network,
+    Node,
+    Message,
+    Signal,
+    EnvironmentVariable,
+}
+
+/// The value type of attribute definition.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeKind {
+    Integer(i64, i64),
+    Hex(i64, i64),
+    Float(f64, f64),
+    String,
+    Enum(Vec<String>),
+}
+
+/// The attribute defined by `BA_DEF_` and `BA_DEF_DEF_`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeDefinition {
+    pub object: AttributeObject,
+    pub kind: AttributeKind,
+    pub default: Option<AttributeValue>,
+}
+
+/// A node(ECU) of network.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Node {
+    pub name: String,
+    pub comment: Option<String>,
+    pub attributes: HashMap<String, AttributeValue>,
+}
+
+/// The DBC database.
+#[derive(Debug, Default, Clone)]
+pub struct Dbc {
+    pub version: String,
+    pub nodes: Vec<Node>,
+    pub messages: Vec<Message>,
+    /// The value tables defined by `VAL_TABLE_`.
+    pub value_tables: HashMap<String, ValueTable>,
+    pub definitions: HashMap<String, AttributeDefinition>,
+    pub comment: Option<String>,
+    /// The attributes of network.
+    pub attributes: HashMap<String, AttributeValue>,
+}
+
+impl FromStr for Dbc {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parser::Parser::new(s).parse()
+    }
+}
+
+impl Dbc {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> CanResult<Self> {
+        let content = std::fs::read(path).map_err(|e| Error::OperationError(e.to_string()))?;
+        // the DBC files are usually encoded in Windows-1252, the invalid UTF-8 is replaced.
+        Self::parse(&String::from_utf8_lossy(&content))
+    }
+
+    #[inline]
+    pub fn parse(content: &str) -> CanResult<Self> {
+        content.parse()
+    }
+
+    #[inline]
+    pub fn node(&self, name: &str) -> Option<&Node> {
+        self.nodes.iter().find(|n| n.name == name)
+    }
+
+    /// The message of CAN identifier.
+    pub fn message(&self, id: crate::CanId) -> Option<&Message> {
+        self.messages
+            .iter()
+            .find(|m| m.can_id().is_ok_and(|v| v == id))
+    }
+
+    #[inline]
+    pub fn message_by_name(&self, name: &str) -> Option<&Message> {
+        self.messages.iter().find(|m| m.name == name)
+    }
+
+    /// The attribute value of network, node, message or signal, the default value
+    /// is returned if it's not assigned.
+    pub fn attribute<'a>(
+        &'a self,
+        attributes: &'a HashMap<String, AttributeValue>,
+        name: &str,
+    ) -> Option<&'a AttributeValue> {
+        attributes
+            .get(name)
+            .or_else(|| self.definitions.get(name)?.default.as_ref())
+    }
+
+    /// Decode the signals of frame.
+    pub fn decode<'a, F: Frame>(
+        &'a self,
+        frame: &F,
+    ) -> CanResult<(&'a Message, Vec<SignalValue<'a>>)> {
+        let id = frame.id();
+        let message = self
+            .message(id)
+            .ok_or(Error::InvalidIdentifier(id.as_raw()))?;
+        Ok((message, message.decode(frame.data())?))
+    }
+
+    /// Encode the signals of message into frame, the message longer than 8 bytes is CAN-FD.
+    pub fn encode<F: Frame>(&self, message: &str, values: &[(&str, f64)]) -> CanResult<F> {
+        let message = self
+            .message_by_name(message)
+            .ok_or_else(|| Error::operation_error(format!("message `{}` is not found", message)))?;
+        let data = message.encode(values)?;
+        let id = message.can_id()?;
+        match data.len() {
+            0..=MAX_FRAME_SIZE => F::new_can(id, &data),
+            _ => F::new_can_fd(id, &data, CanFdFlags::BRS),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        frame::identifier::{ExtendedId, Id, StandardId},
+        virtual_bus::tests::TestFrame,
+    };
+
+    const VEHICLE_DBC: &str = r#"VERSION "1.0"
+
+NS_ :
+    NS_DESC_
+    CM_
+    BA_DEF_
+    VAL_TABLE_
+    SG_MUL_VAL_
+
+BS_:
+
+BU_: Engine Gateway
+
+VAL_TABLE_ Gears 0 "Neutral" 1 "First" 2 "Second" -1 "Reverse" ;
+
+BO_ 256 EngineData: 8 Engine
+ SG_ EngineSpeed : 0|16@1+ (0.25,0) [0|16383.75] "rpm" Gateway
+ SG_ Temperature : 16|8@1- (1,-40) [-168|87] "degC" Gateway
+ SG_ Gear : 31|4@0- (1,0) [-1|2] "" Gateway
+ SG_ Torque : 39|12@0+ (0.5,-1000) [-1000|1047.5] "Nm" Gateway,Engine
+
+BO_ 2147484672 Diagnostics: 8 Gateway
+ SG_ Mode M : 0|8@1+ (1,0) [0|255] "" Engine
+ SG_ Voltage m1 : 8|16@1+ (0.001,0) [0|65.535] "V" Engine
+ SG_ Current m2 : 8|16@1- (0.01,0) [-327.68|327.67] "A" Engine
+ SG_ Sub m3M : 8|8@1+ (1,0) [0|255] "" Engine
+ SG_ Pressure m0 : 16|32@1+ (1,0) [0|0] "kPa" Engine
+
+BO_ 1024 Status: 12 Gateway
+ SG_ Counter : 0|4@1+ (1,0) [0|15] "" Engine
+ SG_ Ratio : 32|32@1+ (1,0) [0|0] "" Engine
+
+CM_ "Vehicle network";
+CM_ BU_ Engine "Engine control unit";
+CM_ BO_ 256 "Engine data
+in two lines";
+CM_ SG_ 256 EngineSpeed "The speed of \"crank\" shaft";
+BA_DEF_ BO_ "GenMsgCycleTime" INT 0 65535;
+BA_DEF_ SG_ "GenSigStartValue" FLOAT -3.4E+038 3.4E+038;
+BA_DEF_ "BusType" STRING ;
+BA_DEF_ BO_ "VFrameFormat" ENUM "StandardCAN","ExtendedCAN","StandardCAN_FD";
+BA_DEF_DEF_ "GenMsgCycleTime" 100;
+BA_DEF_DEF_ "GenSigStartValue" 0;
+BA_DEF_DEF_ "BusType" "CAN";
+BA_DEF_DEF_ "VFrameFormat" "StandardCAN";
+BA_ "BusType" "CAN FD";
+BA_ "GenMsgCycleTime" BO_ 256 10;
+BA_ "GenSigStartValue" SG_ 256 EngineSpeed 800.5;
+BA_ "VFrameFormat" BO_ 1024 2;
+VAL_ 256 Gear 0 "Neutral" 1 "First" 2 "Second" -1 "Reverse" ;
+SG_MUL_VAL_ 2147484672 Pressure Sub 0-3, 5-5;
+SG_MUL_VAL_ 2147484672 Sub Mode 3-3;
+SIG_VALTYPE_ 1024 Ratio : 1;
+"#;
+
+    #[test]
+    fn dbc_is_parsed() {
+        let dbc = Dbc::parse(VEHICLE_DBC).unwrap();
+        assert_eq!(dbc.version, "1.0");
+        assert_eq!(dbc.comment.as_deref(), Some("Vehicle network"));
+        assert_eq!(dbc.nodes.len(), 2);
+        assert_eq!(
+            dbc.node("Engine").unwrap().comment.as_deref(),
+            Some("Engine control unit")
+        );
+        assert_eq!(dbc.value_tables["Gears"][&-1], "Reverse");
+        assert_eq!(dbc.messages.len(), 3);
+
+        let engine = dbc.message_by_name("EngineData").unwrap();
+        assert_eq!(
+            engine.can_id().unwrap(),
+            Id::Standard(StandardId::new(0x100).unwrap())
+        );
+        assert_eq!((engine.size, engine.transmitter.as_str()), (8, "Engine"));
+        assert_eq!(engine.comment.as_deref(), Some("Engine data\nin two lines"));
+        let speed = engine.signal("EngineSpeed").unwrap();
+        assert_eq!((speed.factor, speed.max), (0.25, 16383.75));
+        assert_eq!(speed.unit, "rpm");
+        assert_eq!(
+            speed.comment.as_deref(),
+            Some("The speed of \"crank\" shaft")
+        );
+        let torque = engine.signal("Torque").unwrap();
+        assert_eq!(
+            (torque.byte_order, torque.value_type),
+            (ByteOrder::BigEndian, ValueType::Unsigned)
+        );
+        assert_eq!(torque.receivers, ["Gateway", "Engine"]);
+        assert_eq!(engine.signal("Gear").unwrap().values[&2], "Second");
+
+        let diag = dbc.message_by_name("Diagnostics").unwrap();
+        assert_eq!(
+            diag.can_id().unwrap(),
+            Id::Extended(ExtendedId::new(0x400).unwrap())
+        );
+        let sub = diag.signal("Sub").unwrap();
+        assert_eq!(sub.multiplexing, Multiplexing::MultiplexedMultiplexor(3));
+        assert_eq!(
+            diag.signal("Pressure").unwrap().switch,
+            Some(MultiplexSwitch {
+                name: "Sub".into(),
+                ranges: vec![(0, 3), (5, 5)]
+            })
+        );
+        let status = dbc.message_by_name("Status").unwrap();
+        assert_eq!(
+            status.signal("Ratio").unwrap().value_type,
+            ValueType::Float32
+        );
+
+        assert_eq!(
+            dbc.attribute(&dbc.attributes, "BusType"),
+            Some(&AttributeValue::String("CAN FD".into()))
+        );
+        assert_eq!(
+            dbc.attribute(&engine.attributes, "GenMsgCycleTime"),
+            Some(&AttributeValue::Integer(10))
+        );
+        assert_eq!(
+            dbc.attribute(&diag.attributes, "GenMsgCycleTime"),
+            Some(&AttributeValue::Integer(100))
+        );
+        assert_eq!(speed.attributes["GenSigStartValue"].as_f64(), Some(800.5));
+        let definition = &dbc.definitions["VFrameFormat"];
+        assert_eq!(definition.object, AttributeObject::Message);
+        assert!(matches!(&definition.kind, AttributeKind::Enum(v) if v.len() == 3));
+        assert_eq!(status.attributes["VFrameFormat"].as_i64(), Some(2));
+
+        assert!(Dbc::parse("BO_ 256 EngineData: 8").is_err());
+        assert!(Dbc::parse(
+            "BO_ 256 EngineData: 8 Engine\n SG_ Speed : 0|16@2+ (1,0) [0|0] \"\" X"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn signals_are_decoded() {
+        let dbc = Dbc::parse(VEHICLE_DBC).unwrap();
+        let id = Id::Standard(StandardId::new(0x100).unwrap());
+        // speed 1500 rpm, temperature -10, gear reverse, torque 100 Nm
+        let data = [0x70, 0x17, 0x1E, 0xF0, 0x89, 0x80, 0x00, 0x00];
+        let frame = TestFrame::new_can(id, &data).unwrap();
+        let (message, values) = dbc.decode(&frame).unwrap();
+        assert_eq!(message.name, "EngineData");
+        let values = values
+            .iter()
+            .map(|v| (v.name(), v.value, v.description()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                ("EngineSpeed", 1500.0, None),
+                ("Temperature", -10.0, None),
+                ("Gear", -1.0, Some("Reverse")),
+                ("Torque", 100.0, None),
+            ]
+        );
+
+        let frame: TestFrame = dbc
+            .encode(
+                "EngineData",
+                &[
+                    ("EngineSpeed", 1500.0),
+                    ("Temperature", -10.0),
+                    ("Gear", -1.0),
+                    ("Torque", 100.2),
+                ],
+            )
+            .unwrap();
+        assert_eq!(frame.id(), id);
+        assert_eq!(frame.data(), data);
+        assert!(dbc
+            .encode::<TestFrame>("EngineData", &[("Temperature", 100.0)])
+            .is_err());
+        assert!(dbc
+            .encode::<TestFrame>("EngineData", &[("Unknown", 0.0)])
+            .is_err());
+
+        let other =
+            TestFrame::new_can(Id::Standard(StandardId::new(0x101).unwrap()), &data).unwrap();
+        assert!(matches!(
+            dbc.decode(&other),
+            Err(Error::InvalidIdentifier(0x101))
+        ));
+        let short = TestFrame::new_can(id, &data[..4]).unwrap();
+        assert!(matches!(dbc.decode(&short), Err(Error::InvalidDLC(4))));
+
+        let frame: TestFrame = dbc
+            .encode("Status", &[("Counter", 3.0), ("Ratio", 0.5)])
+            .unwrap();
+        assert_eq!(frame.kind(), crate::CanKind::FD);
+        assert_eq!(frame.data(), [0x03, 0, 0, 0, 0, 0, 0, 0x3F, 0, 0, 0, 0]);
+        let (_, values) = dbc.decode(&frame).unwrap();
+        assert_eq!(values[1].value, 0.5);
+    }
+
+    #[test]
+    fn signals_are_multiplexed() {
+        let dbc = Dbc::parse(VEHICLE_DBC).unwrap();
+        let message = dbc.message_by_name("Diagnostics").unwrap();
+        let names = |data: &[u8]| {
+            message
+                .decode(data)
+                .unwrap()
+                .iter()
+                .map(|v| (v.name().to_owned(), v.value))
+                .collect::<Vec<_>>()
+        };
+
+        let data = message.encode(&[("Mode", 1.0), ("Voltage", 12.5)]).unwrap();
+        assert_eq!(
+            names(&data),
+            [("Mode".into(), 1.0), ("Voltage".into(), 12.5)]
+        );
+        let data = message.encode(&[("Mode", 2.0), ("Current", -1.5)]).unwrap();
+        assert_eq!(
+            names(&data),
+            [("Mode".into(), 2.0), ("Current".into(), -1.5)]
+        );
+
+        // the pressure is present when mode is 3 and sub is 0-3 or 5.
+        let data = message
+            .encode(&[("Mode", 3.0), ("Sub", 5.0), ("Pressure", 101.0)])
+            .unwrap();
+        assert_eq!(
+            names(&data),
+            [
+                ("Mode".into(), 3.0),
+                ("Sub".into(), 5.0),
+                ("Pressure".into(), 101.0)
+            ]
+        );
+        assert_eq!(names(&[3, 4, 0, 0, 0, 0, 0, 0]).len(), 2);
+        assert_eq!(names(&[0, 0, 0, 0, 0, 0, 0, 0]), [("Mode".into(), 0.0)]);
+        assert!(message.encode(&[("Mode", 1.0), ("Current", 1.0)]).is_err());
+        assert!(message
+            .encode(&[("Mode", 3.0), ("Sub", 4.0), ("Pressure", 1.0)])
+            .is_err());
+    }
+}
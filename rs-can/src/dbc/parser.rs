@@ -0,0 +1,605 @@
+use super::{
+    AttributeDefinition, AttributeKind, AttributeObject, AttributeValue, ByteOrder, Dbc, Message,
+    MultiplexSwitch, Multiplexing, Node, Signal, ValueTable, ValueType,
+};
+use crate::{error::Error, CanResult};
+use std::{collections::HashMap, str::FromStr};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'a> {
+    Ident(&'a str),
+    Number(&'a str),
+    Str(String),
+    Punct(char),
+}
+
+#[derive(Debug)]
+struct Lexed<'a> {
+    token: Token<'a>,
+    line: usize,
+    /// The token is at the first column of line.
+    line_start: bool,
+}
+
+fn tokenize(content: &str) -> CanResult<Vec<Lexed<'_>>> {
+    let mut tokens = Vec::new();
+    let mut chars = content.char_indices().peekable();
+    let (mut line, mut column) = (1, 0);
+    while let Some((start, c)) = chars.next() {
+        let token_line = line;
+        let line_start = column == 0;
+        column += 1;
+        let token = match c {
+            '\n' => {
+                line += 1;
+                column = 0;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '/' if chars.peek().is_some_and(|(_, c)| *c == '/') => {
+                while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+                continue;
+            }
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c)) => value.push(c),
+                            None => break,
+                        },
+                        Some((_, c)) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            value.push(c);
+                        }
+                        None => {
+                            return Err(Error::other_error(format!(
+                                "invalid DBC at line {}: unterminated string",
+                                token_line
+                            )))
+                        }
+                    }
+                }
+                Token::Str(value)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = start + 1;
+                while let Some((i, _)) =
+                    chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
+                {
+                    end = i + 1;
+                }
+                Token::Ident(&content[start..end])
+            }
+            c if c.is_ascii_digit() => {
+                let mut end = start + 1;
+                let mut exponent = false;
+                while let Some((i, c)) = chars.next_if(|(_, c)| {
+                    c.is_ascii_digit() || *c == '.' || (!exponent && matches!(c, 'e' | 'E'))
+                }) {
+                    end = i + 1;
+                    if matches!(c, 'e' | 'E') {
+                        exponent = true;
+                        if let Some((i, _)) = chars.next_if(|(_, c)| matches!(c, '+' | '-')) {
+                            end = i + 1;
+                        }
+                    }
+                }
+                Token::Number(&content[start..end])
+            }
+            c => Token::Punct(c),
+        };
+        tokens.push(Lexed {
+            token,
+            line: token_line,
+            line_start,
+        });
+    }
+
+    Ok(tokens)
+}
+
+/// The parser of DBC, the statements are parsed in order.
+pub(crate) struct Parser<'a> {
+    content: &'a str,
+    tokens: Vec<Lexed<'a>>,
+    pos: usize,
+    dbc: Dbc,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn new(content: &'a str) -> Self {
+        Self {
+            content,
+            tokens: Default::default(),
+            pos: 0,
+            dbc: Default::default(),
+        }
+    }
+
+    pub(crate) fn parse(mut self) -> CanResult<Dbc> {
+        self.tokens = tokenize(self.content)?;
+        while let Some(lexed) = self.tokens.get(self.pos) {
+            let Token::Ident(keyword) = lexed.token else {
+                return Err(self.error("keyword is expected"));
+            };
+            self.pos += 1;
+            match keyword {
+                "VERSION" => self.dbc.version = self.string()?,
+                "NS_" | "BS_" => self.skip_lines(),
+                "BU_" => {
+                    self.punct(':')?;
+                    while let Some(Token::Ident(name)) = self.peek_in_line() {
+                        let name = name.to_string();
+                        self.pos += 1;
+                        self.dbc.nodes.push(Node {
+                            name,
+                            ..Default::default()
+                        });
+                    }
+                }
+                "VAL_TABLE_" => {
+                    let name = self.ident()?.to_owned();
+                    let table = self.value_table()?;
+                    self.dbc.value_tables.insert(name, table);
+                }
+                "BO_" => self.message()?,
+                "CM_" => self.comment()?,
+                "BA_DEF_" => self.definition()?,
+                "BA_DEF_DEF_" => {
+                    let name = self.string()?;
+                    let value = self.attribute_value(&name)?;
+                    self.punct(';')?;
+                    match self.dbc.definitions.get_mut(&name) {
+                        Some(definition) => definition.default = Some(value),
+                        None => rsutil::warn!("RUST-CAN - DBC attribute `{}` is not defined", name),
+                    }
+                }
+                "BA_" => self.attribute()?,
+                "VAL_" => self.values()?,
+                "SG_MUL_VAL_" => self.multiplex()?,
+                "SIG_VALTYPE_" => {
+                    let (id, name) = (self.number::<u32>()?, self.ident()?.to_owned());
+                    self.punct(':')?;
+                    let value_type = match self.number::<u8>()? {
+                        1 => ValueType::Float32,
+                        2 => ValueType::Float64,
+                        _ => ValueType::Unsigned,
+                    };
+                    self.punct(';')?;
+                    let signal = self.signal_mut(id, &name)?;
+                    if value_type != ValueType::Unsigned {
+                        signal.value_type = value_type;
+                    }
+                }
+                "SG_" => return Err(self.error("signal is not in message")),
+                _ => self.skip_statement(),
+            }
+        }
+
+        Ok(self.dbc)
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        let line = self
+            .tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or(0, |t| t.line);
+        Error::other_error(format!("invalid DBC at line {}: {}", line, msg))
+    }
+
+    fn next(&mut self) -> CanResult<&Token<'a>> {
+        match self.tokens.get(self.pos) {
+            Some(lexed) => {
+                self.pos += 1;
+                Ok(&lexed.token)
+            }
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.pos).map(|t| &t.token)
+    }
+
+    /// The next token which is not at the start of line.
+    #[inline]
+    fn peek_in_line(&self) -> Option<&Token<'a>> {
+        self.tokens
+            .get(self.pos)
+            .filter(|t| !t.line_start)
+            .map(|t| &t.token)
+    }
+
+    /// Skip the indented lines, such as the symbols of `NS_`.
+    fn skip_lines(&mut self) {
+        while self.peek_in_line().is_some() {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_statement(&mut self) {
+        while let Some(lexed) = self.tokens.get(self.pos) {
+            self.pos += 1;
+            if lexed.token == Token::Punct(';') {
+                break;
+            }
+        }
+    }
+
+    fn punct(&mut self, c: char) -> CanResult<()> {
+        match self.next()? {
+            Token::Punct(v) if *v == c => Ok(()),
+            _ => {
+                self.pos -= 1;
+                Err(self.error(&format!("`{}` is expected", c)))
+            }
+        }
+    }
+
+    fn ident(&mut self) -> CanResult<&'a str> {
+        match self.next()? {
+            Token::Ident(v) => Ok(v),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("identifier is expected"))
+            }
+        }
+    }
+
+    fn string(&mut self) -> CanResult<String> {
+        match self.next()? {
+            Token::Str(v) => Ok(v.clone()),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("string is expected"))
+            }
+        }
+    }
+
+    /// The number with optional sign.
+    fn number<T: FromStr>(&mut self) -> CanResult<T> {
+        let sign = match self.peek() {
+            Some(Token::Punct(c @ ('-' | '+'))) => {
+                let sign = *c;
+                self.pos += 1;
+                Some(sign)
+            }
+            _ => None,
+        };
+        let value = match (sign, self.next()?) {
+            (Some('-'), Token::Number(v)) => format!("-{}", v).parse().ok(),
+            (_, Token::Number(v)) => v.parse().ok(),
+            _ => None,
+        };
+        value.ok_or_else(|| {
+            self.pos -= 1;
+            self.error("number is expected")
+        })
+    }
+
+    fn value_table(&mut self) -> CanResult<ValueTable> {
+        let mut table = ValueTable::new();
+        while self.peek() != Some(&Token::Punct(';')) {
+            let value = self.number::<i64>()?;
+            table.insert(value, self.string()?);
+        }
+        self.punct(';')?;
+        Ok(table)
+    }
+
+    fn message(&mut self) -> CanResult<()> {
+        let id = self.number::<u32>()?;
+        let name = self.ident()?.to_owned();
+        self.punct(':')?;
+        let size = self.number::<usize>()?;
+        let transmitter = self.ident()?.to_owned();
+        let mut message = Message {
+            id,
+            name,
+            size,
+            transmitter,
+            ..Default::default()
+        };
+
+        while self.peek() == Some(&Token::Ident("SG_")) {
+            let line = self.tokens[self.pos].line;
+            self.pos += 1;
+            message.signals.push(self.signal(line)?);
+        }
+        self.dbc.messages.push(message);
+
+        Ok(())
+    }
+
+    fn signal(&mut self, line: usize) -> CanResult<Signal> {
+        let name = self.ident()?.to_owned();
+        let multiplexing = match self.peek() {
+            Some(Token::Ident(mux)) => {
+                let mux = *mux;
+                self.pos += 1;
+                match mux {
+                    "M" => Multiplexing::Multiplexor,
+                    _ => {
+                        let (value, multiplexor) = match mux.strip_suffix('M') {
+                            Some(v) => (v, true),
+                            None => (mux, false),
+                        };
+                        let value = value
+                            .strip_prefix('m')
+                            .and_then(|v| v.parse().ok())
+                            .ok_or_else(|| self.error("invalid multiplexer"))?;
+                        match multiplexor {
+                            true => Multiplexing::MultiplexedMultiplexor(value),
+                            false => Multiplexing::Multiplexed(value),
+                        }
+                    }
+                }
+            }
+            _ => Multiplexing::Plain,
+        };
+        self.punct(':')?;
+        let start_bit = self.number()?;
+        self.punct('|')?;
+        let size = self.number()?;
+        self.punct('@')?;
+        let byte_order = match self.number::<u8>()? {
+            0 => ByteOrder::BigEndian,
+            1 => ByteOrder::LittleEndian,
+            _ => return Err(self.error("invalid byte order")),
+        };
+        let value_type = match self.next()? {
+            Token::Punct('+') => ValueType::Unsigned,
+            Token::Punct('-') => ValueType::Signed,
+            _ => return Err(self.error("invalid value type")),
+        };
+        self.punct('(')?;
+        let factor = self.number()?;
+        self.punct(',')?;
+        let offset = self.number()?;
+        self.punct(')')?;
+        self.punct('[')?;
+        let min = self.number()?;
+        self.punct('|')?;
+        let max = self.number()?;
+        self.punct(']')?;
+        let unit = self.string()?;
+
+        let mut receivers = Vec::new();
+        while let Some(lexed) = self.tokens.get(self.pos).filter(|t| t.line == line) {
+            match &lexed.token {
+                Token::Ident(v) => receivers.push(v.to_string()),
+                Token::Punct(',') => {}
+                _ => break,
+            }
+            self.pos += 1;
+        }
+
+        Ok(Signal {
+            name,
+            start_bit,
+            size,
+            byte_order,
+            value_type,
+            factor,
+            offset,
+            min,
+            max,
+            unit,
+            receivers,
+            multiplexing,
+            ..Default::default()
+        })
+    }
+
+    fn message_mut(&mut self, id: u32) -> CanResult<&mut Message> {
+        match self.dbc.messages.iter().position(|m| m.id == id) {
+            Some(i) => Ok(&mut self.dbc.messages[i]),
+            None => Err(self.error(&format!("message {} is not defined", id))),
+        }
+    }
+
+    fn signal_mut(&mut self, id: u32, name: &str) -> CanResult<&mut Signal> {
+        let error = self.error(&format!(
+            "signal `{}` of message {} is not defined",
+            name, id
+        ));
+        self.message_mut(id)?
+            .signals
+            .iter_mut()
+            .find(|s| s.name == name)
+            .ok_or(error)
+    }
+
+    fn node_mut(&mut self, name: &str) -> CanResult<&mut Node> {
+        let error = self.error(&format!("node `{}` is not defined", name));
+        self.dbc
+            .nodes
+            .iter_mut()
+            .find(|n| n.name == name)
+            .ok_or(error)
+    }
+
+    /// The object of `CM_` and `BA_`, `None` for the network and environment variable.
+    fn object(&mut self) -> CanResult<Option<(AttributeObject, Option<u32>, String)>> {
+        let object = match self.peek() {
+            Some(Token::Ident("BU_")) => AttributeObject::Node,
+            Some(Token::Ident("BO_")) => AttributeObject::Message,
+            Some(Token::Ident("SG_")) => AttributeObject::Signal,
+            Some(Token::Ident("EV_")) => AttributeObject::EnvironmentVariable,
+            _ => return Ok(None),
+        };
+        self.pos += 1;
+        Ok(Some(match object {
+            AttributeObject::Message => (object, Some(self.number()?), String::new()),
+            AttributeObject::Signal => {
+                let id = self.number()?;
+                (object, Some(id), self.ident()?.to_owned())
+            }
+            _ => (object, None, self.ident()?.to_owned()),
+        }))
+    }
+
+    /// The attributes of object, `None` for the environment variable.
+    fn attributes_mut(
+        &mut self,
+        object: Option<(AttributeObject, Option<u32>, String)>,
+    ) -> CanResult<Option<&mut HashMap<String, AttributeValue>>> {
+        Ok(match object {
+            None => Some(&mut self.dbc.attributes),
+            Some((AttributeObject::Node, _, name)) => Some(&mut self.node_mut(&name)?.attributes),
+            Some((AttributeObject::Message, Some(id), _)) => {
+                Some(&mut self.message_mut(id)?.attributes)
+            }
+            Some((AttributeObject::Signal, Some(id), name)) => {
+                Some(&mut self.signal_mut(id, &name)?.attributes)
+            }
+            _ => None,
+        })
+    }
+
+    fn comment(&mut self) -> CanResult<()> {
+        let object = self.object()?;
+        let comment = Some(self.string()?);
+        self.punct(';')?;
+        match object {
+            None => self.dbc.comment = comment,
+            Some((AttributeObject::Node, _, name)) => self.node_mut(&name)?.comment = comment,
+            Some((AttributeObject::Message, Some(id), _)) => {
+                self.message_mut(id)?.comment = comment
+            }
+            Some((AttributeObject::Signal, Some(id), name)) => {
+                self.signal_mut(id, &name)?.comment = comment
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn definition(&mut self) -> CanResult<()> {
+        let object = match self.peek() {
+            Some(Token::Ident("BU_")) => AttributeObject::Node,
+            Some(Token::Ident("BO_")) => AttributeObject::Message,
+            Some(Token::Ident("SG_")) => AttributeObject::Signal,
+            Some(Token::Ident("EV_")) => AttributeObject::EnvironmentVariable,
+            _ => AttributeObject::Network,
+        };
+        if object != AttributeObject::Network {
+            self.pos += 1;
+        }
+        let name = self.string()?;
+        let kind = match self.ident()? {
+            "INT" => AttributeKind::Integer(self.number()?, self.number()?),
+            "HEX" => AttributeKind::Hex(self.number()?, self.number()?),
+            "FLOAT" => AttributeKind::Float(self.number()?, self.number()?),
+            "STRING" => AttributeKind::String,
+            "ENUM" => {
+                let mut values = vec![];
+                while let Some(Token::Str(_)) = self.peek() {
+                    values.push(self.string()?);
+                    if self.peek() == Some(&Token::Punct(',')) {
+                        self.pos += 1;
+                    }
+                }
+                AttributeKind::Enum(values)
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("invalid attribute type"));
+            }
+        };
+        self.punct(';')?;
+        self.dbc.definitions.insert(
+            name,
+            AttributeDefinition {
+                object,
+                kind,
+                default: None,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// The value of attribute which is converted by the definition, the enum value is
+    /// converted to the index.
+    fn attribute_value(&mut self, name: &str) -> CanResult<AttributeValue> {
+        let kind = self.dbc.definitions.get(name).map(|d| d.kind.clone());
+        if let Some(Token::Str(_)) = self.peek() {
+            let value = self.string()?;
+            return Ok(match kind {
+                Some(AttributeKind::Enum(values)) => {
+                    match values.iter().position(|v| *v == value) {
+                        Some(index) => AttributeValue::Integer(index as i64),
+                        None => AttributeValue::String(value),
+                    }
+                }
+                _ => AttributeValue::String(value),
+            });
+        }
+
+        let value = self.number::<f64>()?;
+        Ok(match kind {
+            Some(AttributeKind::Float(..)) => AttributeValue::Float(value),
+            Some(_) => AttributeValue::Integer(value as i64),
+            None if value.fract() == 0.0 => AttributeValue::Integer(value as i64),
+            None => AttributeValue::Float(value),
+        })
+    }
+
+    fn attribute(&mut self) -> CanResult<()> {
+        let name = self.string()?;
+        let object = self.object()?;
+        let value = self.attribute_value(&name)?;
+        self.punct(';')?;
+        if let Some(attributes) = self.attributes_mut(object)? {
+            attributes.insert(name, value);
+        }
+        Ok(())
+    }
+
+    fn values(&mut self) -> CanResult<()> {
+        // the values of environment variable are skipped.
+        if !matches!(self.peek(), Some(Token::Number(_))) {
+            self.skip_statement();
+            return Ok(());
+        }
+        let (id, name) = (self.number::<u32>()?, self.ident()?.to_owned());
+        let table = self.value_table()?;
+        self.signal_mut(id, &name)?.values = table;
+        Ok(())
+    }
+
+    fn multiplex(&mut self) -> CanResult<()> {
+        let (id, name) = (self.number::<u32>()?, self.ident()?.to_owned());
+        let switch = self.ident()?.to_owned();
+        let mut ranges = Vec::new();
+        while self.peek() != Some(&Token::Punct(';')) {
+            let min = self.number()?;
+            self.punct('-')?;
+            ranges.push((min, self.number()?));
+            if self.peek() == Some(&Token::Punct(',')) {
+                self.pos += 1;
+            }
+        }
+        self.punct(';')?;
+
+        let signal = self.signal_mut(id, &name)?;
+        match signal.switch.as_mut() {
+            // the ranges of same multiplexor are merged.
+            Some(v) if v.name == switch => v.ranges.extend(ranges),
+            _ => {
+                signal.switch = Some(MultiplexSwitch {
+                    name: switch,
+                    ranges,
+                })
+            }
+        }
+        Ok(())
+    }
+}
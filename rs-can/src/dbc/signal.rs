@@ -0,0 +1,350 @@
+use super::{AttributeValue, ValueTable};
+use crate::{
+    error::Error,
+    frame::identifier::{ExtendedId, Id, StandardId},
+    CanResult, EFF_MASK,
+};
+use std::collections::HashMap;
+
+/// The bit of DBC message id which indicates the extended id.
+pub const DBC_EXTENDED: u32 = 0x8000_0000;
+
+/// The byte order of signal, `@1` is Intel and `@0` is Motorola.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ByteOrder {
+    #[default]
+    LittleEndian,
+    BigEndian,
+}
+
+/// The type of raw value, the float types are defined by `SIG_VALTYPE_`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    #[default]
+    Unsigned,
+    Signed,
+    Float32,
+    Float64,
+}
+
+/// The multiplexing of signal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Multiplexing {
+    /// The signal is always present.
+    #[default]
+    Plain,
+    /// `M`, the signal selects the multiplexed signals.
+    Multiplexor,
+    /// `m<n>`, the signal is present when the multiplexor is `n`.
+    Multiplexed(u64),
+    /// `m<n>M`, a multiplexed signal which is the multiplexor of others(extended multiplexing).
+    MultiplexedMultiplexor(u64),
+}
+
+/// The extended multiplexing defined by `SG_MUL_VAL_`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MultiplexSwitch {
+    /// The name of multiplexor signal.
+    pub name: String,
+    /// The inclusive ranges of multiplexor value.
+    pub ranges: Vec<(u64, u64)>,
+}
+
+/// A signal of message.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Signal {
+    pub name: String,
+    /// The bit position of LSB for little-endian and MSB for big-endian.
+    pub start_bit: u16,
+    pub size: u16,
+    pub byte_order: ByteOrder,
+    pub value_type: ValueType,
+    pub factor: f64,
+    pub offset: f64,
+    pub min: f64,
+    pub max: f64,
+    pub unit: String,
+    pub receivers: Vec<String>,
+    pub multiplexing: Multiplexing,
+    pub switch: Option<MultiplexSwitch>,
+    /// The descriptions of raw values defined by `VAL_`.
+    pub values: ValueTable,
+    pub comment: Option<String>,
+    pub attributes: HashMap<String, AttributeValue>,
+}
+
+impl Signal {
+    /// The bit positions from MSB to LSB.
+    fn positions(&self) -> impl Iterator<Item = usize> + '_ {
+        let start = self.start_bit as usize;
+        let size = self.size as usize;
+        let mut next = match self.byte_order {
+            ByteOrder::LittleEndian => start + size,
+            ByteOrder::BigEndian => start,
+        };
+        (0..size).map(move |_| match self.byte_order {
+            ByteOrder::LittleEndian => {
+                next -= 1;
+                next
+            }
+            ByteOrder::BigEndian => {
+                let pos = next;
+                // the sawtooth numbering of Motorola byte order.
+                next = match pos % 8 {
+                    0 => pos + 15,
+                    _ => pos - 1,
+                };
+                pos
+            }
+        })
+    }
+
+    #[inline]
+    fn check(&self, data: &[u8]) -> CanResult<()> {
+        if self.size == 0 || self.size > 64 {
+            return Err(Error::operation_error(format!(
+                "signal `{}` size: {} is not supported",
+                self.name, self.size
+            )));
+        }
+        match self.positions().max() {
+            Some(pos) if pos / 8 >= data.len() => Err(Error::InvalidDLC(data.len())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Extract the raw bits of signal.
+    pub fn raw(&self, data: &[u8]) -> CanResult<u64> {
+        self.check(data)?;
+        Ok(self.positions().fold(0, |raw, pos| {
+            (raw << 1) | ((data[pos / 8] >> (pos % 8)) & 0x01) as u64
+        }))
+    }
+
+    /// Insert the raw bits of signal.
+    pub fn set_raw(&self, data: &mut [u8], raw: u64) -> CanResult<()> {
+        self.check(data)?;
+        let size = self.size as usize;
+        for (i, pos) in self.positions().enumerate() {
+            let bit = 1 << (pos % 8);
+            match (raw >> (size - 1 - i)) & 0x01 {
+                0 => data[pos / 8] &= !bit,
+                _ => data[pos / 8] |= bit,
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert the raw bits to physical value.
+    pub fn physical(&self, raw: u64) -> f64 {
+        let value = match self.value_type {
+            ValueType::Unsigned => raw as f64,
+            ValueType::Signed => self.signed(raw) as f64,
+            ValueType::Float32 => f32::from_bits(raw as u32) as f64,
+            ValueType::Float64 => f64::from_bits(raw),
+        };
+        value * self.factor + self.offset
+    }
+
+    /// Convert the physical value to raw bits, the value is rounded to the nearest raw value.
+    pub fn to_raw(&self, value: f64) -> CanResult<u64> {
+        let scaled = (value - self.offset) / self.factor;
+        let bits = self.size as u32;
+        let out_of_range = || {
+            Error::operation_error(format!(
+                "signal `{}` value: {} is out of range",
+                self.name, value
+            ))
+        };
+        let raw = match self.value_type {
+            ValueType::Float32 => (scaled as f32).to_bits() as u64,
+            ValueType::Float64 => scaled.to_bits(),
+            ValueType::Unsigned => {
+                let scaled = scaled.round();
+                if !(0.0..=mask(bits) as f64).contains(&scaled) {
+                    return Err(out_of_range());
+                }
+                scaled as u64
+            }
+            ValueType::Signed => {
+                let scaled = scaled.round();
+                let max = (mask(bits) >> 1) as f64;
+                if !(-max - 1.0..=max).contains(&scaled) {
+                    return Err(out_of_range());
+                }
+                scaled as i64 as u64
+            }
+        };
+
+        Ok(raw & mask(bits))
+    }
+
+    /// Decode the physical value.
+    #[inline]
+    pub fn decode(&self, data: &[u8]) -> CanResult<f64> {
+        self.raw(data).map(|raw| self.physical(raw))
+    }
+
+    /// Encode the physical value into data.
+    #[inline]
+    pub fn encode(&self, data: &mut [u8], value: f64) -> CanResult<()> {
+        let raw = self.to_raw(value)?;
+        self.set_raw(data, raw)
+    }
+
+    /// The description of raw value.
+    #[inline]
+    pub fn description(&self, raw: u64) -> Option<&str> {
+        let key = match self.value_type {
+            ValueType::Signed => self.signed(raw),
+            _ => raw as i64,
+        };
+        self.values.get(&key).map(|v| v.as_str())
+    }
+
+    /// Extend the sign of raw bits.
+    #[inline]
+    fn signed(&self, raw: u64) -> i64 {
+        let shift = 64 - self.size as u32;
+        (raw << shift) as i64 >> shift
+    }
+}
+
+#[inline]
+fn mask(bits: u32) -> u64 {
+    match bits {
+        64.. => u64::MAX,
+        bits => (1 << bits) - 1,
+    }
+}
+
+/// The decoded value of signal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalValue<'a> {
+    pub signal: &'a Signal,
+    pub raw: u64,
+    pub value: f64,
+}
+
+impl SignalValue<'_> {
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.signal.name
+    }
+
+    /// The description defined by value table.
+    #[inline]
+    pub fn description(&self) -> Option<&str> {
+        self.signal.description(self.raw)
+    }
+}
+
+/// A message of database.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Message {
+    /// The raw DBC id, [`DBC_EXTENDED`] is set for extended id.
+    pub id: u32,
+    pub name: String,
+    pub size: usize,
+    pub transmitter: String,
+    pub signals: Vec<Signal>,
+    pub comment: Option<String>,
+    pub attributes: HashMap<String, AttributeValue>,
+}
+
+impl Message {
+    /// The CAN identifier.
+    pub fn can_id(&self) -> CanResult<Id> {
+        match self.id & DBC_EXTENDED {
+            0 => Ok(Id::Standard(StandardId::new(self.id as u16)?)),
+            _ => Ok(Id::Extended(ExtendedId::new(self.id & EFF_MASK)?)),
+        }
+    }
+
+    #[inline]
+    pub fn signal(&self, name: &str) -> Option<&Signal> {
+        self.signals.iter().find(|s| s.name == name)
+    }
+
+    /// Whether the signal is present with the value of multiplexors.
+    pub fn is_active(&self, signal: &Signal, data: &[u8]) -> CanResult<bool> {
+        self.active(signal, data, self.signals.len())
+    }
+
+    fn active(&self, signal: &Signal, data: &[u8], depth: usize) -> CanResult<bool> {
+        let value = match signal.multiplexing {
+            Multiplexing::Plain | Multiplexing::Multiplexor => return Ok(true),
+            Multiplexing::Multiplexed(v) | Multiplexing::MultiplexedMultiplexor(v) => v,
+        };
+        if depth == 0 {
+            return Err(Error::operation_error(format!(
+                "signal `{}` has circular multiplexors",
+                signal.name
+            )));
+        }
+
+        let (switch, ranges) = match &signal.switch {
+            Some(switch) => (self.signal(&switch.name), switch.ranges.as_slice()),
+            None => (
+                self.signals
+                    .iter()
+                    .find(|s| s.multiplexing == Multiplexing::Multiplexor),
+                &[(value, value)][..],
+            ),
+        };
+        let switch = switch.ok_or_else(|| {
+            Error::operation_error(format!("signal `{}` has no multiplexor", signal.name))
+        })?;
+        if !self.active(switch, data, depth - 1)? {
+            return Ok(false);
+        }
+        let raw = switch.raw(data)?;
+
+        Ok(ranges.iter().any(|(min, max)| (*min..=*max).contains(&raw)))
+    }
+
+    /// Decode the present signals.
+    pub fn decode(&self, data: &[u8]) -> CanResult<Vec<SignalValue<'_>>> {
+        let mut values = Vec::with_capacity(self.signals.len());
+        for signal in &self.signals {
+            if self.is_active(signal, data)? {
+                let raw = signal.raw(data)?;
+                values.push(SignalValue {
+                    signal,
+                    raw,
+                    value: signal.physical(raw),
+                });
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Encode the physical values of signals, the other signals are `0`.
+    ///
+    /// The multiplexed signals must be present with the values of multiplexors.
+    pub fn encode(&self, values: &[(&str, f64)]) -> CanResult<Vec<u8>> {
+        let mut data = vec![0; self.size];
+        let mut signals = Vec::with_capacity(values.len());
+        for (name, value) in values {
+            let signal = self.signal(name).ok_or_else(|| {
+                Error::operation_error(format!(
+                    "signal `{}` is not in message `{}`",
+                    name, self.name
+                ))
+            })?;
+            signal.encode(&mut data, *value)?;
+            signals.push(signal);
+        }
+        for signal in signals {
+            if !self.is_active(signal, &data)? {
+                return Err(Error::operation_error(format!(
+                    "signal `{}` is not present with the multiplexors",
+                    signal.name
+                )));
+            }
+        }
+
+        Ok(data)
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{
+    bit_timing::{BitTimingConfig, TdcConfig},
     error::Error,
     frame::{
         identifier::{Filter, Id},
@@ -13,6 +14,7 @@ use std::{
     collections::HashMap,
     fmt::{self, Debug, Display},
     hash::Hash,
+    str::FromStr,
     sync::Weak,
 };
 
@@ -69,6 +71,12 @@ pub struct ChannelConfig {
     pub nominal_bitrate: u32,
     /// Data bitrate for CAN-FD, if not set, it will be the same as nominal bitrate.
     pub data_bitrate: Option<u32>,
+    /// Bit-timing of nominal phase, if not set, it will be determined by device implementation.
+    pub nominal_timing: Option<BitTimingConfig>,
+    /// Bit-timing of data phase for CAN-FD, if not set, it will be determined by device implementation.
+    pub data_timing: Option<BitTimingConfig>,
+    /// Transmitter delay compensation for CAN-FD, if not set, it will be determined by device implementation.
+    pub tdc: Option<TdcConfig>,
     /// Whether the channel has termination resistor, if not set, it will be determined by device implementation.
     pub termination: Option<bool>,
     /// Channel mode, if not set, it will be normal mode. Loopback and ListenOnly modes are useful for testing.
@@ -86,6 +94,9 @@ impl Debug for ChannelConfig {
         f.debug_struct("ChannelConfig")
             .field("nominal_bitrate", &self.nominal_bitrate)
             .field("data_bitrate", &self.data_bitrate)
+            .field("nominal_timing", &self.nominal_timing)
+            .field("data_timing", &self.data_timing)
+            .field("tdc", &self.tdc)
             .field("termination", &self.termination)
             .field("mode", &self.mode)
             .field("filters", &self.filters)
@@ -106,6 +117,21 @@ impl ChannelConfig {
         self
     }
 
+    pub fn set_nominal_timing(&mut self, timing: BitTimingConfig) -> &mut Self {
+        self.nominal_timing = Some(timing);
+        self
+    }
+
+    pub fn set_data_timing(&mut self, timing: BitTimingConfig) -> &mut Self {
+        self.data_timing = Some(timing);
+        self
+    }
+
+    pub fn set_tdc(&mut self, tdc: TdcConfig) -> &mut Self {
+        self.tdc = Some(tdc);
+        self
+    }
+
     pub fn set_termination(&mut self, termination: bool) -> &mut Self {
         self.termination = Some(termination);
         self
@@ -170,6 +196,26 @@ impl<K: Hash + Eq + Default> DeviceBuilder<K> {
     }
 }
 
+impl DeviceBuilder<String> {
+    /// Convert the channel names into the channel type of device, e.g. `"0"` to `0u8`.
+    pub fn parse_channels<K: Hash + Eq + FromStr>(self) -> CanResult<DeviceBuilder<K>> {
+        let configs = self
+            .configs
+            .into_iter()
+            .map(|(chl, cfg)| {
+                chl.parse::<K>()
+                    .map(|v| (v, cfg))
+                    .map_err(|_| Error::InitializeError(format!("invalid channel: {}", chl)))
+            })
+            .collect::<CanResult<_>>()?;
+
+        Ok(DeviceBuilder {
+            configs,
+            others: self.others,
+        })
+    }
+}
+
 #[inline(always)]
 fn get_other<T: Clone + 'static>(
     others: &HashMap<String, Box<dyn Any + Send + Sync>>,
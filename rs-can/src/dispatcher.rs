@@ -0,0 +1,331 @@
+use crate::{
+    device::{Device, Listener},
+    error::Error,
+    frame::Frame,
+    CanResult,
+};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{sync::RwLock, task::JoinHandle};
+
+type Listeners<C, F> = Arc<RwLock<HashMap<String, Arc<dyn Listener<C, F>>>>>;
+
+/// Dispatch the frames of a [`Device`] to the registered [`Listener`]s.
+///
+/// After [`Dispatcher::start`], a receive loop is running for every opened channel,
+/// the received frames are delivered by `on_frame_received`. The frames transmitted
+/// by [`Dispatcher::transmit`] are notified by `on_frame_transmitted` when success.
+///
+/// The listeners are notified without holding the registry, so a listener may register
+/// or unregister listeners of the same dispatcher in its callbacks.
+pub struct Dispatcher<D: Device> {
+    device: Arc<D>,
+    listeners: Listeners<D::Channel, D::Frame>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+    timeout: u32,
+    interval: Duration,
+}
+
+impl<D> Dispatcher<D>
+where
+    D: Device + 'static,
+    D::Channel: Clone + Send + Sync,
+    D::Frame: 'static,
+{
+    /// The receive timeout(ms) of every polling.
+    pub const DEFAULT_TIMEOUT: u32 = 10;
+    /// The sleep interval when no frame or error received.
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(1);
+
+    pub fn new(device: D) -> Self {
+        Self {
+            device: Arc::new(device),
+            listeners: Default::default(),
+            tasks: Default::default(),
+            timeout: Self::DEFAULT_TIMEOUT,
+            interval: Self::DEFAULT_INTERVAL,
+        }
+    }
+
+    /// Set the receive timeout(ms) and the idle interval of the receive loops.
+    ///
+    /// It only affects the loops started after.
+    pub fn set_polling(&mut self, timeout: u32, interval: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self.interval = interval;
+        self
+    }
+
+    #[inline]
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    /// Register a listener, the listener with the same name is replaced.
+    pub async fn register_listener(
+        &self,
+        name: impl Into<String>,
+        listener: Arc<dyn Listener<D::Channel, D::Frame>>,
+    ) {
+        let name = name.into();
+        rsutil::trace!("RUST-CAN - register listener: {}", name);
+        self.listeners.write().await.insert(name, listener);
+    }
+
+    /// Unregister the listener and return it.
+    pub async fn unregister_listener(
+        &self,
+        name: &str,
+    ) -> Option<Arc<dyn Listener<D::Channel, D::Frame>>> {
+        rsutil::trace!("RUST-CAN - unregister listener: {}", name);
+        self.listeners.write().await.remove(name)
+    }
+
+    pub async fn unregister_all(&self) {
+        self.listeners.write().await.clear();
+    }
+
+    pub async fn listener_names(&self) -> Vec<String> {
+        self.listeners.read().await.keys().cloned().collect()
+    }
+
+    /// Transmit the frame and notify the listeners when success.
+    pub async fn transmit(&self, msg: D::Frame, timeout: Option<u32>) -> CanResult<()> {
+        let (channel, id) = (msg.channel(), msg.id());
+        self.device.transmit(msg, timeout).await?;
+
+        for listener in snapshot(&self.listeners).await {
+            listener.on_frame_transmitted(channel.clone(), id).await;
+        }
+
+        Ok(())
+    }
+
+    /// Start the receive loops of all opened channels.
+    pub fn start(&self) -> CanResult<()> {
+        let mut tasks = self.tasks.lock().unwrap();
+        if !tasks.is_empty() {
+            return Err(Error::operation_error("dispatcher is already started"));
+        }
+
+        for channel in self.device.opened_channels() {
+            tasks.push(tokio::spawn(receive_loop(
+                self.device.clone(),
+                self.listeners.clone(),
+                channel,
+                self.timeout,
+                self.interval,
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Stop all receive loops.
+    pub async fn stop(&self) {
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        for task in tasks {
+            task.abort();
+            let _ = task.await;
+        }
+    }
+
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        self.tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|task| !task.is_finished())
+    }
+
+    /// Stop the receive loops and return the device when it isn't shared.
+    pub async fn into_inner(self) -> Option<D> {
+        self.stop().await;
+        let device = self.device.clone();
+        drop(self);
+        Arc::try_unwrap(device).ok()
+    }
+}
+
+impl<D: Device> Drop for Dispatcher<D> {
+    fn drop(&mut self) {
+        if let Ok(tasks) = self.tasks.get_mut() {
+            tasks.iter().for_each(JoinHandle::abort);
+        }
+    }
+}
+
+/// Clone the registered listeners, the registry isn't locked while they are notified.
+async fn snapshot<C, F: Frame>(listeners: &Listeners<C, F>) -> Vec<Arc<dyn Listener<C, F>>> {
+    listeners.read().await.values().cloned().collect()
+}
+
+async fn receive_loop<D>(
+    device: Arc<D>,
+    listeners: Listeners<D::Channel, D::Frame>,
+    channel: D::Channel,
+    timeout: u32,
+    interval: Duration,
+) where
+    D: Device,
+    D::Channel: Clone + Hash + Eq + Display,
+{
+    loop {
+        match device.receive(channel.clone(), Some(timeout)).await {
+            Ok(frames) if !frames.is_empty() => {
+                let frames = Arc::new(frames);
+                for listener in snapshot(&listeners).await {
+                    listener.on_frame_received(Arc::downgrade(&frames)).await;
+                }
+                continue;
+            }
+            Ok(_) | Err(Error::TimeoutError(_)) => {}
+            Err(e) => rsutil::warn!("RUST-CAN - {} when receive at channel: {}", e, channel),
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        device::ChannelConfig,
+        frame::identifier::{Id, StandardId},
+        virtual_bus::{
+            tests::{device, TestFrame},
+            VirtualBus,
+        },
+    };
+    use std::{
+        any::Any,
+        sync::{OnceLock, Weak},
+    };
+
+    #[derive(Default)]
+    struct Recorder {
+        transmitted: Mutex<Vec<(String, Id)>>,
+        received: Mutex<Vec<TestFrame>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Listener<String, TestFrame> for Recorder {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        async fn on_frame_transmitted(&self, channel: String, id: Id) {
+            self.transmitted.lock().unwrap().push((channel, id));
+        }
+
+        async fn on_frame_received(&self, frames: Weak<Vec<TestFrame>>) {
+            if let Some(frames) = frames.upgrade() {
+                self.received.lock().unwrap().extend(frames.iter().cloned());
+            }
+        }
+    }
+
+    /// Unregister itself when a frame is transmitted.
+    #[derive(Default)]
+    struct OneShot {
+        dispatcher: OnceLock<Weak<Dispatcher<VirtualBus<TestFrame>>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Listener<String, TestFrame> for OneShot {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        async fn on_frame_transmitted(&self, _: String, _: Id) {
+            if let Some(dispatcher) = self.dispatcher.get().and_then(Weak::upgrade) {
+                dispatcher.unregister_listener("one-shot").await;
+            }
+        }
+
+        async fn on_frame_received(&self, _: Weak<Vec<TestFrame>>) {}
+    }
+
+    #[tokio::test]
+    async fn listeners_are_notified() {
+        let channel = "dispatcher-notify";
+        let tx = Dispatcher::new(device(channel, ChannelConfig::new(500_000)));
+        let rx = Dispatcher::new(device(channel, ChannelConfig::new(500_000)));
+        let tx_recorder = Arc::new(Recorder::default());
+        let rx_recorder = Arc::new(Recorder::default());
+        tx.register_listener("tx", tx_recorder.clone()).await;
+        rx.register_listener("rx", rx_recorder.clone()).await;
+        rx.start().unwrap();
+        assert!(rx.start().is_err());
+
+        let id = Id::Standard(StandardId::new(0x7DF).unwrap());
+        let mut msg = TestFrame::new_can(id, &[0x02, 0x10, 0x01]).unwrap();
+        msg.set_channel(channel.to_owned());
+        tx.transmit(msg, None).await.unwrap();
+
+        for _ in 0..100 {
+            if !rx_recorder.received.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        assert_eq!(
+            tx_recorder.transmitted.lock().unwrap().as_slice(),
+            &[(channel.to_owned(), id)]
+        );
+        let received = rx_recorder.received.lock().unwrap().clone();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].data(), &[0x02, 0x10, 0x01]);
+
+        rx.stop().await;
+        assert!(!rx.is_running());
+    }
+
+    #[tokio::test]
+    async fn unregistered_listener_is_not_notified() {
+        let channel = "dispatcher-unregister";
+        let dispatcher = Dispatcher::new(device(channel, ChannelConfig::new(500_000)));
+        let recorder = Arc::new(Recorder::default());
+        dispatcher.register_listener("rec", recorder.clone()).await;
+        assert_eq!(dispatcher.listener_names().await, vec!["rec".to_string()]);
+        assert!(dispatcher.unregister_listener("rec").await.is_some());
+
+        let mut msg =
+            TestFrame::new_can(Id::Standard(StandardId::new(0x123).unwrap()), &[]).unwrap();
+        msg.set_channel(channel.to_owned());
+        dispatcher.transmit(msg, None).await.unwrap();
+
+        assert!(recorder.transmitted.lock().unwrap().is_empty());
+        assert!(dispatcher.into_inner().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn listener_unregisters_itself() {
+        let channel = "dispatcher-one-shot";
+        let dispatcher = Arc::new(Dispatcher::new(device(
+            channel,
+            ChannelConfig::new(500_000),
+        )));
+        let listener = Arc::new(OneShot::default());
+        let _ = listener.dispatcher.set(Arc::downgrade(&dispatcher));
+        dispatcher.register_listener("one-shot", listener).await;
+
+        let mut msg =
+            TestFrame::new_can(Id::Standard(StandardId::new(0x123).unwrap()), &[]).unwrap();
+        msg.set_channel(channel.to_owned());
+        tokio::time::timeout(Duration::from_secs(1), dispatcher.transmit(msg, None))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(dispatcher.listener_names().await.is_empty());
+    }
+}
@@ -26,6 +26,12 @@ pub enum Error {
     /// Error when operation like transmit, receive and so on.
     #[error("RUST-CAN - operation error: {0}")]
     OperationError(String),
+    /// Error when the negative response of UDS service received.
+    #[error("RUST-CAN - negative response: {code:#04X} of service: {service:#04X}")]
+    NegativeResponse { service: u8, code: u8 },
+    /// Error when the CANopen SDO transfer is aborted.
+    #[error("RUST-CAN - SDO abort: {code:#010X} of object: {index:#06X}sub{sub_index}")]
+    SdoAbort { index: u16, sub_index: u8, code: u32 },
     /// Error when others.
     #[error("RUST-CAN - other error: {0}")]
     OtherError(String),
@@ -0,0 +1,522 @@
+//! The backend-neutral frame.
+
+use super::{
+    identifier::{CanFdFlags, Id},
+    Direction, Frame, FrameFormat, Kind, Timestamp, XlParams,
+};
+use crate::{error::Error, CanResult, MAX_FD_FRAME_SIZE, MAX_FRAME_SIZE, MAX_XL_FRAME_SIZE};
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt::{self, Display, Formatter};
+
+/// The payload of frame, CAN and CAN-FD data are stored inline,
+/// only the CAN XL data are stored on heap.
+#[derive(Clone)]
+enum Payload {
+    Inline {
+        len: u8,
+        buf: [u8; MAX_FD_FRAME_SIZE],
+    },
+    Heap(Box<[u8]>),
+}
+
+impl Payload {
+    fn new(data: &[u8]) -> Self {
+        match data.len() {
+            len @ ..=MAX_FD_FRAME_SIZE => {
+                let mut buf = [0; MAX_FD_FRAME_SIZE];
+                buf[..len].copy_from_slice(data);
+                Self::Inline {
+                    len: len as u8,
+                    buf,
+                }
+            }
+            _ => Self::Heap(data.into()),
+        }
+    }
+
+    #[inline]
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Self::Inline { len, buf } => &buf[..*len as usize],
+            Self::Heap(data) => data,
+        }
+    }
+}
+
+impl Default for Payload {
+    fn default() -> Self {
+        Self::new(&[])
+    }
+}
+
+impl fmt::Debug for Payload {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_slice(), f)
+    }
+}
+
+impl PartialEq for Payload {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for Payload {}
+
+impl Serialize for Payload {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.as_slice())
+    }
+}
+
+impl<'de> Deserialize<'de> for Payload {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PayloadVisitor;
+
+        impl<'de> Visitor<'de> for PayloadVisitor {
+            type Value = Payload;
+
+            fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+                write!(f, "at most {} bytes", MAX_XL_FRAME_SIZE)
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+                if v.len() > MAX_XL_FRAME_SIZE {
+                    return Err(E::invalid_length(v.len(), &self));
+                }
+
+                Ok(Payload::new(v))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut buf = [0; MAX_XL_FRAME_SIZE];
+                let mut len = 0;
+                while let Some(v) = seq.next_element()? {
+                    if len == MAX_XL_FRAME_SIZE {
+                        return Err(de::Error::invalid_length(len + 1, &self));
+                    }
+                    buf[len] = v;
+                    len += 1;
+                }
+
+                Ok(Payload::new(&buf[..len]))
+            }
+        }
+
+        deserializer.deserialize_seq(PayloadVisitor)
+    }
+}
+
+/// A concrete frame which can be converted from and into the frame of any backend.
+///
+/// The data of CAN and CAN-FD frames are stored inline without allocation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawFrame<C>")]
+pub struct GenericFrame<C> {
+    id: Id,
+    channel: C,
+    kind: Kind,
+    format: FrameFormat,
+    length: usize,
+    data: Payload,
+    direction: Direction,
+    timestamp: Option<Timestamp>,
+    bitrate_switch: bool,
+    error_state_indicator: bool,
+    xl_params: Option<XlParams>,
+}
+
+/// The unchecked frame for deserializing.
+#[derive(Deserialize)]
+struct RawFrame<C> {
+    id: Id,
+    channel: C,
+    kind: Kind,
+    format: FrameFormat,
+    length: usize,
+    data: Payload,
+    direction: Direction,
+    timestamp: Option<Timestamp>,
+    bitrate_switch: bool,
+    error_state_indicator: bool,
+    xl_params: Option<XlParams>,
+}
+
+impl<C> TryFrom<RawFrame<C>> for GenericFrame<C> {
+    type Error = Error;
+
+    fn try_from(raw: RawFrame<C>) -> Result<Self, Self::Error> {
+        let data = raw.data.as_slice();
+        match (raw.format, raw.kind) {
+            (FrameFormat::Remote, Kind::Classical) => {
+                if raw.length > MAX_FRAME_SIZE || !data.is_empty() {
+                    return Err(Error::InvalidDLC(raw.length));
+                }
+            }
+            (FrameFormat::Remote, _) | (FrameFormat::Error, Kind::FD | Kind::XL) => {
+                return Err(Error::InvalidFrame(format!(
+                    "{:?} frame can't be {:?}",
+                    raw.kind, raw.format
+                )))
+            }
+            (_, kind) => {
+                let max = match kind {
+                    Kind::Classical => MAX_FRAME_SIZE,
+                    Kind::FD => MAX_FD_FRAME_SIZE,
+                    Kind::XL => MAX_XL_FRAME_SIZE,
+                };
+                if data.len() > max || raw.length != data.len() {
+                    return Err(Error::InvalidDLC(data.len()));
+                }
+            }
+        }
+        if raw.xl_params.is_some() != matches!(raw.kind, Kind::XL) {
+            return Err(Error::InvalidFrame("mismatched CAN XL params".into()));
+        }
+
+        Ok(Self {
+            id: raw.id,
+            channel: raw.channel,
+            kind: raw.kind,
+            format: raw.format,
+            length: raw.length,
+            data: raw.data,
+            direction: raw.direction,
+            timestamp: raw.timestamp,
+            bitrate_switch: raw.bitrate_switch,
+            error_state_indicator: raw.error_state_indicator,
+            xl_params: raw.xl_params,
+        })
+    }
+}
+
+impl<C: Default> GenericFrame<C> {
+    fn new(id: Id, kind: Kind, format: FrameFormat, data: &[u8]) -> Self {
+        Self {
+            id,
+            channel: Default::default(),
+            kind,
+            format,
+            length: data.len(),
+            data: Payload::new(data),
+            direction: Default::default(),
+            timestamp: None,
+            bitrate_switch: false,
+            error_state_indicator: false,
+            xl_params: None,
+        }
+    }
+}
+
+impl<C> GenericFrame<C> {
+    /// Copy the frame of any device with the same channel type.
+    pub fn from_frame<F: Frame<Channel = C>>(frame: &F) -> Self {
+        Self {
+            id: frame.id(),
+            channel: frame.channel(),
+            kind: frame.kind(),
+            format: frame.format(),
+            length: frame.len(),
+            data: match frame.format() {
+                FrameFormat::Remote => Default::default(),
+                _ => Payload::new(frame.data()),
+            },
+            direction: frame.direction(),
+            timestamp: frame.timestamp(),
+            bitrate_switch: frame.is_bitrate_switch(),
+            error_state_indicator: frame.is_esi(),
+            xl_params: frame.xl_params(),
+        }
+    }
+
+    /// Convert into the frame of any device with the same channel type.
+    pub fn into_frame<F: Frame<Channel = C>>(self) -> CanResult<F> {
+        let data = self.data.as_slice();
+        let mut frame = match (self.format, self.kind) {
+            (FrameFormat::Error, _) => F::new_error(self.id, data)?,
+            (FrameFormat::Remote, _) => F::new_remote(
+                self.id,
+                u8::try_from(self.length).map_err(|_| Error::InvalidDLC(self.length))?,
+            )?,
+            (_, Kind::Classical) => F::new_can(self.id, data)?,
+            (_, Kind::FD) => {
+                let mut flags = CanFdFlags::empty();
+                flags.set(CanFdFlags::BRS, self.bitrate_switch);
+                flags.set(CanFdFlags::ESI, self.error_state_indicator);
+                F::new_can_fd(self.id, data, flags)?
+            }
+            (_, Kind::XL) => F::new_can_xl(
+                self.id.as_raw() as u16,
+                data,
+                self.xl_params.unwrap_or_default(),
+            )?,
+        };
+        frame
+            .set_channel(self.channel)
+            .set_direction(self.direction)
+            .set_timestamp(self.timestamp);
+
+        Ok(frame)
+    }
+
+    /// Convert the channel, such as naming the channel by its `Display`.
+    pub fn map_channel<T>(self, f: impl FnOnce(C) -> T) -> GenericFrame<T> {
+        GenericFrame {
+            id: self.id,
+            channel: f(self.channel),
+            kind: self.kind,
+            format: self.format,
+            length: self.length,
+            data: self.data,
+            direction: self.direction,
+            timestamp: self.timestamp,
+            bitrate_switch: self.bitrate_switch,
+            error_state_indicator: self.error_state_indicator,
+            xl_params: self.xl_params,
+        }
+    }
+
+    /// Convert the channel, such as parsing the channel from its name.
+    pub fn try_map_channel<T, E>(
+        self,
+        f: impl FnOnce(C) -> Result<T, E>,
+    ) -> Result<GenericFrame<T>, E> {
+        let channel = f(self.channel)?;
+        Ok(GenericFrame {
+            id: self.id,
+            channel,
+            kind: self.kind,
+            format: self.format,
+            length: self.length,
+            data: self.data,
+            direction: self.direction,
+            timestamp: self.timestamp,
+            bitrate_switch: self.bitrate_switch,
+            error_state_indicator: self.error_state_indicator,
+            xl_params: self.xl_params,
+        })
+    }
+}
+
+impl<C> Frame for GenericFrame<C>
+where
+    C: Display + Clone + Default + Send + Sync,
+{
+    type Channel = C;
+
+    fn new_can(id: Id, data: &[u8]) -> CanResult<Self> {
+        if data.len() > MAX_FRAME_SIZE {
+            return Err(Error::InvalidDLC(data.len()));
+        }
+
+        Ok(Self::new(id, Kind::Classical, FrameFormat::Data, data))
+    }
+
+    fn new_remote(id: Id, dlc: u8) -> CanResult<Self> {
+        let length = dlc as usize;
+        if length > MAX_FRAME_SIZE {
+            return Err(Error::InvalidDLC(length));
+        }
+
+        let mut frame = Self::new(id, Kind::Classical, FrameFormat::Remote, &[]);
+        frame.length = length;
+        Ok(frame)
+    }
+
+    fn new_can_fd(id: Id, data: &[u8], flags: CanFdFlags) -> CanResult<Self> {
+        if data.len() > MAX_FD_FRAME_SIZE {
+            return Err(Error::InvalidDLC(data.len()));
+        }
+
+        let mut frame = Self::new(id, Kind::FD, FrameFormat::Data, data);
+        frame.bitrate_switch = flags.contains(CanFdFlags::BRS);
+        frame.error_state_indicator = flags.contains(CanFdFlags::ESI);
+        Ok(frame)
+    }
+
+    fn new_can_xl(priority: u16, data: &[u8], params: XlParams) -> CanResult<Self> {
+        if !(1..=MAX_XL_FRAME_SIZE).contains(&data.len()) {
+            return Err(Error::InvalidDLC(data.len()));
+        }
+        let id = Id::from_bits(priority as u32, Some(false))?;
+
+        let mut frame = Self::new(id, Kind::XL, FrameFormat::Data, data);
+        frame.xl_params = Some(params);
+        Ok(frame)
+    }
+
+    fn new_error(id: Id, data: &[u8]) -> CanResult<Self> {
+        if data.len() > MAX_FRAME_SIZE {
+            return Err(Error::InvalidDLC(data.len()));
+        }
+
+        Ok(Self::new(id, Kind::Classical, FrameFormat::Error, data))
+    }
+
+    #[inline]
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    #[inline]
+    fn channel(&self) -> Self::Channel {
+        self.channel.clone()
+    }
+
+    #[inline]
+    fn set_channel(&mut self, v: Self::Channel) -> &mut Self {
+        self.channel = v;
+        self
+    }
+
+    #[inline]
+    fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    #[inline]
+    fn format(&self) -> FrameFormat {
+        self.format
+    }
+
+    #[inline]
+    fn data(&self) -> &[u8] {
+        self.data.as_slice()
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    #[inline]
+    fn xl_params(&self) -> Option<XlParams> {
+        self.xl_params
+    }
+
+    #[inline]
+    fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    #[inline]
+    fn set_direction(&mut self, d: Direction) -> &mut Self {
+        self.direction = d;
+        self
+    }
+
+    #[inline]
+    fn timestamp(&self) -> Option<Timestamp> {
+        self.timestamp
+    }
+
+    #[inline]
+    fn set_timestamp(&mut self, ts: Option<Timestamp>) -> &mut Self {
+        self.timestamp = ts;
+        self
+    }
+
+    #[inline]
+    fn is_bitrate_switch(&self) -> bool {
+        self.bitrate_switch
+    }
+
+    #[inline]
+    fn set_bitrate_switch(&mut self, v: bool) -> &mut Self {
+        self.bitrate_switch = v;
+        self
+    }
+
+    #[inline]
+    fn is_esi(&self) -> bool {
+        self.error_state_indicator
+    }
+
+    #[inline]
+    fn set_esi(&mut self, v: bool) -> &mut Self {
+        self.error_state_indicator = v;
+        self
+    }
+}
+
+impl<C> Display for GenericFrame<C>
+where
+    C: Display + Clone + Default + Send + Sync + 'static,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        <dyn Frame<Channel = C> as Display>::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        frame::identifier::{ExtendedId, StandardId},
+        virtual_bus::tests::TestFrame,
+    };
+
+    fn frames() -> Vec<TestFrame> {
+        let id = Id::Extended(ExtendedId::new(0x18DA_F110).unwrap());
+        let mut frames = vec![
+            TestFrame::new_can(Id::Standard(StandardId::new(0x123).unwrap()), &[1, 2]).unwrap(),
+            TestFrame::new_remote(id, 4).unwrap(),
+            TestFrame::new_can_fd(id, &[0x55; 12], CanFdFlags::BRS | CanFdFlags::ESI).unwrap(),
+            TestFrame::new_can_xl(0x7FF, &[0xA5; 100], XlParams::default()).unwrap(),
+            TestFrame::new_error(Id::Standard(StandardId::new(0x04).unwrap()), &[0, 4]).unwrap(),
+        ];
+        frames.iter_mut().for_each(|f| {
+            f.set_channel("can0".into())
+                .set_direction(Direction::Receive);
+        });
+        frames
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        for frame in frames() {
+            let generic = GenericFrame::from_frame(&frame);
+            assert_eq!(generic.len(), frame.len());
+            let other = generic.clone().into_frame::<TestFrame>().unwrap();
+            assert_eq!(GenericFrame::from_frame(&other), generic);
+        }
+    }
+
+    #[test]
+    fn data_is_inline() {
+        let id = Id::Standard(StandardId::new(0x123).unwrap());
+        let frame = GenericFrame::<u8>::new_can_fd(id, &[0x11; 64], CanFdFlags::empty()).unwrap();
+        assert!(matches!(frame.data, Payload::Inline { len: 64, .. }));
+        let frame = GenericFrame::<u8>::new_can_xl(0x123, &[0x11; 65], Default::default()).unwrap();
+        assert!(matches!(frame.data, Payload::Heap(_)));
+        assert!(GenericFrame::<u8>::new_can(id, &[0; 9]).is_err());
+    }
+
+    #[test]
+    fn channel_is_mapped() {
+        let frame = GenericFrame::<u8>::new_can(Id::from_bits(0x7DF, None).unwrap(), &[2, 1, 0])
+            .unwrap()
+            .map_channel(|c| c.to_string());
+        assert_eq!(frame.channel(), "0");
+        let frame = frame.try_map_channel(|c| c.parse::<u8>()).unwrap();
+        assert_eq!(frame.channel(), 0);
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        for frame in frames() {
+            let generic = GenericFrame::from_frame(&frame);
+            let text = serde_yaml::to_string(&generic).unwrap();
+            let other: GenericFrame<String> = serde_yaml::from_str(&text).unwrap();
+            assert_eq!(other, generic);
+        }
+
+        let mut generic = GenericFrame::from_frame(&frames()[0]);
+        generic.length = 3;
+        let text = serde_yaml::to_string(&generic).unwrap();
+        assert!(serde_yaml::from_str::<GenericFrame<String>>(&text).is_err());
+    }
+}
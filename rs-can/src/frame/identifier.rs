@@ -68,6 +68,22 @@ impl Default for Filter {
     }
 }
 
+impl Filter {
+    /// Whether the identifier is accepted by this filter.
+    ///
+    /// The match is `received_id & mask == filter_id & mask` with the same kind of identifier,
+    /// but a filter with zero mask, e.g. the default one, accepts all identifiers.
+    #[inline]
+    pub fn matches(&self, id: Id) -> bool {
+        let (filter_id, mask, extended) = match *self {
+            Self::Standard { id, mask } => (id.as_raw() as u32, mask as u32, false),
+            Self::Extended { id, mask } => (id.as_raw(), mask, true),
+        };
+
+        mask == 0 || (id.is_extended() == extended && id.as_raw() & mask == filter_id & mask)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Hash)]
 pub enum Id {
     Standard(StandardId),
@@ -197,4 +213,31 @@ mod tests {
         let id = Id::try_from(bits).unwrap();
         assert_eq!(id, Id::Extended(ExtendedId::new(0x0012_3456).unwrap()));
     }
+
+    #[test]
+    fn filter_matches_masked_identifier() {
+        let filter = Filter::Standard {
+            id: StandardId::new(0x700).unwrap(),
+            mask: 0x700,
+        };
+        assert!(filter.matches(Id::Standard(StandardId::new(0x7E0).unwrap())));
+        assert!(!filter.matches(Id::Standard(StandardId::new(0x6E0).unwrap())));
+        assert!(Filter::default().matches(Id::Extended(ExtendedId::new(0x1234).unwrap())));
+    }
+
+    #[test]
+    fn filter_matches_identifier_kind() {
+        let standard = Filter::Standard {
+            id: StandardId::new(0x123).unwrap(),
+            mask: 0x7FF,
+        };
+        let extended = Filter::Extended {
+            id: ExtendedId::new(0x123).unwrap(),
+            mask: 0x1FFF_FFFF,
+        };
+        assert!(standard.matches(Id::Standard(StandardId::new(0x123).unwrap())));
+        assert!(!standard.matches(Id::Extended(ExtendedId::new(0x123).unwrap())));
+        assert!(extended.matches(Id::Extended(ExtendedId::new(0x123).unwrap())));
+        assert!(!extended.matches(Id::Standard(StandardId::new(0x123).unwrap())));
+    }
 }
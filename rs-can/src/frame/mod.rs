@@ -1,11 +1,13 @@
+pub(crate) mod generic;
 pub(crate) mod identifier;
 
 use self::identifier::{CanFdFlags, Id};
-use crate::utils;
-use crate::CanResult;
-use std::fmt::{Display, Formatter, Write};
+use crate::{asc, utils};
+use crate::{error::Error, CanResult};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub enum TimestampSource {
     System,
     Hardware,
@@ -13,13 +15,13 @@ pub enum TimestampSource {
     Unknown,
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Timestamp {
     pub nanos: u128,
     pub source: TimestampSource,
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub enum FrameFormat {
     #[default]
     Data,
@@ -28,7 +30,7 @@ pub enum FrameFormat {
 }
 
 #[repr(C)]
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub enum Kind {
     #[default]
     Classical,
@@ -36,8 +38,21 @@ pub enum Kind {
     XL,
 }
 
+/// The CAN XL specific header fields, the priority id is carried by [`Frame::id`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct XlParams {
+    /// SDU type(SDT), the type of the payload.
+    pub sdu_type: u8,
+    /// virtual CAN network ID(VCID).
+    pub vcid: u8,
+    /// acceptance field(AF).
+    pub acceptance: u32,
+    /// simple extended content(SEC).
+    pub sec: bool,
+}
+
 #[repr(C)]
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub enum Direction {
     #[default]
     Transmit,
@@ -69,6 +84,24 @@ pub trait Frame: Send + Sync {
     where
         Self: Sized;
 
+    /// Create a CAN XL frame with the 11-bit priority id.
+    fn new_can_xl(priority: u16, data: &[u8], params: XlParams) -> CanResult<Self>
+    where
+        Self: Sized,
+    {
+        let _ = (priority, data, params);
+        Err(Error::NotSupportedError)
+    }
+
+    /// Create an error frame, the `id` and `data` carry the error class and details.
+    fn new_error(id: Id, data: &[u8]) -> CanResult<Self>
+    where
+        Self: Sized,
+    {
+        let _ = (id, data);
+        Err(Error::NotSupportedError)
+    }
+
     fn id(&self) -> Id;
     fn channel(&self) -> Self::Channel;
     fn set_channel(&mut self, v: Self::Channel) -> &mut Self
@@ -84,6 +117,19 @@ pub trait Frame: Send + Sync {
         utils::can_dlc(self.len(), self.kind())
     }
 
+    /// The 11-bit DLC of CAN XL frame, `None` if the frame is not a CAN XL frame.
+    fn xl_dlc(&self) -> Option<u16> {
+        match self.kind() {
+            Kind::XL => utils::can_xl_dlc(self.len()).ok(),
+            _ => None,
+        }
+    }
+
+    /// The CAN XL header fields, `None` if the frame is not a CAN XL frame.
+    fn xl_params(&self) -> Option<XlParams> {
+        None
+    }
+
     fn direction(&self) -> Direction;
     fn set_direction(&mut self, d: Direction) -> &mut Self
     where
@@ -120,79 +166,11 @@ pub trait Frame: Send + Sync {
 impl<T: Display> Display for dyn Frame<Channel = T> {
     /// Output Frame as `asc` String.
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let data_str = if self.is_remote() {
-            " ".to_owned()
-        } else {
-            self.data().iter().fold(String::new(), |mut out, &b| {
-                let _ = write!(out, "{b:02x} ");
-                out
-            })
-        };
-
-        match self.kind() {
-            Kind::Classical => {
-                let timestamp_secs = self
-                    .timestamp()
-                    .map(|ts| ts.nanos as f64 / 1_000_000_000.)
-                    .unwrap_or_default();
-                write!(
-                    f,
-                    "{:.3} {} {}{: <4} {} {} {} {}",
-                    timestamp_secs,
-                    self.channel(),
-                    format!("{: >8x}", self.id().as_raw()),
-                    if self.is_extended() { "x" } else { "" },
-                    self.direction(),
-                    // if self.is_rx() { "Rx" } else { "Tx" },
-                    if self.is_remote() { "r" } else { "d" },
-                    format!("{: >2}", self.len()),
-                    data_str,
-                )
-            }
-            Kind::FD => {
-                let timestamp_secs = self
-                    .timestamp()
-                    .map(|ts| ts.nanos as f64 / 1_000_000_000.)
-                    .unwrap_or_default();
-                let dlc = self.dlc().map_err(|_| std::fmt::Error)?;
-                let mut flags = 1 << 12;
-                write!(
-                    f,
-                    "{:.3} CANFD {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
-                    timestamp_secs,
-                    self.channel(),
-                    self.direction(),
-                    // if self.is_rx() { "Rx" } else { "Tx" },
-                    format!("{: >8x}", self.id().as_raw()),
-                    if self.is_bitrate_switch() {
-                        flags |= 1 << 13;
-                        1
-                    } else {
-                        0
-                    },
-                    if self.is_esi() {
-                        flags |= 1 << 14;
-                        1
-                    } else {
-                        0
-                    },
-                    format!("{: >2}", dlc),
-                    format!("{: >2}", self.len()),
-                    data_str,
-                    format!("{: >8}", 0), // message_duration
-                    format!("{: <4}", 0), // message_length
-                    format!("{: >8x}", flags),
-                    format!("{: >8}", 0), // crc
-                    format!("{: >8}", 0), // bit_timing_conf_arb
-                    format!("{: >8}", 0), // bit_timing_conf_data
-                    format!("{: >8}", 0), // bit_timing_conf_ext_arb
-                    format!("{: >8}", 0), // bit_timing_conf_ext_data
-                )
-            }
-            Kind::XL => {
-                // TODO
-                write!(f, "CANXL Frame")
-            }
-        }
+        let timestamp_secs = self
+            .timestamp()
+            .map(|ts| ts.nanos as f64 / 1_000_000_000.)
+            .unwrap_or_default();
+        write!(f, "{:.3} ", timestamp_secs)?;
+        asc::fmt_frame(f, self, &self.channel())
     }
 }
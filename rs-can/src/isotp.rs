@@ -0,0 +1,762 @@
+//! ISO-TP(ISO 15765-2) transport layer over any [`Device`].
+//!
+//! The [`IsoTpChannel`] segments the messages into single/first/consecutive frames
+//! and reassembles them with the flow control frames. Classical CAN and CAN-FD
+//! (including the escape lengths) are supported.
+//!
+//! # Example
+//! ```ignore
+//! let device = Arc::new(builder.build::<SocketCan>()?);
+//! let address = Address::new(CanId::from_bits(0x7E0, None)?, CanId::from_bits(0x7E8, None)?);
+//! let channel = IsoTpChannel::new(device, "can0".into(), address);
+//! channel.transmit(&[0x22, 0xF1, 0x90]).await?;
+//! let response = channel.receive(Some(1000)).await?;
+//! ```
+
+use crate::{
+    device::Device,
+    error::Error,
+    frame::{
+        identifier::{CanFdFlags, Id},
+        Direction, Frame, FrameFormat,
+    },
+    CanResult, DEFAULT_PADDING, MAX_FD_FRAME_SIZE, MAX_FRAME_SIZE,
+};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The max count of frames buffered for later transfers.
+const ISOTP_QUEUE_SIZE: usize = 256;
+/// The max message length of the first frame without escape.
+const FF_DL_MAX: usize = 0xFFF;
+/// The valid lengths of transmitted frames.
+const TX_DL_VALUES: [usize; 8] = [8, 12, 16, 20, 24, 32, 48, 64];
+
+const PCI_SF: u8 = 0x00;
+const PCI_FF: u8 = 0x10;
+const PCI_CF: u8 = 0x20;
+const PCI_FC: u8 = 0x30;
+
+/// The target address type of transmitting.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AddressType {
+    /// 1 to 1 communication, all frame types are supported.
+    #[default]
+    Physical,
+    /// 1 to n communication, only single frame is supported.
+    Functional,
+}
+
+/// The identifiers used by an ISO-TP channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Address {
+    /// The physical identifier of transmitting.
+    pub tx_id: Id,
+    /// The physical identifier of receiving.
+    pub rx_id: Id,
+    /// The functional identifier, such as `0x7DF`, which is transmitted by client
+    /// and received by server.
+    pub functional_id: Option<Id>,
+}
+
+impl Address {
+    pub fn new(tx_id: Id, rx_id: Id) -> Self {
+        Self {
+            tx_id,
+            rx_id,
+            functional_id: None,
+        }
+    }
+
+    pub fn set_functional_id(&mut self, id: Id) -> &mut Self {
+        self.functional_id = Some(id);
+        self
+    }
+}
+
+/// The flow status of flow control frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowStatus {
+    ContinueToSend,
+    Wait,
+    Overflow,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IsoTpConfig {
+    /// The max length of transmitted frames, `8` for CAN, up to `64` for CAN-FD.
+    ///
+    /// It's one of `8`, `12`, `16`, `20`, `24`, `32`, `48` and `64`,
+    /// otherwise the transmitting is rejected.
+    pub tx_dl: usize,
+    /// Whether the CAN-FD frames are transmitted with bitrate switch.
+    pub bitrate_switch: bool,
+    /// The padding byte of frames, the frames are not padded to 8 bytes when `None`.
+    ///
+    /// CAN-FD frames longer than 8 bytes are always padded to a valid length.
+    pub padding: Option<u8>,
+    /// The block size sent in flow control, `0` means no more flow control.
+    pub block_size: u8,
+    /// The raw STmin sent in flow control.
+    pub st_min: u8,
+    /// The max count of continuous `FC.WAIT` before the transmitting is aborted.
+    pub wft_max: u8,
+    /// The max length of received message, the larger message is rejected with `FC.OVFLW`.
+    pub max_length: usize,
+    /// The timeout of frame transmitting.
+    pub n_as: Duration,
+    /// The timeout of waiting flow control.
+    pub n_bs: Duration,
+    /// The timeout of waiting consecutive frame.
+    pub n_cr: Duration,
+}
+
+impl Default for IsoTpConfig {
+    fn default() -> Self {
+        Self {
+            tx_dl: MAX_FRAME_SIZE,
+            bitrate_switch: false,
+            padding: Some(DEFAULT_PADDING),
+            block_size: 0,
+            st_min: 0,
+            wft_max: 10,
+            max_length: FF_DL_MAX,
+            n_as: Duration::from_millis(1000),
+            n_bs: Duration::from_millis(1000),
+            n_cr: Duration::from_millis(1000),
+        }
+    }
+}
+
+impl IsoTpConfig {
+    /// The config of CAN-FD with the max frame length.
+    pub fn can_fd() -> Self {
+        Self {
+            tx_dl: MAX_FD_FRAME_SIZE,
+            bitrate_switch: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// The protocol data unit carried by a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pdu<'a> {
+    Single(&'a [u8]),
+    First {
+        length: usize,
+        data: &'a [u8],
+    },
+    Consecutive {
+        sn: u8,
+        data: &'a [u8],
+    },
+    FlowControl {
+        status: FlowStatus,
+        bs: u8,
+        st_min: u8,
+    },
+}
+
+impl<'a> Pdu<'a> {
+    fn parse(data: &'a [u8]) -> CanResult<Self> {
+        let invalid = || Error::InvalidFrame(format!("invalid ISO-TP frame: {:02X?}", data));
+        let pci = *data.first().ok_or_else(invalid)?;
+        match pci & 0xF0 {
+            PCI_SF => {
+                let (length, offset) = match pci & 0x0F {
+                    0 if data.len() > MAX_FRAME_SIZE => (data[1] as usize, 2),
+                    length => (length as usize, 1),
+                };
+                match length {
+                    0 => Err(invalid()),
+                    _ => data
+                        .get(offset..offset + length)
+                        .map(Self::Single)
+                        .ok_or_else(invalid),
+                }
+            }
+            PCI_FF => {
+                let length =
+                    ((pci as usize & 0x0F) << 8) | *data.get(1).ok_or_else(invalid)? as usize;
+                match length {
+                    0 => {
+                        let length = data.get(2..6).ok_or_else(invalid)?;
+                        Ok(Self::First {
+                            length: u32::from_be_bytes(length.try_into().unwrap()) as usize,
+                            data: &data[6..],
+                        })
+                    }
+                    _ => Ok(Self::First {
+                        length,
+                        data: &data[2..],
+                    }),
+                }
+            }
+            PCI_CF => Ok(Self::Consecutive {
+                sn: pci & 0x0F,
+                data: &data[1..],
+            }),
+            PCI_FC => {
+                let status = match pci & 0x0F {
+                    0 => FlowStatus::ContinueToSend,
+                    1 => FlowStatus::Wait,
+                    2 => FlowStatus::Overflow,
+                    _ => return Err(invalid()),
+                };
+                match data.get(1..3) {
+                    Some(&[bs, st_min]) => Ok(Self::FlowControl { status, bs, st_min }),
+                    _ => Err(invalid()),
+                }
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Convert the raw STmin into duration, the reserved values are treated as 127ms.
+pub fn st_min_duration(st_min: u8) -> Duration {
+    match st_min {
+        0x00..=0x7F => Duration::from_millis(st_min as u64),
+        0xF1..=0xF9 => Duration::from_micros((st_min - 0xF0) as u64 * 100),
+        _ => Duration::from_millis(0x7F),
+    }
+}
+
+/// Encode the data into single frame, `None` if it's too long for `tx_dl`.
+fn single_frame(data: &[u8], tx_dl: usize) -> Option<Vec<u8>> {
+    let length = data.len();
+    let mut buf = Vec::with_capacity(tx_dl);
+    if length < MAX_FRAME_SIZE {
+        buf.push(PCI_SF | length as u8);
+    } else if tx_dl > MAX_FRAME_SIZE && length <= tx_dl - 2 {
+        buf.extend([PCI_SF, length as u8]);
+    } else {
+        return None;
+    }
+    buf.extend_from_slice(data);
+
+    Some(buf)
+}
+
+/// Encode the beginning of data into first frame, return the frame and the consumed length.
+fn first_frame(data: &[u8], tx_dl: usize) -> (Vec<u8>, usize) {
+    let length = data.len();
+    let mut buf = Vec::with_capacity(tx_dl);
+    if length <= FF_DL_MAX {
+        buf.extend([PCI_FF | (length >> 8) as u8, length as u8]);
+    } else {
+        buf.extend([PCI_FF, 0]);
+        buf.extend((length as u32).to_be_bytes());
+    }
+    let size = tx_dl - buf.len();
+    buf.extend_from_slice(&data[..size]);
+
+    (buf, size)
+}
+
+/// An ISO-TP channel on a channel of device.
+///
+/// The frames of `rx_id` which are not expected by the current transfer are buffered
+/// for the later transfers, so only one transfer should be in progress at a time.
+pub struct IsoTpChannel<D: Device> {
+    device: Arc<D>,
+    channel: D::Channel,
+    address: Address,
+    config: IsoTpConfig,
+    pending: Mutex<VecDeque<D::Frame>>,
+}
+
+impl<D> IsoTpChannel<D>
+where
+    D: Device,
+    D::Channel: Clone,
+{
+    pub fn new(device: Arc<D>, channel: D::Channel, address: Address) -> Self {
+        Self {
+            device,
+            channel,
+            address,
+            config: Default::default(),
+            pending: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn device(&self) -> &Arc<D> {
+        &self.device
+    }
+
+    #[inline]
+    pub fn channel(&self) -> &D::Channel {
+        &self.channel
+    }
+
+    #[inline]
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    #[inline]
+    pub fn config(&self) -> &IsoTpConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: IsoTpConfig) -> &mut Self {
+        self.config = config;
+        self
+    }
+
+    /// Discard the buffered frames.
+    pub fn clear(&self) {
+        self.pending.lock().unwrap().clear();
+    }
+
+    /// Transmit the message by physical addressing.
+    #[inline]
+    pub async fn transmit(&self, data: &[u8]) -> CanResult<()> {
+        self.transmit_to(data, AddressType::Physical).await
+    }
+
+    /// Transmit the message, the multi-frame message is only supported by physical addressing.
+    pub async fn transmit_to(&self, data: &[u8], address_type: AddressType) -> CanResult<()> {
+        if data.is_empty() {
+            return Err(Error::InvalidDLC(0));
+        }
+
+        let tx_dl = self.config.tx_dl;
+        if !TX_DL_VALUES.contains(&tx_dl) {
+            return Err(Error::operation_error(format!(
+                "ISO-TP tx_dl: {} is not a valid frame length",
+                tx_dl
+            )));
+        }
+        let id = match address_type {
+            AddressType::Physical => self.address.tx_id,
+            AddressType::Functional => self
+                .address
+                .functional_id
+                .ok_or(Error::operation_error("ISO-TP functional id is not set"))?,
+        };
+        if let Some(frame) = single_frame(data, tx_dl) {
+            return self.write(id, frame).await;
+        }
+        if matches!(address_type, AddressType::Functional) || data.len() > u32::MAX as usize {
+            return Err(Error::InvalidDLC(data.len()));
+        }
+
+        let (frame, mut offset) = first_frame(data, tx_dl);
+        self.write(id, frame).await?;
+        let mut sn = 1;
+        loop {
+            let (bs, st_min) = self.wait_flow_control().await?;
+            let mut count = 0usize;
+            loop {
+                let size = (tx_dl - 1).min(data.len() - offset);
+                let mut frame = Vec::with_capacity(tx_dl);
+                frame.push(PCI_CF | sn);
+                frame.extend_from_slice(&data[offset..offset + size]);
+                self.write(id, frame).await?;
+
+                offset += size;
+                if offset >= data.len() {
+                    return Ok(());
+                }
+                sn = (sn + 1) & 0x0F;
+                count += 1;
+                if bs != 0 && count == bs as usize {
+                    break;
+                }
+                tokio::time::sleep(st_min).await;
+            }
+        }
+    }
+
+    /// Receive a message, `timeout`(ms) is the time of waiting the single or first frame.
+    #[inline]
+    pub async fn receive(&self, timeout: Option<u32>) -> CanResult<Vec<u8>> {
+        self.receive_from(timeout).await.map(|(_, data)| data)
+    }
+
+    /// Receive a message with its address type, the functional message is received
+    /// only when `functional_id` is set.
+    pub async fn receive_from(&self, timeout: Option<u32>) -> CanResult<(AddressType, Vec<u8>)> {
+        let deadline = timeout.map(|ms| Instant::now() + Duration::from_millis(ms as u64));
+        loop {
+            let frame = self.read(deadline, "receive", |_| true).await?;
+            let address_type = match frame.id() == self.address.rx_id {
+                true => AddressType::Physical,
+                false => AddressType::Functional,
+            };
+            match (Pdu::parse(frame.data()), address_type) {
+                (Ok(Pdu::Single(data)), _) => return Ok((address_type, data.to_vec())),
+                (Ok(Pdu::First { length, data }), AddressType::Physical) => {
+                    let data = self.receive_consecutive(length, data).await?;
+                    return Ok((address_type, data));
+                }
+                (Ok(pdu), _) => rsutil::warn!("RUST-CAN - ISO-TP unexpected frame: {:?}", pdu),
+                (Err(e), _) => rsutil::warn!("{}", e),
+            }
+        }
+    }
+
+    async fn receive_consecutive(&self, length: usize, first: &[u8]) -> CanResult<Vec<u8>> {
+        let tx_id = self.address.tx_id;
+        if length > self.config.max_length {
+            self.write(tx_id, vec![PCI_FC | 2, 0, 0]).await?;
+            return Err(Error::InvalidDLC(length));
+        }
+
+        let bs = self.config.block_size;
+        let fc = vec![PCI_FC, bs, self.config.st_min];
+        self.write(tx_id, fc.clone()).await?;
+
+        let mut buf = Vec::with_capacity(length);
+        buf.extend_from_slice(&first[..first.len().min(length)]);
+        let (mut sn, mut count) = (1, 0usize);
+        while buf.len() < length {
+            let deadline = Some(Instant::now() + self.config.n_cr);
+            let frame = self
+                .read(deadline, "N_Cr", |f| self.is_physical(f, PCI_CF))
+                .await?;
+            let Pdu::Consecutive { sn: actual, data } = Pdu::parse(frame.data())? else {
+                unreachable!()
+            };
+            if actual != sn {
+                return Err(Error::InvalidFrame(format!(
+                    "ISO-TP wrong sequence number: {}, expected: {}",
+                    actual, sn
+                )));
+            }
+            let size = data.len().min(length - buf.len());
+            buf.extend_from_slice(&data[..size]);
+
+            sn = (sn + 1) & 0x0F;
+            count += 1;
+            if bs != 0 && count == bs as usize && buf.len() < length {
+                self.write(tx_id, fc.clone()).await?;
+                count = 0;
+            }
+        }
+
+        Ok(buf)
+    }
+
+    async fn wait_flow_control(&self) -> CanResult<(u8, Duration)> {
+        let mut waits = 0;
+        loop {
+            let deadline = Some(Instant::now() + self.config.n_bs);
+            let frame = self
+                .read(deadline, "N_Bs", |f| self.is_physical(f, PCI_FC))
+                .await?;
+            match Pdu::parse(frame.data()) {
+                Ok(Pdu::FlowControl {
+                    status: FlowStatus::ContinueToSend,
+                    bs,
+                    st_min,
+                }) => return Ok((bs, st_min_duration(st_min))),
+                Ok(Pdu::FlowControl {
+                    status: FlowStatus::Wait,
+                    ..
+                }) => {
+                    waits += 1;
+                    if waits > self.config.wft_max {
+                        return Err(Error::operation_error("ISO-TP too many FC.WAIT received"));
+                    }
+                }
+                Ok(_) => return Err(Error::operation_error("ISO-TP receiver is overflow")),
+                Err(e) => rsutil::warn!("{}", e),
+            }
+        }
+    }
+
+    #[inline]
+    fn is_physical(&self, frame: &D::Frame, pci: u8) -> bool {
+        frame.id() == self.address.rx_id && frame.data()[0] & 0xF0 == pci
+    }
+
+    /// Pad and transmit the frame.
+    async fn write(&self, id: Id, mut data: Vec<u8>) -> CanResult<()> {
+        let fd = self.config.tx_dl > MAX_FRAME_SIZE;
+        let length = match data.len() {
+            ..=MAX_FRAME_SIZE => match self.config.padding {
+                Some(_) => MAX_FRAME_SIZE,
+                None => data.len(),
+            },
+            9..=12 => 12,
+            13..=16 => 16,
+            17..=20 => 20,
+            21..=24 => 24,
+            25..=32 => 32,
+            33..=48 => 48,
+            _ => MAX_FD_FRAME_SIZE,
+        };
+        data.resize(length, self.config.padding.unwrap_or(DEFAULT_PADDING));
+
+        let mut frame = match fd {
+            true => {
+                let mut flags = CanFdFlags::empty();
+                flags.set(CanFdFlags::BRS, self.config.bitrate_switch);
+                D::Frame::new_can_fd(id, &data, flags)?
+            }
+            false => D::Frame::new_can(id, &data)?,
+        };
+        frame.set_channel(self.channel.clone());
+        let timeout = self.config.n_as.as_millis() as u32;
+        self.device
+            .transmit(frame, Some(timeout))
+            .await
+            .map_err(|e| match e {
+                Error::TimeoutError(_) => {
+                    Error::TimeoutError(format!("N_As at channel: {}", self.channel))
+                }
+                e => e,
+            })
+    }
+
+    /// Read the first frame matched by `expected`, the others are buffered.
+    async fn read(
+        &self,
+        deadline: Option<Instant>,
+        timer: &str,
+        expected: impl Fn(&D::Frame) -> bool,
+    ) -> CanResult<D::Frame> {
+        loop {
+            {
+                let mut pending = self.pending.lock().unwrap();
+                if let Some(pos) = pending.iter().position(&expected) {
+                    return Ok(pending.remove(pos).unwrap());
+                }
+            }
+
+            let timeout = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(Error::TimeoutError(format!(
+                            "{} at channel: {}",
+                            timer, self.channel
+                        )));
+                    }
+                    Some((deadline - now).as_millis().max(1) as u32)
+                }
+                None => None,
+            };
+            let frames = match self.device.receive(self.channel.clone(), timeout).await {
+                Ok(frames) => frames,
+                Err(Error::TimeoutError(_)) => continue,
+                Err(e) => return Err(e),
+            };
+
+            let mut pending = self.pending.lock().unwrap();
+            for frame in frames {
+                let id = frame.id();
+                if (id != self.address.rx_id && Some(id) != self.address.functional_id)
+                    || frame.direction() != Direction::Receive
+                    || frame.format() != FrameFormat::Data
+                    || frame.data().is_empty()
+                {
+                    continue;
+                }
+                if pending.len() >= ISOTP_QUEUE_SIZE {
+                    rsutil::warn!("RUST-CAN - ISO-TP queue is full, the oldest frame is dropped");
+                    pending.pop_front();
+                }
+                pending.push_back(frame);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{
+        device::ChannelConfig,
+        virtual_bus::{
+            tests::{device, TestFrame},
+            VirtualBus,
+        },
+    };
+
+    pub(crate) type Bus = VirtualBus<TestFrame>;
+
+    /// A channel on the virtual bus of `channel`, whose functional id is `0x7DF`.
+    pub(crate) fn isotp(channel: &str, tx_id: u32, rx_id: u32, canfd: bool) -> IsoTpChannel<Bus> {
+        let mut cfg = ChannelConfig::new(500_000);
+        if canfd {
+            cfg.set_data_bitrate(2_000_000);
+        }
+        let mut address = Address::new(
+            Id::from_bits(tx_id, None).unwrap(),
+            Id::from_bits(rx_id, None).unwrap(),
+        );
+        address.set_functional_id(Id::from_bits(0x7DF, None).unwrap());
+        let mut isotp =
+            IsoTpChannel::new(Arc::new(device(channel, cfg)), channel.to_owned(), address);
+        if canfd {
+            isotp.set_config(IsoTpConfig::can_fd());
+        }
+
+        isotp
+    }
+
+    fn channels(channel: &str, canfd: bool) -> (IsoTpChannel<Bus>, IsoTpChannel<Bus>) {
+        (
+            isotp(channel, 0x7E0, 0x7E8, canfd),
+            isotp(channel, 0x7E8, 0x7E0, canfd),
+        )
+    }
+
+    #[test]
+    fn frames_are_encoded() {
+        assert_eq!(single_frame(&[0x3E, 0x00], 8).unwrap(), [0x02, 0x3E, 0x00]);
+        assert!(single_frame(&[0x11; 8], 8).is_none());
+        let frame = single_frame(&[0x11; 20], 64).unwrap();
+        assert_eq!(&frame[..2], &[0x00, 20]);
+        assert_eq!(Pdu::parse(&frame).unwrap(), Pdu::Single(&[0x11; 20]));
+
+        let (frame, size) = first_frame(&[0x22; 100], 8);
+        assert_eq!((&frame[..2], size), (&[0x10, 100][..], 6));
+        let (frame, size) = first_frame(&[0x22; 5000], 64);
+        assert_eq!((&frame[..6], size), (&[0x10, 0, 0, 0, 0x13, 0x88][..], 58));
+        assert_eq!(
+            Pdu::parse(&frame).unwrap(),
+            Pdu::First {
+                length: 5000,
+                data: &[0x22; 58]
+            }
+        );
+
+        assert_eq!(
+            Pdu::parse(&[0x30, 2, 0xF5]).unwrap(),
+            Pdu::FlowControl {
+                status: FlowStatus::ContinueToSend,
+                bs: 2,
+                st_min: 0xF5
+            }
+        );
+        assert!(Pdu::parse(&[0x00, 0xAA]).is_err());
+        assert!(Pdu::parse(&[0x05, 0x11]).is_err());
+        assert_eq!(st_min_duration(0xF5), Duration::from_micros(500));
+        assert_eq!(st_min_duration(0x80), Duration::from_millis(127));
+    }
+
+    #[tokio::test]
+    async fn multi_frame_is_transferred() {
+        let (mut tester, mut ecu) = channels("isotp-classic", false);
+        ecu.set_config(IsoTpConfig {
+            block_size: 4,
+            st_min: 1,
+            ..Default::default()
+        });
+        tester.set_config(Default::default());
+        let data = (0..200).map(|v| v as u8).collect::<Vec<_>>();
+
+        let (sent, received) = tokio::join!(tester.transmit(&data), ecu.receive(Some(1000)));
+        sent.unwrap();
+        assert_eq!(received.unwrap(), data);
+
+        let (sent, received) = tokio::join!(
+            ecu.transmit(&[0x62, 0xF1, 0x90]),
+            tester.receive(Some(1000))
+        );
+        sent.unwrap();
+        assert_eq!(received.unwrap(), [0x62, 0xF1, 0x90]);
+    }
+
+    #[tokio::test]
+    async fn unlimited_block_is_transferred() {
+        // 4095 bytes are 585 consecutive frames without any FC between them.
+        let (tester, ecu) = channels("isotp-unlimited", false);
+        let data = (0..4095).map(|v| v as u8).collect::<Vec<_>>();
+
+        let (sent, received) = tokio::join!(tester.transmit(&data), ecu.receive(Some(1000)));
+        sent.unwrap();
+        assert_eq!(received.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn can_fd_escape_lengths_are_transferred() {
+        let (tester, mut ecu) = channels("isotp-fd", true);
+        ecu.set_config(IsoTpConfig {
+            max_length: 8192,
+            ..IsoTpConfig::can_fd()
+        });
+
+        let (sent, received) = tokio::join!(tester.transmit(&[0x55; 40]), ecu.receive(Some(1000)));
+        sent.unwrap();
+        assert_eq!(received.unwrap(), [0x55; 40]);
+
+        let data = (0..5000).map(|v| v as u8).collect::<Vec<_>>();
+        let (sent, received) = tokio::join!(tester.transmit(&data), ecu.receive(Some(1000)));
+        sent.unwrap();
+        assert_eq!(received.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn flow_control_timeout() {
+        let (mut tester, _ecu) = channels("isotp-timeout", false);
+        tester.set_config(IsoTpConfig {
+            n_bs: Duration::from_millis(50),
+            ..Default::default()
+        });
+
+        let ret = tester.transmit(&[0x11; 20]).await;
+        assert!(matches!(ret, Err(Error::TimeoutError(msg)) if msg.starts_with("N_Bs")));
+    }
+
+    #[tokio::test]
+    async fn overflow_is_rejected() {
+        let (tester, ecu) = channels("isotp-overflow", false);
+
+        let (sent, received) =
+            tokio::join!(tester.transmit(&[0x11; 5000]), ecu.receive(Some(1000)));
+        assert!(matches!(sent, Err(Error::OperationError(_))));
+        assert!(matches!(received, Err(Error::InvalidDLC(5000))));
+    }
+
+    #[tokio::test]
+    async fn invalid_tx_dl_is_rejected() {
+        let (mut tester, _ecu) = channels("isotp-tx-dl", true);
+
+        for tx_dl in [0, 1, 7, 10, 65] {
+            tester.set_config(IsoTpConfig {
+                tx_dl,
+                ..IsoTpConfig::can_fd()
+            });
+            assert!(
+                matches!(
+                    tester.transmit(&[0x11; 100]).await,
+                    Err(Error::OperationError(_))
+                ),
+                "{}",
+                tx_dl
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn functional_addressing() {
+        let (tester, ecu) = channels("isotp-functional", false);
+
+        assert!(matches!(
+            tester
+                .transmit_to(&[0x11; 20], AddressType::Functional)
+                .await,
+            Err(Error::InvalidDLC(20))
+        ));
+        let (sent, received) = tokio::join!(
+            tester.transmit_to(&[0x3E, 0x80], AddressType::Functional),
+            ecu.receive_from(Some(100))
+        );
+        sent.unwrap();
+        assert_eq!(
+            received.unwrap(),
+            (AddressType::Functional, vec![0x3E, 0x80])
+        );
+    }
+}
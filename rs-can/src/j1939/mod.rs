@@ -0,0 +1,267 @@
+//! SAE J1939 over any [`Device`](crate::CanDevice).
+//!
+//! The [`J1939Id`] decodes the priority, PGN and addresses of an [`ExtendedId`],
+//! the [`J1939Node`] claims its address with [`Name`] and transfers the messages
+//! up to 1785 bytes by the transport protocol(BAM and RTS/CTS).
+
+mod node;
+
+pub use node::*;
+
+use crate::{
+    error::Error,
+    frame::identifier::{ExtendedId, Id},
+    CanResult,
+};
+
+/// The destination address of broadcasting.
+pub const GLOBAL_ADDRESS: u8 = 0xFF;
+/// The source address of node which has not claimed an address.
+pub const NULL_ADDRESS: u8 = 0xFE;
+/// The max data length transferred by transport protocol.
+pub const MAX_TP_SIZE: usize = 1785;
+/// The default priority of messages.
+pub const DEFAULT_PRIORITY: u8 = 6;
+
+/// The parameter group number, which includes EDP, DP, PF and PS.
+///
+/// The PS of PDU1 format(PF < 240) is the destination address, so it's always zero in PGN.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Pgn(u32);
+
+impl Pgn {
+    pub const MAX: u32 = 0x3FFFF;
+    pub const ACKNOWLEDGEMENT: Self = Self(0xE800);
+    pub const REQUEST: Self = Self(0xEA00);
+    pub const TP_DT: Self = Self(0xEB00);
+    pub const TP_CM: Self = Self(0xEC00);
+    pub const ADDRESS_CLAIMED: Self = Self(0xEE00);
+    pub const COMMANDED_ADDRESS: Self = Self(0xFED8);
+
+    pub fn new(pgn: u32) -> CanResult<Self> {
+        let pgn = Self(pgn);
+        if pgn.0 > Self::MAX || (pgn.is_pdu1() && pgn.pdu_specific() != 0) {
+            Err(Error::InvalidIdentifier(pgn.0))
+        } else {
+            Ok(pgn)
+        }
+    }
+
+    #[inline]
+    pub fn as_raw(self) -> u32 {
+        self.0
+    }
+
+    #[inline]
+    pub fn pdu_format(self) -> u8 {
+        (self.0 >> 8) as u8
+    }
+
+    #[inline]
+    pub fn pdu_specific(self) -> u8 {
+        self.0 as u8
+    }
+
+    /// Whether the PGN is destination specific.
+    #[inline]
+    pub fn is_pdu1(self) -> bool {
+        self.pdu_format() < 0xF0
+    }
+
+    /// The 3 bytes little-endian PGN used by request and transport protocol.
+    #[inline]
+    pub fn to_bytes(self) -> [u8; 3] {
+        let [b0, b1, b2, _] = self.0.to_le_bytes();
+        [b0, b1, b2]
+    }
+
+    #[inline]
+    pub fn from_bytes(bytes: [u8; 3]) -> CanResult<Self> {
+        let [b0, b1, b2] = bytes;
+        Self::new(u32::from_le_bytes([b0, b1, b2, 0]))
+    }
+}
+
+/// The J1939 fields of a 29-bit identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct J1939Id {
+    pub priority: u8,
+    pub pgn: Pgn,
+    pub source: u8,
+    /// The destination address, it's [`GLOBAL_ADDRESS`] for PDU2 format.
+    pub destination: u8,
+}
+
+impl J1939Id {
+    pub fn new(priority: u8, pgn: Pgn, source: u8, destination: u8) -> Self {
+        Self {
+            priority: priority & 0x07,
+            pgn,
+            source,
+            destination: match pgn.is_pdu1() {
+                true => destination,
+                false => GLOBAL_ADDRESS,
+            },
+        }
+    }
+}
+
+impl From<ExtendedId> for J1939Id {
+    fn from(id: ExtendedId) -> Self {
+        let raw = id.as_raw();
+        let pgn = Pgn((raw >> 8) & Pgn::MAX);
+        let (pgn, destination) = match pgn.is_pdu1() {
+            true => (Pgn(pgn.0 & !0xFF), pgn.pdu_specific()),
+            false => (pgn, GLOBAL_ADDRESS),
+        };
+
+        Self {
+            priority: (raw >> 26) as u8 & 0x07,
+            pgn,
+            source: raw as u8,
+            destination,
+        }
+    }
+}
+
+impl From<J1939Id> for ExtendedId {
+    fn from(id: J1939Id) -> Self {
+        let mut raw = ((id.priority as u32 & 0x07) << 26) | (id.pgn.0 << 8) | id.source as u32;
+        if id.pgn.is_pdu1() {
+            raw |= (id.destination as u32) << 8;
+        }
+        // the 3 bits priority and 18 bits PGN never overflow.
+        ExtendedId::new(raw).unwrap()
+    }
+}
+
+impl From<J1939Id> for Id {
+    #[inline]
+    fn from(id: J1939Id) -> Self {
+        Self::Extended(id.into())
+    }
+}
+
+/// The 64 bits NAME of node, the lower value has higher priority in address claiming.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Name {
+    /// 21 bits
+    pub identity_number: u32,
+    /// 11 bits
+    pub manufacturer_code: u16,
+    /// 3 bits
+    pub ecu_instance: u8,
+    /// 5 bits
+    pub function_instance: u8,
+    pub function: u8,
+    /// 7 bits
+    pub vehicle_system: u8,
+    /// 4 bits
+    pub vehicle_system_instance: u8,
+    /// 3 bits
+    pub industry_group: u8,
+    /// Whether the node can claim an address in `128..=247` when the preferred is lost.
+    pub arbitrary_address_capable: bool,
+}
+
+impl Name {
+    #[inline]
+    pub fn to_bytes(self) -> [u8; 8] {
+        u64::from(self).to_le_bytes()
+    }
+
+    #[inline]
+    pub fn from_bytes(bytes: [u8; 8]) -> Self {
+        u64::from_le_bytes(bytes).into()
+    }
+}
+
+impl From<u64> for Name {
+    fn from(v: u64) -> Self {
+        Self {
+            identity_number: v as u32 & 0x1F_FFFF,
+            manufacturer_code: (v >> 21) as u16 & 0x07FF,
+            ecu_instance: (v >> 32) as u8 & 0x07,
+            function_instance: (v >> 35) as u8 & 0x1F,
+            function: (v >> 40) as u8,
+            vehicle_system: (v >> 49) as u8 & 0x7F,
+            vehicle_system_instance: (v >> 56) as u8 & 0x0F,
+            industry_group: (v >> 60) as u8 & 0x07,
+            arbitrary_address_capable: v >> 63 != 0,
+        }
+    }
+}
+
+impl From<Name> for u64 {
+    fn from(name: Name) -> Self {
+        (name.identity_number as u64 & 0x1F_FFFF)
+            | (name.manufacturer_code as u64 & 0x07FF) << 21
+            | (name.ecu_instance as u64 & 0x07) << 32
+            | (name.function_instance as u64 & 0x1F) << 35
+            | (name.function as u64) << 40
+            | (name.vehicle_system as u64 & 0x7F) << 49
+            | (name.vehicle_system_instance as u64 & 0x0F) << 56
+            | (name.industry_group as u64 & 0x07) << 60
+            | (name.arbitrary_address_capable as u64) << 63
+    }
+}
+
+/// A J1939 message, the data of transport protocol is reassembled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct J1939Message {
+    /// The priority of frame, it's the priority of TP.CM frame for transport protocol.
+    pub priority: u8,
+    pub pgn: Pgn,
+    pub source: u8,
+    pub destination: u8,
+    pub data: Vec<u8>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identifier_is_decoded() {
+        // EEC1 from engine
+        let id = J1939Id::from(ExtendedId::new(0x0CF00400).unwrap());
+        assert_eq!(id, J1939Id::new(3, Pgn::new(0xF004).unwrap(), 0x00, 0x12));
+        assert_eq!(id.destination, GLOBAL_ADDRESS);
+        assert_eq!(ExtendedId::from(id).as_raw(), 0x0CF00400);
+
+        // request from 0xF9 to 0x00
+        let id = J1939Id::from(ExtendedId::new(0x18EA00F9).unwrap());
+        assert_eq!(
+            (id.priority, id.pgn, id.source, id.destination),
+            (6, Pgn::REQUEST, 0xF9, 0x00)
+        );
+        assert_eq!(ExtendedId::from(id).as_raw(), 0x18EA00F9);
+
+        assert!(Pgn::new(0xEA12).is_err());
+        assert!(Pgn::new(0x40000).is_err());
+        assert_eq!(Pgn::ADDRESS_CLAIMED.to_bytes(), [0x00, 0xEE, 0x00]);
+        assert_eq!(
+            Pgn::from_bytes([0xD8, 0xFE, 0x00]).unwrap(),
+            Pgn::COMMANDED_ADDRESS
+        );
+    }
+
+    #[test]
+    fn name_round_trip() {
+        let name = Name {
+            identity_number: 0x1ABCDE,
+            manufacturer_code: 0x5A5,
+            ecu_instance: 1,
+            function_instance: 2,
+            function: 0x81,
+            vehicle_system: 0x23,
+            vehicle_system_instance: 4,
+            industry_group: 2,
+            arbitrary_address_capable: true,
+        };
+        let raw = u64::from(name);
+        assert_eq!(raw, 0xA446_8111_B4BA_BCDE);
+        assert_eq!(Name::from(raw), name);
+        assert_eq!(Name::from_bytes(name.to_bytes()), name);
+    }
+}
@@ -0,0 +1,842 @@
+use super::{
+    J1939Id, J1939Message, Name, Pgn, DEFAULT_PRIORITY, GLOBAL_ADDRESS, MAX_TP_SIZE, NULL_ADDRESS,
+};
+use crate::{
+    device::Device,
+    error::Error,
+    frame::{identifier::Id, Direction, Frame, FrameFormat},
+    CanResult, MAX_FRAME_SIZE,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The max count of frames buffered for later transfers.
+const J1939_QUEUE_SIZE: usize = 256;
+/// The priority of transport protocol.
+const TP_PRIORITY: u8 = 7;
+/// The data length of each TP.DT packet.
+const TP_PACKET_SIZE: usize = 7;
+/// The padding of unused bytes.
+const PADDING: u8 = 0xFF;
+
+const TP_CM_RTS: u8 = 16;
+const TP_CM_CTS: u8 = 17;
+const TP_CM_EOMA: u8 = 19;
+const TP_CM_BAM: u8 = 32;
+const TP_CM_ABORT: u8 = 255;
+
+/// The abort reason of timeout.
+const ABORT_TIMEOUT: u8 = 3;
+/// The abort reason of bad sequence number.
+const ABORT_BAD_SEQUENCE: u8 = 7;
+
+/// The timing parameters of address claiming and transport protocol.
+#[derive(Debug, Clone, Copy)]
+pub struct J1939Config {
+    /// The interval of BAM data packets, `50..=200` ms.
+    pub bam_interval: Duration,
+    /// The max packets sent in a CTS.
+    pub cts_packets: u8,
+    /// The timeout of waiting address claimed by the others.
+    pub claim_timeout: Duration,
+    /// The timeout between the data packets.
+    pub t1: Duration,
+    /// The timeout of waiting data packet after CTS.
+    pub t2: Duration,
+    /// The timeout of waiting CTS or EOMA after the last data packet.
+    pub t3: Duration,
+    /// The timeout of waiting CTS after the CTS which holds the connection.
+    pub t4: Duration,
+}
+
+impl Default for J1939Config {
+    fn default() -> Self {
+        Self {
+            bam_interval: Duration::from_millis(50),
+            cts_packets: u8::MAX,
+            claim_timeout: Duration::from_millis(250),
+            t1: Duration::from_millis(750),
+            t2: Duration::from_millis(1250),
+            t3: Duration::from_millis(1250),
+            t4: Duration::from_millis(1050),
+        }
+    }
+}
+
+/// Encode the TP.CM with the control byte, 4 bytes parameters and PGN.
+#[inline]
+fn tp_cm(control: u8, params: [u8; 4], pgn: Pgn) -> Vec<u8> {
+    let mut data = vec![control];
+    data.extend(params);
+    data.extend(pgn.to_bytes());
+    data
+}
+
+/// The parameters of RTS, BAM and EOMA.
+#[inline]
+fn tp_size(size: usize, packets: usize) -> [u8; 4] {
+    let [lo, hi] = (size as u16).to_le_bytes();
+    [lo, hi, packets as u8, PADDING]
+}
+
+#[inline]
+fn tp_packets(size: usize) -> usize {
+    size.div_ceil(TP_PACKET_SIZE)
+}
+
+/// A J1939 node(controller application) on a channel of device.
+///
+/// The address is claimed by [`J1939Node::claim_address`] before transmitting, and
+/// the node defends its address while receiving. The requests of address claimed and
+/// the PGNs set by [`J1939Node::set_response`] are responded automatically, the other
+/// requests are received as messages.
+///
+/// Only one transfer should be in progress at a time, the frames which are not expected
+/// by the current transfer are buffered for the later transfers.
+///
+/// # Example
+/// ```ignore
+/// let node = J1939Node::new(device, "can0".into(), name, 0x80);
+/// node.claim_address().await?;
+/// node.transmit(Pgn::new(0xFEEC)?, DEFAULT_PRIORITY, GLOBAL_ADDRESS, b"VIN*").await?;
+/// let message = node.receive(Some(1000)).await?;
+/// ```
+pub struct J1939Node<D: Device> {
+    device: Arc<D>,
+    channel: D::Channel,
+    name: Name,
+    preferred: u8,
+    address: Mutex<u8>,
+    config: J1939Config,
+    pending: Mutex<VecDeque<(J1939Id, Vec<u8>)>>,
+    responses: Mutex<HashMap<Pgn, Vec<u8>>>,
+    names: Mutex<HashMap<u8, Name>>,
+}
+
+impl<D> J1939Node<D>
+where
+    D: Device,
+    D::Channel: Clone,
+{
+    pub fn new(device: Arc<D>, channel: D::Channel, name: Name, preferred: u8) -> Self {
+        Self {
+            device,
+            channel,
+            name,
+            preferred,
+            address: Mutex::new(NULL_ADDRESS),
+            config: Default::default(),
+            pending: Default::default(),
+            responses: Default::default(),
+            names: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn device(&self) -> &Arc<D> {
+        &self.device
+    }
+
+    #[inline]
+    pub fn channel(&self) -> &D::Channel {
+        &self.channel
+    }
+
+    #[inline]
+    pub fn name(&self) -> Name {
+        self.name
+    }
+
+    /// The claimed address, [`NULL_ADDRESS`] if it's not claimed.
+    #[inline]
+    pub fn address(&self) -> u8 {
+        *self.address.lock().unwrap()
+    }
+
+    #[inline]
+    pub fn config(&self) -> &J1939Config {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: J1939Config) -> &mut Self {
+        self.config = config;
+        self
+    }
+
+    /// The addresses claimed by the other nodes.
+    pub fn network(&self) -> HashMap<u8, Name> {
+        self.names.lock().unwrap().clone()
+    }
+
+    /// Set the data responded to the request of `pgn`.
+    pub fn set_response(&self, pgn: Pgn, data: Vec<u8>) -> &Self {
+        self.responses.lock().unwrap().insert(pgn, data);
+        self
+    }
+
+    /// Discard the buffered frames.
+    pub fn clear(&self) {
+        self.pending.lock().unwrap().clear();
+    }
+
+    /// Claim the preferred address, the arbitrary address capable node tries
+    /// the free addresses in `128..=247` when the preferred is lost.
+    pub async fn claim_address(&self) -> CanResult<u8> {
+        *self.address.lock().unwrap() = NULL_ADDRESS;
+        let ours = u64::from(self.name);
+        let mut tried = Vec::new();
+        while let Some(address) = self.next_candidate(&tried) {
+            tried.push(address);
+            self.write(
+                J1939Id::new(
+                    DEFAULT_PRIORITY,
+                    Pgn::ADDRESS_CLAIMED,
+                    address,
+                    GLOBAL_ADDRESS,
+                ),
+                self.name.to_bytes().to_vec(),
+            )
+            .await?;
+
+            let deadline = Instant::now() + self.config.claim_timeout;
+            let lost = loop {
+                let (id, data) = match self
+                    .read(Some(deadline), "address claiming", |id, _| {
+                        id.pgn == Pgn::ADDRESS_CLAIMED
+                    })
+                    .await
+                {
+                    Ok(v) => v,
+                    Err(Error::TimeoutError(_)) => break false,
+                    Err(e) => return Err(e),
+                };
+                let Some(name) = self.update_name(&id, &data) else {
+                    continue;
+                };
+                if id.source == address {
+                    match u64::from(name) < ours {
+                        true => break true,
+                        false => self.claim(address).await?,
+                    }
+                }
+            };
+
+            if !lost {
+                *self.address.lock().unwrap() = address;
+                return Ok(address);
+            }
+        }
+
+        self.claim(NULL_ADDRESS).await?;
+        Err(Error::operation_error("J1939 cannot claim address"))
+    }
+
+    /// Transmit the message from the claimed address, the message longer than 8 bytes
+    /// is transferred by BAM to global or RTS/CTS to the specific destination.
+    pub async fn transmit(
+        &self,
+        pgn: Pgn,
+        priority: u8,
+        destination: u8,
+        data: &[u8],
+    ) -> CanResult<()> {
+        let source = self.source()?;
+        let id = J1939Id::new(priority, pgn, source, destination);
+        match data.len() {
+            ..=MAX_FRAME_SIZE => self.write(id, data.to_vec()).await,
+            9..=MAX_TP_SIZE => match id.destination {
+                GLOBAL_ADDRESS => self.transmit_bam(id, data).await,
+                _ => self.transmit_rts(id, data).await,
+            },
+            len => Err(Error::InvalidDLC(len)),
+        }
+    }
+
+    /// Request the `pgn` from `destination`, the response is received by [`J1939Node::receive`].
+    pub async fn request(&self, pgn: Pgn, destination: u8) -> CanResult<()> {
+        let source = match self.address() {
+            // request address claimed is allowed before claiming.
+            NULL_ADDRESS if pgn == Pgn::ADDRESS_CLAIMED => NULL_ADDRESS,
+            _ => self.source()?,
+        };
+        let id = J1939Id::new(DEFAULT_PRIORITY, Pgn::REQUEST, source, destination);
+        self.write(id, pgn.to_bytes().to_vec()).await
+    }
+
+    /// Receive a message to the node or global, `timeout`(ms) is the time of waiting
+    /// the single frame or the connection management of transport protocol.
+    pub async fn receive(&self, timeout: Option<u32>) -> CanResult<J1939Message> {
+        let deadline = timeout.map(|ms| Instant::now() + Duration::from_millis(ms as u64));
+        loop {
+            let (id, data) = self.read(deadline, "receive", |_, _| true).await?;
+            match id.pgn {
+                Pgn::ADDRESS_CLAIMED => self.defend(&id, &data).await?,
+                Pgn::REQUEST if data.len() >= 3 => {
+                    if !self.respond(&id, &data).await? {
+                        return Ok(Self::message(id, data));
+                    }
+                }
+                Pgn::TP_CM => match data[0] {
+                    TP_CM_BAM if id.destination == GLOBAL_ADDRESS => {
+                        if let Some(message) = self.receive_bam(id, &data).await? {
+                            return Ok(message);
+                        }
+                    }
+                    TP_CM_RTS if id.destination != GLOBAL_ADDRESS => {
+                        if let Some(message) = self.receive_rts(id, &data).await? {
+                            return Ok(message);
+                        }
+                    }
+                    control => rsutil::warn!(
+                        "RUST-CAN - J1939 unexpected TP.CM: {} from {:#04X}",
+                        control,
+                        id.source
+                    ),
+                },
+                Pgn::TP_DT => {
+                    rsutil::warn!("RUST-CAN - J1939 unexpected TP.DT from {:#04X}", id.source)
+                }
+                _ => return Ok(Self::message(id, data)),
+            }
+        }
+    }
+
+    #[inline]
+    fn message(id: J1939Id, data: Vec<u8>) -> J1939Message {
+        J1939Message {
+            priority: id.priority,
+            pgn: id.pgn,
+            source: id.source,
+            destination: id.destination,
+            data,
+        }
+    }
+
+    #[inline]
+    fn source(&self) -> CanResult<u8> {
+        match self.address() {
+            NULL_ADDRESS => Err(Error::operation_error("J1939 address is not claimed")),
+            address => Ok(address),
+        }
+    }
+
+    /// The preferred address if it's not claimed by the higher priority name,
+    /// then the free arbitrary addresses.
+    fn next_candidate(&self, tried: &[u8]) -> Option<u8> {
+        let names = self.names.lock().unwrap();
+        let ours = u64::from(self.name);
+        let preferred = self.preferred;
+        if !tried.contains(&preferred)
+            && names
+                .get(&preferred)
+                .map_or(true, |name| u64::from(*name) > ours)
+        {
+            return Some(preferred);
+        }
+        if !self.name.arbitrary_address_capable {
+            return None;
+        }
+
+        // the addresses used by arbitrary address capable nodes.
+        (128..=247).find(|address| !tried.contains(address) && !names.contains_key(address))
+    }
+
+    /// Record the name of address claimed message.
+    fn update_name(&self, id: &J1939Id, data: &[u8]) -> Option<Name> {
+        let name = Name::from_bytes(data.try_into().ok()?);
+        let mut names = self.names.lock().unwrap();
+        match id.source {
+            NULL_ADDRESS => {
+                names.retain(|_, v| *v != name);
+                None
+            }
+            address => {
+                names.retain(|k, v| *k == address || *v != name);
+                names.insert(address, name);
+                Some(name)
+            }
+        }
+    }
+
+    #[inline]
+    async fn claim(&self, address: u8) -> CanResult<()> {
+        let id = J1939Id::new(
+            DEFAULT_PRIORITY,
+            Pgn::ADDRESS_CLAIMED,
+            address,
+            GLOBAL_ADDRESS,
+        );
+        self.write(id, self.name.to_bytes().to_vec()).await
+    }
+
+    /// Defend the claimed address, or claim another when it's lost.
+    async fn defend(&self, id: &J1939Id, data: &[u8]) -> CanResult<()> {
+        let Some(name) = self.update_name(id, data) else {
+            return Ok(());
+        };
+        let address = self.address();
+        if address == NULL_ADDRESS || id.source != address || name == self.name {
+            return Ok(());
+        }
+
+        match u64::from(name) < u64::from(self.name) {
+            true => {
+                rsutil::warn!("RUST-CAN - J1939 address {:#04X} is lost", address);
+                self.claim_address().await.map(|_| ())
+            }
+            false => self.claim(address).await,
+        }
+    }
+
+    /// Respond the request, return false if the requested PGN is unknown.
+    async fn respond(&self, id: &J1939Id, data: &[u8]) -> CanResult<bool> {
+        let address = self.address();
+        if id.destination != GLOBAL_ADDRESS && id.destination != address {
+            return Ok(true);
+        }
+        let pgn = match Pgn::from_bytes([data[0], data[1], data[2]]) {
+            Ok(pgn) => pgn,
+            Err(e) => {
+                rsutil::warn!(
+                    "RUST-CAN - J1939 invalid request from {:#04X}: {}",
+                    id.source,
+                    e
+                );
+                return Ok(true);
+            }
+        };
+        if pgn == Pgn::ADDRESS_CLAIMED {
+            self.claim(address).await?;
+            return Ok(true);
+        }
+        if address == NULL_ADDRESS {
+            return Ok(true);
+        }
+
+        let response = self.responses.lock().unwrap().get(&pgn).cloned();
+        match response {
+            Some(response) => {
+                let destination = match id.destination {
+                    GLOBAL_ADDRESS => GLOBAL_ADDRESS,
+                    _ => id.source,
+                };
+                self.transmit(pgn, DEFAULT_PRIORITY, destination, &response)
+                    .await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn transmit_bam(&self, id: J1939Id, data: &[u8]) -> CanResult<()> {
+        let packets = tp_packets(data.len());
+        let cm = J1939Id::new(TP_PRIORITY, Pgn::TP_CM, id.source, GLOBAL_ADDRESS);
+        self.write(cm, tp_cm(TP_CM_BAM, tp_size(data.len(), packets), id.pgn))
+            .await?;
+
+        let dt = J1939Id::new(TP_PRIORITY, Pgn::TP_DT, id.source, GLOBAL_ADDRESS);
+        for (i, chunk) in data.chunks(TP_PACKET_SIZE).enumerate() {
+            tokio::time::sleep(self.config.bam_interval).await;
+            self.write(dt, Self::packet(i as u8 + 1, chunk)).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn transmit_rts(&self, id: J1939Id, data: &[u8]) -> CanResult<()> {
+        let packets = tp_packets(data.len());
+        let cm = J1939Id::new(TP_PRIORITY, Pgn::TP_CM, id.source, id.destination);
+        let dt = J1939Id {
+            pgn: Pgn::TP_DT,
+            ..cm
+        };
+        self.write(cm, tp_cm(TP_CM_RTS, tp_size(data.len(), packets), id.pgn))
+            .await?;
+
+        let mut timeout = self.config.t3;
+        loop {
+            let deadline = Some(Instant::now() + timeout);
+            let (_, response) = self
+                .read(deadline, "T3", |r, d| {
+                    r.pgn == Pgn::TP_CM
+                        && r.source == id.destination
+                        && r.destination == id.source
+                        && matches!(d[0], TP_CM_CTS | TP_CM_EOMA | TP_CM_ABORT)
+                })
+                .await?;
+            match response[0] {
+                TP_CM_CTS if response[1] == 0 => timeout = self.config.t4,
+                TP_CM_CTS => {
+                    let (count, next) = (response[1] as usize, response[2] as usize);
+                    if next == 0 || next > packets {
+                        self.abort(cm, ABORT_BAD_SEQUENCE, id.pgn).await?;
+                        return Err(Error::InvalidFrame(format!(
+                            "J1939 invalid CTS packet: {}",
+                            next
+                        )));
+                    }
+                    let last = (next + count - 1).min(packets);
+                    for sn in next..=last {
+                        let offset = (sn - 1) * TP_PACKET_SIZE;
+                        let chunk = &data[offset..(offset + TP_PACKET_SIZE).min(data.len())];
+                        self.write(dt, Self::packet(sn as u8, chunk)).await?;
+                    }
+                    timeout = self.config.t3;
+                }
+                TP_CM_EOMA => return Ok(()),
+                _ => {
+                    return Err(Error::operation_error(format!(
+                        "J1939 transfer is aborted by {:#04X}, reason: {}",
+                        id.destination, response[1]
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Receive the BAM packets, return `None` if the announcement is invalid.
+    async fn receive_bam(&self, id: J1939Id, cm: &[u8]) -> CanResult<Option<J1939Message>> {
+        let Some((size, packets, pgn)) = Self::parse_cm(cm) else {
+            return Ok(None);
+        };
+
+        let mut data = Vec::with_capacity(packets * TP_PACKET_SIZE);
+        for sn in 1..=packets {
+            let deadline = Some(Instant::now() + self.config.t1);
+            let (_, packet) = self
+                .read(deadline, "T1", |r, _| {
+                    r.pgn == Pgn::TP_DT && r.source == id.source && r.destination == GLOBAL_ADDRESS
+                })
+                .await?;
+            if packet[0] as usize != sn {
+                rsutil::warn!(
+                    "RUST-CAN - J1939 BAM wrong sequence number: {}, expected: {}",
+                    packet[0],
+                    sn
+                );
+                return Ok(None);
+            }
+            data.extend_from_slice(&packet[1..]);
+        }
+        data.truncate(size);
+
+        Ok(Some(J1939Message {
+            priority: id.priority,
+            pgn,
+            source: id.source,
+            destination: GLOBAL_ADDRESS,
+            data,
+        }))
+    }
+
+    /// Receive the packets by CTS, return `None` if the request is invalid.
+    async fn receive_rts(&self, id: J1939Id, cm: &[u8]) -> CanResult<Option<J1939Message>> {
+        let Some((size, packets, pgn)) = Self::parse_cm(cm) else {
+            return Ok(None);
+        };
+        let tx = J1939Id::new(TP_PRIORITY, Pgn::TP_CM, id.destination, id.source);
+        let max = cm[4].min(self.config.cts_packets).max(1) as usize;
+
+        let mut data = Vec::with_capacity(packets * TP_PACKET_SIZE);
+        let mut sn = 1;
+        while sn <= packets {
+            let count = max.min(packets - sn + 1);
+            self.write(
+                tx,
+                tp_cm(TP_CM_CTS, [count as u8, sn as u8, PADDING, PADDING], pgn),
+            )
+            .await?;
+            let (mut timer, mut timeout) = ("T2", self.config.t2);
+            for _ in 0..count {
+                let deadline = Some(Instant::now() + timeout);
+                let packet = self
+                    .read(deadline, timer, |r, _| {
+                        r.pgn == Pgn::TP_DT
+                            && r.source == id.source
+                            && r.destination == id.destination
+                    })
+                    .await;
+                let (_, packet) = match packet {
+                    Ok(v) => v,
+                    Err(e) => {
+                        self.abort(tx, ABORT_TIMEOUT, pgn).await?;
+                        return Err(e);
+                    }
+                };
+                if packet[0] as usize != sn {
+                    self.abort(tx, ABORT_BAD_SEQUENCE, pgn).await?;
+                    return Err(Error::InvalidFrame(format!(
+                        "J1939 wrong sequence number: {}, expected: {}",
+                        packet[0], sn
+                    )));
+                }
+                data.extend_from_slice(&packet[1..]);
+                sn += 1;
+                (timer, timeout) = ("T1", self.config.t1);
+            }
+        }
+        data.truncate(size);
+        self.write(tx, tp_cm(TP_CM_EOMA, tp_size(size, packets), pgn))
+            .await?;
+
+        Ok(Some(J1939Message {
+            priority: id.priority,
+            pgn,
+            source: id.source,
+            destination: id.destination,
+            data,
+        }))
+    }
+
+    /// Parse the size, packets and PGN of RTS or BAM.
+    fn parse_cm(cm: &[u8]) -> Option<(usize, usize, Pgn)> {
+        let size = u16::from_le_bytes([cm[1], cm[2]]) as usize;
+        let packets = cm[3] as usize;
+        let pgn = Pgn::from_bytes([cm[5], cm[6], cm[7]]).ok()?;
+        if !(MAX_FRAME_SIZE + 1..=MAX_TP_SIZE).contains(&size) || packets != tp_packets(size) {
+            rsutil::warn!(
+                "RUST-CAN - J1939 invalid TP.CM size: {}, packets: {}",
+                size,
+                packets
+            );
+            return None;
+        }
+
+        Some((size, packets, pgn))
+    }
+
+    #[inline]
+    async fn abort(&self, id: J1939Id, reason: u8, pgn: Pgn) -> CanResult<()> {
+        let params = [reason, PADDING, PADDING, PADDING];
+        self.write(id, tp_cm(TP_CM_ABORT, params, pgn)).await
+    }
+
+    #[inline]
+    fn packet(sn: u8, chunk: &[u8]) -> Vec<u8> {
+        let mut packet = Vec::with_capacity(MAX_FRAME_SIZE);
+        packet.push(sn);
+        packet.extend_from_slice(chunk);
+        packet.resize(MAX_FRAME_SIZE, PADDING);
+        packet
+    }
+
+    async fn write(&self, id: J1939Id, data: Vec<u8>) -> CanResult<()> {
+        let mut frame = D::Frame::new_can(Id::from(id), &data)?;
+        frame.set_channel(self.channel.clone());
+        self.device.transmit(frame, None).await
+    }
+
+    /// Read the first message matched by `expected`, the others are buffered.
+    async fn read(
+        &self,
+        deadline: Option<Instant>,
+        timer: &str,
+        expected: impl Fn(&J1939Id, &[u8]) -> bool,
+    ) -> CanResult<(J1939Id, Vec<u8>)> {
+        loop {
+            {
+                let mut pending = self.pending.lock().unwrap();
+                if let Some(pos) = pending.iter().position(|(id, data)| expected(id, data)) {
+                    return Ok(pending.remove(pos).unwrap());
+                }
+            }
+
+            let timeout = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(Error::TimeoutError(format!(
+                            "{} at channel: {}",
+                            timer, self.channel
+                        )));
+                    }
+                    Some((deadline - now).as_millis().max(1) as u32)
+                }
+                None => None,
+            };
+            let frames = match self.device.receive(self.channel.clone(), timeout).await {
+                Ok(frames) => frames,
+                Err(Error::TimeoutError(_)) => continue,
+                Err(e) => return Err(e),
+            };
+
+            let address = self.address();
+            let mut pending = self.pending.lock().unwrap();
+            for frame in frames {
+                let Id::Extended(id) = frame.id() else {
+                    continue;
+                };
+                let id = J1939Id::from(id);
+                if frame.direction() != Direction::Receive
+                    || frame.format() != FrameFormat::Data
+                    || (id.destination != GLOBAL_ADDRESS && id.destination != address)
+                    || (matches!(id.pgn, Pgn::TP_CM | Pgn::TP_DT)
+                        && frame.data().len() != MAX_FRAME_SIZE)
+                {
+                    continue;
+                }
+                if pending.len() >= J1939_QUEUE_SIZE {
+                    rsutil::warn!("RUST-CAN - J1939 queue is full, the oldest frame is dropped");
+                    pending.pop_front();
+                }
+                pending.push_back((id, frame.data().to_vec()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        device::ChannelConfig,
+        virtual_bus::{
+            tests::{device, TestFrame},
+            VirtualBus,
+        },
+    };
+
+    type Node = J1939Node<VirtualBus<TestFrame>>;
+
+    fn node(channel: &str, identity_number: u32, address: u8) -> Node {
+        let device = Arc::new(device(channel, ChannelConfig::new(250_000)));
+        let name = Name {
+            identity_number,
+            arbitrary_address_capable: true,
+            ..Default::default()
+        };
+        let mut node = J1939Node::new(device, channel.to_owned(), name, address);
+        node.set_config(J1939Config {
+            bam_interval: Duration::from_millis(5),
+            claim_timeout: Duration::from_millis(50),
+            ..Default::default()
+        });
+        node
+    }
+
+    async fn claimed(channel: &str) -> (Node, Node) {
+        let (a, b) = (node(channel, 1, 0x10), node(channel, 2, 0x20));
+        let (ra, rb) = tokio::join!(a.claim_address(), b.claim_address());
+        assert_eq!((ra.unwrap(), rb.unwrap()), (0x10, 0x20));
+        (a, b)
+    }
+
+    #[tokio::test]
+    async fn address_is_arbitrated() {
+        let channel = "j1939-claim";
+        let (a, b) = (node(channel, 1, 0x80), node(channel, 2, 0x80));
+        let (ra, rb) = tokio::join!(a.claim_address(), b.claim_address());
+        assert_eq!(ra.unwrap(), 0x80);
+        assert_eq!(rb.unwrap(), 0x81);
+        assert_eq!(b.network().get(&0x80), Some(&a.name()));
+
+        // the node without arbitrary address capable has higher priority.
+        let mut c = node(channel, 3, 0x80);
+        c.name.arbitrary_address_capable = false;
+        let (rc, _) = tokio::join!(c.claim_address(), a.receive(Some(100)));
+        assert_eq!(rc.unwrap(), 0x80);
+        assert_eq!(a.address(), 0x82);
+
+        // and it cannot claim the lost address.
+        let mut d = node(channel, 4, 0x80);
+        d.name.arbitrary_address_capable = false;
+        let (rd, _) = tokio::join!(d.claim_address(), c.receive(Some(100)));
+        assert!(rd.is_err());
+        assert_eq!(d.address(), NULL_ADDRESS);
+        assert_eq!(c.address(), 0x80);
+    }
+
+    #[tokio::test]
+    async fn messages_are_transferred() {
+        let (a, b) = claimed("j1939-transfer").await;
+        let pgn = Pgn::new(0xFEF1).unwrap();
+
+        a.transmit(pgn, 3, GLOBAL_ADDRESS, &[0x11; 8])
+            .await
+            .unwrap();
+        let message = b.receive(Some(100)).await.unwrap();
+        assert_eq!((message.pgn, message.source), (pgn, 0x10));
+        assert_eq!(message.data, [0x11; 8]);
+
+        // BAM
+        let data = (0..100).map(|v| v as u8).collect::<Vec<_>>();
+        let (sent, received) = tokio::join!(
+            a.transmit(pgn, DEFAULT_PRIORITY, GLOBAL_ADDRESS, &data),
+            b.receive(Some(100))
+        );
+        sent.unwrap();
+        let message = received.unwrap();
+        assert_eq!((message.pgn, message.destination), (pgn, GLOBAL_ADDRESS));
+        assert_eq!(message.priority, TP_PRIORITY);
+        assert_eq!(message.data, data);
+
+        // RTS/CTS
+        let pgn = Pgn::new(0xEF00).unwrap();
+        let data = (0..MAX_TP_SIZE).map(|v| v as u8).collect::<Vec<_>>();
+        let (sent, received) = tokio::join!(
+            a.transmit(pgn, DEFAULT_PRIORITY, 0x20, &data),
+            b.receive(Some(100))
+        );
+        sent.unwrap();
+        let message = received.unwrap();
+        assert_eq!((message.pgn, message.destination), (pgn, 0x20));
+        assert_eq!(message.priority, TP_PRIORITY);
+        assert_eq!(message.data, data);
+
+        assert!(matches!(
+            a.transmit(pgn, DEFAULT_PRIORITY, 0x20, &[0; MAX_TP_SIZE + 1])
+                .await,
+            Err(Error::InvalidDLC(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn requests_are_responded() {
+        let (a, b) = claimed("j1939-request").await;
+        let pgn = Pgn::new(0xFEDA).unwrap();
+        b.set_response(pgn, b"1.0.0*".to_vec());
+
+        // responded by b
+        let (requested, received) = tokio::join!(
+            async {
+                a.request(pgn, 0x20).await?;
+                a.receive(Some(100)).await
+            },
+            b.receive(Some(100))
+        );
+        assert!(received.is_err());
+        let message = requested.unwrap();
+        assert_eq!((message.pgn, message.source), (pgn, 0x20));
+        assert_eq!(message.data, b"1.0.0*");
+
+        // unknown request is received
+        a.request(Pgn::new(0xFEE5).unwrap(), GLOBAL_ADDRESS)
+            .await
+            .unwrap();
+        let message = b.receive(Some(100)).await.unwrap();
+        assert_eq!(message.pgn, Pgn::REQUEST);
+        assert_eq!(message.data, [0xE5, 0xFE, 0x00]);
+
+        // address claimed
+        let (requested, _) = tokio::join!(
+            async {
+                a.request(Pgn::ADDRESS_CLAIMED, GLOBAL_ADDRESS).await?;
+                a.receive(Some(100)).await
+            },
+            b.receive(Some(100))
+        );
+        assert!(requested.is_err());
+        assert_eq!(a.network().get(&0x20), Some(&b.name()));
+
+        // the invalid request is ignored
+        let id = J1939Id::new(DEFAULT_PRIORITY, Pgn::REQUEST, 0x10, 0x20);
+        a.write(id, vec![0x00, 0x00, 0xFF]).await.unwrap();
+        assert!(matches!(
+            b.receive(Some(50)).await,
+            Err(Error::TimeoutError(_))
+        ));
+    }
+}
@@ -1,27 +1,49 @@
+mod any_device;
+pub mod arxml;
+pub mod asc;
+mod bit_timing;
+pub mod blf;
 mod bus;
 pub mod can_utils;
+pub mod candump;
+pub mod canopen;
 mod constants;
+pub mod dbc;
 mod device;
+mod dispatcher;
 mod error;
 mod frame;
+pub mod isotp;
+pub mod j1939;
+mod replay;
+mod scheduler;
+pub mod uds;
+mod virtual_bus;
 
 pub(crate) use can_utils as utils;
 
 pub type CanResult<R> = Result<R, crate::error::Error>;
 
 pub use crate::{
+    any_device::{AnyDevice, AnyFrame, DynDevice},
+    bit_timing::{BitTiming, BitTimingConfig, BitTimingConst, TdcConfig},
     constants::*,
     device::{
         ChannelConfig, ChannelMode, Device as CanDevice, DeviceBuilder, Listener as CanListener,
     },
+    dispatcher::Dispatcher as CanDispatcher,
     error::Error as CanError,
     frame::{
+        generic::GenericFrame,
         identifier::{
             CanFdFlags, CanXlFlags, ExtendedId, Filter as CanFilter, Id as CanId, IdentifierFlags,
             StandardId,
         },
         Direction as CanDirection, Frame as CanFrame, FrameFormat, Kind as CanKind, Timestamp,
-        TimestampSource,
+        TimestampSource, XlParams as CanXlParams,
     },
+    replay::ReplayDevice,
+    scheduler::{CyclicMessage, CyclicOffload, CyclicScheduler, CyclicStats},
+    virtual_bus::VirtualBus,
 };
 pub use bus::*;
@@ -0,0 +1,611 @@
+//! A CAN device which plays back a recorded trace.
+
+use crate::{
+    asc::AscReader,
+    blf::BlfReader,
+    candump::CandumpReader,
+    constants::{REPLAY_FILE, REPLAY_LOOP, REPLAY_SOURCE, REPLAY_SPEED, REPLAY_VALIDATE_TX},
+    device::{ChannelConfig, Device, DeviceBuilder},
+    error::Error,
+    frame::{Direction, Frame, Timestamp, TimestampSource},
+    utils, CanResult,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    hash::Hash,
+    path::Path,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::sync::Notify;
+
+/// The max count of frames buffered by a channel.
+const REPLAY_QUEUE_SIZE: usize = 4096;
+/// The min duration of a round when looping.
+const MIN_ROUND_DURATION: Duration = Duration::from_millis(1);
+
+/// The playback position.
+struct Playback<F: Frame> {
+    cursor: usize,
+    /// The instant of the first frame in current round.
+    base: Option<Instant>,
+    finished: bool,
+    queues: HashMap<F::Channel, VecDeque<F>>,
+    /// The index of next expected Tx frame of each source channel.
+    expected: HashMap<F::Channel, usize>,
+    transmitted: Vec<F>,
+}
+
+impl<F: Frame> Default for Playback<F> {
+    fn default() -> Self {
+        Self {
+            cursor: 0,
+            base: None,
+            finished: false,
+            queues: Default::default(),
+            expected: Default::default(),
+            transmitted: Default::default(),
+        }
+    }
+}
+
+/// A CAN device which plays back a recorded trace, e.g. a `candump -L`, ASC or BLF file.
+///
+/// - The recorded Rx frames are received on the channels whose source channel matches,
+///   at the recorded inter-frame timing divided by the speed factor,
+///   or as fast as possible when the speed factor is `0`.
+///   The frames are stamped with system timestamp when they are due.
+/// - The recorded Tx frames are the expected transmitting. The transmitted frames are recorded,
+///   and validated against the expected frames of the same source channel if enabled.
+/// - The playback restarts at the end of trace when looping,
+///   otherwise [`Device::receive`] waits until timeout.
+///
+/// The device is configured by [`DeviceBuilder`] options [`REPLAY_FILE`], [`REPLAY_SPEED`],
+/// [`REPLAY_LOOP`], [`REPLAY_VALIDATE_TX`] and by channel options [`REPLAY_SOURCE`]
+/// and `recv_own_msg`. The source channel is the channel itself if not set.
+///
+/// # Example
+/// ```ignore
+/// let mut cfg = ChannelConfig::new(500_000);
+/// cfg.add_other(REPLAY_SOURCE, Box::new("can0".to_string()));
+/// let mut builder = DeviceBuilder::new();
+/// builder
+///     .add_config("vcan0".to_string(), cfg)
+///     .add_other(REPLAY_FILE, Box::new("candump.log".to_string()))
+///     .add_other(REPLAY_SPEED, Box::new(2.0_f64));
+/// let device = builder.build::<ReplayDevice<SocketCanFrame>>()?;
+/// ```
+pub struct ReplayDevice<F: Frame> {
+    /// The recorded Rx frames with the offset from the first frame.
+    trace: Vec<(Duration, F)>,
+    /// The recorded Tx frames of each source channel.
+    expected: HashMap<F::Channel, Vec<F>>,
+    /// The source channel of each opened channel.
+    sources: HashMap<F::Channel, F::Channel>,
+    /// The opened channels of each source channel.
+    routes: HashMap<F::Channel, Vec<F::Channel>>,
+    recv_own_msg: HashSet<F::Channel>,
+    /// Wake the waiting receiving of each opened channel when an own message is pushed.
+    notifies: HashMap<F::Channel, Notify>,
+    speed: f64,
+    looping: bool,
+    validate_tx: bool,
+    state: Mutex<Playback<F>>,
+}
+
+impl<F> ReplayDevice<F>
+where
+    F: Frame + Clone,
+    F::Channel: Hash + Eq + Clone,
+{
+    /// Create the device with recorded frames, no channel is opened.
+    pub fn from_frames(frames: impl IntoIterator<Item = F>) -> Self {
+        let mut trace = Vec::new();
+        let mut expected: HashMap<F::Channel, Vec<F>> = HashMap::new();
+        let mut first = None;
+        let mut offset = Duration::ZERO;
+        for frame in frames {
+            if matches!(frame.direction(), Direction::Transmit) {
+                expected.entry(frame.channel()).or_default().push(frame);
+                continue;
+            }
+
+            if let Some(ts) = frame.timestamp() {
+                let first = *first.get_or_insert(ts.nanos);
+                let nanos = ts.nanos.saturating_sub(first);
+                // keep the offsets in order
+                offset = offset.max(Duration::from_nanos(nanos.min(u64::MAX as u128) as u64));
+            }
+            trace.push((offset, frame));
+        }
+
+        Self {
+            trace,
+            expected,
+            sources: Default::default(),
+            routes: Default::default(),
+            recv_own_msg: Default::default(),
+            notifies: Default::default(),
+            speed: 1.,
+            looping: false,
+            validate_tx: false,
+            state: Default::default(),
+        }
+    }
+
+    /// Open the channel which plays the recorded frames of `source` channel.
+    pub fn init_channel(&mut self, channel: F::Channel, cfg: &ChannelConfig) -> CanResult<()>
+    where
+        F::Channel: FromStr,
+    {
+        let source = match cfg.get_other::<String>(REPLAY_SOURCE)? {
+            Some(v) => v
+                .parse()
+                .map_err(|_| Error::InitializeError(format!("invalid replay source: {}", v)))?,
+            None => channel.clone(),
+        };
+        if cfg.recv_own_msg.unwrap_or_default() {
+            self.recv_own_msg.insert(channel.clone());
+        }
+        self.routes
+            .entry(source.clone())
+            .or_default()
+            .push(channel.clone());
+        self.notifies.entry(channel.clone()).or_default();
+        self.sources.insert(channel, source);
+
+        Ok(())
+    }
+
+    /// Set the speed factor, `0` plays as fast as possible.
+    pub fn set_speed(&mut self, speed: f64) -> &mut Self {
+        self.speed = if speed.is_finite() { speed.max(0.) } else { 0. };
+        self
+    }
+
+    pub fn set_looping(&mut self, looping: bool) -> &mut Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn set_validate_tx(&mut self, validate: bool) -> &mut Self {
+        self.validate_tx = validate;
+        self
+    }
+
+    /// Whether all recorded frames are played, always `false` when looping.
+    pub fn is_finished(&self) -> bool {
+        self.state.lock().unwrap().finished
+    }
+
+    /// Restart the playback and the validation of transmitting.
+    pub fn restart(&self) {
+        let mut state = self.state.lock().unwrap();
+        let transmitted = std::mem::take(&mut state.transmitted);
+        *state = Playback {
+            transmitted,
+            ..Default::default()
+        };
+    }
+
+    /// The frames transmitted since created.
+    pub fn transmitted(&self) -> Vec<F> {
+        self.state.lock().unwrap().transmitted.clone()
+    }
+
+    /// Take the frames transmitted since last taken.
+    pub fn take_transmitted(&self) -> Vec<F> {
+        std::mem::take(&mut self.state.lock().unwrap().transmitted)
+    }
+
+    #[inline]
+    fn is_asap(&self) -> bool {
+        self.speed == 0.
+    }
+
+    /// The duration of a round, the next round starts an average frame interval after the last frame.
+    fn round_duration(&self) -> Duration {
+        let last = self.trace.last().map(|(v, _)| *v).unwrap_or_default();
+        let interval = last / self.trace.len().saturating_sub(1).max(1) as u32;
+        (last + interval).max(MIN_ROUND_DURATION)
+    }
+
+    /// Move the due frames into the queues.
+    ///
+    /// Return the instant of next frame, `None` if no frame should be waited.
+    fn advance(&self, state: &mut Playback<F>, channel: &F::Channel) -> Option<Instant> {
+        let now = Instant::now();
+        if self.trace.is_empty() {
+            state.finished = true;
+        }
+
+        if self.is_asap() {
+            // at most a round to avoid spinning when the channel has no frame.
+            let mut budget = self.trace.len();
+            while budget > 0
+                && !state.finished
+                && state.queues.get(channel).map_or(true, |q| q.is_empty())
+            {
+                self.dispatch(state, now, now);
+                budget -= 1;
+            }
+            return None;
+        }
+
+        while !state.finished {
+            let base = *state.base.get_or_insert(now);
+            let due = base + self.trace[state.cursor].0.div_f64(self.speed);
+            if due > now {
+                return Some(due);
+            }
+            self.dispatch(state, due, now);
+        }
+
+        None
+    }
+
+    /// Move the frame at cursor into the queues of routed channels.
+    fn dispatch(&self, state: &mut Playback<F>, due: Instant, now: Instant) {
+        let frame = &self.trace[state.cursor].1;
+        let timestamp = utils::system_timestamp();
+        let timestamp = Timestamp {
+            nanos: timestamp.nanos.saturating_sub((now - due).as_nanos()),
+            source: TimestampSource::System,
+        };
+
+        for channel in self.routes.get(&frame.channel()).into_iter().flatten() {
+            let mut frame = frame.clone();
+            frame
+                .set_channel(channel.clone())
+                .set_direction(Direction::Receive)
+                .set_timestamp(Some(timestamp));
+            Self::push(state, channel, frame);
+        }
+
+        state.cursor += 1;
+        if state.cursor >= self.trace.len() {
+            if self.looping {
+                state.cursor = 0;
+                if !self.is_asap() {
+                    state.base = state
+                        .base
+                        .map(|v| v + self.round_duration().div_f64(self.speed));
+                }
+            } else {
+                state.finished = true;
+            }
+        }
+    }
+
+    fn push(state: &mut Playback<F>, channel: &F::Channel, frame: F) {
+        let queue = state.queues.entry(channel.clone()).or_default();
+        if queue.len() >= REPLAY_QUEUE_SIZE {
+            rsutil::warn!("RUST-CAN - replay queue is full, the oldest frame is dropped");
+            queue.pop_front();
+        }
+        queue.push_back(frame);
+    }
+
+    /// Validate the frame against the next expected frame of source channel.
+    fn validate(&self, state: &mut Playback<F>, source: &F::Channel, frame: &F) -> CanResult<()> {
+        let expected = self
+            .expected
+            .get(source)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let index = state.expected.entry(source.clone()).or_default();
+        let next = match self.looping && !expected.is_empty() {
+            true => expected.get(*index % expected.len()),
+            false => expected.get(*index),
+        };
+
+        match next {
+            Some(v)
+                if v.id() == frame.id()
+                    && v.kind() == frame.kind()
+                    && v.format() == frame.format()
+                    && v.len() == frame.len()
+                    && (frame.is_remote() || v.data() == frame.data()) =>
+            {
+                *index += 1;
+                Ok(())
+            }
+            Some(v) => Err(Error::OperationError(format!(
+                "unexpected frame: {:?} at channel: {}, expected: {:?}",
+                frame.id(),
+                frame.channel(),
+                v.id()
+            ))),
+            None => Err(Error::OperationError(format!(
+                "unexpected frame: {:?} at channel: {}, no more frame is expected",
+                frame.id(),
+                frame.channel()
+            ))),
+        }
+    }
+}
+
+impl<F> ReplayDevice<F>
+where
+    F: Frame + Clone,
+    F::Channel: Hash + Eq + Clone + FromStr,
+{
+    /// Read the trace file, the format is detected by extension:
+    /// `.asc`, `.blf`, otherwise `candump -L`.
+    pub fn open<P: AsRef<Path>>(path: P) -> CanResult<Self> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .map(|v| v.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        let frames = match extension.as_str() {
+            "asc" => AscReader::<_, F>::open(path)?.collect::<CanResult<Vec<_>>>(),
+            "blf" => BlfReader::<_, F>::open(path)?.collect::<CanResult<Vec<_>>>(),
+            _ => CandumpReader::<_, F>::open(path)?.collect::<CanResult<Vec<_>>>(),
+        }?;
+
+        Ok(Self::from_frames(frames))
+    }
+}
+
+#[async_trait::async_trait]
+impl<F> Device for ReplayDevice<F>
+where
+    F: Frame + Clone + 'static,
+    F::Channel: Hash + Eq + Clone + Default + FromStr + Send + Sync + 'static,
+{
+    type Channel = F::Channel;
+    type Frame = F;
+
+    fn new(builder: DeviceBuilder<Self::Channel>) -> CanResult<Self> {
+        let path = builder
+            .get_other::<String>(REPLAY_FILE)?
+            .ok_or(Error::InitializeError(format!(
+                "`{}` is not set",
+                REPLAY_FILE
+            )))?;
+        let mut device = Self::open(path)?;
+        device
+            .set_speed(builder.get_other::<f64>(REPLAY_SPEED)?.unwrap_or(1.))
+            .set_looping(builder.get_other::<bool>(REPLAY_LOOP)?.unwrap_or_default())
+            .set_validate_tx(
+                builder
+                    .get_other::<bool>(REPLAY_VALIDATE_TX)?
+                    .unwrap_or_default(),
+            );
+        builder
+            .channel_configs()
+            .iter()
+            .try_for_each(|(chl, cfg)| device.init_channel(chl.clone(), cfg))?;
+
+        Ok(device)
+    }
+
+    #[inline]
+    fn opened_channels(&self) -> Vec<Self::Channel> {
+        self.sources.keys().cloned().collect()
+    }
+
+    async fn transmit(&self, msg: Self::Frame, _: Option<u32>) -> CanResult<()> {
+        let channel = msg.channel();
+        let source = self
+            .sources
+            .get(&channel)
+            .ok_or(Error::channel_not_opened(&channel))?;
+
+        let mut state = self.state.lock().unwrap();
+        if self.validate_tx {
+            self.validate(&mut state, source, &msg)?;
+        }
+
+        let mut msg = msg;
+        msg.set_direction(Direction::Transmit)
+            .set_timestamp(Some(utils::system_timestamp()));
+        if self.recv_own_msg.contains(&channel) {
+            Self::push(&mut state, &channel, msg.clone());
+            self.notifies[&channel].notify_one();
+        }
+        state.transmitted.push(msg);
+
+        Ok(())
+    }
+
+    async fn receive(
+        &self,
+        channel: Self::Channel,
+        timeout: Option<u32>,
+    ) -> CanResult<Vec<Self::Frame>> {
+        if !self.sources.contains_key(&channel) {
+            return Err(Error::channel_not_opened(channel));
+        }
+
+        let notify = &self.notifies[&channel];
+        let deadline = timeout.map(|v| Instant::now() + Duration::from_millis(v as u64));
+        loop {
+            let due = {
+                let mut state = self.state.lock().unwrap();
+                let due = self.advance(&mut state, &channel);
+                if let Some(queue) = state.queues.get_mut(&channel) {
+                    if !queue.is_empty() {
+                        return Ok(queue.drain(..).collect());
+                    }
+                }
+                due
+            };
+
+            if deadline.is_some_and(|v| Instant::now() >= v) {
+                return Err(Error::channel_timeout(channel));
+            }
+            let wake = match (due, deadline) {
+                (Some(due), Some(deadline)) => Some(due.min(deadline)),
+                (Some(v), None) | (None, Some(v)) => Some(v),
+                (None, None) => None,
+            };
+            match wake {
+                Some(v) => {
+                    let _ = tokio::time::timeout_at(v.into(), notify.notified()).await;
+                }
+                None => notify.notified().await,
+            }
+        }
+    }
+
+    fn shutdown(&mut self) {
+        self.sources.clear();
+        self.routes.clear();
+        self.recv_own_msg.clear();
+        self.notifies.clear();
+        self.state.lock().unwrap().queues.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        frame::identifier::{Id, StandardId},
+        virtual_bus::tests::TestFrame,
+    };
+
+    fn frame(channel: &str, id: u16, offset_ms: u128, direction: Direction) -> TestFrame {
+        let mut frame =
+            TestFrame::new_can(Id::Standard(StandardId::new(id).unwrap()), &[id as u8]).unwrap();
+        frame
+            .set_channel(channel.into())
+            .set_direction(direction)
+            .set_timestamp(Some(Timestamp {
+                nanos: 1_000_000_000 + offset_ms * 1_000_000,
+                source: TimestampSource::System,
+            }));
+        frame
+    }
+
+    fn device(frames: Vec<TestFrame>, channels: &[(&str, &str)]) -> ReplayDevice<TestFrame> {
+        let mut device = ReplayDevice::from_frames(frames);
+        for (channel, source) in channels {
+            let mut cfg = ChannelConfig::new(500_000);
+            cfg.add_other(REPLAY_SOURCE, Box::new(source.to_string()));
+            device.init_channel(channel.to_string(), &cfg).unwrap();
+        }
+        device
+    }
+
+    #[tokio::test]
+    async fn frames_are_played_at_recorded_timing() {
+        let frames = (0..3)
+            .map(|i| frame("can0", 0x100 + i, i as u128 * 40, Direction::Receive))
+            .collect();
+        let mut device = device(frames, &[("can0", "can0")]);
+        device.set_speed(2.);
+
+        let start = Instant::now();
+        let mut received = Vec::new();
+        while received.len() < 3 {
+            received.extend(device.receive("can0".into(), Some(1000)).await.unwrap());
+        }
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(40), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(500), "{:?}", elapsed);
+        assert_eq!(received[2].id().as_raw(), 0x102);
+        assert!(device.is_finished());
+        assert!(matches!(
+            device.receive("can0".into(), Some(10)).await,
+            Err(Error::TimeoutError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn channels_are_remapped_and_looped() {
+        let frames = vec![
+            frame("can0", 0x100, 0, Direction::Receive),
+            frame("can1", 0x200, 10_000, Direction::Receive),
+            frame("can0", 0x101, 20_000, Direction::Receive),
+        ];
+        let mut device = device(frames, &[("replay", "can0")]);
+        device.set_speed(0.).set_looping(true);
+
+        let mut ids = Vec::new();
+        while ids.len() < 4 {
+            for frame in device.receive("replay".into(), Some(100)).await.unwrap() {
+                assert_eq!(frame.channel(), "replay");
+                assert_eq!(frame.direction(), Direction::Receive);
+                ids.push(frame.id().as_raw());
+            }
+        }
+        assert_eq!(ids, [0x100, 0x101, 0x100, 0x101]);
+        assert!(!device.is_finished());
+        assert!(device.receive("can1".into(), Some(10)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn transmitted_frames_are_validated() {
+        let frames = vec![
+            frame("can0", 0x7E0, 0, Direction::Transmit),
+            frame("can0", 0x7E8, 1, Direction::Receive),
+            frame("can0", 0x7E1, 2, Direction::Transmit),
+        ];
+        let mut device = device(frames, &[("can0", "can0")]);
+        device.set_validate_tx(true);
+
+        let tx = |id| frame("can0", id, 0, Direction::Transmit);
+        device.transmit(tx(0x7E0), None).await.unwrap();
+        assert!(device.transmit(tx(0x7E0), None).await.is_err());
+        device.transmit(tx(0x7E1), None).await.unwrap();
+        assert!(device.transmit(tx(0x7E2), None).await.is_err());
+        assert_eq!(device.take_transmitted().len(), 2);
+        assert!(device.transmitted().is_empty());
+
+        // the recorded Tx frames are not received
+        let received = device.receive("can0".into(), Some(100)).await.unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].id().as_raw(), 0x7E8);
+    }
+
+    #[tokio::test]
+    async fn own_message_wakes_receiving() {
+        let frames = vec![frame("can0", 0x100, 0, Direction::Receive)];
+        let mut device = ReplayDevice::from_frames(frames);
+        let mut cfg = ChannelConfig::new(500_000);
+        cfg.set_recv_own_msg(true);
+        device.init_channel("can0".into(), &cfg).unwrap();
+        device.set_speed(0.);
+        assert_eq!(device.receive("can0".into(), None).await.unwrap().len(), 1);
+
+        let start = Instant::now();
+        let (_, received) = tokio::join!(
+            async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                let tx = frame("can0", 0x7E0, 0, Direction::Transmit);
+                device.transmit(tx, None).await.unwrap();
+            },
+            device.receive("can0".into(), Some(1000))
+        );
+        assert_eq!(received.unwrap()[0].id().as_raw(), 0x7E0);
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn device_is_built_from_trace_file() {
+        let path = std::env::temp_dir().join(format!("rs-can-replay-{}.log", std::process::id()));
+        std::fs::write(
+            &path,
+            "(1697371234.000000) can0 123#01\n(1697371234.001000) can0 124#02\n",
+        )
+        .unwrap();
+
+        let mut builder = DeviceBuilder::new();
+        builder
+            .add_config("can0".to_string(), ChannelConfig::new(500_000))
+            .add_other(REPLAY_FILE, Box::new(path.to_string_lossy().to_string()))
+            .add_other(REPLAY_SPEED, Box::new(0_f64));
+        let device = builder.build::<ReplayDevice<TestFrame>>();
+        std::fs::remove_file(&path).unwrap();
+
+        let device = device.unwrap();
+        assert_eq!(device.opened_channels(), ["can0"]);
+        let frames = device.receive("can0".into(), Some(100)).await.unwrap();
+        assert_eq!(frames[0].data(), &[0x01]);
+    }
+}
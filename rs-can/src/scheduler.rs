@@ -0,0 +1,666 @@
+use crate::{
+    device::Device,
+    error::Error,
+    frame::{identifier::CanFdFlags, Frame, Kind},
+    CanResult,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{task::JoinHandle, time::Instant};
+
+/// Transmit the frames periodically by the device itself, such as the auto-send of ZLG devices.
+///
+/// The slots are allocated per channel by [`CyclicScheduler`], from 0.
+pub trait CyclicOffload: Device {
+    /// Start(or replace) transmitting `msg` every `period` at the slot `index` of its channel.
+    fn start_cyclic(&self, index: u16, msg: &Self::Frame, period: Duration) -> CanResult<()>;
+    /// Stop the transmission at the slot `index` of `channel`.
+    fn stop_cyclic(&self, channel: Self::Channel, index: u16) -> CanResult<()>;
+}
+
+/// A message transmitted periodically by [`CyclicScheduler`].
+#[derive(Debug, Clone)]
+pub struct CyclicMessage<F> {
+    pub frame: F,
+    pub period: Duration,
+    /// The delay of the first transmission, used to spread the messages of the same period.
+    pub offset: Duration,
+    /// The count of frames transmitted in every period.
+    pub burst: u32,
+    /// The count of periods, `None` means forever.
+    pub count: Option<u32>,
+}
+
+impl<F> CyclicMessage<F> {
+    pub fn new(frame: F, period: Duration) -> Self {
+        Self {
+            frame,
+            period,
+            offset: Default::default(),
+            burst: 1,
+            count: None,
+        }
+    }
+
+    pub fn set_offset(&mut self, offset: Duration) -> &mut Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn set_burst(&mut self, burst: u32) -> &mut Self {
+        self.burst = burst;
+        self
+    }
+
+    pub fn set_count(&mut self, count: u32) -> &mut Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Whether the device could transmit it, the offset, burst and count are not offloaded.
+    #[inline]
+    fn offloadable(&self) -> bool {
+        self.offset.is_zero() && self.burst == 1 && self.count.is_none()
+    }
+}
+
+/// The statistics of a cyclic message.
+///
+/// The jitter is the delay between the scheduled and the actual time of every period,
+/// it's not measured when the message is offloaded to device.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CyclicStats {
+    /// Whether the message is transmitted by device.
+    pub offloaded: bool,
+    pub periods: u64,
+    pub sent: u64,
+    pub errors: u64,
+    pub min_jitter: Duration,
+    pub max_jitter: Duration,
+    pub total_jitter: Duration,
+}
+
+impl CyclicStats {
+    #[inline]
+    pub fn mean_jitter(&self) -> Duration {
+        match self.periods {
+            0 => Duration::ZERO,
+            n => Duration::from_nanos((self.total_jitter.as_nanos() / n as u128) as u64),
+        }
+    }
+
+    fn record(&mut self, jitter: Duration) {
+        if self.periods == 0 || jitter < self.min_jitter {
+            self.min_jitter = jitter;
+        }
+        self.max_jitter = self.max_jitter.max(jitter);
+        self.total_jitter += jitter;
+        self.periods += 1;
+    }
+}
+
+struct Shared<F> {
+    message: Mutex<CyclicMessage<F>>,
+    stats: Mutex<CyclicStats>,
+}
+
+enum State {
+    Stopped,
+    Software(JoinHandle<()>),
+    Offloaded(u16),
+}
+
+struct Entry<F> {
+    shared: Arc<Shared<F>>,
+    state: State,
+}
+
+type StartFn<D> = fn(&D, u16, &<D as Device>::Frame, Duration) -> CanResult<()>;
+type StopFn<D> = fn(&D, <D as Device>::Channel, u16) -> CanResult<()>;
+
+/// Transmit a set of messages at their own periods.
+///
+/// The messages are driven by tokio timers, and offloaded to the device when the scheduler
+/// is created by [`CyclicScheduler::with_offload`] and the device accepts it. A message is
+/// identified by the id returned from [`CyclicScheduler::add`].
+pub struct CyclicScheduler<D: Device> {
+    device: Arc<D>,
+    offload: Option<(StartFn<D>, StopFn<D>)>,
+    entries: Mutex<HashMap<usize, Entry<D::Frame>>>,
+    next_id: Mutex<usize>,
+}
+
+impl<D> CyclicScheduler<D>
+where
+    D: Device + 'static,
+    D::Channel: Clone + Send + Sync,
+    D::Frame: Clone + 'static,
+{
+    pub fn new(device: Arc<D>) -> Self {
+        Self {
+            device,
+            offload: None,
+            entries: Default::default(),
+            next_id: Default::default(),
+        }
+    }
+
+    /// Create the scheduler which tries to offload the messages to device first.
+    pub fn with_offload(device: Arc<D>) -> Self
+    where
+        D: CyclicOffload,
+    {
+        let mut scheduler = Self::new(device);
+        scheduler.offload = Some((D::start_cyclic, D::stop_cyclic));
+        scheduler
+    }
+
+    #[inline]
+    pub fn device(&self) -> &Arc<D> {
+        &self.device
+    }
+
+    /// Add the message and start it, return the id of message.
+    pub fn add(&self, message: CyclicMessage<D::Frame>) -> CanResult<usize> {
+        if message.period.is_zero() || message.burst == 0 {
+            return Err(Error::other_error(format!(
+                "invalid cyclic message, period: {:?}, burst: {}",
+                message.period, message.burst
+            )));
+        }
+
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            *next_id
+        };
+        let mut entries = self.entries.lock().unwrap();
+        let mut entry = Entry {
+            shared: Arc::new(Shared {
+                message: Mutex::new(message),
+                stats: Default::default(),
+            }),
+            state: State::Stopped,
+        };
+        entry.state = self.launch(&entries, &entry.shared);
+        entries.insert(id, entry);
+
+        Ok(id)
+    }
+
+    /// Replace the frame of message, it's transmitted from the next period.
+    pub fn update(&self, id: usize, frame: D::Frame) -> CanResult<()> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(&id).ok_or_else(|| Self::not_found(id))?;
+        let mut message = entry.shared.message.lock().unwrap();
+        if let (State::Offloaded(index), Some((start, _))) = (&entry.state, &self.offload) {
+            start(&self.device, *index, &frame, message.period)?;
+        }
+        message.frame = frame;
+
+        Ok(())
+    }
+
+    /// Replace the payload of message, the id and flags of frame are kept.
+    pub fn update_data(&self, id: usize, data: &[u8]) -> CanResult<()> {
+        let frame = {
+            let entries = self.entries.lock().unwrap();
+            let entry = entries.get(&id).ok_or_else(|| Self::not_found(id))?;
+            let message = entry.shared.message.lock().unwrap();
+            let old = &message.frame;
+            let mut frame = match old.kind() {
+                Kind::Classical => D::Frame::new_can(old.id(), data)?,
+                Kind::FD => {
+                    let mut flags = CanFdFlags::empty();
+                    flags.set(CanFdFlags::BRS, old.is_bitrate_switch());
+                    flags.set(CanFdFlags::ESI, old.is_esi());
+                    D::Frame::new_can_fd(old.id(), data, flags)?
+                }
+                Kind::XL => return Err(Error::NotSupportedError),
+            };
+            frame.set_channel(old.channel());
+            frame
+        };
+
+        self.update(id, frame)
+    }
+
+    /// Start the stopped message, the offset is counted from now.
+    pub fn start(&self, id: usize) -> CanResult<()> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get(&id).ok_or_else(|| Self::not_found(id))?;
+        if !matches!(entry.state, State::Stopped) {
+            return Ok(());
+        }
+
+        let state = self.launch(&entries, &entry.shared.clone());
+        if let Some(entry) = entries.get_mut(&id) {
+            entry.state = state;
+        }
+
+        Ok(())
+    }
+
+    /// Stop the message, it could be started again by [`CyclicScheduler::start`].
+    pub fn stop(&self, id: usize) -> CanResult<()> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get_mut(&id).ok_or_else(|| Self::not_found(id))?;
+        self.halt(entry);
+
+        Ok(())
+    }
+
+    /// Stop and remove the message.
+    pub fn remove(&self, id: usize) -> CanResult<()> {
+        let mut entry = self
+            .entries
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .ok_or_else(|| Self::not_found(id))?;
+        self.halt(&mut entry);
+
+        Ok(())
+    }
+
+    /// Stop and remove all messages.
+    pub fn clear(&self) {
+        let entries = std::mem::take(&mut *self.entries.lock().unwrap());
+        for (_, mut entry) in entries {
+            self.halt(&mut entry);
+        }
+    }
+
+    pub fn ids(&self) -> Vec<usize> {
+        let mut ids = self
+            .entries
+            .lock()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Whether the message is transmitting by software or device.
+    pub fn is_running(&self, id: usize) -> bool {
+        match self.entries.lock().unwrap().get(&id).map(|e| &e.state) {
+            Some(State::Software(task)) => !task.is_finished(),
+            Some(State::Offloaded(_)) => true,
+            _ => false,
+        }
+    }
+
+    pub fn stats(&self, id: usize) -> Option<CyclicStats> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map(|e| *e.shared.stats.lock().unwrap())
+    }
+
+    #[inline]
+    fn not_found(id: usize) -> Error {
+        Error::other_error(format!("cyclic message: {} is not found", id))
+    }
+
+    /// Offload the message to device when possible, otherwise spawn the transmit loop.
+    fn launch(
+        &self,
+        entries: &HashMap<usize, Entry<D::Frame>>,
+        shared: &Arc<Shared<D::Frame>>,
+    ) -> State {
+        let message = shared.message.lock().unwrap().clone();
+        if let (Some((start, _)), true) = (&self.offload, message.offloadable()) {
+            let channel = message.frame.channel();
+            let index = (0..=u16::MAX).find(|i| {
+                !entries.values().any(|e| {
+                    matches!(e.state, State::Offloaded(v) if v == *i)
+                        && e.shared.message.lock().unwrap().frame.channel() == channel
+                })
+            });
+            if let Some(index) = index {
+                match start(&self.device, index, &message.frame, message.period) {
+                    Ok(()) => {
+                        rsutil::trace!(
+                            "RUST-CAN - cyclic message: {:?} is offloaded to slot: {} of channel: {}",
+                            message.frame.id(),
+                            index,
+                            channel
+                        );
+                        shared.stats.lock().unwrap().offloaded = true;
+                        return State::Offloaded(index);
+                    }
+                    Err(e) => rsutil::debug!(
+                        "RUST-CAN - {} when offload cyclic message: {:?}, transmit by software",
+                        e,
+                        message.frame.id()
+                    ),
+                }
+            }
+        }
+
+        shared.stats.lock().unwrap().offloaded = false;
+        State::Software(tokio::spawn(cyclic_loop(
+            self.device.clone(),
+            shared.clone(),
+            Instant::now() + message.offset,
+        )))
+    }
+
+    fn halt(&self, entry: &mut Entry<D::Frame>) {
+        match std::mem::replace(&mut entry.state, State::Stopped) {
+            State::Software(task) => task.abort(),
+            State::Offloaded(index) => {
+                if let Some((_, stop)) = &self.offload {
+                    let channel = entry.shared.message.lock().unwrap().frame.channel();
+                    if let Err(e) = stop(&self.device, channel.clone(), index) {
+                        rsutil::warn!(
+                            "RUST-CAN - {} when stop slot: {} of channel: {}",
+                            e,
+                            index,
+                            channel
+                        );
+                    }
+                }
+            }
+            State::Stopped => {}
+        }
+    }
+}
+
+impl<D: Device> Drop for CyclicScheduler<D> {
+    fn drop(&mut self) {
+        let Ok(entries) = self.entries.get_mut() else {
+            return;
+        };
+        for entry in entries.values() {
+            match &entry.state {
+                State::Software(task) => task.abort(),
+                State::Offloaded(index) => {
+                    if let (Some((_, stop)), Ok(message)) =
+                        (&self.offload, entry.shared.message.lock())
+                    {
+                        let _ = stop(&self.device, message.frame.channel(), *index);
+                    }
+                }
+                State::Stopped => {}
+            }
+        }
+    }
+}
+
+async fn cyclic_loop<D: Device>(device: Arc<D>, shared: Arc<Shared<D::Frame>>, start: Instant)
+where
+    D::Frame: Clone,
+{
+    let mut next = start;
+    let mut periods = 0;
+    loop {
+        tokio::time::sleep_until(next).await;
+        let now = Instant::now();
+        let (frame, period, burst, count) = {
+            let message = shared.message.lock().unwrap();
+            (
+                message.frame.clone(),
+                message.period,
+                message.burst,
+                message.count,
+            )
+        };
+
+        let (mut sent, mut errors) = (0, 0);
+        for _ in 0..burst {
+            match device.transmit(frame.clone(), None).await {
+                Ok(()) => sent += 1,
+                Err(e) => {
+                    errors += 1;
+                    rsutil::warn!(
+                        "RUST-CAN - {} when transmit cyclic message: {:?}",
+                        e,
+                        frame.id()
+                    );
+                }
+            }
+        }
+        {
+            let mut stats = shared.stats.lock().unwrap();
+            stats.sent += sent;
+            stats.errors += errors;
+            stats.record(now - next);
+        }
+
+        periods += 1;
+        if count.is_some_and(|count| periods >= count) {
+            break;
+        }
+        // skip the periods missed, the phase is kept.
+        next += period;
+        while next <= now {
+            next += period;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        device::{ChannelConfig, DeviceBuilder},
+        frame::identifier::{Id, StandardId},
+        virtual_bus::{tests::TestFrame, VirtualBus},
+    };
+
+    fn device(channel: &str) -> Arc<VirtualBus<TestFrame>> {
+        Arc::new(crate::virtual_bus::tests::device(
+            channel,
+            ChannelConfig::new(500_000),
+        ))
+    }
+
+    fn frame(channel: &str, id: u16, data: &[u8]) -> TestFrame {
+        let mut frame =
+            TestFrame::new_can(Id::Standard(StandardId::new(id).unwrap()), data).unwrap();
+        frame.set_channel(channel.to_owned());
+        frame
+    }
+
+    #[test]
+    fn mean_jitter_of_many_periods() {
+        let stats = CyclicStats {
+            periods: u32::MAX as u64 + 1,
+            total_jitter: Duration::from_secs(1 << 32),
+            ..Default::default()
+        };
+        assert_eq!(stats.mean_jitter(), Duration::from_secs(1));
+        assert_eq!(CyclicStats::default().mean_jitter(), Duration::ZERO);
+    }
+
+    async fn drain(device: &VirtualBus<TestFrame>, channel: &str) -> Vec<TestFrame> {
+        let mut frames = Vec::new();
+        while let Ok(mut v) = device.receive(channel.to_owned(), Some(10)).await {
+            frames.append(&mut v);
+        }
+        frames
+    }
+
+    /// Record the slots instead of transmitting.
+    struct OffloadBus {
+        inner: VirtualBus<TestFrame>,
+        slots: Mutex<HashMap<(String, u16), (TestFrame, Duration)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Device for OffloadBus {
+        type Channel = String;
+        type Frame = TestFrame;
+
+        fn new(builder: DeviceBuilder<String>) -> CanResult<Self> {
+            Ok(Self {
+                inner: Device::new(builder)?,
+                slots: Default::default(),
+            })
+        }
+
+        fn opened_channels(&self) -> Vec<Self::Channel> {
+            self.inner.opened_channels()
+        }
+
+        async fn transmit(&self, msg: Self::Frame, timeout: Option<u32>) -> CanResult<()> {
+            self.inner.transmit(msg, timeout).await
+        }
+
+        async fn receive(
+            &self,
+            channel: Self::Channel,
+            timeout: Option<u32>,
+        ) -> CanResult<Vec<Self::Frame>> {
+            self.inner.receive(channel, timeout).await
+        }
+
+        fn shutdown(&mut self) {
+            self.inner.shutdown()
+        }
+    }
+
+    impl CyclicOffload for OffloadBus {
+        fn start_cyclic(&self, index: u16, msg: &TestFrame, period: Duration) -> CanResult<()> {
+            let mut slots = self.slots.lock().unwrap();
+            // only 2 slots of device.
+            if index > 1 {
+                return Err(Error::NotSupportedError);
+            }
+            slots.insert((msg.channel(), index), (msg.clone(), period));
+            Ok(())
+        }
+
+        fn stop_cyclic(&self, channel: String, index: u16) -> CanResult<()> {
+            self.slots.lock().unwrap().remove(&(channel, index));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn messages_are_scheduled() {
+        let channel = "cyclic-software";
+        let (sender, receiver) = (device(channel), device(channel));
+        let scheduler = CyclicScheduler::new(sender);
+
+        let fast = scheduler
+            .add(CyclicMessage::new(
+                frame(channel, 0x100, &[1]),
+                Duration::from_millis(20),
+            ))
+            .unwrap();
+        let mut message =
+            CyclicMessage::new(frame(channel, 0x200, &[2]), Duration::from_millis(40));
+        message
+            .set_offset(Duration::from_millis(10))
+            .set_burst(2)
+            .set_count(3);
+        let burst = scheduler.add(message).unwrap();
+        assert_eq!(scheduler.ids(), [fast, burst]);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let frames = drain(&receiver, channel).await;
+        let count = |id: u32| frames.iter().filter(|f| f.id().as_raw() == id).count();
+        assert!((8..=12).contains(&count(0x100)), "{}", count(0x100));
+        assert_eq!(count(0x200), 6);
+
+        let stats = scheduler.stats(burst).unwrap();
+        assert!(!stats.offloaded);
+        assert_eq!((stats.periods, stats.sent, stats.errors), (3, 6, 0));
+        assert!(stats.min_jitter <= stats.mean_jitter() && stats.mean_jitter() <= stats.max_jitter);
+        assert!(!scheduler.is_running(burst));
+        assert!(scheduler.is_running(fast));
+
+        scheduler.update_data(fast, &[3, 4]).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let frames = drain(&receiver, channel).await;
+        assert_eq!(frames.last().unwrap().data(), [3, 4]);
+
+        scheduler.stop(fast).unwrap();
+        assert!(!scheduler.is_running(fast));
+        drain(&receiver, channel).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(drain(&receiver, channel).await.is_empty());
+
+        scheduler.start(fast).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!drain(&receiver, channel).await.is_empty());
+
+        scheduler.remove(fast).unwrap();
+        assert!(scheduler.stats(fast).is_none());
+        assert!(scheduler.remove(fast).is_err());
+        assert!(scheduler
+            .add(CyclicMessage::new(
+                frame(channel, 0x300, &[]),
+                Duration::ZERO
+            ))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn messages_are_offloaded() {
+        let channel = "cyclic-offload";
+        let mut builder = DeviceBuilder::new();
+        builder.add_config(channel.to_owned(), ChannelConfig::new(500_000));
+        let device = Arc::new(builder.build::<OffloadBus>().unwrap());
+        let receiver = self::device(channel);
+        let scheduler = CyclicScheduler::with_offload(device.clone());
+        let period = Duration::from_millis(10);
+        let slot = |index: u16| {
+            device
+                .slots
+                .lock()
+                .unwrap()
+                .get(&(channel.to_owned(), index))
+                .map(|(frame, period)| (frame.id().as_raw(), frame.data().to_vec(), *period))
+        };
+
+        let first = scheduler
+            .add(CyclicMessage::new(frame(channel, 0x100, &[1]), period))
+            .unwrap();
+        let second = scheduler
+            .add(CyclicMessage::new(frame(channel, 0x101, &[2]), period))
+            .unwrap();
+        assert!(scheduler.stats(first).unwrap().offloaded);
+        assert_eq!(slot(0), Some((0x100, vec![1], period)));
+        assert_eq!(slot(1), Some((0x101, vec![2], period)));
+        // the slots of device are full, and the count is not offloaded.
+        let third = scheduler
+            .add(CyclicMessage::new(frame(channel, 0x102, &[3]), period))
+            .unwrap();
+        let mut message = CyclicMessage::new(frame(channel, 0x103, &[4]), period);
+        message.set_count(2);
+        let fourth = scheduler.add(message).unwrap();
+        assert!(!scheduler.stats(third).unwrap().offloaded);
+        assert!(!scheduler.stats(fourth).unwrap().offloaded);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let frames = drain(&receiver, channel).await;
+        assert!(frames
+            .iter()
+            .all(|f| [0x102, 0x103].contains(&f.id().as_raw())));
+        assert_eq!(scheduler.stats(fourth).unwrap().sent, 2);
+
+        scheduler.update_data(second, &[5, 6]).unwrap();
+        assert_eq!(slot(1), Some((0x101, vec![5, 6], period)));
+
+        scheduler.remove(first).unwrap();
+        assert_eq!(slot(0), None);
+        scheduler.stop(second).unwrap();
+        assert_eq!(slot(1), None);
+        assert!(!scheduler.is_running(second));
+        scheduler.start(third).unwrap();
+        scheduler.start(second).unwrap();
+        assert_eq!(slot(0), Some((0x101, vec![5, 6], period)));
+
+        drop(scheduler);
+        assert!(device.slots.lock().unwrap().is_empty());
+    }
+}
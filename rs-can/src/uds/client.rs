@@ -0,0 +1,634 @@
+use super::{
+    be_uint, Dtc, MemoryLocation, Nrc, Service, NEGATIVE_RESPONSE, POSITIVE_OFFSET,
+    SUPPRESS_POSITIVE,
+};
+use crate::{
+    device::Device,
+    error::Error,
+    isotp::{AddressType, IsoTpChannel},
+    CanResult,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+/// The default P2 of server.
+const P2_SERVER: Duration = Duration::from_millis(50);
+/// The default P2* of server.
+const P2_STAR_SERVER: Duration = Duration::from_millis(5000);
+
+/// Calculate the key from the security level and seed.
+pub type SecurityAlgorithm = Box<dyn Fn(u8, &[u8]) -> CanResult<Vec<u8>> + Send + Sync>;
+
+/// The timing parameters of client.
+#[derive(Debug, Clone, Copy)]
+pub struct UdsConfig {
+    /// The timeout of waiting response.
+    pub p2: Duration,
+    /// The timeout of waiting response after `requestCorrectlyReceived-ResponsePending`.
+    pub p2_star: Duration,
+    /// The margin added to the server timing, which is reported by `DiagnosticSessionControl`.
+    pub delta_p2: Duration,
+}
+
+impl Default for UdsConfig {
+    fn default() -> Self {
+        let delta_p2 = Duration::from_millis(100);
+        Self {
+            p2: P2_SERVER + delta_p2,
+            p2_star: P2_STAR_SERVER + delta_p2,
+            delta_p2,
+        }
+    }
+}
+
+/// The server timing reported by `DiagnosticSessionControl`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionTiming {
+    pub p2: Duration,
+    pub p2_star: Duration,
+}
+
+#[inline]
+fn invalid_response(response: &[u8]) -> Error {
+    Error::InvalidFrame(format!("invalid UDS response: {:02X?}", response))
+}
+
+struct Inner<D: Device> {
+    channel: IsoTpChannel<D>,
+    config: Mutex<UdsConfig>,
+    /// Only one request is in progress at a time.
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl<D> Inner<D>
+where
+    D: Device,
+    D::Channel: Clone,
+{
+    async fn request(&self, data: &[u8], address_type: AddressType) -> CanResult<Option<Vec<u8>>> {
+        let sid = *data.first().ok_or(Error::InvalidDLC(0))?;
+        let suppress = Service::try_from(sid).is_ok_and(|s| s.has_sub_function())
+            && data.get(1).is_some_and(|v| v & SUPPRESS_POSITIVE != 0);
+
+        let _guard = self.lock.lock().await;
+        self.channel.transmit_to(data, address_type).await?;
+        if suppress {
+            return Ok(None);
+        }
+
+        let config = *self.config.lock().unwrap();
+        let mut timeout = config.p2;
+        loop {
+            let response = self
+                .channel
+                .receive(Some(timeout.as_millis() as u32))
+                .await?;
+            match *response.as_slice() {
+                [NEGATIVE_RESPONSE, service, code, ..] if service == sid => {
+                    if Nrc(code) != Nrc::RESPONSE_PENDING {
+                        return Err(Error::NegativeResponse { service, code });
+                    }
+                    rsutil::trace!("RUST-CAN - UDS service: {:#04X} response pending", sid);
+                    timeout = config.p2_star;
+                }
+                [service, ..] if service == sid.wrapping_add(POSITIVE_OFFSET) => {
+                    return Ok(Some(response))
+                }
+                _ => rsutil::warn!("RUST-CAN - UDS unexpected response: {:02X?}", response),
+            }
+        }
+    }
+}
+
+/// The UDS client(tester) over an ISO-TP channel.
+///
+/// The negative responses are returned as [`Error::NegativeResponse`], and
+/// `requestCorrectlyReceived-ResponsePending` extends the timeout from P2 to P2*.
+///
+/// # Example
+/// ```ignore
+/// let mut client = UdsClient::new(IsoTpChannel::new(device, "can0".into(), address));
+/// client.set_security_algorithm(|_, seed| Ok(seed.iter().map(|v| !v).collect()));
+/// client.session_control(0x03).await?;
+/// client.start_tester_present(Duration::from_secs(2), AddressType::Functional);
+/// client.security_access(0x01).await?;
+/// let vin = client.read_data_by_identifier(0xF190).await?;
+/// ```
+pub struct UdsClient<D: Device> {
+    inner: Arc<Inner<D>>,
+    security: Option<SecurityAlgorithm>,
+    tester_present: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl<D> UdsClient<D>
+where
+    D: Device + 'static,
+    D::Channel: Clone + Send + Sync,
+{
+    pub fn new(channel: IsoTpChannel<D>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                channel,
+                config: Default::default(),
+                lock: Default::default(),
+            }),
+            security: None,
+            tester_present: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn channel(&self) -> &IsoTpChannel<D> {
+        &self.inner.channel
+    }
+
+    #[inline]
+    pub fn config(&self) -> UdsConfig {
+        *self.inner.config.lock().unwrap()
+    }
+
+    pub fn set_config(&self, config: UdsConfig) -> &Self {
+        *self.inner.config.lock().unwrap() = config;
+        self
+    }
+
+    /// Set the algorithm of [`UdsClient::security_access`].
+    pub fn set_security_algorithm(
+        &mut self,
+        algorithm: impl Fn(u8, &[u8]) -> CanResult<Vec<u8>> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.security = Some(Box::new(algorithm));
+        self
+    }
+
+    /// Send the request by physical addressing and return the positive response.
+    pub async fn request(&self, data: &[u8]) -> CanResult<Vec<u8>> {
+        self.inner
+            .request(data, AddressType::Physical)
+            .await?
+            .ok_or(Error::operation_error(
+                "UDS positive response is suppressed",
+            ))
+    }
+
+    /// Send the request and return the positive response, `None` if it's suppressed.
+    #[inline]
+    pub async fn request_to(
+        &self,
+        data: &[u8],
+        address_type: AddressType,
+    ) -> CanResult<Option<Vec<u8>>> {
+        self.inner.request(data, address_type).await
+    }
+
+    /// `DiagnosticSessionControl`, the P2/P2* are updated by the server timing.
+    pub async fn session_control(&self, session: u8) -> CanResult<SessionTiming> {
+        let response = self
+            .request(&[Service::SessionControl as u8, session])
+            .await?;
+        let timing = match response.get(2..6) {
+            Some(&[p2_hi, p2_lo, star_hi, star_lo]) => SessionTiming {
+                p2: Duration::from_millis(u16::from_be_bytes([p2_hi, p2_lo]) as u64),
+                p2_star: Duration::from_millis(u16::from_be_bytes([star_hi, star_lo]) as u64 * 10),
+            },
+            _ => SessionTiming {
+                p2: P2_SERVER,
+                p2_star: P2_STAR_SERVER,
+            },
+        };
+
+        let mut config = self.inner.config.lock().unwrap();
+        config.p2 = timing.p2 + config.delta_p2;
+        config.p2_star = timing.p2_star + config.delta_p2;
+
+        Ok(timing)
+    }
+
+    /// `ECUReset`, return the power down time of `enableRapidPowerShutDown`.
+    pub async fn ecu_reset(&self, reset_type: u8) -> CanResult<Option<u8>> {
+        let response = self.request(&[Service::EcuReset as u8, reset_type]).await?;
+        Ok(response.get(2).copied())
+    }
+
+    /// `SecurityAccess` requestSeed, `level` is the odd sub-function.
+    pub async fn request_seed(&self, level: u8) -> CanResult<Vec<u8>> {
+        let response = self
+            .request(&[Service::SecurityAccess as u8, level])
+            .await?;
+        match response.get(2..) {
+            Some(seed) => Ok(seed.to_vec()),
+            None => Err(invalid_response(&response)),
+        }
+    }
+
+    /// `SecurityAccess` sendKey, `level` is the even sub-function.
+    pub async fn send_key(&self, level: u8, key: &[u8]) -> CanResult<()> {
+        let mut request = vec![Service::SecurityAccess as u8, level];
+        request.extend_from_slice(key);
+        self.request(&request).await.map(|_| ())
+    }
+
+    /// Unlock the security `level` by the algorithm, the zero seed means it's already unlocked.
+    ///
+    /// The `level` must be an odd requestSeed sub-function below `0x7F`.
+    pub async fn security_access(&self, level: u8) -> CanResult<()> {
+        if level % 2 == 0 || level >= 0x7F {
+            return Err(Error::other_error(format!(
+                "invalid UDS security level: {:#04X}",
+                level
+            )));
+        }
+        let algorithm = self
+            .security
+            .as_ref()
+            .ok_or(Error::operation_error("UDS security algorithm is not set"))?;
+        let seed = self.request_seed(level).await?;
+        if seed.iter().all(|v| *v == 0) {
+            return Ok(());
+        }
+
+        let key = algorithm(level, &seed)?;
+        self.send_key(level + 1, &key).await
+    }
+
+    /// `TesterPresent` with positive response suppressed.
+    #[inline]
+    pub async fn tester_present(&self, address_type: AddressType) -> CanResult<()> {
+        self.request_to(
+            &[Service::TesterPresent as u8, SUPPRESS_POSITIVE],
+            address_type,
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Send `TesterPresent` every `interval` in background until stopped.
+    pub fn start_tester_present(&self, interval: Duration, address_type: AddressType) {
+        let inner = Arc::downgrade(&self.inner);
+        let task = tokio::spawn(async move {
+            let request = [Service::TesterPresent as u8, SUPPRESS_POSITIVE];
+            loop {
+                tokio::time::sleep(interval).await;
+                let Some(inner) = inner.upgrade() else {
+                    break;
+                };
+                if let Err(e) = inner.request(&request, address_type).await {
+                    rsutil::warn!("RUST-CAN - UDS tester present error: {}", e);
+                }
+            }
+        });
+
+        if let Some(task) = self.tester_present.lock().unwrap().replace(task) {
+            task.abort();
+        }
+    }
+
+    pub fn stop_tester_present(&self) {
+        if let Some(task) = self.tester_present.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+
+    /// `ReadDataByIdentifier` of one identifier.
+    pub async fn read_data_by_identifier(&self, did: u16) -> CanResult<Vec<u8>> {
+        let [hi, lo] = did.to_be_bytes();
+        let response = self
+            .request(&[Service::ReadDataByIdentifier as u8, hi, lo])
+            .await?;
+        match response.get(1..3) {
+            Some(v) if v == [hi, lo] => Ok(response[3..].to_vec()),
+            _ => Err(invalid_response(&response)),
+        }
+    }
+
+    /// `WriteDataByIdentifier`
+    pub async fn write_data_by_identifier(&self, did: u16, data: &[u8]) -> CanResult<()> {
+        let mut request = vec![Service::WriteDataByIdentifier as u8];
+        request.extend(did.to_be_bytes());
+        request.extend_from_slice(data);
+        self.request(&request).await.map(|_| ())
+    }
+
+    /// `RoutineControl`, return the routine status record.
+    ///
+    /// The `control_type` is `0x01` to start, `0x02` to stop, `0x03` to request results.
+    pub async fn routine_control(
+        &self,
+        control_type: u8,
+        routine_id: u16,
+        option: &[u8],
+    ) -> CanResult<Vec<u8>> {
+        let mut request = vec![Service::RoutineControl as u8, control_type];
+        request.extend(routine_id.to_be_bytes());
+        request.extend_from_slice(option);
+        let response = self.request(&request).await?;
+        match response.get(4..) {
+            Some(v) => Ok(v.to_vec()),
+            None => Err(invalid_response(&response)),
+        }
+    }
+
+    /// `RequestDownload`, return the `maxNumberOfBlockLength`.
+    pub async fn request_download(
+        &self,
+        data_format: u8,
+        location: &MemoryLocation,
+    ) -> CanResult<usize> {
+        let mut request = vec![Service::RequestDownload as u8, data_format];
+        request.extend(location.encode()?);
+        let response = self.request(&request).await?;
+        let length = response
+            .get(1)
+            .map(|v| (v >> 4) as usize)
+            .unwrap_or_default();
+        match response.get(2..2 + length) {
+            Some(v) if (1..=8).contains(&length) => Ok(be_uint(v) as usize),
+            _ => Err(invalid_response(&response)),
+        }
+    }
+
+    /// `TransferData`, return the `transferResponseParameterRecord`.
+    pub async fn transfer_data(&self, sequence: u8, data: &[u8]) -> CanResult<Vec<u8>> {
+        let mut request = vec![Service::TransferData as u8, sequence];
+        request.extend_from_slice(data);
+        let response = self.request(&request).await?;
+        match response.get(1) {
+            Some(v) if *v == sequence => Ok(response[2..].to_vec()),
+            _ => Err(invalid_response(&response)),
+        }
+    }
+
+    /// `RequestTransferExit`, return the `transferResponseParameterRecord`.
+    pub async fn request_transfer_exit(&self, data: &[u8]) -> CanResult<Vec<u8>> {
+        let mut request = vec![Service::RequestTransferExit as u8];
+        request.extend_from_slice(data);
+        let response = self.request(&request).await?;
+        Ok(response[1..].to_vec())
+    }
+
+    /// Download the data by `RequestDownload`, `TransferData` and `RequestTransferExit`.
+    pub async fn download(
+        &self,
+        data_format: u8,
+        location: &MemoryLocation,
+        data: &[u8],
+    ) -> CanResult<()> {
+        let max_length = self.request_download(data_format, location).await?;
+        // the block length includes the service id and block sequence counter.
+        if max_length <= 2 {
+            return Err(Error::InvalidDLC(max_length));
+        }
+
+        for (i, block) in data.chunks(max_length - 2).enumerate() {
+            self.transfer_data((i + 1) as u8, block).await?;
+        }
+        self.request_transfer_exit(&[]).await.map(|_| ())
+    }
+
+    /// `ReadDTCInformation`, return the response after sub-function.
+    pub async fn read_dtc_information(
+        &self,
+        sub_function: u8,
+        record: &[u8],
+    ) -> CanResult<Vec<u8>> {
+        let mut request = vec![Service::ReadDtcInfo as u8, sub_function];
+        request.extend_from_slice(record);
+        let response = self.request(&request).await?;
+        match response.get(1) {
+            Some(v) if *v == sub_function => Ok(response[2..].to_vec()),
+            _ => Err(invalid_response(&response)),
+        }
+    }
+
+    /// `reportDTCByStatusMask`, return the `DTCStatusAvailabilityMask` and DTCs.
+    pub async fn read_dtc_by_status_mask(&self, mask: u8) -> CanResult<(u8, Vec<Dtc>)> {
+        let response = self.read_dtc_information(0x02, &[mask]).await?;
+        let (&availability, records) = response
+            .split_first()
+            .ok_or_else(|| invalid_response(&response))?;
+        if records.len() % 4 != 0 {
+            return Err(invalid_response(&response));
+        }
+
+        let dtcs = records
+            .chunks(4)
+            .map(|v| Dtc {
+                code: be_uint(&v[..3]) as u32,
+                status: v[3],
+            })
+            .collect();
+        Ok((availability, dtcs))
+    }
+
+    /// `ClearDiagnosticInformation` of the 3 bytes DTC group, `0xFFFFFF` means all groups.
+    pub async fn clear_dtc(&self, group: u32) -> CanResult<()> {
+        let [_, hi, mid, lo] = group.to_be_bytes();
+        self.request(&[Service::ClearDiagnosticInfo as u8, hi, mid, lo])
+            .await
+            .map(|_| ())
+    }
+}
+
+impl<D: Device> Drop for UdsClient<D> {
+    fn drop(&mut self) {
+        if let Some(task) = self.tester_present.get_mut().unwrap().take() {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isotp::tests::{isotp, Bus};
+
+    /// Create the client and an ECU which responds by `handler`, the responses
+    /// are sent with an interval of 80ms.
+    fn client(
+        channel: &str,
+        handler: impl Fn(&[u8]) -> Vec<Vec<u8>> + Send + 'static,
+    ) -> (UdsClient<Bus>, JoinHandle<()>) {
+        let ecu = isotp(channel, 0x7E8, 0x7E0, false);
+        let client = UdsClient::new(isotp(channel, 0x7E0, 0x7E8, false));
+        let task = tokio::spawn(async move {
+            loop {
+                let Ok(request) = ecu.receive(None).await else {
+                    continue;
+                };
+                for (i, response) in handler(&request).into_iter().enumerate() {
+                    if i > 0 {
+                        tokio::time::sleep(Duration::from_millis(80)).await;
+                    }
+                    ecu.transmit(&response).await.unwrap();
+                }
+            }
+        });
+
+        (client, task)
+    }
+
+    #[tokio::test]
+    async fn services_are_requested() {
+        let (client, ecu) = client("uds-services", |request| match request {
+            [0x10, 0x03] => vec![vec![0x50, 0x03, 0x00, 0x32, 0x01, 0xF4]],
+            [0x11, 0x01] => vec![vec![0x51, 0x01]],
+            [0x22, 0xF1, 0x90] => vec![[&[0x62, 0xF1, 0x90][..], &[b'W'; 17]].concat()],
+            [0x22, ..] => vec![vec![0x7F, 0x22, 0x31]],
+            [0x2E, 0xF1, 0x90, ..] => vec![vec![0x6E, 0xF1, 0x90]],
+            [0x31, 0x01, 0xFF, 0x00, ..] => vec![vec![0x71, 0x01, 0xFF, 0x00, 0x00]],
+            [0x19, 0x02, 0xFF] => vec![vec![
+                0x59, 0x02, 0xFF, 0x12, 0x34, 0x56, 0x09, 0xC0, 0x01, 0x00, 0x08,
+            ]],
+            [0x14, 0xFF, 0xFF, 0xFF] => vec![vec![0x54]],
+            _ => vec![vec![0x7F, request[0], 0x11]],
+        });
+
+        let timing = client.session_control(0x03).await.unwrap();
+        assert_eq!(timing.p2, Duration::from_millis(50));
+        assert_eq!(timing.p2_star, Duration::from_millis(5000));
+        assert_eq!(client.config().p2, Duration::from_millis(150));
+        assert_eq!(client.ecu_reset(0x01).await.unwrap(), None);
+
+        let vin = client.read_data_by_identifier(0xF190).await.unwrap();
+        assert_eq!(vin, [b'W'; 17]);
+        assert!(matches!(
+            client.read_data_by_identifier(0xF191).await,
+            Err(Error::NegativeResponse {
+                service: 0x22,
+                code: 0x31
+            })
+        ));
+        client
+            .write_data_by_identifier(0xF190, &[b'W'; 17])
+            .await
+            .unwrap();
+        let status = client.routine_control(0x01, 0xFF00, &[0x01]).await.unwrap();
+        assert_eq!(status, [0x00]);
+
+        let (availability, dtcs) = client.read_dtc_by_status_mask(0xFF).await.unwrap();
+        assert_eq!(availability, 0xFF);
+        assert_eq!(
+            dtcs,
+            [
+                Dtc {
+                    code: 0x123456,
+                    status: 0x09
+                },
+                Dtc {
+                    code: 0xC00100,
+                    status: 0x08
+                }
+            ]
+        );
+        client.clear_dtc(0xFFFFFF).await.unwrap();
+        assert!(client.request(&[0x85, 0x02]).await.is_err());
+
+        ecu.abort();
+    }
+
+    #[tokio::test]
+    async fn response_pending_extends_timeout() {
+        let (client, ecu) = client("uds-pending", |request| match request {
+            [0x31, 0x01, 0xFF, 0x00] => vec![
+                vec![0x7F, 0x31, 0x78],
+                vec![0x7F, 0x31, 0x78],
+                vec![0x71, 0x01, 0xFF, 0x00, 0x00],
+            ],
+            _ => vec![vec![0x7F, 0x31, 0x78]],
+        });
+        client.set_config(UdsConfig {
+            p2: Duration::from_millis(50),
+            p2_star: Duration::from_millis(200),
+            ..Default::default()
+        });
+
+        let status = client.routine_control(0x01, 0xFF00, &[]).await.unwrap();
+        assert_eq!(status, [0x00]);
+        // the server doesn't respond after pending
+        let ret = client.routine_control(0x02, 0xFF00, &[]).await;
+        assert!(matches!(ret, Err(Error::TimeoutError(_))));
+
+        ecu.abort();
+    }
+
+    #[tokio::test]
+    async fn security_is_unlocked() {
+        let (mut client, ecu) = client("uds-security", |request| match request {
+            [0x27, 0x01] => vec![vec![0x67, 0x01, 0x12, 0x34]],
+            [0x27, 0x02, 0xED, 0xCB] => vec![vec![0x67, 0x02]],
+            [0x27, 0x02, ..] => vec![vec![0x7F, 0x27, 0x35]],
+            [0x27, 0x03] => vec![vec![0x67, 0x03, 0x00, 0x00]],
+            _ => vec![vec![0x7F, request[0], 0x11]],
+        });
+
+        assert!(client.security_access(0x01).await.is_err());
+        client.set_security_algorithm(|_, seed| Ok(seed.iter().map(|v| !v).collect()));
+        client.security_access(0x01).await.unwrap();
+        client.security_access(0x03).await.unwrap();
+
+        client.set_security_algorithm(|_, seed| Ok(seed.to_vec()));
+        assert!(matches!(
+            client.security_access(0x01).await,
+            Err(Error::NegativeResponse { code: 0x35, .. })
+        ));
+        for level in [0x02, 0x7F, 0xFF] {
+            assert!(matches!(
+                client.security_access(level).await,
+                Err(Error::OtherError(_))
+            ));
+        }
+
+        ecu.abort();
+    }
+
+    #[tokio::test]
+    async fn data_is_downloaded() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let (client, ecu) = client("uds-download", {
+            let received = received.clone();
+            move |request| match request {
+                [0x34, 0x00, 0x44, rest @ ..] => {
+                    assert_eq!(rest, [0x08, 0, 0, 0, 0, 0, 0, 100]);
+                    vec![vec![0x74, 0x20, 0x00, 0x12]]
+                }
+                [0x36, sequence, data @ ..] => {
+                    received.lock().unwrap().extend_from_slice(data);
+                    vec![vec![0x76, *sequence]]
+                }
+                [0x37] => vec![vec![0x77]],
+                _ => vec![vec![0x7F, request[0], 0x11]],
+            }
+        });
+
+        let data = (0..100).collect::<Vec<u8>>();
+        client
+            .download(0x00, &MemoryLocation::new(0x0800_0000, 100), &data)
+            .await
+            .unwrap();
+        assert_eq!(*received.lock().unwrap(), data);
+
+        ecu.abort();
+    }
+
+    #[tokio::test]
+    async fn tester_present_is_sent() {
+        let ecu = isotp("uds-tester", 0x7E8, 0x7E0, false);
+        let client = UdsClient::new(isotp("uds-tester", 0x7E0, 0x7E8, false));
+
+        client.start_tester_present(Duration::from_millis(20), AddressType::Functional);
+        for _ in 0..3 {
+            let (address_type, request) = ecu.receive_from(Some(100)).await.unwrap();
+            assert_eq!(address_type, AddressType::Functional);
+            assert_eq!(request, [0x3E, 0x80]);
+        }
+
+        client.stop_tester_present();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        ecu.clear();
+        while ecu.receive(Some(10)).await.is_ok() {}
+        assert!(ecu.receive(Some(60)).await.is_err());
+    }
+}
@@ -0,0 +1,240 @@
+//! UDS(ISO 14229) over the ISO-TP transport layer.
+
+mod client;
+mod server;
+
+pub use client::*;
+pub use server::*;
+
+use crate::{error::Error, CanResult};
+use std::fmt::{Display, Formatter};
+
+/// The service identifier of negative response.
+pub const NEGATIVE_RESPONSE: u8 = 0x7F;
+/// The offset of positive response service identifier.
+pub const POSITIVE_OFFSET: u8 = 0x40;
+/// The bit of sub-function which suppresses the positive response.
+pub const SUPPRESS_POSITIVE: u8 = 0x80;
+
+/// The diagnostic services.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Service {
+    SessionControl = 0x10,
+    EcuReset = 0x11,
+    ClearDiagnosticInfo = 0x14,
+    ReadDtcInfo = 0x19,
+    ReadDataByIdentifier = 0x22,
+    SecurityAccess = 0x27,
+    WriteDataByIdentifier = 0x2E,
+    RoutineControl = 0x31,
+    RequestDownload = 0x34,
+    TransferData = 0x36,
+    RequestTransferExit = 0x37,
+    TesterPresent = 0x3E,
+}
+
+impl Service {
+    /// Whether the request of service has sub-function.
+    pub fn has_sub_function(&self) -> bool {
+        matches!(
+            self,
+            Self::SessionControl
+                | Self::EcuReset
+                | Self::ReadDtcInfo
+                | Self::SecurityAccess
+                | Self::RoutineControl
+                | Self::TesterPresent
+        )
+    }
+}
+
+impl TryFrom<u8> for Service {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x10 => Ok(Self::SessionControl),
+            0x11 => Ok(Self::EcuReset),
+            0x14 => Ok(Self::ClearDiagnosticInfo),
+            0x19 => Ok(Self::ReadDtcInfo),
+            0x22 => Ok(Self::ReadDataByIdentifier),
+            0x27 => Ok(Self::SecurityAccess),
+            0x2E => Ok(Self::WriteDataByIdentifier),
+            0x31 => Ok(Self::RoutineControl),
+            0x34 => Ok(Self::RequestDownload),
+            0x36 => Ok(Self::TransferData),
+            0x37 => Ok(Self::RequestTransferExit),
+            0x3E => Ok(Self::TesterPresent),
+            v => Err(v),
+        }
+    }
+}
+
+/// The negative response code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Nrc(pub u8);
+
+impl Nrc {
+    pub const GENERAL_REJECT: Self = Self(0x10);
+    pub const SERVICE_NOT_SUPPORTED: Self = Self(0x11);
+    pub const SUB_FUNCTION_NOT_SUPPORTED: Self = Self(0x12);
+    pub const INCORRECT_MESSAGE_LENGTH: Self = Self(0x13);
+    pub const RESPONSE_TOO_LONG: Self = Self(0x14);
+    pub const BUSY_REPEAT_REQUEST: Self = Self(0x21);
+    pub const CONDITIONS_NOT_CORRECT: Self = Self(0x22);
+    pub const REQUEST_SEQUENCE_ERROR: Self = Self(0x24);
+    pub const REQUEST_OUT_OF_RANGE: Self = Self(0x31);
+    pub const SECURITY_ACCESS_DENIED: Self = Self(0x33);
+    pub const INVALID_KEY: Self = Self(0x35);
+    pub const EXCEEDED_NUMBER_OF_ATTEMPTS: Self = Self(0x36);
+    pub const REQUIRED_TIME_DELAY_NOT_EXPIRED: Self = Self(0x37);
+    pub const UPLOAD_DOWNLOAD_NOT_ACCEPTED: Self = Self(0x70);
+    pub const TRANSFER_DATA_SUSPENDED: Self = Self(0x71);
+    pub const GENERAL_PROGRAMMING_FAILURE: Self = Self(0x72);
+    pub const WRONG_BLOCK_SEQUENCE_COUNTER: Self = Self(0x73);
+    pub const RESPONSE_PENDING: Self = Self(0x78);
+    pub const SUB_FUNCTION_NOT_SUPPORTED_IN_ACTIVE_SESSION: Self = Self(0x7E);
+    pub const SERVICE_NOT_SUPPORTED_IN_ACTIVE_SESSION: Self = Self(0x7F);
+}
+
+impl Display for Nrc {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match *self {
+            Self::GENERAL_REJECT => "generalReject",
+            Self::SERVICE_NOT_SUPPORTED => "serviceNotSupported",
+            Self::SUB_FUNCTION_NOT_SUPPORTED => "subFunctionNotSupported",
+            Self::INCORRECT_MESSAGE_LENGTH => "incorrectMessageLengthOrInvalidFormat",
+            Self::RESPONSE_TOO_LONG => "responseTooLong",
+            Self::BUSY_REPEAT_REQUEST => "busyRepeatRequest",
+            Self::CONDITIONS_NOT_CORRECT => "conditionsNotCorrect",
+            Self::REQUEST_SEQUENCE_ERROR => "requestSequenceError",
+            Self::REQUEST_OUT_OF_RANGE => "requestOutOfRange",
+            Self::SECURITY_ACCESS_DENIED => "securityAccessDenied",
+            Self::INVALID_KEY => "invalidKey",
+            Self::EXCEEDED_NUMBER_OF_ATTEMPTS => "exceededNumberOfAttempts",
+            Self::REQUIRED_TIME_DELAY_NOT_EXPIRED => "requiredTimeDelayNotExpired",
+            Self::UPLOAD_DOWNLOAD_NOT_ACCEPTED => "uploadDownloadNotAccepted",
+            Self::TRANSFER_DATA_SUSPENDED => "transferDataSuspended",
+            Self::GENERAL_PROGRAMMING_FAILURE => "generalProgrammingFailure",
+            Self::WRONG_BLOCK_SEQUENCE_COUNTER => "wrongBlockSequenceCounter",
+            Self::RESPONSE_PENDING => "requestCorrectlyReceived-ResponsePending",
+            Self::SUB_FUNCTION_NOT_SUPPORTED_IN_ACTIVE_SESSION => {
+                "subFunctionNotSupportedInActiveSession"
+            }
+            Self::SERVICE_NOT_SUPPORTED_IN_ACTIVE_SESSION => "serviceNotSupportedInActiveSession",
+            _ => return write!(f, "NRC({:#04X})", self.0),
+        };
+        f.write_str(name)
+    }
+}
+
+/// The diagnostic trouble code with its status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dtc {
+    /// The 3 bytes DTC number.
+    pub code: u32,
+    pub status: u8,
+}
+
+/// The memory address and size of download, the lengths are the bytes count on the bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLocation {
+    pub address: u64,
+    pub size: u64,
+    pub address_len: u8,
+    pub size_len: u8,
+}
+
+impl MemoryLocation {
+    /// The location with 4 bytes address and size.
+    pub fn new(address: u32, size: u32) -> Self {
+        Self {
+            address: address as u64,
+            size: size as u64,
+            address_len: 4,
+            size_len: 4,
+        }
+    }
+
+    /// Encode as `addressAndLengthFormatIdentifier`, `memoryAddress` and `memorySize`.
+    ///
+    /// The lengths must be in `1..=8` and fit the address and size.
+    pub fn encode(&self) -> CanResult<Vec<u8>> {
+        fn check(name: &str, value: u64, len: u8) -> CanResult<usize> {
+            match len {
+                1..=8 if len == 8 || value >> (len * 8) == 0 => Ok(len as usize),
+                _ => Err(Error::other_error(format!(
+                    "memory {}: {:#X} doesn't fit in {} bytes",
+                    name, value, len
+                ))),
+            }
+        }
+
+        let address_len = check("address", self.address, self.address_len)?;
+        let size_len = check("size", self.size, self.size_len)?;
+        let mut buf = Vec::with_capacity(1 + address_len + size_len);
+        buf.push((self.size_len << 4) | self.address_len);
+        buf.extend_from_slice(&self.address.to_be_bytes()[8 - address_len..]);
+        buf.extend_from_slice(&self.size.to_be_bytes()[8 - size_len..]);
+        Ok(buf)
+    }
+
+    /// Decode from `addressAndLengthFormatIdentifier`, `memoryAddress` and `memorySize`.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let format = *data.first()?;
+        let (address_len, size_len) = (format & 0x0F, format >> 4);
+        if !(1..=8).contains(&address_len) || !(1..=8).contains(&size_len) {
+            return None;
+        }
+        let (address, size) = data[1..].split_at_checked(address_len as usize)?;
+        if size.len() != size_len as usize {
+            return None;
+        }
+
+        Some(Self {
+            address: be_uint(address),
+            size: be_uint(size),
+            address_len,
+            size_len,
+        })
+    }
+}
+
+/// Decode the big-endian unsigned integer with at most 8 bytes.
+#[inline]
+pub(crate) fn be_uint(data: &[u8]) -> u64 {
+    data.iter().fold(0, |acc, v| (acc << 8) | *v as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_location_round_trip() {
+        let location = MemoryLocation {
+            address: 0x0800_0000,
+            size: 0x1234,
+            address_len: 4,
+            size_len: 2,
+        };
+        let data = location.encode().unwrap();
+        assert_eq!(data, [0x24, 0x08, 0x00, 0x00, 0x00, 0x12, 0x34]);
+        assert_eq!(MemoryLocation::decode(&data), Some(location));
+        assert_eq!(MemoryLocation::decode(&data[..6]), None);
+        for (address_len, size_len) in [(0, 2), (9, 2), (4, 0), (4, 9), (3, 2)] {
+            let location = MemoryLocation {
+                address_len,
+                size_len,
+                ..location
+            };
+            assert!(location.encode().is_err());
+        }
+        assert_eq!(
+            Nrc(0x78).to_string(),
+            "requestCorrectlyReceived-ResponsePending"
+        );
+        assert_eq!(Nrc(0x99).to_string(), "NRC(0x99)");
+    }
+}
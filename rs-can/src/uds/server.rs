@@ -0,0 +1,621 @@
+use super::{
+    Nrc, SecurityAlgorithm, Service, SessionTiming, NEGATIVE_RESPONSE, POSITIVE_OFFSET,
+    SUPPRESS_POSITIVE,
+};
+use crate::{
+    device::Device,
+    error::Error,
+    isotp::{AddressType, IsoTpChannel},
+    CanResult,
+};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// The default diagnostic session.
+pub const DEFAULT_SESSION: u8 = 0x01;
+/// The max count of invalid keys before `exceededNumberOfAttempts`.
+const MAX_ATTEMPTS: u8 = 3;
+
+/// Handle the request with the sub-function's suppress bit cleared,
+/// return the positive response without service identifier.
+pub type ServiceHandler =
+    Box<dyn Fn(&mut ServerState, &[u8]) -> Result<Vec<u8>, Nrc> + Send + Sync>;
+/// Generate the seed of the security level.
+pub type SeedGenerator = Box<dyn Fn(u8) -> Vec<u8> + Send + Sync>;
+/// Check the precondition of service.
+pub type ServiceCondition = Box<dyn Fn(&ServerState) -> bool + Send + Sync>;
+
+/// The session and security state of server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerState {
+    pub session: u8,
+    /// The unlocked security level(the odd sub-function of requestSeed), `0` means locked.
+    pub security_level: u8,
+    /// The requested seed which is waiting for key.
+    seed: Option<(u8, Vec<u8>)>,
+    attempts: u8,
+}
+
+impl Default for ServerState {
+    fn default() -> Self {
+        Self {
+            session: DEFAULT_SESSION,
+            security_level: 0,
+            seed: None,
+            attempts: 0,
+        }
+    }
+}
+
+impl ServerState {
+    /// Back to the default session and lock the security.
+    pub fn reset(&mut self) {
+        *self = Default::default();
+    }
+}
+
+/// The conditions of a service, the NRCs are generated when the conditions are not met.
+#[derive(Default)]
+pub struct ServiceOptions {
+    handler: Option<ServiceHandler>,
+    sessions: Vec<u8>,
+    security_level: Option<u8>,
+    min_length: usize,
+    condition: Option<ServiceCondition>,
+}
+
+impl ServiceOptions {
+    /// The sessions which support the service, all sessions by default.
+    ///
+    /// `serviceNotSupportedInActiveSession` is responded in the others.
+    pub fn set_sessions(&mut self, sessions: &[u8]) -> &mut Self {
+        self.sessions = sessions.to_vec();
+        self
+    }
+
+    /// The security level required, `securityAccessDenied` is responded when locked.
+    pub fn set_security_level(&mut self, level: u8) -> &mut Self {
+        self.security_level = Some(level);
+        self
+    }
+
+    /// The min length of request, `incorrectMessageLengthOrInvalidFormat` is responded
+    /// for the shorter request.
+    pub fn set_min_length(&mut self, length: usize) -> &mut Self {
+        self.min_length = length;
+        self
+    }
+
+    /// The precondition, `conditionsNotCorrect` is responded when it returns false.
+    pub fn set_condition(
+        &mut self,
+        condition: impl Fn(&ServerState) -> bool + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.condition = Some(Box::new(condition));
+        self
+    }
+
+    fn check(&self, state: &ServerState, request: &[u8]) -> Result<(), Nrc> {
+        if request.len() < self.min_length {
+            return Err(Nrc::INCORRECT_MESSAGE_LENGTH);
+        }
+        if !self.sessions.is_empty() && !self.sessions.contains(&state.session) {
+            return Err(Nrc::SERVICE_NOT_SUPPORTED_IN_ACTIVE_SESSION);
+        }
+        if self
+            .security_level
+            .is_some_and(|level| state.security_level != level)
+        {
+            return Err(Nrc::SECURITY_ACCESS_DENIED);
+        }
+        if self.condition.as_ref().is_some_and(|f| !f(state)) {
+            return Err(Nrc::CONDITIONS_NOT_CORRECT);
+        }
+
+        Ok(())
+    }
+}
+
+/// The UDS server(ECU) over an ISO-TP channel.
+///
+/// The requests are routed to the registered handlers. When no handler is registered,
+/// the built-in services are used:
+/// - `DiagnosticSessionControl` switches between the supported sessions.
+/// - `ECUReset` resets the session and security state.
+/// - `SecurityAccess` checks the key by the algorithm set by [`UdsServer::set_security`].
+/// - `TesterPresent` keeps the non-default session alive.
+/// - `Read/WriteDataByIdentifier` access the data set by [`UdsServer::set_data`].
+///
+/// The non-default session falls back to default session without request in S3.
+///
+/// # Example
+/// ```ignore
+/// let mut server = UdsServer::new(IsoTpChannel::new(device, "can0".into(), address));
+/// server.set_security(|_| vec![0x12, 0x34], |_, seed| Ok(seed.iter().map(|v| !v).collect()));
+/// server
+///     .register_handler(0x31, |_, request| Ok(request[1..4].to_vec()))
+///     .set_sessions(&[0x03])
+///     .set_security_level(0x01)
+///     .set_min_length(4);
+/// server.serve().await?;
+/// ```
+pub struct UdsServer<D: Device> {
+    channel: IsoTpChannel<D>,
+    services: HashMap<u8, ServiceOptions>,
+    state: Mutex<ServerState>,
+    sessions: Vec<u8>,
+    timing: SessionTiming,
+    s3: Duration,
+    seed: Option<SeedGenerator>,
+    security: Option<SecurityAlgorithm>,
+    data: Mutex<HashMap<u16, Vec<u8>>>,
+}
+
+impl<D> UdsServer<D>
+where
+    D: Device,
+    D::Channel: Clone,
+{
+    pub fn new(channel: IsoTpChannel<D>) -> Self {
+        Self {
+            channel,
+            services: Default::default(),
+            state: Default::default(),
+            sessions: vec![DEFAULT_SESSION, 0x02, 0x03],
+            timing: SessionTiming {
+                p2: Duration::from_millis(50),
+                p2_star: Duration::from_millis(5000),
+            },
+            s3: Duration::from_millis(5000),
+            seed: None,
+            security: None,
+            data: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn channel(&self) -> &IsoTpChannel<D> {
+        &self.channel
+    }
+
+    #[inline]
+    pub fn state(&self) -> ServerState {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// Set the sessions supported by `DiagnosticSessionControl`.
+    pub fn set_sessions(&mut self, sessions: &[u8]) -> &mut Self {
+        self.sessions = sessions.to_vec();
+        self
+    }
+
+    /// Set the P2/P2* reported by `DiagnosticSessionControl`.
+    pub fn set_timing(&mut self, timing: SessionTiming) -> &mut Self {
+        self.timing = timing;
+        self
+    }
+
+    /// Set the timeout of non-default session.
+    pub fn set_s3(&mut self, s3: Duration) -> &mut Self {
+        self.s3 = s3;
+        self
+    }
+
+    /// Enable `SecurityAccess` with the seed generator and key algorithm.
+    pub fn set_security(
+        &mut self,
+        seed: impl Fn(u8) -> Vec<u8> + Send + Sync + 'static,
+        algorithm: impl Fn(u8, &[u8]) -> CanResult<Vec<u8>> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.seed = Some(Box::new(seed));
+        self.security = Some(Box::new(algorithm));
+        self
+    }
+
+    /// Set the data of identifier, which is read and written by the built-in services.
+    pub fn set_data(&self, did: u16, data: Vec<u8>) -> &Self {
+        self.data.lock().unwrap().insert(did, data);
+        self
+    }
+
+    #[inline]
+    pub fn data(&self, did: u16) -> Option<Vec<u8>> {
+        self.data.lock().unwrap().get(&did).cloned()
+    }
+
+    /// Register the handler of service, the handler with the same service is replaced.
+    pub fn register_handler(
+        &mut self,
+        sid: u8,
+        handler: impl Fn(&mut ServerState, &[u8]) -> Result<Vec<u8>, Nrc> + Send + Sync + 'static,
+    ) -> &mut ServiceOptions {
+        let options = self.services.entry(sid).or_default();
+        options.handler = Some(Box::new(handler));
+        options
+    }
+
+    /// Set the conditions of the service, including the built-in services.
+    pub fn service_options(&mut self, sid: u8) -> &mut ServiceOptions {
+        self.services.entry(sid).or_default()
+    }
+
+    /// Handle the request, return the response, `None` if the response is suppressed.
+    pub fn handle(&self, request: &[u8], address_type: AddressType) -> Option<Vec<u8>> {
+        let &sid = request.first()?;
+        let mut request = request.to_vec();
+        let suppress = match Service::try_from(sid) {
+            Ok(service) if service.has_sub_function() && request.len() > 1 => {
+                let suppress = request[1] & SUPPRESS_POSITIVE != 0;
+                request[1] &= !SUPPRESS_POSITIVE;
+                suppress
+            }
+            _ => false,
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let options = self.services.get(&sid);
+        let ret = options
+            .map_or(Ok(()), |o| o.check(&state, &request))
+            .and_then(|_| match options.and_then(|o| o.handler.as_ref()) {
+                Some(handler) => handler(&mut state, &request),
+                None => self.builtin(&mut state, &request),
+            });
+        drop(state);
+
+        match ret {
+            Ok(_) if suppress => None,
+            Ok(data) => {
+                let mut response = Vec::with_capacity(data.len() + 1);
+                response.push(sid.wrapping_add(POSITIVE_OFFSET));
+                response.extend(data);
+                Some(response)
+            }
+            // these NRCs are not responded for functional request.
+            Err(
+                Nrc::SERVICE_NOT_SUPPORTED
+                | Nrc::SUB_FUNCTION_NOT_SUPPORTED
+                | Nrc::SERVICE_NOT_SUPPORTED_IN_ACTIVE_SESSION
+                | Nrc::SUB_FUNCTION_NOT_SUPPORTED_IN_ACTIVE_SESSION
+                | Nrc::REQUEST_OUT_OF_RANGE,
+            ) if matches!(address_type, AddressType::Functional) => None,
+            Err(nrc) => Some(vec![NEGATIVE_RESPONSE, sid, nrc.0]),
+        }
+    }
+
+    /// Receive and respond the requests until the device error,
+    /// the transmit errors are only logged and the next request is served.
+    pub async fn serve(&self) -> CanResult<()> {
+        let mut last = Instant::now();
+        loop {
+            let timeout = match self.state.lock().unwrap().session {
+                DEFAULT_SESSION => None,
+                _ => Some(self.s3.saturating_sub(last.elapsed()).as_millis() as u32),
+            };
+            let (address_type, request) = match self.channel.receive_from(timeout).await {
+                Ok(v) => v,
+                Err(Error::TimeoutError(_)) => {
+                    if last.elapsed() >= self.s3 {
+                        rsutil::debug!("RUST-CAN - UDS server S3 timeout");
+                        self.state.lock().unwrap().reset();
+                    }
+                    continue;
+                }
+                Err(e @ (Error::InvalidFrame(_) | Error::InvalidDLC(_))) => {
+                    rsutil::warn!("RUST-CAN - UDS server receive error: {}", e);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            last = Instant::now();
+
+            if let Some(response) = self.handle(&request, address_type) {
+                if let Err(e) = self.channel.transmit(&response).await {
+                    rsutil::warn!("RUST-CAN - UDS server transmit error: {}", e);
+                    self.channel.clear();
+                    self.state.lock().unwrap().seed = None;
+                }
+            }
+        }
+    }
+
+    fn builtin(&self, state: &mut ServerState, request: &[u8]) -> Result<Vec<u8>, Nrc> {
+        let sid = request[0];
+        let service = Service::try_from(sid).map_err(|_| Nrc::SERVICE_NOT_SUPPORTED)?;
+        if service.has_sub_function() && request.len() < 2 {
+            return Err(Nrc::INCORRECT_MESSAGE_LENGTH);
+        }
+
+        match service {
+            Service::SessionControl => {
+                let session = request[1];
+                if request.len() != 2 {
+                    return Err(Nrc::INCORRECT_MESSAGE_LENGTH);
+                }
+                if !self.sessions.contains(&session) {
+                    return Err(Nrc::SUB_FUNCTION_NOT_SUPPORTED);
+                }
+                state.reset();
+                state.session = session;
+
+                let mut response = vec![session];
+                response.extend((self.timing.p2.as_millis() as u16).to_be_bytes());
+                response.extend(((self.timing.p2_star.as_millis() / 10) as u16).to_be_bytes());
+                Ok(response)
+            }
+            Service::EcuReset => match request {
+                [_, reset_type @ 0x01..=0x03] => {
+                    state.reset();
+                    Ok(vec![*reset_type])
+                }
+                [_, _] => Err(Nrc::SUB_FUNCTION_NOT_SUPPORTED),
+                _ => Err(Nrc::INCORRECT_MESSAGE_LENGTH),
+            },
+            Service::SecurityAccess => self.security_access(state, request),
+            Service::TesterPresent => match request {
+                [_, 0x00] => Ok(vec![0x00]),
+                [_, _] => Err(Nrc::SUB_FUNCTION_NOT_SUPPORTED),
+                _ => Err(Nrc::INCORRECT_MESSAGE_LENGTH),
+            },
+            Service::ReadDataByIdentifier => {
+                let dids = &request[1..];
+                if dids.is_empty() || dids.len() % 2 != 0 {
+                    return Err(Nrc::INCORRECT_MESSAGE_LENGTH);
+                }
+                let data = self.data.lock().unwrap();
+                let mut response = Vec::new();
+                for did in dids.chunks(2) {
+                    let value = data
+                        .get(&u16::from_be_bytes([did[0], did[1]]))
+                        .ok_or(Nrc::REQUEST_OUT_OF_RANGE)?;
+                    response.extend_from_slice(did);
+                    response.extend_from_slice(value);
+                }
+                Ok(response)
+            }
+            Service::WriteDataByIdentifier => {
+                if request.len() < 4 {
+                    return Err(Nrc::INCORRECT_MESSAGE_LENGTH);
+                }
+                let did = u16::from_be_bytes([request[1], request[2]]);
+                let mut data = self.data.lock().unwrap();
+                let value = data.get_mut(&did).ok_or(Nrc::REQUEST_OUT_OF_RANGE)?;
+                *value = request[3..].to_vec();
+                Ok(request[1..3].to_vec())
+            }
+            _ => Err(Nrc::SERVICE_NOT_SUPPORTED),
+        }
+    }
+
+    fn security_access(&self, state: &mut ServerState, request: &[u8]) -> Result<Vec<u8>, Nrc> {
+        let (Some(seed), Some(algorithm)) = (&self.seed, &self.security) else {
+            return Err(Nrc::SERVICE_NOT_SUPPORTED);
+        };
+        if state.session == DEFAULT_SESSION {
+            return Err(Nrc::SERVICE_NOT_SUPPORTED_IN_ACTIVE_SESSION);
+        }
+
+        let level = request[1];
+        match level % 2 {
+            // requestSeed
+            1 => {
+                if request.len() != 2 {
+                    return Err(Nrc::INCORRECT_MESSAGE_LENGTH);
+                }
+                if state.attempts >= MAX_ATTEMPTS {
+                    return Err(Nrc::REQUIRED_TIME_DELAY_NOT_EXPIRED);
+                }
+                let seed = match state.security_level == level {
+                    true => vec![0; seed(level).len()],
+                    false => {
+                        let seed = seed(level);
+                        state.seed = Some((level, seed.clone()));
+                        seed
+                    }
+                };
+
+                let mut response = vec![level];
+                response.extend(seed);
+                Ok(response)
+            }
+            // sendKey
+            _ => {
+                let (seed_level, seed) = state
+                    .seed
+                    .take()
+                    .filter(|(l, _)| l + 1 == level)
+                    .ok_or(Nrc::REQUEST_SEQUENCE_ERROR)?;
+                let key = algorithm(seed_level, &seed).map_err(|_| Nrc::GENERAL_REJECT)?;
+                if key != request[2..] {
+                    state.attempts += 1;
+                    return match state.attempts >= MAX_ATTEMPTS {
+                        true => Err(Nrc::EXCEEDED_NUMBER_OF_ATTEMPTS),
+                        false => Err(Nrc::INVALID_KEY),
+                    };
+                }
+
+                state.attempts = 0;
+                state.security_level = seed_level;
+                Ok(vec![level])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        isotp::{
+            tests::{isotp, Bus},
+            IsoTpConfig,
+        },
+        uds::UdsClient,
+    };
+    use std::sync::Arc;
+
+    fn server(channel: &str) -> UdsServer<Bus> {
+        let mut server = UdsServer::new(isotp(channel, 0x7E8, 0x7E0, false));
+        server.set_security(
+            |_| vec![0x12, 0x34],
+            |_, seed| Ok(seed.iter().map(|v| !v).collect()),
+        );
+        server.set_data(0xF190, b"WDB0000000000000".to_vec());
+        server
+            .register_handler(0x31, |_, request| Ok(request[1..4].to_vec()))
+            .set_sessions(&[0x03])
+            .set_security_level(0x01)
+            .set_min_length(4);
+        server
+    }
+
+    fn nrc(sid: u8, nrc: Nrc) -> Option<Vec<u8>> {
+        Some(vec![NEGATIVE_RESPONSE, sid, nrc.0])
+    }
+
+    #[test]
+    fn requests_are_handled() {
+        let server = server("uds-server-handle");
+        let physical = AddressType::Physical;
+
+        assert_eq!(
+            server.handle(&[0x85, 0x01], physical),
+            nrc(0x85, Nrc::SERVICE_NOT_SUPPORTED)
+        );
+        assert_eq!(server.handle(&[0x85, 0x01], AddressType::Functional), None);
+        assert_eq!(
+            server.handle(&[0x31, 0x01, 0xFF, 0x00], physical),
+            nrc(0x31, Nrc::SERVICE_NOT_SUPPORTED_IN_ACTIVE_SESSION)
+        );
+        assert_eq!(
+            server.handle(&[0x10, 0x03], physical),
+            Some(vec![0x50, 0x03, 0x00, 0x32, 0x01, 0xF4])
+        );
+        assert_eq!(
+            server.handle(&[0x31, 0x01, 0xFF], physical),
+            nrc(0x31, Nrc::INCORRECT_MESSAGE_LENGTH)
+        );
+        assert_eq!(
+            server.handle(&[0x31, 0x01, 0xFF, 0x00], physical),
+            nrc(0x31, Nrc::SECURITY_ACCESS_DENIED)
+        );
+
+        // security access
+        assert_eq!(
+            server.handle(&[0x27, 0x02, 0xED, 0xCB], physical),
+            nrc(0x27, Nrc::REQUEST_SEQUENCE_ERROR)
+        );
+        for _ in 0..2 {
+            server.handle(&[0x27, 0x01], physical);
+            assert_eq!(
+                server.handle(&[0x27, 0x02, 0x00, 0x00], physical),
+                nrc(0x27, Nrc::INVALID_KEY)
+            );
+        }
+        assert_eq!(
+            server.handle(&[0x27, 0x01], physical),
+            Some(vec![0x67, 0x01, 0x12, 0x34])
+        );
+        assert_eq!(
+            server.handle(&[0x27, 0x02, 0xED, 0xCB], physical),
+            Some(vec![0x67, 0x02])
+        );
+        assert_eq!(server.state().security_level, 0x01);
+        assert_eq!(
+            server.handle(&[0x27, 0x01], physical),
+            Some(vec![0x67, 0x01, 0x00, 0x00])
+        );
+
+        assert_eq!(
+            server.handle(&[0x31, 0x01, 0xFF, 0x00], physical),
+            Some(vec![0x71, 0x01, 0xFF, 0x00])
+        );
+        assert_eq!(server.handle(&[0x3E, 0x80], AddressType::Functional), None);
+        assert_eq!(
+            server.handle(&[0x11, 0x01], physical),
+            Some(vec![0x51, 0x01])
+        );
+        assert_eq!(server.state(), ServerState::default());
+    }
+
+    #[test]
+    fn conditions_are_checked() {
+        let mut server = server("uds-server-condition");
+        server
+            .service_options(0x2E)
+            .set_condition(|state| state.session == 0x03);
+
+        assert_eq!(
+            server.handle(&[0x2E, 0xF1, 0x90, 0x01], AddressType::Physical),
+            nrc(0x2E, Nrc::CONDITIONS_NOT_CORRECT)
+        );
+        server.handle(&[0x10, 0x03], AddressType::Physical);
+        assert_eq!(
+            server.handle(&[0x2E, 0xF1, 0x90, 0x01], AddressType::Physical),
+            Some(vec![0x6E, 0xF1, 0x90])
+        );
+        assert_eq!(server.data(0xF190), Some(vec![0x01]));
+        assert_eq!(
+            server.handle(&[0x22, 0xF1, 0x91], AddressType::Physical),
+            nrc(0x22, Nrc::REQUEST_OUT_OF_RANGE)
+        );
+    }
+
+    #[tokio::test]
+    async fn client_is_served() {
+        let channel = "uds-server-serve";
+        let mut server = server(channel);
+        server.set_s3(Duration::from_millis(100));
+        let server = Arc::new(server);
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move { server.serve().await }
+        });
+
+        let mut client = UdsClient::new(isotp(channel, 0x7E0, 0x7E8, false));
+        client.set_security_algorithm(|_, seed| Ok(seed.iter().map(|v| !v).collect()));
+        client.session_control(0x03).await.unwrap();
+        client.security_access(0x01).await.unwrap();
+        let status = client.routine_control(0x01, 0xFF00, &[]).await.unwrap();
+        assert!(status.is_empty());
+        let vin = client.read_data_by_identifier(0xF190).await.unwrap();
+        assert_eq!(vin, b"WDB0000000000000");
+
+        // S3 timeout
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(server.state().session, DEFAULT_SESSION);
+        assert!(matches!(
+            client.routine_control(0x01, 0xFF00, &[]).await,
+            Err(Error::NegativeResponse { code: 0x7F, .. })
+        ));
+
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn transmit_error_is_skipped() {
+        let channel = "uds-server-transmit";
+        let mut isotp_server = isotp(channel, 0x7E8, 0x7E0, false);
+        isotp_server.set_config(IsoTpConfig {
+            n_bs: Duration::from_millis(50),
+            ..Default::default()
+        });
+        let server = UdsServer::new(isotp_server);
+        server.set_data(0xF190, b"WDB0000000000000".to_vec());
+        let task = tokio::spawn(async move { server.serve().await });
+
+        // the tester never sends FC, so the multi-frame response is timeout.
+        let tester = isotp(channel, 0x7E0, 0x7E8, false);
+        tester.transmit(&[0x22, 0xF1, 0x90]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!task.is_finished());
+
+        let client = UdsClient::new(isotp(channel, 0x7E0, 0x7E8, false));
+        let vin = client.read_data_by_identifier(0xF190).await.unwrap();
+        assert_eq!(vin, b"WDB0000000000000");
+
+        task.abort();
+    }
+}
@@ -0,0 +1,572 @@
+//! A pure-software CAN bus shared by all devices of the same process.
+
+use crate::{
+    bus::{BusCapabilities, BusCapability},
+    device::{ChannelConfig, ChannelMode, Device, DeviceBuilder},
+    error::Error,
+    frame::{identifier::Filter, Direction, Frame, Kind},
+    utils, CanResult,
+};
+use std::{
+    any::{Any, TypeId},
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock, Weak,
+    },
+    time::Duration,
+};
+use tokio::sync::Notify;
+
+/// The max count of frames buffered by a channel.
+const VIRTUAL_QUEUE_SIZE: usize = 4096;
+
+type BusKey = (TypeId, String);
+type Registry = Mutex<HashMap<BusKey, Weak<dyn Any + Send + Sync>>>;
+
+/// All alive buses, keyed by frame type and channel name.
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+struct Bus<F> {
+    next_id: AtomicUsize,
+    nodes: Mutex<Vec<Arc<Node<F>>>>,
+}
+
+impl<F: Frame<Channel = String> + Clone + 'static> Bus<F> {
+    /// Find the bus of `channel` or create it when no device is attached.
+    fn get_or_create(channel: &str) -> Arc<Self> {
+        let key = (TypeId::of::<F>(), channel.to_owned());
+        let mut registry = registry().lock().unwrap();
+        if let Some(bus) = registry
+            .get(&key)
+            .and_then(Weak::upgrade)
+            .and_then(|v| v.downcast::<Self>().ok())
+        {
+            return bus;
+        }
+
+        registry.retain(|_, v| v.strong_count() > 0);
+        let bus = Arc::new(Self {
+            next_id: Default::default(),
+            nodes: Default::default(),
+        });
+        let erased: Arc<dyn Any + Send + Sync> = bus.clone();
+        registry.insert(key, Arc::downgrade(&erased));
+
+        bus
+    }
+
+    fn attach(&self, cfg: &ChannelConfig) -> Arc<Node<F>> {
+        let node = Arc::new(Node {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            canfd: cfg.data_bitrate.is_some(),
+            mode: cfg.mode.unwrap_or(ChannelMode::Normal),
+            recv_own_msg: cfg.recv_own_msg.unwrap_or_default(),
+            filters: cfg.filters.clone(),
+            queue: Default::default(),
+            notify: Default::default(),
+        });
+        self.nodes.lock().unwrap().push(node.clone());
+
+        node
+    }
+
+    fn detach(&self, id: usize) {
+        self.nodes.lock().unwrap().retain(|n| n.id != id);
+    }
+
+    fn broadcast(&self, sender: &Node<F>, frame: F) {
+        let loopback = matches!(sender.mode, ChannelMode::Loopback);
+        let nodes = self.nodes.lock().unwrap();
+        for node in nodes.iter() {
+            if node.id == sender.id {
+                if loopback || sender.recv_own_msg {
+                    node.push(frame.clone());
+                }
+            } else if !loopback
+                && !matches!(node.mode, ChannelMode::Loopback)
+                && node.accept(&frame)
+            {
+                let mut frame = frame.clone();
+                frame.set_direction(Direction::Receive);
+                node.push(frame);
+            }
+        }
+    }
+}
+
+struct Node<F> {
+    id: usize,
+    canfd: bool,
+    mode: ChannelMode,
+    recv_own_msg: bool,
+    filters: Vec<Filter>,
+    queue: Mutex<VecDeque<F>>,
+    notify: Notify,
+}
+
+impl<F: Frame> Node<F> {
+    fn accept(&self, frame: &F) -> bool {
+        if !self.canfd && !matches!(frame.kind(), Kind::Classical) {
+            return false;
+        }
+
+        self.filters.is_empty() || self.filters.iter().any(|f| f.matches(frame.id()))
+    }
+
+    fn push(&self, frame: F) {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.len() >= VIRTUAL_QUEUE_SIZE {
+            rsutil::warn!("RUST-CAN - virtual bus queue is full, the oldest frame is dropped");
+            queue.pop_front();
+        }
+        queue.push_back(frame);
+        drop(queue);
+
+        self.notify.notify_one();
+    }
+
+    async fn pop_all(&self) -> Vec<F> {
+        loop {
+            let frames = self.queue.lock().unwrap().drain(..).collect::<Vec<_>>();
+            if !frames.is_empty() {
+                return frames;
+            }
+            self.notify.notified().await;
+        }
+    }
+}
+
+/// A device port attached to a bus, detached when dropped.
+struct Port<F: Frame<Channel = String> + Clone + 'static> {
+    bus: Arc<Bus<F>>,
+    node: Arc<Node<F>>,
+}
+
+impl<F: Frame<Channel = String> + Clone + 'static> Drop for Port<F> {
+    fn drop(&mut self) {
+        self.bus.detach(self.node.id);
+    }
+}
+
+/// A CAN device without hardware.
+///
+/// Every [`VirtualBus`] that opens the same channel name with the same frame type `F`,
+/// in the same process, is connected to the same bus. The buses are keyed by both,
+/// so a `VirtualBus<A>` and a `VirtualBus<B>` opening the same name do not see each other.
+/// The frames are stamped with system timestamp when transmitted.
+///
+/// The channel configuration is honored as follows:
+/// - `filters` are applied on receiving.
+/// - `data_bitrate` enables CAN-FD, otherwise FD frames are neither sent nor received.
+/// - `ChannelMode::Loopback` disconnects the channel from the bus and echoes the frames back.
+/// - `ChannelMode::ListenOnly` rejects all transmitting.
+/// - `recv_own_msg` echoes the transmitted frames back to the sender.
+///
+/// # Example
+/// ```ignore
+/// let mut builder = DeviceBuilder::new();
+/// builder.add_config("vbus0".to_string(), ChannelConfig::new(500_000));
+/// let device = builder.build::<VirtualBus<MyFrame>>()?;
+/// ```
+#[derive(Clone)]
+pub struct VirtualBus<F: Frame<Channel = String> + Clone + 'static> {
+    ports: Arc<HashMap<String, Port<F>>>,
+}
+
+impl<F: Frame<Channel = String> + Clone + 'static> Default for VirtualBus<F> {
+    fn default() -> Self {
+        Self {
+            ports: Default::default(),
+        }
+    }
+}
+
+impl<F: Frame<Channel = String> + Clone + 'static> VirtualBus<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attach the channel to the bus with the same name and frame type.
+    pub fn init_channel(&mut self, channel: &str, cfg: &ChannelConfig) -> CanResult<()> {
+        let ports = Arc::get_mut(&mut self.ports).ok_or(Error::operation_error(
+            "device is shared, the channel can't be initialized",
+        ))?;
+        let bus = Bus::get_or_create(channel);
+        let node = bus.attach(cfg);
+        ports.insert(channel.to_owned(), Port { bus, node });
+
+        Ok(())
+    }
+
+    #[inline]
+    fn port(&self, channel: &str) -> CanResult<&Port<F>> {
+        self.ports
+            .get(channel)
+            .ok_or(Error::channel_not_opened(channel))
+    }
+}
+
+#[async_trait::async_trait]
+impl<F: Frame<Channel = String> + Clone + 'static> Device for VirtualBus<F> {
+    type Channel = String;
+    type Frame = F;
+
+    fn new(builder: DeviceBuilder<String>) -> CanResult<Self> {
+        let mut device = Self::default();
+        builder
+            .channel_configs()
+            .iter()
+            .try_for_each(|(chl, cfg)| device.init_channel(chl, cfg))?;
+
+        Ok(device)
+    }
+
+    #[inline]
+    fn opened_channels(&self) -> Vec<Self::Channel> {
+        self.ports.keys().cloned().collect()
+    }
+
+    async fn transmit(&self, msg: Self::Frame, _: Option<u32>) -> CanResult<()> {
+        let channel = msg.channel();
+        let port = self.port(&channel)?;
+        if matches!(port.node.mode, ChannelMode::ListenOnly) {
+            return Err(Error::operation_error(format!(
+                "channel: {} is listen-only",
+                channel
+            )));
+        }
+        if !port.node.canfd && !matches!(msg.kind(), Kind::Classical) {
+            return Err(Error::NotSupportedError);
+        }
+
+        let mut msg = msg;
+        msg.set_direction(Direction::Transmit)
+            .set_timestamp(Some(utils::system_timestamp()));
+        port.bus.broadcast(&port.node, msg);
+
+        Ok(())
+    }
+
+    async fn receive(
+        &self,
+        channel: Self::Channel,
+        timeout: Option<u32>,
+    ) -> CanResult<Vec<Self::Frame>> {
+        let port = self.port(&channel)?;
+        match timeout {
+            Some(timeout) => {
+                tokio::time::timeout(Duration::from_millis(timeout as u64), port.node.pop_all())
+                    .await
+                    .map_err(|_| Error::channel_timeout(channel))
+            }
+            None => Ok(port.node.pop_all().await),
+        }
+    }
+
+    /// Close the channels of this handle only,
+    /// the ports are detached when all clones are shut down or dropped.
+    fn shutdown(&mut self) {
+        self.ports = Default::default();
+    }
+}
+
+impl<F: Frame<Channel = String> + Clone + 'static> BusCapability for VirtualBus<F> {
+    fn capabilities(&self) -> BusCapabilities {
+        BusCapabilities {
+            can_fd: true,
+            bitrate_switch: true,
+            error_state_indicator: true,
+            listen_only: true,
+            loopback: true,
+            hardware_timestamp: false,
+            bus_diagnostics: false,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{
+        frame::{
+            identifier::{CanFdFlags, Id, StandardId},
+            FrameFormat, Timestamp, XlParams,
+        },
+        ERR_MASK,
+    };
+
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct TestFrame {
+        id: u32,
+        extended: bool,
+        channel: String,
+        data: Vec<u8>,
+        kind: Kind,
+        direction: Direction,
+        timestamp: Option<Timestamp>,
+        remote: bool,
+        error: bool,
+        brs: bool,
+        esi: bool,
+        xl: Option<XlParams>,
+    }
+
+    impl Frame for TestFrame {
+        type Channel = String;
+
+        fn new_can(id: Id, data: &[u8]) -> CanResult<Self> {
+            Ok(Self {
+                id: id.as_raw() & ERR_MASK,
+                extended: id.is_extended(),
+                data: data.to_vec(),
+                ..Default::default()
+            })
+        }
+
+        fn new_remote(id: Id, dlc: u8) -> CanResult<Self> {
+            let mut frame = Self::new_can(id, &vec![0; dlc as usize])?;
+            frame.remote = true;
+            Ok(frame)
+        }
+
+        fn new_can_fd(id: Id, data: &[u8], flags: CanFdFlags) -> CanResult<Self> {
+            let mut frame = Self::new_can(id, data)?;
+            frame.kind = Kind::FD;
+            frame.brs = flags.contains(CanFdFlags::BRS);
+            frame.esi = flags.contains(CanFdFlags::ESI);
+            Ok(frame)
+        }
+
+        fn new_can_xl(priority: u16, data: &[u8], params: XlParams) -> CanResult<Self> {
+            let mut frame = Self::new_can(Id::Standard(StandardId::new(priority)?), data)?;
+            frame.kind = Kind::XL;
+            frame.xl = Some(params);
+            Ok(frame)
+        }
+
+        fn new_error(id: Id, data: &[u8]) -> CanResult<Self> {
+            let mut frame = Self::new_can(id, data)?;
+            frame.error = true;
+            Ok(frame)
+        }
+
+        fn id(&self) -> Id {
+            Id::from_bits(self.id, Some(self.extended)).unwrap()
+        }
+
+        fn channel(&self) -> Self::Channel {
+            self.channel.clone()
+        }
+
+        fn set_channel(&mut self, v: Self::Channel) -> &mut Self {
+            self.channel = v;
+            self
+        }
+
+        fn kind(&self) -> Kind {
+            self.kind
+        }
+
+        fn format(&self) -> FrameFormat {
+            if self.error {
+                FrameFormat::Error
+            } else if self.remote {
+                FrameFormat::Remote
+            } else {
+                FrameFormat::Data
+            }
+        }
+
+        fn data(&self) -> &[u8] {
+            &self.data
+        }
+
+        fn len(&self) -> usize {
+            self.data.len()
+        }
+
+        fn xl_params(&self) -> Option<XlParams> {
+            self.xl
+        }
+
+        fn direction(&self) -> Direction {
+            self.direction
+        }
+
+        fn set_direction(&mut self, d: Direction) -> &mut Self {
+            self.direction = d;
+            self
+        }
+
+        fn timestamp(&self) -> Option<Timestamp> {
+            self.timestamp
+        }
+
+        fn set_timestamp(&mut self, ts: Option<Timestamp>) -> &mut Self {
+            self.timestamp = ts;
+            self
+        }
+
+        fn is_bitrate_switch(&self) -> bool {
+            self.brs
+        }
+
+        fn set_bitrate_switch(&mut self, v: bool) -> &mut Self {
+            self.brs = v;
+            self
+        }
+
+        fn is_esi(&self) -> bool {
+            self.esi
+        }
+
+        fn set_esi(&mut self, v: bool) -> &mut Self {
+            self.esi = v;
+            self
+        }
+    }
+
+    /// A device attached to the bus of `channel`, which is shared by the tests of all protocols.
+    pub(crate) fn device(channel: &str, cfg: ChannelConfig) -> VirtualBus<TestFrame> {
+        let mut builder = DeviceBuilder::new();
+        builder.add_config(channel.to_owned(), cfg);
+        builder.build().unwrap()
+    }
+
+    fn frame(channel: &str, id: u16) -> TestFrame {
+        let mut frame =
+            TestFrame::new_can(Id::Standard(StandardId::new(id).unwrap()), &[0x01, 0x02]).unwrap();
+        frame.set_channel(channel.to_owned());
+        frame
+    }
+
+    #[tokio::test]
+    async fn frames_are_delivered_to_other_devices() {
+        let channel = "vbus-deliver";
+        let tx = device(channel, ChannelConfig::new(500_000));
+        let rx = device(channel, ChannelConfig::new(500_000));
+
+        tx.transmit(frame(channel, 0x123), None).await.unwrap();
+
+        let frames = rx.receive(channel.into(), Some(100)).await.unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].direction(), Direction::Receive);
+        assert!(frames[0].timestamp().is_some());
+        // own frames are not received by default
+        assert!(tx.receive(channel.into(), Some(10)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn filters_and_modes_are_honored() {
+        let channel = "vbus-modes";
+        let mut filtered = ChannelConfig::new(500_000);
+        filtered.filters = vec![Filter::Standard {
+            id: StandardId::new(0x700).unwrap(),
+            mask: 0x700,
+        }];
+        let mut listen_only = ChannelConfig::new(500_000);
+        listen_only.set_channel_mode(ChannelMode::ListenOnly);
+        let mut own = ChannelConfig::new(500_000);
+        own.set_recv_own_msg(true);
+
+        let tx = device(channel, own);
+        let rx = device(channel, filtered);
+        let listener = device(channel, listen_only);
+
+        tx.transmit(frame(channel, 0x123), None).await.unwrap();
+        tx.transmit(frame(channel, 0x7E0), None).await.unwrap();
+
+        let frames = rx.receive(channel.into(), Some(100)).await.unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].id().as_raw(), 0x7E0);
+        assert_eq!(
+            listener
+                .receive(channel.into(), Some(100))
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        let own = tx.receive(channel.into(), Some(100)).await.unwrap();
+        assert_eq!(own.len(), 2);
+        assert_eq!(own[0].direction(), Direction::Transmit);
+        assert!(listener
+            .transmit(frame(channel, 0x123), None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn loopback_channel_is_disconnected_from_bus() {
+        let channel = "vbus-loopback";
+        let mut loopback = ChannelConfig::new(500_000);
+        loopback.set_channel_mode(ChannelMode::Loopback);
+        let tx = device(channel, loopback);
+        let rx = device(channel, ChannelConfig::new(500_000));
+
+        tx.transmit(frame(channel, 0x123), None).await.unwrap();
+
+        assert_eq!(
+            tx.receive(channel.into(), Some(100)).await.unwrap().len(),
+            1
+        );
+        assert!(rx.receive(channel.into(), Some(10)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn fd_frames_require_fd_channel() {
+        let channel = "vbus-fd";
+        let mut fd = ChannelConfig::new(500_000);
+        fd.set_data_bitrate(2_000_000);
+        let tx = device(channel, fd);
+        let classic = device(channel, ChannelConfig::new(500_000));
+
+        let mut msg = TestFrame::new_can_fd(
+            Id::Standard(StandardId::new(0x123).unwrap()),
+            &[0x00; 12],
+            CanFdFlags::empty(),
+        )
+        .unwrap();
+        msg.set_channel(channel.to_owned());
+        tx.transmit(msg.clone(), None).await.unwrap();
+        assert!(classic.transmit(msg, None).await.is_err());
+        assert!(classic.receive(channel.into(), Some(10)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn shutdown_detaches_channel() {
+        let channel = "vbus-shutdown";
+        let tx = device(channel, ChannelConfig::new(500_000));
+        let mut rx = device(channel, ChannelConfig::new(500_000));
+        rx.shutdown();
+        assert!(rx.is_closed());
+
+        tx.transmit(frame(channel, 0x123), None).await.unwrap();
+        let bus = Bus::<TestFrame>::get_or_create(channel);
+        assert_eq!(bus.nodes.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn shutdown_keeps_clones_attached() {
+        let channel = "vbus-shutdown-clone";
+        let tx = device(channel, ChannelConfig::new(500_000));
+        let rx = device(channel, ChannelConfig::new(500_000));
+        let mut clone = rx.clone();
+        clone.shutdown();
+        assert!(clone.is_closed());
+        assert!(clone.receive(channel.into(), Some(10)).await.is_err());
+
+        tx.transmit(frame(channel, 0x123), None).await.unwrap();
+        assert_eq!(
+            rx.receive(channel.into(), Some(100)).await.unwrap().len(),
+            1
+        );
+        drop(rx);
+        let bus = Bus::<TestFrame>::get_or_create(channel);
+        assert_eq!(bus.nodes.lock().unwrap().len(), 1);
+    }
+}
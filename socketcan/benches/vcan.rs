@@ -0,0 +1,101 @@
+//! Compare the throughput of single and batched I/O on a virtual interface.
+//!
+//! ```shell
+//! sudo ip link add dev vcan0 type vcan
+//! sudo ip link set dev vcan0 up
+//! cargo bench -p socketcan-rs --bench vcan
+//! ```
+//! The interface is `vcan0` unless `SOCKETCAN_BENCH_IFACE` is set.
+
+use rs_can::{CanDevice, CanError, CanFrame, CanId, ChannelConfig, DeviceBuilder};
+use socketcan_rs::{SocketCan, SocketCanFrame, DEFAULT_RECV_BATCH, RECV_BATCH};
+use std::time::{Duration, Instant};
+
+const FRAMES: usize = 100_000;
+const SEND_BATCH: usize = 64;
+const IDLE_TIMEOUT: u32 = 500;
+
+fn device(iface: &str, recv_batch: usize) -> Result<SocketCan, CanError> {
+    let mut cfg = ChannelConfig::new(500_000);
+    cfg.add_other(RECV_BATCH, Box::new(recv_batch as u32));
+    let mut builder = DeviceBuilder::new();
+    builder.add_config(iface.to_owned(), cfg);
+    builder.build()
+}
+
+fn frames(iface: &str, count: usize) -> Vec<SocketCanFrame> {
+    (0..count)
+        .map(|i| {
+            let id = CanId::try_from((i % 0x7FF) as u32).unwrap();
+            let mut frame = SocketCanFrame::new_can(id, &(i as u64).to_le_bytes()).unwrap();
+            frame.set_channel(iface.to_owned());
+            frame
+        })
+        .collect()
+}
+
+async fn run(name: &str, iface: &str, batched: bool) -> Result<(), CanError> {
+    let recv_batch = if batched { DEFAULT_RECV_BATCH } else { 1 };
+    let (sender, receiver) = (device(iface, recv_batch)?, device(iface, recv_batch)?);
+
+    let channel = iface.to_owned();
+    let receive = tokio::spawn(async move {
+        let (mut received, mut calls) = (0, 0);
+        while received < FRAMES {
+            match receiver.receive(channel.clone(), Some(IDLE_TIMEOUT)).await {
+                Ok(frames) => {
+                    received += frames.len();
+                    calls += 1;
+                }
+                Err(_) => break,
+            }
+        }
+        (received, calls)
+    });
+
+    let start = Instant::now();
+    let mut frames = frames(iface, FRAMES);
+    if batched {
+        while !frames.is_empty() {
+            let rest = frames.split_off(frames.len().min(SEND_BATCH));
+            sender.transmit_batch(frames, Some(1000)).await?;
+            frames = rest;
+        }
+    } else {
+        for frame in frames {
+            sender.transmit(frame, Some(1000)).await?;
+        }
+    }
+    let sent = start.elapsed();
+    let (received, calls) = receive.await.unwrap();
+    let elapsed = start.elapsed();
+    let elapsed = match received < FRAMES {
+        // the receiver waited for the idle timeout
+        true => elapsed.saturating_sub(Duration::from_millis(IDLE_TIMEOUT as u64)),
+        false => elapsed,
+    };
+
+    println!(
+        "{:<8} transmit: {:>10.0} frames/s, receive: {:>10.0} frames/s, {} of {} frames in {} calls",
+        name,
+        FRAMES as f64 / sent.as_secs_f64(),
+        received as f64 / elapsed.as_secs_f64(),
+        received,
+        FRAMES,
+        calls
+    );
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), CanError> {
+    let iface = std::env::var("SOCKETCAN_BENCH_IFACE").unwrap_or("vcan0".into());
+    if let Err(e) = device(&iface, 1) {
+        println!("skip the benchmark, {} is not available: {}", iface, e);
+        return Ok(());
+    }
+
+    run("single", &iface, false).await?;
+    run("batched", &iface, true).await
+}
@@ -0,0 +1,58 @@
+use libc::{CAN_ISOTP, SOL_CAN_BASE};
+use std::os::raw::c_int;
+
+/// Enable CAN XL frames on the channel, the value is a `bool`.
+pub const CAN_XL: &str = "can-xl";
+/// Enable the bus error reporting of controller, the value is a `bool`.
+pub const BERR_REPORTING: &str = "berr-reporting";
+/// Use the non-ISO(Bosch) CAN-FD of controller, the value is a `bool`.
+pub const FD_NON_ISO: &str = "fd-non-iso";
+/// The delay of automatic restart after bus-off in ms, 0 disables it, the value is a `u32`.
+pub const RESTART_MS: &str = "restart-ms";
+/// The length of transmit queue of interface, the value is a `u32`.
+pub const TXQUEUELEN: &str = "txqueuelen";
+/// The maximum number of frames read by a receive, the value is a `u32`.
+pub const RECV_BATCH: &str = "recv-batch";
+
+/// The socket option level of ISO-TP, see `linux/can/isotp.h`.
+pub const SOL_CAN_ISOTP: c_int = SOL_CAN_BASE + CAN_ISOTP;
+
+/// pass struct [`IsoTpOptions`](crate::IsoTpOptions)
+pub const CAN_ISOTP_OPTS: c_int = 1;
+/// pass struct [`IsoTpFcOptions`](crate::IsoTpFcOptions)
+pub const CAN_ISOTP_RECV_FC: c_int = 2;
+/// pass `u32` value in nano secs, overwrite the received STmin
+pub const CAN_ISOTP_TX_STMIN: c_int = 3;
+/// pass `u32` value in nano secs, ignore the received CF frames which timestamps differ less than val
+pub const CAN_ISOTP_RX_STMIN: c_int = 4;
+/// pass struct [`IsoTpLlOptions`](crate::IsoTpLlOptions)
+pub const CAN_ISOTP_LL_OPTS: c_int = 5;
+
+/// listen only (do not send FC)
+pub const CAN_ISOTP_LISTEN_MODE: u32 = 0x0001;
+/// enable extended addressing
+pub const CAN_ISOTP_EXTEND_ADDR: u32 = 0x0002;
+/// enable CAN frame padding tx path
+pub const CAN_ISOTP_TX_PADDING: u32 = 0x0004;
+/// enable CAN frame padding rx path
+pub const CAN_ISOTP_RX_PADDING: u32 = 0x0008;
+/// check received CAN frame padding
+pub const CAN_ISOTP_CHK_PAD_LEN: u32 = 0x0010;
+/// check received CAN frame padding
+pub const CAN_ISOTP_CHK_PAD_DATA: u32 = 0x0020;
+/// half duplex error state handling
+pub const CAN_ISOTP_HALF_DUPLEX: u32 = 0x0040;
+/// ignore stmin from received FC
+pub const CAN_ISOTP_FORCE_TXSTMIN: u32 = 0x0080;
+/// ignore CFs depending on rx stmin
+pub const CAN_ISOTP_FORCE_RXSTMIN: u32 = 0x0100;
+/// different rx extended addressing
+pub const CAN_ISOTP_RX_EXT_ADDR: u32 = 0x0200;
+/// wait for tx completion
+pub const CAN_ISOTP_WAIT_TX_DONE: u32 = 0x0400;
+/// 1-to-N functional addressing
+pub const CAN_ISOTP_SF_BROADCAST: u32 = 0x0800;
+/// 1-to-N transmission w/o FC
+pub const CAN_ISOTP_CF_BROADCAST: u32 = 0x1000;
+/// dynamic FC parameters BS/STmin
+pub const CAN_ISOTP_DYN_FC_PARMS: u32 = 0x2000;
@@ -1,31 +1,163 @@
 use crate::{
-    c_timeval_new, raw_open_socket, raw_write_frame, set_fd_mode, set_socket_option,
-    set_socket_option_mult, CanAddr, CanAnyFrame, SocketCanFrame,
+    netlink, raw_open_socket, raw_write_frame, set_fd_mode, set_nonblocking, set_socket_option,
+    set_socket_option_mult, CanAddr, CanAnyFrame, CanInterface, NonBlockingSocket, SocketCanFrame,
 };
 use libc::{
-    can_filter, can_frame, canfd_frame, canxl_frame, fcntl, read, CAN_RAW_ERR_FILTER,
-    CAN_RAW_FILTER, CAN_RAW_JOIN_FILTERS, CAN_RAW_LOOPBACK, CAN_RAW_RECV_OWN_MSGS, EINPROGRESS,
-    F_GETFL, F_SETFL, O_NONBLOCK, SOL_CAN_RAW, SOL_SOCKET, SO_RCVTIMEO, SO_SNDTIMEO,
+    can_filter, can_frame, canfd_frame, canxl_frame, iovec, mmsghdr, read, recvmmsg, sendmmsg,
+    CANXL_HDR_SIZE, CAN_RAW_ERR_FILTER, CAN_RAW_FILTER, CAN_RAW_JOIN_FILTERS, CAN_RAW_LOOPBACK,
+    CAN_RAW_RECV_OWN_MSGS, CAN_RAW_XL_FRAMES, ENOPROTOOPT, MSG_DONTWAIT, SOL_CAN_RAW,
+};
+use nix::poll::PollFlags;
+use rs_can::{
+    BusState, CanDirection, CanError, CanFilter, CanFrame, CanResult, CanXlFlags, ChannelConfig,
+    ErrorCounters, ERR_MASK,
 };
-use rs_can::{CanDirection, CanError, CanFilter, CanFrame, CanResult, ERR_MASK};
 use std::{
     collections::HashMap,
-    io,
+    fmt, io, mem,
     os::{
-        fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
-        raw::{c_int, c_void},
+        fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+        raw::{c_int, c_uint, c_void},
+    },
+    ptr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
     },
-    sync::Arc,
-    time::{Duration, Instant},
+    time::Duration,
 };
+use tokio::{io::Interest, time};
 
 pub(crate) const FRAME_SIZE: usize = std::mem::size_of::<can_frame>();
 pub(crate) const FD_FRAME_SIZE: usize = std::mem::size_of::<canfd_frame>();
 pub(crate) const XL_FRAME_SIZE: usize = std::mem::size_of::<canxl_frame>();
+/// `canxl_frame.flags` is at the same offset as the length of `can_frame` and `canfd_frame`.
+const CANXL_FLAGS_OFFSET: usize = 4;
+/// `CAN_RAW_XL_VCID_OPTS` and the flags of `struct can_raw_vcid_options`, since Linux 6.9.
+const CAN_RAW_XL_VCID_OPTS: c_int = 8;
+const CAN_RAW_XL_VCID_TX_PASS: u8 = 0x02;
+const CAN_RAW_XL_VCID_RX_FILTER: u8 = 0x04;
+
+#[repr(C)]
+#[derive(Default)]
+struct CanRawVcidOptions {
+    flags: u8,
+    tx_vcid: u8,
+    rx_vcid: u8,
+    rx_vcid_mask: u8,
+}
+
+/// The default maximum number of frames read by a receive of [`rs_can::CanDevice`].
+pub const DEFAULT_RECV_BATCH: usize = 32;
+/// The maximum number of messages of a `sendmmsg`(`UIO_MAXIOV`).
+const SEND_BATCH_MAX: usize = 1024;
+
+/// The raw socket of a channel.
+#[derive(Debug)]
+pub(crate) struct CanSocket {
+    socket: NonBlockingSocket,
+    /// The maximum number of frames read by a `recvmmsg`.
+    recv_batch: AtomicUsize,
+    /// Whether CAN XL frames are enabled, the receive buffers are sized by it.
+    xl: AtomicBool,
+    recv_buffers: Mutex<RecvBuffers>,
+    /// The timeout of the reads and writes which have no timeout.
+    read_timeout: Mutex<Option<Duration>>,
+    write_timeout: Mutex<Option<Duration>>,
+}
+
+impl CanSocket {
+    #[inline]
+    pub(crate) fn new(fd: OwnedFd) -> io::Result<Self> {
+        Ok(Self {
+            socket: NonBlockingSocket::new(fd)?,
+            recv_batch: AtomicUsize::new(DEFAULT_RECV_BATCH),
+            xl: AtomicBool::new(false),
+            recv_buffers: Default::default(),
+            read_timeout: Default::default(),
+            write_timeout: Default::default(),
+        })
+    }
+}
+
+/// The buffers of `recvmmsg` reused by every receive of a channel.
+#[derive(Default)]
+struct RecvBuffers {
+    frame_size: usize,
+    data: Vec<u8>,
+    iovecs: Vec<iovec>,
+    msgs: Vec<mmsghdr>,
+}
+
+// SAFETY: the pointers of `iovecs` and `msgs` only point to the heap owned by itself.
+unsafe impl Send for RecvBuffers {}
+
+impl fmt::Debug for RecvBuffers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecvBuffers")
+            .field("frame_size", &self.frame_size)
+            .field("batch", &self.msgs.len())
+            .finish()
+    }
+}
+
+impl RecvBuffers {
+    /// Rebuild the buffers only when the batch or frame size is changed.
+    fn prepare(&mut self, batch: usize, frame_size: usize) {
+        if self.msgs.len() == batch && self.frame_size == frame_size {
+            return;
+        }
+
+        self.frame_size = frame_size;
+        self.data = vec![0; batch * frame_size];
+        self.iovecs = self
+            .data
+            .chunks_exact_mut(frame_size)
+            .map(|buffer| iovec {
+                iov_base: buffer.as_mut_ptr() as *mut c_void,
+                iov_len: frame_size,
+            })
+            .collect();
+        self.msgs = self
+            .iovecs
+            .iter_mut()
+            .map(|iov| {
+                let mut msg: mmsghdr = unsafe { mem::zeroed() };
+                msg.msg_hdr.msg_iov = iov;
+                msg.msg_hdr.msg_iovlen = 1;
+                msg
+            })
+            .collect();
+    }
+
+    /// The received frames of the last `recvmmsg`, the unparsable ones are skipped.
+    fn frames(&self, count: usize) -> Vec<SocketCanFrame> {
+        self.msgs[..count]
+            .iter()
+            .zip(self.data.chunks_exact(self.frame_size))
+            .filter_map(
+                |(msg, buffer)| match parse_frame(buffer, msg.msg_len as usize) {
+                    Ok(frame) => Some(frame),
+                    Err(e) => {
+                        rsutil::warn!("RUST-CAN - a received frame is skipped: {}", e);
+                        None
+                    }
+                },
+            )
+            .collect()
+    }
+}
+
+impl AsRawFd for CanSocket {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct SocketCan {
-    pub(crate) sockets: Arc<HashMap<String, OwnedFd>>,
+    pub(crate) sockets: Arc<HashMap<String, CanSocket>>,
 }
 
 impl SocketCan {
@@ -35,203 +167,243 @@ impl SocketCan {
         }
     }
 
+    /// Open the socket of `channel` in non-blocking mode, it's registered with the tokio reactor
+    /// on the first async I/O, so the blocking I/O works without a runtime.
     pub fn init_channel(&mut self, channel: &str, canfd: bool) -> CanResult<()> {
         let addr =
             CanAddr::from_iface(channel).map_err(|e| CanError::InitializeError(e.to_string()))?;
 
-        let _ = raw_open_socket(&addr)
-            .and_then(|fd| set_fd_mode(fd, canfd))
+        let socket = raw_open_socket(&addr)
+            .map(|fd| unsafe { OwnedFd::from_raw_fd(fd) })
             .and_then(|fd| {
-                Arc::get_mut(&mut self.sockets)
-                    .ok_or(io::Error::last_os_error())?
-                    .insert(channel.to_owned(), unsafe { OwnedFd::from_raw_fd(fd) });
-                // Rc::get_mut(&mut self.sockets)
-                //     .ok_or(io::Error::last_os_error())?
-                //     .insert(channel.to_owned(), unsafe { OwnedFd::from_raw_fd(fd) });
-                Ok(())
+                set_fd_mode(fd.as_raw_fd(), canfd)?;
+                CanSocket::new(fd)
             })
-            .map_err(|_| CanError::InitializeError("device open failed".into()));
+            .map_err(|e| CanError::InitializeError(format!("device open failed: {}", e)))?;
+        Arc::get_mut(&mut self.sockets)
+            .ok_or(CanError::InitializeError("device is shared".into()))?
+            .insert(channel.to_owned(), socket);
 
         Ok(())
     }
 
+    /// Blocking read a single can frame, waiting until the read timeout if it's set.
+    #[inline]
     pub fn read(&self, channel: &str) -> CanResult<SocketCanFrame> {
-        match self.sockets.get(channel) {
-            Some(s) => {
-                let mut buffer = [0; XL_FRAME_SIZE];
-
-                let rd = unsafe {
-                    read(
-                        s.as_raw_fd(),
-                        &mut buffer as *mut _ as *mut c_void,
-                        XL_FRAME_SIZE,
-                    )
-                };
-                match rd as usize {
-                    FRAME_SIZE => {
-                        let frame = unsafe { *(&buffer as *const _ as *const can_frame) };
-                        let mut frame = SocketCanFrame::try_from(CanAnyFrame::from(frame))?;
-                        frame.set_direction(CanDirection::Receive);
-                        Ok(frame)
-                    }
-                    FD_FRAME_SIZE => {
-                        let frame = unsafe { *(&buffer as *const _ as *const canfd_frame) };
-                        let mut frame = SocketCanFrame::try_from(CanAnyFrame::from(frame))?;
-                        frame.set_direction(CanDirection::Receive);
-                        Ok(frame)
-                    }
-                    XL_FRAME_SIZE => {
-                        let frame = unsafe { *(&buffer as *const _ as *const canxl_frame) };
-                        let mut frame = SocketCanFrame::try_from(CanAnyFrame::from(frame))?;
-                        frame.set_direction(CanDirection::Receive);
-                        Ok(frame)
-                    }
-                    _ => Err(CanError::OperationError(
-                        io::Error::last_os_error().to_string(),
-                    )),
-                }
-            }
-            None => Err(CanError::channel_not_opened(channel)),
-        }
+        self.read_blocking(channel, self.read_timeout_of(channel)?)
     }
 
     /// Blocking read a single can frame with timeout.
+    #[inline]
     pub fn read_timeout(&self, channel: &str, timeout: Duration) -> CanResult<SocketCanFrame> {
-        match self.sockets.get(channel) {
-            Some(s) => {
-                use nix::poll::{poll, PollFd, PollFlags};
-                let borrowed_fd = unsafe { BorrowedFd::borrow_raw(s.as_raw_fd()) };
-                let pollfd = PollFd::new(borrowed_fd, PollFlags::POLLIN);
-
-                match poll::<u16>(&mut [pollfd], timeout.as_millis() as u16)
-                    .map_err(|e| CanError::OperationError(e.to_string()))?
-                {
-                    0 => Err(CanError::channel_timeout(channel)),
-                    _ => self.read(channel),
-                }
-            }
-            None => Err(CanError::channel_not_opened(channel)),
-        }
+        self.read_blocking(channel, Some(timeout))
+    }
+
+    /// Read a single can frame when the socket gets readable,
+    /// the worker thread of runtime isn't blocked while waiting.
+    pub async fn read_async(&self, channel: &str) -> CanResult<SocketCanFrame> {
+        let s = self.socket(channel)?;
+        let mut buffer = [0; XL_FRAME_SIZE];
+        let size = s
+            .socket
+            .async_io(Interest::READABLE, |fd| read_raw(fd, &mut buffer))
+            .await
+            .map_err(|e| io_error(channel, e))?;
+
+        parse_frame(&buffer, size)
     }
 
+    /// Blocking write a single can frame, waiting until the write timeout if it's set.
+    #[inline]
     pub fn write(&self, msg: SocketCanFrame) -> CanResult<()> {
-        let channel = msg.channel();
-        match self.sockets.get(&channel) {
-            Some(s) => {
-                let frame: CanAnyFrame = msg.into();
-                match frame {
-                    CanAnyFrame::Normal(f) | CanAnyFrame::Remote(f) | CanAnyFrame::Error(f) => {
-                        raw_write_frame(s.as_raw_fd(), &f, frame.size())
-                            .map_err(|e| CanError::OtherError(e.to_string()))
-                    }
-                    CanAnyFrame::FD(f) => raw_write_frame(s.as_raw_fd(), &f, frame.size())
-                        .map_err(|e| CanError::OtherError(e.to_string())),
-                    CanAnyFrame::XL(f) => raw_write_frame(s.as_raw_fd(), &f, frame.size())
-                        .map_err(|e| CanError::OtherError(e.to_string())),
-                }
-            }
-            None => Err(CanError::channel_not_opened(channel)),
-        }
+        let timeout = self.write_timeout_of(&msg.channel())?;
+        self.write_blocking(msg, timeout)
     }
 
-    /// Blocking write a single can frame, retrying until it gets sent successfully.
+    /// Blocking write a single can frame, waiting until the socket gets writable or timeout.
+    #[inline]
     pub fn write_timeout(&self, msg: SocketCanFrame, timeout: Duration) -> CanResult<()> {
+        self.write_blocking(msg, Some(timeout))
+    }
+
+    /// Write a single can frame when the socket gets writable,
+    /// the worker thread of runtime isn't blocked while waiting.
+    pub async fn write_async(&self, msg: SocketCanFrame) -> CanResult<()> {
         let channel = msg.channel();
+        let s = self.socket(&channel)?;
         let frame: CanAnyFrame = msg.into();
-        let start = Instant::now();
-        while start.elapsed() < timeout {
-            match self.sockets.get(&channel) {
-                Some(s) => {
-                    if let Err(e) = match frame {
-                        CanAnyFrame::Normal(f) | CanAnyFrame::Remote(f) | CanAnyFrame::Error(f) => {
-                            raw_write_frame(s.as_raw_fd(), &f, frame.size())
-                        }
-                        CanAnyFrame::FD(f) => raw_write_frame(s.as_raw_fd(), &f, frame.size()),
-                        CanAnyFrame::XL(f) => raw_write_frame(s.as_raw_fd(), &f, frame.size()),
-                    } {
-                        match e.kind() {
-                            io::ErrorKind::WouldBlock => {}
-                            io::ErrorKind::Other => {
-                                if !matches!(e.raw_os_error(), Some(errno) if errno == EINPROGRESS)
-                                {
-                                    return Err(CanError::OperationError(e.to_string()));
-                                }
-                            }
-                            _ => return Err(CanError::OperationError(e.to_string())),
-                        }
-                    } else {
-                        return Ok(());
-                    }
-                }
-                None => return Err(CanError::channel_not_opened(channel)),
+        s.socket
+            .async_io(Interest::WRITABLE, |fd| write_frame(fd, &frame))
+            .await
+            .map_err(|e| io_error(&channel, e))
+    }
+
+    /// Read the queued frames by `recvmmsg` when the socket gets readable,
+    /// at most the receive batch size of channel.
+    ///
+    /// The unparsable frames are skipped with a warning, so the result may be empty.
+    pub async fn read_batch_async(&self, channel: &str) -> CanResult<Vec<SocketCanFrame>> {
+        let s = self.socket(channel)?;
+        let batch = s.recv_batch.load(Ordering::Relaxed);
+        let frame_size = if s.xl.load(Ordering::Relaxed) {
+            XL_FRAME_SIZE
+        } else {
+            FD_FRAME_SIZE
+        };
+        s.socket
+            .async_io(Interest::READABLE, |fd| {
+                let mut buffers = s
+                    .recv_buffers
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                buffers.prepare(batch, frame_size);
+                let count = read_raw_batch(fd, &mut buffers.msgs)?;
+                Ok(buffers.frames(count))
+            })
+            .await
+            .map_err(|e| io_error(channel, e))
+    }
+
+    /// Write the frames by `sendmmsg` with `timeout`(ms) of all.
+    ///
+    /// The consecutive frames of the same channel are written by a single call,
+    /// and the rest are written when the socket gets writable again.
+    pub async fn transmit_batch(
+        &self,
+        msgs: Vec<SocketCanFrame>,
+        timeout: Option<u32>,
+    ) -> CanResult<()> {
+        let mut batches: Vec<(String, Vec<CanAnyFrame>)> = Vec::new();
+        for mut msg in msgs {
+            msg.set_direction(CanDirection::Transmit);
+            let channel = msg.channel();
+            match batches.last_mut() {
+                Some((chl, frames)) if *chl == channel => frames.push(msg.into()),
+                _ => batches.push((channel, vec![msg.into()])),
             }
         }
 
-        Err(CanError::channel_timeout(channel))
+        let write = async {
+            for (channel, frames) in &batches {
+                self.write_batch_async(channel, frames).await?;
+            }
+            Ok(())
+        };
+        match timeout {
+            Some(timeout) => time::timeout(Duration::from_millis(timeout as u64), write)
+                .await
+                .map_err(|_| CanError::TimeoutError("transmit batch".into()))?,
+            None => write.await,
+        }
     }
 
-    /// Change socket to non-blocking mode or back to blocking mode.
-    pub fn set_nonblocking(&self, channel: &str, nonblocking: bool) -> CanResult<()> {
-        match self.sockets.get(channel) {
-            Some(s) => {
-                // retrieve current flags
-                let oldfl = unsafe { fcntl(s.as_raw_fd(), F_GETFL) };
+    /// Set the maximum number of frames read by a receive of [`rs_can::CanDevice`],
+    /// it's [`DEFAULT_RECV_BATCH`] by default.
+    pub fn set_recv_batch(&self, channel: &str, batch: usize) -> CanResult<()> {
+        if batch == 0 {
+            return Err(CanError::other_error(
+                "the receive batch size must be positive",
+            ));
+        }
+        self.socket(channel)?
+            .recv_batch
+            .store(batch, Ordering::Relaxed);
+        Ok(())
+    }
 
-                if oldfl == -1 {
-                    return Err(CanError::OperationError(
-                        io::Error::last_os_error().to_string(),
-                    ));
-                }
+    async fn write_batch_async(&self, channel: &str, frames: &[CanAnyFrame]) -> CanResult<()> {
+        let s = self.socket(channel)?;
+        let mut sent = 0;
+        while sent < frames.len() {
+            sent += s
+                .socket
+                .async_io(Interest::WRITABLE, |fd| {
+                    write_raw_batch(fd, &frames[sent..])
+                })
+                .await
+                .map_err(|e| io_error(channel, e))?;
+        }
 
-                let newfl = if nonblocking {
-                    oldfl | O_NONBLOCK
-                } else {
-                    oldfl & !O_NONBLOCK
-                };
+        Ok(())
+    }
 
-                let ret = unsafe { fcntl(s.as_raw_fd(), F_SETFL, newfl) };
+    fn read_blocking(&self, channel: &str, timeout: Option<Duration>) -> CanResult<SocketCanFrame> {
+        let s = self.socket(channel)?;
+        let mut buffer = [0; XL_FRAME_SIZE];
+        let size = s
+            .socket
+            .poll_io(PollFlags::POLLIN, timeout, |fd| read_raw(fd, &mut buffer))
+            .map_err(|e| io_error(channel, e))?;
 
-                if ret != 0 {
-                    Err(CanError::OperationError(
-                        io::Error::last_os_error().to_string(),
-                    ))
-                } else {
-                    Ok(())
-                }
-            }
-            None => Err(CanError::channel_not_opened(channel)),
-        }
+        parse_frame(&buffer, size)
+    }
+
+    fn write_blocking(&self, msg: SocketCanFrame, timeout: Option<Duration>) -> CanResult<()> {
+        let channel = msg.channel();
+        let s = self.socket(&channel)?;
+        let frame: CanAnyFrame = msg.into();
+        s.socket
+            .poll_io(PollFlags::POLLOUT, timeout, |fd| write_frame(fd, &frame))
+            .map_err(|e| io_error(&channel, e))
     }
 
-    /// Sets the read timeout on the socket
+    #[inline]
+    fn socket(&self, channel: &str) -> CanResult<&CanSocket> {
+        self.sockets
+            .get(channel)
+            .ok_or_else(|| CanError::channel_not_opened(channel))
+    }
+
+    /// Change socket to non-blocking mode or back to blocking mode.
+    ///
+    /// The socket is non-blocking by default, and it must be kept for the async I/O of
+    /// [`rs_can::CanDevice`], otherwise the worker thread of runtime may be blocked.
+    pub fn set_nonblocking(&self, channel: &str, nonblocking: bool) -> CanResult<()> {
+        let s = self.socket(channel)?;
+        set_nonblocking(s.as_raw_fd(), nonblocking)
+            .map_err(|e| CanError::OperationError(e.to_string()))
+    }
+
+    /// Sets the read timeout of the channel.
     ///
-    /// For convenience, the result value can be checked using
-    /// `ShouldRetry::should_retry` when a timeout is set.
+    /// It's the timeout of [`SocketCan::read`] and the receive of [`rs_can::CanDevice`]
+    /// without timeout, a [`CanError::TimeoutError`] is returned when it's elapsed.
     pub fn set_read_timeout(&self, channel: &str, duration: Duration) -> CanResult<()> {
-        match self.sockets.get(channel) {
-            Some(s) => set_socket_option(
-                s.as_raw_fd(),
-                SOL_SOCKET,
-                SO_RCVTIMEO,
-                &c_timeval_new(duration),
-            )
-            .map_err(|e| CanError::OperationError(e.to_string())),
-            None => Err(CanError::channel_not_opened(channel)),
-        }
+        *self
+            .socket(channel)?
+            .read_timeout
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(duration);
+        Ok(())
     }
 
-    /// Sets the write timeout on the socket
+    /// Sets the write timeout of the channel.
+    ///
+    /// It's the timeout of [`SocketCan::write`] and the transmit of [`rs_can::CanDevice`]
+    /// without timeout, a [`CanError::TimeoutError`] is returned when it's elapsed.
     pub fn set_write_timeout(&self, channel: &str, duration: Duration) -> CanResult<()> {
-        match self.sockets.get(channel) {
-            Some(s) => set_socket_option(
-                s.as_raw_fd(),
-                SOL_SOCKET,
-                SO_SNDTIMEO,
-                &c_timeval_new(duration),
-            )
-            .map_err(|e| CanError::OperationError(e.to_string())),
-            None => Err(CanError::channel_not_opened(channel)),
-        }
+        *self
+            .socket(channel)?
+            .write_timeout
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(duration);
+        Ok(())
+    }
+
+    pub(crate) fn read_timeout_of(&self, channel: &str) -> CanResult<Option<Duration>> {
+        Ok(*self
+            .socket(channel)?
+            .read_timeout
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner))
+    }
+
+    pub(crate) fn write_timeout_of(&self, channel: &str) -> CanResult<Option<Duration>> {
+        Ok(*self
+            .socket(channel)?
+            .write_timeout
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner))
     }
 }
 
@@ -351,6 +523,44 @@ impl SocketCan {
         }
     }
 
+    /// Enable or disable CAN XL frames.
+    ///
+    /// The interface must be a CAN XL capable interface, e.g. a `vcan` with `mtu 2060`.
+    /// When enabled, the VCID of the transmitted frames is passed through and
+    /// the frames of all VCIDs are received if the kernel supports it.
+    pub fn set_xl_frames(&self, channel: &str, enabled: bool) -> CanResult<()> {
+        match self.sockets.get(channel) {
+            Some(s) => {
+                let xl_frames = c_int::from(enabled);
+                set_socket_option(s.as_raw_fd(), SOL_CAN_RAW, CAN_RAW_XL_FRAMES, &xl_frames)
+                    .map_err(|e| CanError::OperationError(e.to_string()))?;
+
+                if enabled {
+                    let options = CanRawVcidOptions {
+                        flags: CAN_RAW_XL_VCID_TX_PASS | CAN_RAW_XL_VCID_RX_FILTER,
+                        ..Default::default()
+                    };
+                    match set_socket_option(
+                        s.as_raw_fd(),
+                        SOL_CAN_RAW,
+                        CAN_RAW_XL_VCID_OPTS,
+                        &options,
+                    ) {
+                        Err(e) if e.raw_os_error() == Some(ENOPROTOOPT) => rsutil::warn!(
+                            "RUST-CAN - VCID options are not supported at channel: {}",
+                            channel
+                        ),
+                        r => r.map_err(|e| CanError::OperationError(e.to_string()))?,
+                    }
+                }
+                s.xl.store(enabled, Ordering::Relaxed);
+
+                Ok(())
+            }
+            None => Err(CanError::channel_not_opened(channel)),
+        }
+    }
+
     /// Enable or disable join filters.
     ///
     /// By default a frame is accepted if it matches any of the filters set
@@ -372,3 +582,404 @@ impl SocketCan {
         }
     }
 }
+
+impl SocketCan {
+    /// Get the interface index of an opened channel.
+    fn ifindex(&self, channel: &str) -> CanResult<u32> {
+        if !self.sockets.contains_key(channel) {
+            return Err(CanError::channel_not_opened(channel));
+        }
+
+        CanAddr::from_iface(channel)
+            .map(|addr| addr.as_ref().can_ifindex as u32)
+            .map_err(|e| CanError::OperationError(e.to_string()))
+    }
+
+    /// Read the CAN controller state of the interface via rtnetlink.
+    ///
+    /// Virtual interfaces(e.g. `vcan`) have no controller, [`BusState::Unknown`] is returned.
+    pub fn can_state(&self, channel: &str) -> CanResult<BusState> {
+        let data = netlink::link_info_data(self.ifindex(channel)?)
+            .map_err(|e| CanError::OperationError(e.to_string()))?;
+        let state = data
+            .as_deref()
+            .and_then(|data| netlink::find_attribute(data, netlink::IFLA_CAN_STATE))
+            .and_then(|state| state.try_into().ok())
+            .map(u32::from_ne_bytes);
+
+        Ok(match state {
+            Some(netlink::CAN_STATE_ERROR_ACTIVE) | Some(netlink::CAN_STATE_ERROR_WARNING) => {
+                BusState::ErrorActive
+            }
+            Some(netlink::CAN_STATE_ERROR_PASSIVE) => BusState::ErrorPassive,
+            Some(netlink::CAN_STATE_BUS_OFF) => BusState::BusOff,
+            _ => BusState::Unknown,
+        })
+    }
+
+    /// Read the bus error counters of the interface via rtnetlink.
+    ///
+    /// Return [`CanError::NotSupportedError`] when the driver doesn't report the counters.
+    pub fn berr_counter(&self, channel: &str) -> CanResult<ErrorCounters> {
+        let data = netlink::link_info_data(self.ifindex(channel)?)
+            .map_err(|e| CanError::OperationError(e.to_string()))?;
+        // struct can_berr_counter { __u16 txerr; __u16 rxerr; }
+        match data
+            .as_deref()
+            .and_then(|data| netlink::find_attribute(data, netlink::IFLA_CAN_BERR_COUNTER))
+        {
+            Some(&[tx0, tx1, rx0, rx1, ..]) => Ok(ErrorCounters {
+                tx: u16::from_ne_bytes([tx0, tx1]) as u32,
+                rx: u16::from_ne_bytes([rx0, rx1]) as u32,
+            }),
+            _ => Err(CanError::NotSupportedError),
+        }
+    }
+
+    /// Set the bit-timing of the interface via rtnetlink by the typed timing of `cfg`.
+    ///
+    /// It's the same as [`CanInterface::configure`], the other options of `cfg` are applied
+    /// and the interface is brought up too. It needs `CAP_NET_ADMIN` when reconfigured.
+    pub fn set_bit_timing(&self, channel: &str, cfg: &ChannelConfig) -> CanResult<()> {
+        self.ifindex(channel)?;
+        CanInterface::open(channel)?.configure(cfg)
+    }
+
+    /// Restart the CAN controller manually by `IFLA_CAN_RESTART`.
+    ///
+    /// The kernel only accepts it when the interface is bus-off, and it needs `CAP_NET_ADMIN`.
+    pub fn restart(&self, channel: &str) -> CanResult<()> {
+        netlink::set_can_info_data(self.ifindex(channel)?, |request| {
+            request.attr_u32(netlink::IFLA_CAN_RESTART, 1);
+        })
+        .map_err(|e| CanError::OperationError(e.to_string()))
+    }
+}
+
+fn read_raw(fd: &OwnedFd, buffer: &mut [u8; XL_FRAME_SIZE]) -> io::Result<usize> {
+    match unsafe {
+        read(
+            fd.as_raw_fd(),
+            buffer.as_mut_ptr() as *mut c_void,
+            XL_FRAME_SIZE,
+        )
+    } {
+        -1 => Err(io::Error::last_os_error()),
+        size => Ok(size as usize),
+    }
+}
+
+fn parse_frame(buffer: &[u8], size: usize) -> CanResult<SocketCanFrame> {
+    let frame = match size {
+        // CAN XL frames are variable in size, identified by the XLF flag.
+        size if (CANXL_HDR_SIZE + 1..=XL_FRAME_SIZE).contains(&size)
+            && buffer.len() >= XL_FRAME_SIZE
+            && buffer[CANXL_FLAGS_OFFSET] & CanXlFlags::XLF.bits() != 0 =>
+        {
+            let frame = unsafe { ptr::read_unaligned(buffer.as_ptr() as *const canxl_frame) };
+            CanAnyFrame::from(frame)
+        }
+        FRAME_SIZE => {
+            let frame = unsafe { ptr::read_unaligned(buffer.as_ptr() as *const can_frame) };
+            CanAnyFrame::from(frame)
+        }
+        FD_FRAME_SIZE => {
+            let frame = unsafe { ptr::read_unaligned(buffer.as_ptr() as *const canfd_frame) };
+            CanAnyFrame::from(frame)
+        }
+        size => {
+            return Err(CanError::OperationError(format!(
+                "invalid frame size: {}",
+                size
+            )))
+        }
+    };
+
+    let mut frame = SocketCanFrame::try_from(frame)?;
+    frame.set_direction(CanDirection::Receive);
+    Ok(frame)
+}
+
+/// Read the queued frames into the buffers of `msgs`, and return the number of them.
+fn read_raw_batch(fd: &OwnedFd, msgs: &mut [mmsghdr]) -> io::Result<usize> {
+    let ret = unsafe {
+        recvmmsg(
+            fd.as_raw_fd(),
+            msgs.as_mut_ptr(),
+            msgs.len() as c_uint,
+            MSG_DONTWAIT,
+            ptr::null_mut(),
+        )
+    };
+    match ret {
+        -1 => Err(io::Error::last_os_error()),
+        count => Ok(count as usize),
+    }
+}
+
+/// Write the frames, and return the number of the written ones.
+fn write_raw_batch(fd: &OwnedFd, frames: &[CanAnyFrame]) -> io::Result<usize> {
+    let mut iovecs = frames
+        .iter()
+        .take(SEND_BATCH_MAX)
+        .map(|frame| iovec {
+            iov_base: frame_ptr(frame) as *mut c_void,
+            iov_len: frame.size(),
+        })
+        .collect::<Vec<_>>();
+    let mut msgs = iovecs
+        .iter_mut()
+        .map(|iov| {
+            let mut msg: mmsghdr = unsafe { mem::zeroed() };
+            msg.msg_hdr.msg_iov = iov;
+            msg.msg_hdr.msg_iovlen = 1;
+            msg
+        })
+        .collect::<Vec<_>>();
+
+    let ret = unsafe {
+        sendmmsg(
+            fd.as_raw_fd(),
+            msgs.as_mut_ptr(),
+            msgs.len() as c_uint,
+            MSG_DONTWAIT,
+        )
+    };
+    match ret {
+        -1 => Err(io::Error::last_os_error()),
+        count => Ok(count as usize),
+    }
+}
+
+#[inline]
+fn write_frame(fd: &OwnedFd, frame: &CanAnyFrame) -> io::Result<()> {
+    raw_write_frame(fd.as_raw_fd(), frame_ptr(frame), frame.size())
+}
+
+fn frame_ptr(frame: &CanAnyFrame) -> *const c_void {
+    match frame {
+        CanAnyFrame::Normal(f) | CanAnyFrame::Remote(f) | CanAnyFrame::Error(f) => {
+            f as *const can_frame as *const c_void
+        }
+        CanAnyFrame::FD(f) => f as *const canfd_frame as *const c_void,
+        CanAnyFrame::XL(f) => f as *const canxl_frame as *const c_void,
+    }
+}
+
+#[inline]
+fn io_error(channel: &str, e: io::Error) -> CanError {
+    match e.kind() {
+        io::ErrorKind::TimedOut => CanError::channel_timeout(channel),
+        _ => CanError::OperationError(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rs_can::{CanDevice, CanId};
+    use std::{os::unix::net::UnixDatagram, time::Instant};
+
+    /// A device whose channel is a datagram socket pair, a CAN frame is a datagram too.
+    fn device_with_peer() -> (SocketCan, UnixDatagram) {
+        let (socket, peer) = UnixDatagram::pair().unwrap();
+        let socket = CanSocket::new(OwnedFd::from(socket)).unwrap();
+        let device = SocketCan {
+            sockets: Arc::new(HashMap::from([("can0".to_owned(), socket)])),
+        };
+
+        (device, peer)
+    }
+
+    #[test]
+    fn blocking_io_works_without_runtime() {
+        let (device, peer) = device_with_peer();
+        assert!(matches!(
+            device.read_timeout("can0", Duration::from_millis(10)),
+            Err(CanError::TimeoutError(_))
+        ));
+
+        let mut frame = [0u8; FRAME_SIZE];
+        frame[..4].copy_from_slice(&0x123u32.to_ne_bytes());
+        peer.send(&frame).unwrap();
+        let frame = device.read("can0").unwrap();
+        assert_eq!(frame.id().as_raw(), 0x123);
+
+        let mut frame = SocketCanFrame::new_can(CanId::try_from(0x456_u32).unwrap(), &[]).unwrap();
+        frame.set_channel("can0".into());
+        device.write(frame).unwrap();
+        let mut buffer = [0u8; XL_FRAME_SIZE];
+        assert_eq!(peer.recv(&mut buffer).unwrap(), FRAME_SIZE);
+
+        // the async I/O needs a runtime still
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()
+            .unwrap();
+        assert!(runtime
+            .block_on(device.receive("can0".into(), Some(10)))
+            .is_err_and(|e| matches!(e, CanError::TimeoutError(_))));
+    }
+
+    #[tokio::test]
+    async fn default_timeouts_are_applied() {
+        let (device, _peer) = device_with_peer();
+        device
+            .set_read_timeout("can0", Duration::from_millis(20))
+            .unwrap();
+        device
+            .set_write_timeout("can0", Duration::from_millis(20))
+            .unwrap();
+        assert!(matches!(
+            device.read("can0"),
+            Err(CanError::TimeoutError(_))
+        ));
+        assert!(matches!(
+            device.receive("can0".into(), None).await,
+            Err(CanError::TimeoutError(_))
+        ));
+
+        // the peer never reads, so the socket gets unwritable finally
+        let mut frame = SocketCanFrame::new_can(CanId::try_from(0x456_u32).unwrap(), &[]).unwrap();
+        frame.set_channel("can0".into());
+        let ret = (0..10_000)
+            .map(|_| device.write(frame.clone()))
+            .find(Result::is_err);
+        assert!(matches!(ret, Some(Err(CanError::TimeoutError(_)))));
+        assert!(matches!(
+            device.transmit(frame, None).await,
+            Err(CanError::TimeoutError(_))
+        ));
+    }
+
+    #[test]
+    fn async_io_follows_runtime() {
+        let (device, peer) = device_with_peer();
+        let runtime = || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_io()
+                .enable_time()
+                .build()
+                .unwrap()
+        };
+        assert!(runtime()
+            .block_on(device.receive("can0".into(), Some(10)))
+            .is_err_and(|e| matches!(e, CanError::TimeoutError(_))));
+
+        // the first runtime is dropped, the socket is registered with the second one
+        let mut frame = [0u8; FRAME_SIZE];
+        frame[..4].copy_from_slice(&0x123u32.to_ne_bytes());
+        peer.send(&frame).unwrap();
+        let frames = runtime()
+            .block_on(device.receive("can0".into(), Some(50)))
+            .unwrap();
+        assert_eq!(frames[0].id().as_raw(), 0x123);
+    }
+
+    #[tokio::test]
+    async fn receive_awaits_readiness() {
+        let (device, peer) = device_with_peer();
+        let start = Instant::now();
+        assert!(matches!(
+            device.receive("can0".into(), Some(50)).await,
+            Err(CanError::TimeoutError(_))
+        ));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert!(matches!(
+            device.read_timeout("can0", Duration::from_millis(10)),
+            Err(CanError::TimeoutError(_))
+        ));
+
+        let sender = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let mut frame = [0u8; FRAME_SIZE];
+            frame[..4].copy_from_slice(&0x123u32.to_ne_bytes());
+            frame[4] = 2;
+            frame[8..10].copy_from_slice(&[0xAA, 0x55]);
+            peer.send(&frame).unwrap();
+            peer
+        });
+        let frames = device.receive("can0".into(), None).await.unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].id().as_raw(), 0x123);
+        assert_eq!(frames[0].data(), &[0xAA, 0x55]);
+        assert_eq!(frames[0].channel(), "can0");
+
+        let peer = sender.await.unwrap();
+        let mut frame = SocketCanFrame::new_can(CanId::try_from(0x456_u32).unwrap(), &[1]).unwrap();
+        frame.set_channel("can0".into());
+        device.transmit(frame, Some(50)).await.unwrap();
+        let mut buffer = [0u8; XL_FRAME_SIZE];
+        assert_eq!(peer.recv(&mut buffer).unwrap(), FRAME_SIZE);
+        assert_eq!(&buffer[..4], &0x456u32.to_ne_bytes());
+    }
+
+    #[tokio::test]
+    async fn frames_are_batched() {
+        let (device, peer) = device_with_peer();
+        for id in 0..5u32 {
+            let mut frame = [0u8; FRAME_SIZE];
+            frame[..4].copy_from_slice(&id.to_ne_bytes());
+            peer.send(&frame).unwrap();
+        }
+        assert!(device.set_recv_batch("can0", 0).is_err());
+        device.set_recv_batch("can0", 3).unwrap();
+
+        let frames = device.receive("can0".into(), Some(50)).await.unwrap();
+        assert_eq!(
+            frames.iter().map(|f| f.id().as_raw()).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        let frames = device.receive("can0".into(), Some(50)).await.unwrap();
+        assert_eq!(frames.len(), 2);
+
+        let frames = (0..4u32)
+            .map(|id| {
+                let mut frame =
+                    SocketCanFrame::new_can(CanId::try_from(id).unwrap(), &[id as u8]).unwrap();
+                frame.set_channel("can0".into());
+                frame
+            })
+            .collect();
+        device.transmit_batch(frames, Some(50)).await.unwrap();
+        let mut buffer = [0u8; XL_FRAME_SIZE];
+        for id in 0..4u32 {
+            assert_eq!(peer.recv(&mut buffer).unwrap(), FRAME_SIZE);
+            assert_eq!(&buffer[..4], &id.to_ne_bytes());
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_frame_is_skipped() {
+        let (device, peer) = device_with_peer();
+        let mut frame = [0u8; FRAME_SIZE];
+        frame[..4].copy_from_slice(&0x11u32.to_ne_bytes());
+        peer.send(&frame).unwrap();
+        peer.send(&[0u8; 3]).unwrap();
+        frame[..4].copy_from_slice(&0x22u32.to_ne_bytes());
+        peer.send(&frame).unwrap();
+
+        let frames = device.receive("can0".into(), Some(50)).await.unwrap();
+        assert_eq!(
+            frames.iter().map(|f| f.id().as_raw()).collect::<Vec<_>>(),
+            vec![0x11, 0x22]
+        );
+
+        // the buffers are reused until the batch size is changed
+        let data = device.sockets["can0"]
+            .recv_buffers
+            .lock()
+            .unwrap()
+            .data
+            .as_ptr();
+        peer.send(&frame).unwrap();
+        assert_eq!(
+            device.receive("can0".into(), Some(50)).await.unwrap().len(),
+            1
+        );
+        let buffers = device.sockets["can0"].recv_buffers.lock().unwrap();
+        assert_eq!(buffers.data.as_ptr(), data);
+        assert_eq!(buffers.data.len(), DEFAULT_RECV_BATCH * FD_FRAME_SIZE);
+    }
+}
@@ -1,11 +1,15 @@
-use crate::{socket, FD_FRAME_SIZE, FRAME_SIZE, XL_FRAME_SIZE};
-use libc::{can_frame, canfd_frame, canxl_frame};
+use crate::{socket, FD_FRAME_SIZE, FRAME_SIZE};
+use libc::{can_frame, canfd_frame, canxl_frame, CANXL_HDR_SIZE};
 use rs_can::{
-    can_utils, CanDirection, CanError, CanFdFlags, CanFrame, CanId, CanKind, CanResult,
-    FrameFormat, IdentifierFlags, Timestamp, EFF_MASK, MAX_FD_FRAME_SIZE, MAX_FRAME_SIZE,
+    can_utils, AnyFrame, CanDirection, CanError, CanFdFlags, CanFrame, CanId, CanKind, CanResult,
+    CanXlFlags, CanXlParams, FrameFormat, GenericFrame, IdentifierFlags, StandardId, Timestamp,
+    EFF_MASK, MAX_FD_FRAME_SIZE, MAX_FRAME_SIZE, MAX_XL_FRAME_SIZE, SFF_MASK,
 };
 use std::fmt::{Display, Formatter};
 
+/// The VCID is carried in the bits 16..24 of `canxl_frame.prio`.
+const CANXL_VCID_OFFSET: u32 = 16;
+
 pub enum CanAnyFrame {
     Normal(can_frame),
     Remote(can_frame),
@@ -21,7 +25,7 @@ impl CanAnyFrame {
             Self::Remote(_) => FRAME_SIZE,
             Self::Error(_) => FRAME_SIZE,
             Self::FD(_) => FD_FRAME_SIZE,
-            Self::XL(_) => XL_FRAME_SIZE,
+            Self::XL(f) => CANXL_HDR_SIZE + f.len as usize,
         }
     }
 }
@@ -67,6 +71,7 @@ pub struct SocketCanFrame {
     pub(crate) direction: CanDirection,
     pub(crate) bitrate_switch: bool,
     pub(crate) error_state_indicator: bool,
+    pub(crate) xl_params: Option<CanXlParams>,
 }
 
 impl SocketCanFrame {
@@ -104,6 +109,7 @@ impl TryFrom<CanAnyFrame> for SocketCanFrame {
                 direction: Default::default(),
                 bitrate_switch: false,
                 error_state_indicator: false,
+                xl_params: None,
             }),
             CanAnyFrame::Remote(f) => Ok(Self {
                 timestamp: None,
@@ -118,6 +124,7 @@ impl TryFrom<CanAnyFrame> for SocketCanFrame {
                 direction: Default::default(),
                 bitrate_switch: false,
                 error_state_indicator: false,
+                xl_params: None,
             }),
             CanAnyFrame::Error(f) => Ok(Self {
                 timestamp: None,
@@ -132,6 +139,7 @@ impl TryFrom<CanAnyFrame> for SocketCanFrame {
                 direction: Default::default(),
                 bitrate_switch: false,
                 error_state_indicator: false,
+                xl_params: None,
             }),
             CanAnyFrame::FD(f) => Ok(Self {
                 timestamp: None,
@@ -146,8 +154,40 @@ impl TryFrom<CanAnyFrame> for SocketCanFrame {
                 direction: Default::default(),
                 bitrate_switch: f.flags & 0x01 != 0,
                 error_state_indicator: f.flags & 0x02 != 0,
+                xl_params: None,
             }),
-            CanAnyFrame::XL(_) => Err(CanError::NotImplementedError),
+            CanAnyFrame::XL(f) => {
+                let length = f.len as usize;
+                if f.flags & CanXlFlags::XLF.bits() == 0
+                    || !(1..=MAX_XL_FRAME_SIZE).contains(&length)
+                {
+                    return Err(CanError::InvalidFrame(format!(
+                        "invalid CAN XL frame with flags: {:02x}, length: {}",
+                        f.flags, length
+                    )));
+                }
+
+                Ok(Self {
+                    timestamp: None,
+                    arbitration_id: f.prio & SFF_MASK,
+                    is_extended_id: false,
+                    is_remote_frame: false,
+                    is_error_frame: false,
+                    channel: Default::default(),
+                    length,
+                    data: f.data[..length].to_vec(),
+                    kind: CanKind::XL,
+                    direction: Default::default(),
+                    bitrate_switch: false,
+                    error_state_indicator: false,
+                    xl_params: Some(CanXlParams {
+                        sdu_type: f.sdt,
+                        vcid: (f.prio >> CANXL_VCID_OFFSET) as u8,
+                        acceptance: f.af,
+                        sec: f.flags & CanXlFlags::SEC.bits() != 0,
+                    }),
+                })
+            }
         }
     }
 }
@@ -194,7 +234,24 @@ impl Into<CanAnyFrame> for SocketCanFrame {
 
                 CanAnyFrame::FD(frame)
             }
-            CanKind::XL => todo!("XL is not supported now!"),
+            CanKind::XL => {
+                let mut frame = socket::canxl_frame_default();
+                let params = self.xl_params.unwrap_or_default();
+
+                let length = self.data.len();
+                frame.prio =
+                    (self.arbitration_id & SFF_MASK) | (params.vcid as u32) << CANXL_VCID_OFFSET;
+                frame.flags = CanXlFlags::XLF.bits();
+                if params.sec {
+                    frame.flags |= CanXlFlags::SEC.bits();
+                }
+                frame.sdt = params.sdu_type;
+                frame.len = length as u16;
+                frame.af = params.acceptance;
+                frame.data[..length].copy_from_slice(&self.data);
+
+                CanAnyFrame::XL(frame)
+            }
         }
     }
 }
@@ -221,6 +278,7 @@ impl CanFrame for SocketCanFrame {
             direction: Default::default(),
             bitrate_switch: false,
             error_state_indicator: false,
+            xl_params: None,
         })
     }
 
@@ -245,6 +303,7 @@ impl CanFrame for SocketCanFrame {
             direction: Default::default(),
             bitrate_switch: false,
             error_state_indicator: false,
+            xl_params: None,
         })
     }
 
@@ -267,6 +326,54 @@ impl CanFrame for SocketCanFrame {
             direction: Default::default(),
             bitrate_switch: flags.contains(CanFdFlags::BRS),
             error_state_indicator: flags.contains(CanFdFlags::ESI),
+            xl_params: None,
+        })
+    }
+
+    fn new_can_xl(priority: u16, data: &[u8], params: CanXlParams) -> CanResult<Self> {
+        let length = data.len();
+        if !(1..=MAX_XL_FRAME_SIZE).contains(&length) {
+            return Err(CanError::InvalidDLC(length));
+        }
+        let priority = StandardId::new(priority)?;
+
+        Ok(Self {
+            timestamp: None,
+            arbitration_id: priority.as_raw() as u32,
+            is_extended_id: false,
+            is_remote_frame: false,
+            is_error_frame: false,
+            channel: Default::default(),
+            length,
+            data: data.to_vec(),
+            kind: CanKind::XL,
+            direction: Default::default(),
+            bitrate_switch: false,
+            error_state_indicator: false,
+            xl_params: Some(params),
+        })
+    }
+
+    fn new_error(id: CanId, data: &[u8]) -> CanResult<Self> {
+        let length = data.len();
+        if length > MAX_FRAME_SIZE {
+            return Err(CanError::InvalidDLC(length));
+        }
+
+        Ok(Self {
+            timestamp: None,
+            arbitration_id: id.as_raw(),
+            is_extended_id: id.is_extended(),
+            is_remote_frame: false,
+            is_error_frame: true,
+            channel: Default::default(),
+            length,
+            data: data.to_vec(),
+            kind: CanKind::Classical,
+            direction: Default::default(),
+            bitrate_switch: false,
+            error_state_indicator: false,
+            xl_params: None,
         })
     }
 
@@ -305,6 +412,10 @@ impl CanFrame for SocketCanFrame {
         self.length
     }
 
+    fn xl_params(&self) -> Option<CanXlParams> {
+        self.xl_params
+    }
+
     fn direction(&self) -> CanDirection {
         self.direction
     }
@@ -355,6 +466,7 @@ impl PartialEq for SocketCanFrame {
                 && (self.is_extended_id == other.is_extended_id)
                 && (self.is_error_frame == other.is_error_frame)
                 && (self.error_state_indicator == other.error_state_indicator)
+                && (self.xl_params == other.xl_params)
                 && (self.data == other.data)
         }
     }
@@ -365,3 +477,31 @@ impl Display for SocketCanFrame {
         <dyn CanFrame<Channel = String> as Display>::fmt(self, f)
     }
 }
+
+impl From<SocketCanFrame> for AnyFrame {
+    fn from(frame: SocketCanFrame) -> Self {
+        AnyFrame::from_frame(&frame)
+    }
+}
+
+impl TryFrom<AnyFrame> for SocketCanFrame {
+    type Error = CanError;
+
+    fn try_from(frame: AnyFrame) -> Result<Self, Self::Error> {
+        frame.to_frame()
+    }
+}
+
+impl From<SocketCanFrame> for GenericFrame<String> {
+    fn from(frame: SocketCanFrame) -> Self {
+        GenericFrame::from_frame(&frame)
+    }
+}
+
+impl TryFrom<GenericFrame<String>> for SocketCanFrame {
+    type Error = CanError;
+
+    fn try_from(frame: GenericFrame<String>) -> Result<Self, Self::Error> {
+        frame.into_frame()
+    }
+}
@@ -0,0 +1,461 @@
+use crate::{
+    netlink::{self, Bittiming, Link},
+    BERR_REPORTING, FD_NON_ISO, RESTART_MS, TXQUEUELEN,
+};
+use libc::IFF_UP;
+use rs_can::{BitTimingConfig, CanError, CanResult, ChannelConfig, ChannelMode, TdcConfig};
+use std::{ffi::CString, io};
+
+/// The default termination resistance when the driver doesn't report the supported values.
+const TERMINATION_OHM: u16 = 120;
+
+/// The CAN controller mode of `struct can_ctrlmode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CanCtrlMode {
+    Loopback,
+    ListenOnly,
+    OneShot,
+    BerrReporting,
+    /// It must be set with both bit-timings, see [`CanInterface::set_fd_bitrate`].
+    Fd,
+    FdNonIso,
+}
+
+impl CanCtrlMode {
+    #[inline]
+    fn flag(self) -> u32 {
+        match self {
+            Self::Loopback => netlink::CAN_CTRLMODE_LOOPBACK,
+            Self::ListenOnly => netlink::CAN_CTRLMODE_LISTENONLY,
+            Self::OneShot => netlink::CAN_CTRLMODE_ONE_SHOT,
+            Self::BerrReporting => netlink::CAN_CTRLMODE_BERR_REPORTING,
+            Self::Fd => netlink::CAN_CTRLMODE_FD,
+            Self::FdNonIso => netlink::CAN_CTRLMODE_FD_NON_ISO,
+        }
+    }
+}
+
+/// A CAN network interface configured via rtnetlink.
+///
+/// All the setters need `CAP_NET_ADMIN`, and the CAN specific attributes
+/// can only be changed when the interface is down.
+#[derive(Debug, Clone)]
+pub struct CanInterface {
+    name: String,
+    ifindex: u32,
+}
+
+impl CanInterface {
+    pub fn open(name: &str) -> CanResult<Self> {
+        let c_name = CString::new(name).map_err(|e| CanError::InitializeError(e.to_string()))?;
+        match unsafe { libc::if_nametoindex(c_name.as_ptr()) } {
+            0 => Err(CanError::InitializeError(format!(
+                "interface {} is not found",
+                name
+            ))),
+            ifindex => Ok(Self {
+                name: name.to_owned(),
+                ifindex,
+            }),
+        }
+    }
+
+    /// Create a `vcan` interface.
+    pub fn create_vcan(name: &str) -> CanResult<Self> {
+        netlink::create_link(name, "vcan", None).map_err(|e| Self::error(name, e))?;
+        Self::open(name)
+    }
+
+    /// Create a pair of `vxcan` interfaces, the frames sent on one are received on the other.
+    pub fn create_vxcan(name: &str, peer: &str) -> CanResult<(Self, Self)> {
+        netlink::create_link(name, "vxcan", Some(peer)).map_err(|e| Self::error(name, e))?;
+        Ok((Self::open(name)?, Self::open(peer)?))
+    }
+
+    /// Delete the interface, the peer of `vxcan` is deleted too.
+    pub fn delete(self) -> CanResult<()> {
+        netlink::delete_link(self.ifindex).map_err(|e| Self::error(&self.name, e))
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    pub fn ifindex(&self) -> u32 {
+        self.ifindex
+    }
+
+    pub fn is_up(&self) -> CanResult<bool> {
+        self.link().map(|link| link.flags() & IFF_UP as u32 > 0)
+    }
+
+    pub fn set_up(&self) -> CanResult<()> {
+        netlink::set_link_up(self.ifindex, true).map_err(|e| Self::error(&self.name, e))
+    }
+
+    pub fn set_down(&self) -> CanResult<()> {
+        netlink::set_link_up(self.ifindex, false).map_err(|e| Self::error(&self.name, e))
+    }
+
+    /// Set the bitrate and `sample_point`(in one-tenth of a percent) of classical CAN,
+    /// the bit-timing is calculated by the kernel.
+    pub fn set_bitrate(&self, bitrate: u32, sample_point: Option<u16>) -> CanResult<()> {
+        let bittiming = Bittiming::with_bitrate(bitrate, sample_point.unwrap_or_default() as u32);
+        self.set_can_info_data(|request| {
+            request.attr(netlink::IFLA_CAN_BITTIMING, &bittiming.to_bytes());
+        })
+    }
+
+    /// Enable CAN-FD with the bitrates and sample points of both phases.
+    pub fn set_fd_bitrate(
+        &self,
+        bitrate: u32,
+        sample_point: Option<u16>,
+        data_bitrate: u32,
+        data_sample_point: Option<u16>,
+    ) -> CanResult<()> {
+        let nominal = Bittiming::with_bitrate(bitrate, sample_point.unwrap_or_default() as u32);
+        let data =
+            Bittiming::with_bitrate(data_bitrate, data_sample_point.unwrap_or_default() as u32);
+        let ctrlmode = ctrlmode(netlink::CAN_CTRLMODE_FD, netlink::CAN_CTRLMODE_FD);
+        self.set_can_info_data(|request| {
+            request
+                .attr(netlink::IFLA_CAN_CTRLMODE, &ctrlmode)
+                .attr(netlink::IFLA_CAN_BITTIMING, &nominal.to_bytes())
+                .attr(netlink::IFLA_CAN_DATA_BITTIMING, &data.to_bytes());
+        })
+    }
+
+    pub fn set_ctrlmode(&self, mode: CanCtrlMode, enable: bool) -> CanResult<()> {
+        let flag = mode.flag();
+        let ctrlmode = ctrlmode(flag, if enable { flag } else { 0 });
+        self.set_can_info_data(|request| {
+            request.attr(netlink::IFLA_CAN_CTRLMODE, &ctrlmode);
+        })
+    }
+
+    /// Set the delay of automatic restart after bus-off, 0 disables it.
+    pub fn set_restart_ms(&self, ms: u32) -> CanResult<()> {
+        self.set_can_info_data(|request| {
+            request.attr_u32(netlink::IFLA_CAN_RESTART_MS, ms);
+        })
+    }
+
+    /// Set the termination resistance in ohm, 0 disables it.
+    pub fn set_termination(&self, ohm: u16) -> CanResult<()> {
+        self.set_can_info_data(|request| {
+            request.attr(netlink::IFLA_CAN_TERMINATION, &ohm.to_ne_bytes());
+        })
+    }
+
+    pub fn set_txqueuelen(&self, len: u32) -> CanResult<()> {
+        netlink::set_txqueuelen(self.ifindex, len).map_err(|e| Self::error(&self.name, e))
+    }
+
+    /// Apply the channel configuration and bring the interface up.
+    ///
+    /// The interface is only reconfigured(down and up) when the configuration differs
+    /// from the current one, so no privilege is needed for a configured interface.
+    /// The bitrate is ignored by virtual interfaces which have no controller, and the
+    /// controller settings(bit-timing, TDC, listen-only or one-shot mode) are rejected by them.
+    ///
+    /// [`ChannelMode::Loopback`] is the loopback of socket(`CAN_RAW_LOOPBACK`) set by
+    /// [`SocketCan::set_loopback`](crate::SocketCan::set_loopback), it leaves the loopback
+    /// mode of controller as it is.
+    pub fn configure(&self, cfg: &ChannelConfig) -> CanResult<()> {
+        self.apply(cfg, true)
+    }
+
+    /// Apply the channel configuration when a device is created.
+    ///
+    /// Unlike [`Self::configure`], a missing `CAP_NET_ADMIN` is only warned,
+    /// so a device is still opened with the current configuration of interface.
+    #[inline]
+    pub(crate) fn configure_requested(&self, cfg: &ChannelConfig) -> CanResult<()> {
+        self.apply(cfg, false)
+    }
+
+    /// Whether `cfg` has the settings which can't be applied without CAN controller.
+    #[inline]
+    fn requests_controller(cfg: &ChannelConfig) -> bool {
+        matches!(
+            cfg.mode,
+            Some(ChannelMode::ListenOnly | ChannelMode::OneShot)
+        ) || cfg.nominal_timing.is_some()
+            || cfg.data_timing.is_some()
+            || cfg.tdc.is_some()
+    }
+
+    fn apply(&self, cfg: &ChannelConfig, strict: bool) -> CanResult<()> {
+        let link = self.link()?;
+        let txqueuelen = cfg.get_other::<u32>(TXQUEUELEN)?.filter(|&len| {
+            netlink::u32_attribute(link.attributes(), libc::IFLA_TXQLEN) != Some(len)
+        });
+        let up = link.flags() & IFF_UP as u32 > 0;
+        let Some(data) = link.info_data() else {
+            if Self::requests_controller(cfg) {
+                return Err(CanError::NotSupportedError);
+            }
+            return self.write(strict, || {
+                if let Some(len) = txqueuelen {
+                    netlink::set_txqueuelen(self.ifindex, len)?;
+                }
+                match up {
+                    true => Ok(()),
+                    false => netlink::set_link_up(self.ifindex, true),
+                }
+            });
+        };
+
+        let clock = netlink::u32_attribute(data, netlink::IFLA_CAN_CLOCK);
+        let bittiming = |bitrate: u32, timing: Option<BitTimingConfig>, ty: u16| match timing {
+            Some(timing) => {
+                let limits =
+                    netlink::bittiming_const(data, ty).ok_or(CanError::NotSupportedError)?;
+                let clock = clock.ok_or(CanError::NotSupportedError)?;
+                let timing = timing.resolve(Some(clock), bitrate, &limits)?;
+                Ok(Bittiming::with_timing(&timing, clock))
+            }
+            None => Ok(Bittiming::with_bitrate(bitrate, 0)),
+        };
+        let nominal = bittiming(
+            cfg.nominal_bitrate,
+            cfg.nominal_timing,
+            netlink::IFLA_CAN_BITTIMING_CONST,
+        )?;
+        let data_timing = match cfg.data_bitrate {
+            Some(bitrate) => Some(bittiming(
+                bitrate,
+                cfg.data_timing,
+                netlink::IFLA_CAN_DATA_BITTIMING_CONST,
+            )?),
+            None if cfg.data_timing.is_some() || cfg.tdc.is_some() => {
+                return Err(CanError::other_error(
+                    "the data bitrate is required by data bit-timing and TDC",
+                ))
+            }
+            None => None,
+        };
+
+        let (mask, flags) = Self::ctrlmode_of(cfg, data_timing.is_some())?;
+        let restart_ms = cfg.get_other::<u32>(RESTART_MS)?;
+        let termination = match cfg.termination {
+            Some(enable) => {
+                match netlink::find_attribute(data, netlink::IFLA_CAN_TERMINATION_CONST) {
+                    Some(values) => Some(match enable {
+                        true => values
+                            .chunks_exact(2)
+                            .map(|v| u16::from_ne_bytes([v[0], v[1]]))
+                            .find(|&v| v > 0)
+                            .unwrap_or(TERMINATION_OHM),
+                        false => 0,
+                    }),
+                    None => {
+                        rsutil::warn!(
+                            "SocketCAN - the termination of {} is not configurable",
+                            self.name
+                        );
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let current = |ty: u16| netlink::find_attribute(data, ty).and_then(Bittiming::from_bytes);
+        let applied = current(netlink::IFLA_CAN_BITTIMING).is_some_and(|v| nominal.is_applied(&v))
+            && data_timing.map_or(true, |timing| {
+                current(netlink::IFLA_CAN_DATA_BITTIMING).is_some_and(|v| timing.is_applied(&v))
+            })
+            // struct can_ctrlmode { __u32 mask; __u32 flags; }
+            && netlink::find_attribute(data, netlink::IFLA_CAN_CTRLMODE)
+                .and_then(|v| v.get(4..8))
+                .is_some_and(|v| u32::from_ne_bytes(v.try_into().unwrap()) & mask == flags)
+            && restart_ms.map_or(true, |ms| {
+                netlink::u32_attribute(data, netlink::IFLA_CAN_RESTART_MS) == Some(ms)
+            })
+            && termination.map_or(true, |ohm| {
+                netlink::find_attribute(data, netlink::IFLA_CAN_TERMINATION)
+                    .is_some_and(|v| v.get(..2) == Some(&ohm.to_ne_bytes()[..]))
+            });
+
+        self.write(strict, || {
+            if let Some(len) = txqueuelen {
+                netlink::set_txqueuelen(self.ifindex, len)?;
+            }
+            if applied {
+                return match up {
+                    true => Ok(()),
+                    false => netlink::set_link_up(self.ifindex, true),
+                };
+            }
+
+            if up {
+                netlink::set_link_up(self.ifindex, false)?;
+            }
+            netlink::set_can_info_data(self.ifindex, |request| {
+                request
+                    .attr(netlink::IFLA_CAN_CTRLMODE, &ctrlmode(mask, flags))
+                    .attr(netlink::IFLA_CAN_BITTIMING, &nominal.to_bytes());
+                if let Some(timing) = data_timing {
+                    request.attr(netlink::IFLA_CAN_DATA_BITTIMING, &timing.to_bytes());
+                }
+                if let Some(TdcConfig::Manual { value, offset }) = cfg.tdc {
+                    request
+                        .begin_nested(netlink::IFLA_CAN_TDC)
+                        .attr_u32(netlink::IFLA_CAN_TDC_TDCV, value)
+                        .attr_u32(netlink::IFLA_CAN_TDC_TDCO, offset)
+                        .end_nested();
+                }
+                if let Some(ms) = restart_ms {
+                    request.attr_u32(netlink::IFLA_CAN_RESTART_MS, ms);
+                }
+                if let Some(ohm) = termination {
+                    request.attr(netlink::IFLA_CAN_TERMINATION, &ohm.to_ne_bytes());
+                }
+            })?;
+            netlink::set_link_up(self.ifindex, true)
+        })
+    }
+
+    /// The `mask` and `flags` of `struct can_ctrlmode` from the channel configuration.
+    ///
+    /// Only the modes set by `cfg` are in the mask, and CAN-FD only when `fd` is requested,
+    /// so the other modes of the interface are kept. The loopback mode of controller
+    /// isn't in the mask, [`ChannelMode::Loopback`] is the loopback of socket.
+    fn ctrlmode_of(cfg: &ChannelConfig, fd: bool) -> CanResult<(u32, u32)> {
+        let (mut mask, mut flags) = match cfg.mode {
+            Some(mode) => (
+                netlink::CAN_CTRLMODE_LISTENONLY | netlink::CAN_CTRLMODE_ONE_SHOT,
+                match mode {
+                    ChannelMode::ListenOnly => netlink::CAN_CTRLMODE_LISTENONLY,
+                    ChannelMode::OneShot => netlink::CAN_CTRLMODE_ONE_SHOT,
+                    ChannelMode::Normal | ChannelMode::Loopback => 0,
+                },
+            ),
+            None => (0, 0),
+        };
+        if fd {
+            mask |= netlink::CAN_CTRLMODE_FD;
+            flags |= netlink::CAN_CTRLMODE_FD;
+        }
+        for (name, mode) in [
+            (BERR_REPORTING, CanCtrlMode::BerrReporting),
+            (FD_NON_ISO, CanCtrlMode::FdNonIso),
+        ] {
+            if let Some(enable) = cfg.get_other::<bool>(name)? {
+                mask |= mode.flag();
+                if enable {
+                    flags |= mode.flag();
+                }
+            }
+        }
+        if let Some(tdc) = cfg.tdc {
+            mask |= netlink::CAN_CTRLMODE_TDC_AUTO | netlink::CAN_CTRLMODE_TDC_MANUAL;
+            flags |= match tdc {
+                TdcConfig::Disabled => 0,
+                TdcConfig::Auto => netlink::CAN_CTRLMODE_TDC_AUTO,
+                TdcConfig::Manual { .. } => netlink::CAN_CTRLMODE_TDC_MANUAL,
+            };
+        }
+
+        Ok((mask, flags))
+    }
+
+    #[inline]
+    fn link(&self) -> CanResult<Link> {
+        Link::query(self.ifindex).map_err(|e| Self::error(&self.name, e))
+    }
+
+    #[inline]
+    fn set_can_info_data(&self, build: impl FnOnce(&mut netlink::LinkRequest)) -> CanResult<()> {
+        netlink::set_can_info_data(self.ifindex, build).map_err(|e| Self::error(&self.name, e))
+    }
+
+    /// Run the netlink requests of [`Self::apply`], a missing privilege is only warned
+    /// when it's not `strict`.
+    fn write(&self, strict: bool, f: impl FnOnce() -> io::Result<()>) -> CanResult<()> {
+        match f() {
+            Err(e) if !strict && e.kind() == io::ErrorKind::PermissionDenied => {
+                rsutil::warn!(
+                    "SocketCAN - {} is not configured without CAP_NET_ADMIN: {}",
+                    self.name,
+                    e
+                );
+                Ok(())
+            }
+            ret => ret.map_err(|e| Self::error(&self.name, e)),
+        }
+    }
+
+    #[inline]
+    fn error(name: &str, e: io::Error) -> CanError {
+        CanError::OperationError(format!("configure {} failed: {}", name, e))
+    }
+}
+
+/// `struct can_ctrlmode { __u32 mask; __u32 flags; }`
+#[inline]
+fn ctrlmode(mask: u32, flags: u32) -> Vec<u8> {
+    [mask.to_ne_bytes(), flags.to_ne_bytes()].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ctrlmode_is_built_from_config() {
+        let mut cfg = ChannelConfig::new(500_000);
+        cfg.set_channel_mode(ChannelMode::ListenOnly)
+            .set_tdc(TdcConfig::Auto)
+            .add_other(BERR_REPORTING, Box::new(true));
+        let (mask, flags) = CanInterface::ctrlmode_of(&cfg, true).unwrap();
+        assert_eq!(
+            flags,
+            netlink::CAN_CTRLMODE_LISTENONLY
+                | netlink::CAN_CTRLMODE_FD
+                | netlink::CAN_CTRLMODE_BERR_REPORTING
+                | netlink::CAN_CTRLMODE_TDC_AUTO
+        );
+        assert_eq!(mask & netlink::CAN_CTRLMODE_FD_NON_ISO, 0);
+        assert_eq!(mask & flags, flags);
+
+        // the modes not set by the configuration are kept
+        let cfg = ChannelConfig::new(500_000);
+        assert_eq!(CanInterface::ctrlmode_of(&cfg, false).unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn loopback_is_not_controller_mode() {
+        let mut cfg = ChannelConfig::new(500_000);
+        cfg.set_channel_mode(ChannelMode::Loopback);
+        assert!(!CanInterface::requests_controller(&cfg));
+        let (mask, flags) = CanInterface::ctrlmode_of(&cfg, true).unwrap();
+        assert_eq!(mask & netlink::CAN_CTRLMODE_LOOPBACK, 0);
+        assert_eq!(flags, netlink::CAN_CTRLMODE_FD);
+    }
+
+    #[test]
+    fn virtual_link_rejects_controller_mode() {
+        // `lo` is up and has no CAN controller.
+        let lo = CanInterface::open("lo").unwrap();
+        assert!(lo.configure(&ChannelConfig::new(500_000)).is_ok());
+
+        let mut cfg = ChannelConfig::new(500_000);
+        cfg.set_channel_mode(ChannelMode::ListenOnly);
+        assert!(matches!(
+            lo.configure(&cfg),
+            Err(CanError::NotSupportedError)
+        ));
+        assert!(matches!(
+            lo.configure_requested(&cfg),
+            Err(CanError::NotSupportedError)
+        ));
+        cfg.set_channel_mode(ChannelMode::Loopback);
+        assert!(lo.configure_requested(&cfg).is_ok());
+        assert!(CanInterface::open("rs-can-none").is_err());
+    }
+}
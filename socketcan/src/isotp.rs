@@ -0,0 +1,398 @@
+//! The ISO-TP socket implemented by Linux kernel(`CAN_ISOTP`), see `linux/can/isotp.h`.
+//!
+//! The kernel module `can-isotp` is required, it's merged since Linux 5.10.
+
+use crate::{
+    isotp_open_socket, set_socket_option, CanAddr, NonBlockingSocket, CAN_ISOTP_SF_BROADCAST,
+    CAN_ISOTP_TX_PADDING, CAN_ISOTP_TX_STMIN, SOL_CAN_ISOTP,
+};
+use libc::{canid_t, read, write, CANFD_BRS, CANFD_MTU, CAN_EFF_FLAG, EINPROGRESS};
+use nix::poll::PollFlags;
+use rs_can::{
+    isotp::{Address, AddressType, IsoTpConfig},
+    CanError, CanId, CanResult, MAX_FRAME_SIZE,
+};
+use std::{
+    io,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        raw::c_void,
+    },
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+use tokio::{io::Interest, time};
+
+/// The max message length received by default kernel config.
+const MAX_PDU_SIZE: usize = 8300;
+
+/// `struct can_isotp_options`
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IsoTpOptions {
+    /// set flags for isotp behaviour.
+    pub flags: u32,
+    /// frame transmission time (N_As/N_Ar), time in nano secs
+    pub frame_txtime: u32,
+    /// set address for extended addressing
+    pub ext_address: u8,
+    /// set content of padding byte (tx)
+    pub txpad_content: u8,
+    /// set content of padding byte (rx)
+    pub rxpad_content: u8,
+    /// set address for extended addressing
+    pub rx_ext_address: u8,
+}
+
+/// `struct can_isotp_fc_options`
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IsoTpFcOptions {
+    /// blocksize provided in FC frame, 0 = off
+    pub bs: u8,
+    /// separation time provided in FC frame
+    pub stmin: u8,
+    /// max. number of wait frame transmiss., 0 = omit FC N_PDU WT
+    pub wftmax: u8,
+}
+
+/// `struct can_isotp_ll_options`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct IsoTpLlOptions {
+    /// generated & accepted CAN frame type, `CAN_MTU` or `CANFD_MTU`
+    pub mtu: u8,
+    /// tx link layer data length in bytes
+    pub tx_dl: u8,
+    /// set into struct canfd_frame.flags at frame creation
+    pub tx_flags: u8,
+}
+
+impl From<&IsoTpConfig> for IsoTpOptions {
+    fn from(config: &IsoTpConfig) -> Self {
+        let mut opts = Self::default();
+        if let Some(padding) = config.padding {
+            opts.flags |= CAN_ISOTP_TX_PADDING;
+            opts.txpad_content = padding;
+        }
+        opts
+    }
+}
+
+impl From<&IsoTpConfig> for IsoTpFcOptions {
+    fn from(config: &IsoTpConfig) -> Self {
+        Self {
+            bs: config.block_size,
+            stmin: config.st_min,
+            wftmax: config.wft_max,
+        }
+    }
+}
+
+impl IsoTpLlOptions {
+    /// The CAN-FD link layer options, `None` for classical CAN.
+    pub fn from_config(config: &IsoTpConfig) -> Option<Self> {
+        match config.tx_dl {
+            ..=MAX_FRAME_SIZE => None,
+            tx_dl => Some(Self {
+                mtu: CANFD_MTU as u8,
+                tx_dl: tx_dl as u8,
+                tx_flags: match config.bitrate_switch {
+                    true => CANFD_BRS as u8,
+                    false => 0,
+                },
+            }),
+        }
+    }
+}
+
+#[inline]
+fn raw_id(id: CanId) -> canid_t {
+    match id {
+        CanId::Standard(_) => id.as_raw(),
+        CanId::Extended(_) => id.as_raw() | CAN_EFF_FLAG,
+    }
+}
+
+/// An ISO-TP socket, the segmentation and flow control are handled by kernel.
+///
+/// The N_As/N_Bs/N_Cr timeouts of [`IsoTpConfig`] are ignored, the kernel uses
+/// its own timers.
+///
+/// # Example
+/// ```ignore
+/// let address = Address::new(CanId::from_bits(0x7E0, None)?, CanId::from_bits(0x7E8, None)?);
+/// let socket = IsoTpSocket::open("can0", &address, &IsoTpConfig::default())?;
+/// socket.write(&[0x22, 0xF1, 0x90])?;
+/// let response = socket.read_timeout(Duration::from_secs(1))?;
+/// ```
+#[derive(Debug)]
+pub struct IsoTpSocket {
+    iface: String,
+    socket: NonBlockingSocket,
+    write_timeout: Mutex<Option<Duration>>,
+}
+
+impl IsoTpSocket {
+    /// Open the socket with the physical identifiers of `address`.
+    #[inline]
+    pub fn open(iface: &str, address: &Address, config: &IsoTpConfig) -> CanResult<Self> {
+        Self::open_to(iface, address, config, AddressType::Physical)
+    }
+
+    /// Open the socket with the physical or functional identifiers of `address`,
+    /// the functional socket only transmits single frames.
+    pub fn open_to(
+        iface: &str,
+        address: &Address,
+        config: &IsoTpConfig,
+        address_type: AddressType,
+    ) -> CanResult<Self> {
+        let mut opts = IsoTpOptions::from(config);
+        let tx_id = match address_type {
+            AddressType::Physical => address.tx_id,
+            AddressType::Functional => {
+                opts.flags |= CAN_ISOTP_SF_BROADCAST;
+                address
+                    .functional_id
+                    .ok_or(CanError::operation_error("ISO-TP functional id is not set"))?
+            }
+        };
+
+        Self::open_with(
+            iface,
+            tx_id,
+            address.rx_id,
+            &opts,
+            &config.into(),
+            IsoTpLlOptions::from_config(config).as_ref(),
+        )
+    }
+
+    /// Open the socket with the raw options.
+    pub fn open_with(
+        iface: &str,
+        tx_id: CanId,
+        rx_id: CanId,
+        opts: &IsoTpOptions,
+        fc_opts: &IsoTpFcOptions,
+        ll_opts: Option<&IsoTpLlOptions>,
+    ) -> CanResult<Self> {
+        let mut addr =
+            CanAddr::from_iface(iface).map_err(|e| CanError::InitializeError(e.to_string()))?;
+        addr.set_tp_ids(raw_id(tx_id), raw_id(rx_id));
+
+        let socket = isotp_open_socket(&addr, opts, fc_opts, ll_opts)
+            .and_then(|fd| NonBlockingSocket::new(unsafe { OwnedFd::from_raw_fd(fd) }))
+            .map_err(|e| CanError::InitializeError(e.to_string()))?;
+
+        Ok(Self {
+            iface: iface.to_owned(),
+            socket,
+            write_timeout: Default::default(),
+        })
+    }
+
+    #[inline]
+    pub fn iface(&self) -> &str {
+        &self.iface
+    }
+
+    /// Write a message, it's blocked until the message is queued by kernel
+    /// or the write timeout is elapsed.
+    pub fn write(&self, data: &[u8]) -> CanResult<()> {
+        self.socket
+            .poll_io(PollFlags::POLLOUT, self.write_timeout(), |fd| {
+                write_raw(fd, data)
+            })
+            .map_err(|e| self.io_error(e))
+    }
+
+    /// Blocking read a message.
+    #[inline]
+    pub fn read(&self) -> CanResult<Vec<u8>> {
+        self.read_blocking(None)
+    }
+
+    /// Blocking read a message with timeout.
+    #[inline]
+    pub fn read_timeout(&self, timeout: Duration) -> CanResult<Vec<u8>> {
+        self.read_blocking(Some(timeout))
+    }
+
+    /// Sets the timeout of [`IsoTpSocket::write`] and [`IsoTpSocket::transmit`].
+    pub fn set_write_timeout(&self, duration: Duration) -> CanResult<()> {
+        *self
+            .write_timeout
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(duration);
+        Ok(())
+    }
+
+    /// Sets the STmin(ns) of transmitting, the kernel uses it instead of the received STmin
+    /// only when [`CAN_ISOTP_FORCE_TXSTMIN`](crate::CAN_ISOTP_FORCE_TXSTMIN) is set
+    /// in the flags of [`IsoTpOptions`].
+    pub fn set_tx_stmin(&self, nanos: u32) -> CanResult<()> {
+        set_socket_option(
+            self.socket.as_raw_fd(),
+            SOL_CAN_ISOTP,
+            CAN_ISOTP_TX_STMIN,
+            &nanos,
+        )
+        .map_err(|e| CanError::OperationError(e.to_string()))
+    }
+
+    /// Transmit a message, the same as [`IsoTpChannel::transmit`](rs_can::isotp::IsoTpChannel::transmit).
+    ///
+    /// It waits until the socket gets writable or the write timeout is elapsed,
+    /// the worker thread of runtime isn't blocked.
+    pub async fn transmit(&self, data: &[u8]) -> CanResult<()> {
+        let write = self
+            .socket
+            .async_io(Interest::WRITABLE, |fd| write_raw(fd, data));
+        match self.write_timeout() {
+            Some(timeout) => time::timeout(timeout, write)
+                .await
+                .map_err(|_| CanError::channel_timeout(&self.iface))?,
+            None => write.await,
+        }
+        .map_err(|e| self.io_error(e))
+    }
+
+    /// Receive a message, the same as [`IsoTpChannel::receive`](rs_can::isotp::IsoTpChannel::receive).
+    ///
+    /// It waits until the socket gets readable, the worker thread of runtime isn't blocked.
+    pub async fn receive(&self, timeout: Option<u32>) -> CanResult<Vec<u8>> {
+        let mut buffer = vec![0; MAX_PDU_SIZE];
+        let read = self
+            .socket
+            .async_io(Interest::READABLE, |fd| read_raw(fd, &mut buffer));
+        let size = match timeout {
+            Some(timeout) => time::timeout(Duration::from_millis(timeout as u64), read)
+                .await
+                .map_err(|_| CanError::channel_timeout(&self.iface))?,
+            None => read.await,
+        }
+        .map_err(|e| self.io_error(e))?;
+
+        buffer.truncate(size);
+        Ok(buffer)
+    }
+
+    fn read_blocking(&self, timeout: Option<Duration>) -> CanResult<Vec<u8>> {
+        let mut buffer = vec![0; MAX_PDU_SIZE];
+        let size = self
+            .socket
+            .poll_io(PollFlags::POLLIN, timeout, |fd| read_raw(fd, &mut buffer))
+            .map_err(|e| self.io_error(e))?;
+
+        buffer.truncate(size);
+        Ok(buffer)
+    }
+
+    #[inline]
+    fn write_timeout(&self) -> Option<Duration> {
+        *self
+            .write_timeout
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn io_error(&self, e: io::Error) -> CanError {
+        match e.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+                CanError::channel_timeout(&self.iface)
+            }
+            _ if e.raw_os_error() == Some(EINPROGRESS) => CanError::channel_timeout(&self.iface),
+            _ => CanError::OperationError(e.to_string()),
+        }
+    }
+}
+
+fn write_raw(fd: &OwnedFd, data: &[u8]) -> io::Result<()> {
+    match unsafe { write(fd.as_raw_fd(), data.as_ptr().cast(), data.len()) } {
+        size if size as usize == data.len() => Ok(()),
+        -1 => Err(io::Error::last_os_error()),
+        size => Err(io::Error::other(format!(
+            "only {} of {} bytes are written",
+            size,
+            data.len()
+        ))),
+    }
+}
+
+fn read_raw(fd: &OwnedFd, buffer: &mut [u8]) -> io::Result<usize> {
+    match unsafe {
+        read(
+            fd.as_raw_fd(),
+            buffer.as_mut_ptr() as *mut c_void,
+            buffer.len(),
+        )
+    } {
+        -1 => Err(io::Error::last_os_error()),
+        size => Ok(size as usize),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixDatagram;
+
+    /// A socket whose peer is a datagram socket, an ISO-TP message is a datagram too.
+    fn socket_with_peer() -> (IsoTpSocket, UnixDatagram) {
+        let (socket, peer) = UnixDatagram::pair().unwrap();
+        let socket = IsoTpSocket {
+            iface: "can0".into(),
+            socket: NonBlockingSocket::new(OwnedFd::from(socket)).unwrap(),
+            write_timeout: Default::default(),
+        };
+
+        (socket, peer)
+    }
+
+    #[tokio::test]
+    async fn receive_awaits_readiness() {
+        let (socket, peer) = socket_with_peer();
+        assert!(matches!(
+            socket.receive(Some(20)).await,
+            Err(CanError::TimeoutError(_))
+        ));
+        assert!(matches!(
+            socket.read_timeout(Duration::from_millis(10)),
+            Err(CanError::TimeoutError(_))
+        ));
+
+        // the sender runs on the same thread, so the receive mustn't block it
+        let sender = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            peer.send(&[0x62, 0xF1, 0x90]).unwrap();
+            peer
+        });
+        assert_eq!(socket.receive(None).await.unwrap(), vec![0x62, 0xF1, 0x90]);
+
+        let peer = sender.await.unwrap();
+        socket.transmit(&[0x22, 0xF1, 0x90]).await.unwrap();
+        socket.write(&[0x3E, 0x00]).unwrap();
+        let mut buffer = [0; MAX_PDU_SIZE];
+        assert_eq!(peer.recv(&mut buffer).unwrap(), 3);
+        assert_eq!(&buffer[..3], &[0x22, 0xF1, 0x90]);
+        assert_eq!(peer.recv(&mut buffer).unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn write_timeout_is_applied() {
+        let (socket, _peer) = socket_with_peer();
+        socket.set_write_timeout(Duration::from_millis(20)).unwrap();
+
+        // the peer never reads, so the socket gets unwritable finally
+        let ret = (0..10_000)
+            .map(|_| socket.write(&[0x3E, 0x00]))
+            .find(Result::is_err);
+        assert!(matches!(ret, Some(Err(CanError::TimeoutError(_)))));
+        assert!(matches!(
+            socket.transmit(&[0x3E, 0x00]).await,
+            Err(CanError::TimeoutError(_))
+        ));
+    }
+}
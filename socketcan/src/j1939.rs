@@ -0,0 +1,352 @@
+//! The J1939 socket implemented by Linux kernel(`CAN_J1939`), see `linux/can/j1939.h`.
+//!
+//! The kernel module `can-j1939` is required, it's merged since Linux 5.4.
+
+use crate::{j1939_open_socket, set_socket_option, CanAddr, NonBlockingSocket};
+use libc::{
+    cmsghdr, iovec, msghdr, recvmsg, sendto, sockaddr_can, socklen_t, CMSG_DATA, CMSG_FIRSTHDR,
+    CMSG_NXTHDR, CMSG_SPACE, J1939_NO_ADDR, J1939_NO_PGN, MSG_TRUNC, SCM_J1939_DEST_ADDR, SCM_J1939_PRIO,
+    SOL_CAN_J1939, SOL_SOCKET, SO_BROADCAST, SO_J1939_PROMISC, SO_J1939_SEND_PRIO,
+};
+use nix::poll::PollFlags;
+use rs_can::{
+    j1939::{J1939Message, Name, Pgn, DEFAULT_PRIORITY, GLOBAL_ADDRESS},
+    CanError, CanResult,
+};
+use std::{
+    io, mem,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        raw::{c_int, c_void},
+    },
+    ptr,
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+use tokio::{io::Interest, time};
+
+/// The max message length of transport protocol.
+const MAX_PDU_SIZE: usize = 1785;
+/// `J1939_NO_NAME`, which is `c_ulong` in libc.
+const NO_NAME: u64 = 0;
+/// The size of control messages buffer, the kernel sends the destination address(`u8`),
+/// the destination name(`u64`) and the priority(`u8`).
+const CMSG_SIZE: usize = unsafe { (CMSG_SPACE(1) * 2 + CMSG_SPACE(8)) as usize };
+
+/// The buffer of control messages, which is aligned for `cmsghdr`.
+#[repr(C, align(8))]
+struct ControlBuffer([u8; CMSG_SIZE]);
+
+/// A J1939 socket, the address claiming and transport protocol are handled by kernel.
+///
+/// The socket bound with a [`Name`] uses the address claimed by the name, the address
+/// claimed message must be sent by the user.
+///
+/// # Example
+/// ```ignore
+/// let socket = J1939Socket::open("can0", None, 0x80, None)?;
+/// socket.set_broadcast(true)?;
+/// socket.send_to(Pgn::new(0xFEEC)?, GLOBAL_ADDRESS, b"VIN*")?;
+/// let message = socket.recv_timeout(Duration::from_secs(1))?;
+/// ```
+#[derive(Debug)]
+pub struct J1939Socket {
+    iface: String,
+    socket: NonBlockingSocket,
+    write_timeout: Mutex<Option<Duration>>,
+}
+
+impl J1939Socket {
+    /// Open the socket with the static `address`, or the address claimed by `name`.
+    ///
+    /// Only the messages of `pgn` are received if it's set.
+    pub fn open(iface: &str, name: Option<Name>, address: u8, pgn: Option<Pgn>) -> CanResult<Self> {
+        let mut addr =
+            CanAddr::from_iface(iface).map_err(|e| CanError::InitializeError(e.to_string()))?;
+        addr.set_j1939(
+            name.map_or(NO_NAME, u64::from),
+            pgn.map_or(J1939_NO_PGN, |pgn| pgn.as_raw()),
+            address,
+        );
+
+        let socket = j1939_open_socket(&addr)
+            .and_then(|fd| NonBlockingSocket::new(unsafe { OwnedFd::from_raw_fd(fd) }))
+            .map_err(|e| CanError::InitializeError(e.to_string()))?;
+
+        Ok(Self {
+            iface: iface.to_owned(),
+            socket,
+            write_timeout: Default::default(),
+        })
+    }
+
+    #[inline]
+    pub fn iface(&self) -> &str {
+        &self.iface
+    }
+
+    /// Enable sending to and receiving from global address.
+    pub fn set_broadcast(&self, enable: bool) -> CanResult<()> {
+        self.set_option(SOL_SOCKET, SO_BROADCAST, &(enable as c_int))
+    }
+
+    /// Receive the messages to the other addresses.
+    pub fn set_promisc(&self, enable: bool) -> CanResult<()> {
+        self.set_option(SOL_CAN_J1939, SO_J1939_PROMISC, &(enable as c_int))
+    }
+
+    /// Sets the priority of sending, `0..=7`.
+    pub fn set_send_priority(&self, priority: u8) -> CanResult<()> {
+        self.set_option(SOL_CAN_J1939, SO_J1939_SEND_PRIO, &(priority as c_int))
+    }
+
+    /// Sets the timeout of [`J1939Socket::send_to`] and [`J1939Socket::transmit`].
+    pub fn set_write_timeout(&self, duration: Duration) -> CanResult<()> {
+        *self
+            .write_timeout
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(duration);
+        Ok(())
+    }
+
+    /// Send a message, the message longer than 8 bytes is transferred by transport protocol.
+    ///
+    /// It's blocked until the message is queued by kernel or the write timeout is elapsed.
+    pub fn send_to(&self, pgn: Pgn, destination: u8, data: &[u8]) -> CanResult<()> {
+        let addr = send_addr(pgn, destination);
+        self.socket
+            .poll_io(PollFlags::POLLOUT, self.write_timeout(), |fd| {
+                send_raw(fd, &addr, data)
+            })
+            .map_err(|e| self.io_error(e))
+    }
+
+    /// Blocking receive a message.
+    #[inline]
+    pub fn recv(&self) -> CanResult<J1939Message> {
+        self.recv_blocking(None)
+    }
+
+    /// Blocking receive a message with timeout.
+    #[inline]
+    pub fn recv_timeout(&self, timeout: Duration) -> CanResult<J1939Message> {
+        self.recv_blocking(Some(timeout))
+    }
+
+    /// Transmit a message with the priority set by [`J1939Socket::set_send_priority`].
+    ///
+    /// It waits until the socket gets writable or the write timeout is elapsed,
+    /// the worker thread of runtime isn't blocked.
+    pub async fn transmit(&self, pgn: Pgn, destination: u8, data: &[u8]) -> CanResult<()> {
+        let addr = send_addr(pgn, destination);
+        let send = self
+            .socket
+            .async_io(Interest::WRITABLE, |fd| send_raw(fd, &addr, data));
+        match self.write_timeout() {
+            Some(timeout) => time::timeout(timeout, send)
+                .await
+                .map_err(|_| CanError::channel_timeout(&self.iface))?,
+            None => send.await,
+        }
+        .map_err(|e| self.io_error(e))
+    }
+
+    /// Receive a message, the same as [`J1939Node::receive`](rs_can::j1939::J1939Node::receive).
+    ///
+    /// It waits until the socket gets readable, the worker thread of runtime isn't blocked.
+    pub async fn receive(&self, timeout: Option<u32>) -> CanResult<J1939Message> {
+        let mut buffer = vec![0u8; MAX_PDU_SIZE];
+        let recv = self
+            .socket
+            .async_io(Interest::READABLE, |fd| recv_raw(fd, &mut buffer));
+        let received = match timeout {
+            Some(timeout) => time::timeout(Duration::from_millis(timeout as u64), recv)
+                .await
+                .map_err(|_| CanError::channel_timeout(&self.iface))?,
+            None => recv.await,
+        }
+        .map_err(|e| self.io_error(e))?;
+
+        received.into_message(buffer)
+    }
+
+    fn recv_blocking(&self, timeout: Option<Duration>) -> CanResult<J1939Message> {
+        let mut buffer = vec![0u8; MAX_PDU_SIZE];
+        let received = self
+            .socket
+            .poll_io(PollFlags::POLLIN, timeout, |fd| recv_raw(fd, &mut buffer))
+            .map_err(|e| self.io_error(e))?;
+
+        received.into_message(buffer)
+    }
+
+    #[inline]
+    fn write_timeout(&self) -> Option<Duration> {
+        *self
+            .write_timeout
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn io_error(&self, e: io::Error) -> CanError {
+        match e.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+                CanError::channel_timeout(&self.iface)
+            }
+            _ => CanError::OperationError(e.to_string()),
+        }
+    }
+
+    #[inline]
+    fn set_option<T>(&self, level: c_int, name: c_int, val: &T) -> CanResult<()> {
+        set_socket_option(self.socket.as_raw_fd(), level, name, val)
+            .map_err(|e| CanError::OperationError(e.to_string()))
+    }
+}
+
+/// The source and control messages of a received message.
+struct Received {
+    size: usize,
+    pgn: u32,
+    source: u8,
+    priority: u8,
+    destination: u8,
+}
+
+impl Received {
+    fn into_message(self, mut buffer: Vec<u8>) -> CanResult<J1939Message> {
+        buffer.truncate(self.size);
+        Ok(J1939Message {
+            priority: self.priority,
+            pgn: Pgn::new(self.pgn)?,
+            source: self.source,
+            destination: self.destination,
+            data: buffer,
+        })
+    }
+}
+
+#[inline]
+fn send_addr(pgn: Pgn, destination: u8) -> CanAddr {
+    let mut addr = CanAddr::default();
+    addr.set_j1939(NO_NAME, pgn.as_raw(), destination);
+    addr
+}
+
+fn send_raw(fd: &OwnedFd, addr: &CanAddr, data: &[u8]) -> io::Result<()> {
+    let ret = unsafe {
+        sendto(
+            fd.as_raw_fd(),
+            data.as_ptr().cast(),
+            data.len(),
+            0,
+            addr.as_sockaddr_ptr(),
+            CanAddr::len() as socklen_t,
+        )
+    };
+    match ret {
+        size if size as usize == data.len() => Ok(()),
+        -1 => Err(io::Error::last_os_error()),
+        size => Err(io::Error::other(format!(
+            "only {} of {} bytes are sent",
+            size,
+            data.len()
+        ))),
+    }
+}
+
+fn recv_raw(fd: &OwnedFd, buffer: &mut [u8]) -> io::Result<Received> {
+    let mut addr: sockaddr_can = unsafe { mem::zeroed() };
+    let mut control = ControlBuffer([0; CMSG_SIZE]);
+    let mut iov = iovec {
+        iov_base: buffer.as_mut_ptr() as *mut c_void,
+        iov_len: buffer.len(),
+    };
+    let mut msg: msghdr = unsafe { mem::zeroed() };
+    msg.msg_name = &mut addr as *mut _ as *mut c_void;
+    msg.msg_namelen = CanAddr::len() as socklen_t;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.0.as_mut_ptr() as *mut c_void;
+    msg.msg_controllen = CMSG_SIZE;
+
+    let ret = unsafe { recvmsg(fd.as_raw_fd(), &mut msg, 0) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if msg.msg_flags & MSG_TRUNC != 0 {
+        return Err(io::Error::other(format!(
+            "the message is truncated to {} bytes",
+            buffer.len()
+        )));
+    }
+
+    let (mut priority, mut destination) = (DEFAULT_PRIORITY, GLOBAL_ADDRESS);
+    unsafe {
+        let mut cmsg: *mut cmsghdr = CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == SOL_CAN_J1939 {
+                match (*cmsg).cmsg_type {
+                    SCM_J1939_DEST_ADDR => destination = ptr::read(CMSG_DATA(cmsg)),
+                    SCM_J1939_PRIO => priority = ptr::read(CMSG_DATA(cmsg)),
+                    _ => {}
+                }
+            }
+            cmsg = CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    if destination == J1939_NO_ADDR {
+        destination = GLOBAL_ADDRESS;
+    }
+
+    let j1939 = unsafe { addr.can_addr.j1939 };
+    Ok(Received {
+        size: ret as usize,
+        pgn: j1939.pgn,
+        source: j1939.addr,
+        priority,
+        destination,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixDatagram;
+
+    #[tokio::test]
+    async fn receive_awaits_readiness() {
+        let (socket, peer) = UnixDatagram::pair().unwrap();
+        let socket = J1939Socket {
+            iface: "can0".into(),
+            socket: NonBlockingSocket::new(OwnedFd::from(socket)).unwrap(),
+            write_timeout: Default::default(),
+        };
+        assert!(matches!(
+            socket.receive(Some(20)).await,
+            Err(CanError::TimeoutError(_))
+        ));
+        assert!(matches!(
+            socket.recv_timeout(Duration::from_millis(10)),
+            Err(CanError::TimeoutError(_))
+        ));
+
+        // the sender runs on the same thread, so the receive mustn't block it
+        let sender = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            peer.send(b"VIN*").unwrap();
+            peer
+        });
+        let message = socket.receive(None).await.unwrap();
+        assert_eq!(message.data, b"VIN*");
+        assert_eq!(message.priority, DEFAULT_PRIORITY);
+        assert_eq!(message.destination, GLOBAL_ADDRESS);
+
+        let peer = sender.await.unwrap();
+        peer.send(&[0x01; 9]).unwrap();
+        assert_eq!(socket.recv().unwrap().data, vec![0x01; 9]);
+
+        peer.send(&[0x01; MAX_PDU_SIZE + 1]).unwrap();
+        assert!(matches!(socket.recv(), Err(CanError::OperationError(_))));
+    }
+}
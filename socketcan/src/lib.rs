@@ -1,11 +1,20 @@
+mod constants;
 mod driver;
 mod frame;
+mod interface;
+mod isotp;
+mod j1939;
+mod netlink;
 mod socket;
 
-pub use self::{driver::*, frame::*, socket::*};
+pub use self::{constants::*, driver::*, frame::*, interface::*, isotp::*, j1939::*, socket::*};
 
-use rs_can::{CanDevice, CanDirection, CanError, CanFrame, CanResult, ChannelMode, DeviceBuilder};
+use rs_can::{
+    BusCapabilities, BusCapability, BusDiagnostic, BusState, CanDevice, CanDirection, CanError,
+    CanFrame, CanResult, ChannelMode, DeviceBuilder, ErrorCounters,
+};
 use std::{sync::Arc, time::Duration};
+use tokio::time;
 
 #[async_trait::async_trait]
 impl CanDevice for SocketCan {
@@ -18,6 +27,8 @@ impl CanDevice for SocketCan {
             .channel_configs()
             .iter()
             .try_for_each(|(chl, cfg)| {
+                CanInterface::open(chl)?.configure_requested(cfg)?;
+
                 let canfd = cfg.data_bitrate.is_some();
                 device.init_channel(chl, canfd)?;
 
@@ -25,13 +36,16 @@ impl CanDevice for SocketCan {
                     device.set_filters(chl, &cfg.filters)?;
                 }
 
-                if let Some(mode) = cfg.mode {
-                    match mode {
-                        ChannelMode::Normal => {}
-                        ChannelMode::Loopback => device.set_loopback(chl, true)?,
-                        ChannelMode::ListenOnly => return Err(CanError::NotSupportedError),
-                        ChannelMode::OneShot => return Err(CanError::NotSupportedError),
-                    }
+                if let Some(ChannelMode::Loopback) = cfg.mode {
+                    device.set_loopback(chl, true)?;
+                }
+
+                if cfg.get_other::<bool>(CAN_XL)?.unwrap_or_default() {
+                    device.set_xl_frames(chl, true)?;
+                }
+
+                if let Some(batch) = cfg.get_other::<u32>(RECV_BATCH)? {
+                    device.set_recv_batch(chl, batch as usize)?;
                 }
 
                 if let Some(recv_own_msg) = cfg.recv_own_msg {
@@ -53,9 +67,16 @@ impl CanDevice for SocketCan {
     async fn transmit(&self, msg: Self::Frame, timeout: Option<u32>) -> CanResult<()> {
         let mut msg = msg;
         msg.set_direction(CanDirection::Transmit);
+        let channel = msg.channel();
+        let timeout = match timeout {
+            Some(timeout) => Some(Duration::from_millis(timeout as u64)),
+            None => self.write_timeout_of(&channel)?,
+        };
         match timeout {
-            Some(timeout) => self.write_timeout(msg, Duration::from_millis(timeout as u64)),
-            None => self.write(msg),
+            Some(timeout) => time::timeout(timeout, self.write_async(msg))
+                .await
+                .map_err(|_| CanError::channel_timeout(channel))?,
+            None => self.write_async(msg).await,
         }
     }
 
@@ -65,13 +86,20 @@ impl CanDevice for SocketCan {
         channel: Self::Channel,
         timeout: Option<u32>,
     ) -> CanResult<Vec<Self::Frame>> {
-        let mut msg = match timeout {
-            Some(timeout) => self.read_timeout(&channel, Duration::from_millis(timeout as u64))?,
-            None => self.read(&channel)?,
+        let timeout = match timeout {
+            Some(timeout) => Some(Duration::from_millis(timeout as u64)),
+            None => self.read_timeout_of(&channel)?,
+        };
+        let mut msgs = match timeout {
+            Some(timeout) => time::timeout(timeout, self.read_batch_async(&channel))
+                .await
+                .map_err(|_| CanError::channel_timeout(&channel))??,
+            None => self.read_batch_async(&channel).await?,
         };
-        msg.set_channel(channel);
-        // .set_direct(CanDirect::Receive);
-        Ok(vec![msg])
+        for msg in msgs.iter_mut() {
+            msg.set_channel(channel.clone());
+        }
+        Ok(msgs)
     }
 
     #[inline(always)]
@@ -82,3 +110,34 @@ impl CanDevice for SocketCan {
         }
     }
 }
+
+impl BusDiagnostic for SocketCan {
+    #[inline]
+    fn bus_state(&self, channel: Self::Channel) -> CanResult<BusState> {
+        self.can_state(&channel)
+    }
+
+    #[inline]
+    fn error_counters(&self, channel: Self::Channel) -> CanResult<ErrorCounters> {
+        self.berr_counter(&channel)
+    }
+
+    #[inline]
+    fn recover_bus_off(&self, channel: Self::Channel) -> CanResult<()> {
+        self.restart(&channel)
+    }
+}
+
+impl BusCapability for SocketCan {
+    fn capabilities(&self) -> BusCapabilities {
+        BusCapabilities {
+            can_fd: true,
+            bitrate_switch: true,
+            error_state_indicator: true,
+            listen_only: true,
+            loopback: true,
+            hardware_timestamp: false,
+            bus_diagnostics: true,
+        }
+    }
+}
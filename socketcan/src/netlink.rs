@@ -0,0 +1,542 @@
+//! Minimal rtnetlink client for the CAN link configuration.
+//!
+//! ref: `include/uapi/linux/rtnetlink.h` and `include/uapi/linux/can/netlink.h`
+
+use libc::{
+    sockaddr, sockaddr_nl, socket, AF_NETLINK, AF_UNSPEC, IFF_UP, IFLA_IFNAME, IFLA_INFO_DATA,
+    IFLA_INFO_KIND, IFLA_LINKINFO, IFLA_TXQLEN, NETLINK_ROUTE, NLMSG_DONE, NLMSG_ERROR, NLM_F_ACK,
+    NLM_F_CREATE, NLM_F_EXCL, NLM_F_REQUEST, RTM_DELLINK, RTM_GETLINK, RTM_NEWLINK, SOCK_CLOEXEC,
+    SOCK_RAW,
+};
+use rs_can::{BitTiming, BitTimingConst};
+use std::{
+    io, mem,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        raw::c_void,
+    },
+};
+
+pub(crate) const IFLA_CAN_BITTIMING: u16 = 1;
+pub(crate) const IFLA_CAN_BITTIMING_CONST: u16 = 2;
+pub(crate) const IFLA_CAN_CLOCK: u16 = 3;
+pub(crate) const IFLA_CAN_STATE: u16 = 4;
+pub(crate) const IFLA_CAN_CTRLMODE: u16 = 5;
+pub(crate) const IFLA_CAN_RESTART_MS: u16 = 6;
+pub(crate) const IFLA_CAN_RESTART: u16 = 7;
+pub(crate) const IFLA_CAN_BERR_COUNTER: u16 = 8;
+pub(crate) const IFLA_CAN_DATA_BITTIMING: u16 = 9;
+pub(crate) const IFLA_CAN_DATA_BITTIMING_CONST: u16 = 10;
+pub(crate) const IFLA_CAN_TERMINATION: u16 = 11;
+pub(crate) const IFLA_CAN_TERMINATION_CONST: u16 = 12;
+pub(crate) const IFLA_CAN_TDC: u16 = 16;
+
+/// The attributes nested in `IFLA_CAN_TDC`.
+pub(crate) const IFLA_CAN_TDC_TDCV: u16 = 7;
+pub(crate) const IFLA_CAN_TDC_TDCO: u16 = 8;
+
+/// The attribute nested in `IFLA_INFO_DATA` of a `vxcan` link.
+const VXCAN_INFO_PEER: u16 = 1;
+
+/// The flags of `struct can_ctrlmode`.
+pub(crate) const CAN_CTRLMODE_LOOPBACK: u32 = 0x01;
+pub(crate) const CAN_CTRLMODE_LISTENONLY: u32 = 0x02;
+pub(crate) const CAN_CTRLMODE_ONE_SHOT: u32 = 0x08;
+pub(crate) const CAN_CTRLMODE_BERR_REPORTING: u32 = 0x10;
+pub(crate) const CAN_CTRLMODE_FD: u32 = 0x20;
+pub(crate) const CAN_CTRLMODE_FD_NON_ISO: u32 = 0x80;
+pub(crate) const CAN_CTRLMODE_TDC_AUTO: u32 = 0x200;
+pub(crate) const CAN_CTRLMODE_TDC_MANUAL: u32 = 0x400;
+
+/// The CAN controller state, `enum can_state`.
+pub(crate) const CAN_STATE_ERROR_ACTIVE: u32 = 0;
+pub(crate) const CAN_STATE_ERROR_WARNING: u32 = 1;
+pub(crate) const CAN_STATE_ERROR_PASSIVE: u32 = 2;
+pub(crate) const CAN_STATE_BUS_OFF: u32 = 3;
+
+const NLMSG_HDRLEN: usize = 16;
+const IFINFOMSG_LEN: usize = 16;
+const RTA_HDRLEN: usize = 4;
+const NLA_TYPE_MASK: u16 = 0x3FFF;
+const RECV_BUFFER_SIZE: usize = 32 * 1024;
+
+#[inline(always)]
+const fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// A `RTM_*LINK` request with its attributes.
+pub(crate) struct LinkRequest {
+    buffer: Vec<u8>,
+    nested: Vec<usize>,
+}
+
+impl LinkRequest {
+    pub fn new(msg_type: u16, flags: u16, ifindex: u32) -> Self {
+        let mut buffer = Vec::with_capacity(256);
+        // struct nlmsghdr, the length is filled when sending.
+        buffer.extend_from_slice(&0u32.to_ne_bytes());
+        buffer.extend_from_slice(&msg_type.to_ne_bytes());
+        buffer.extend_from_slice(&(flags | NLM_F_REQUEST as u16).to_ne_bytes());
+        buffer.extend_from_slice(&1u32.to_ne_bytes());
+        buffer.extend_from_slice(&0u32.to_ne_bytes());
+        // struct ifinfomsg
+        buffer.push(AF_UNSPEC as u8);
+        buffer.push(0);
+        buffer.extend_from_slice(&0u16.to_ne_bytes());
+        buffer.extend_from_slice(&(ifindex as i32).to_ne_bytes());
+        buffer.extend_from_slice(&0u32.to_ne_bytes());
+        buffer.extend_from_slice(&0u32.to_ne_bytes());
+
+        Self {
+            buffer,
+            nested: Default::default(),
+        }
+    }
+
+    /// Set `ifi_flags` and `ifi_change` of `struct ifinfomsg`.
+    pub fn flags(&mut self, flags: u32, change: u32) -> &mut Self {
+        let offset = NLMSG_HDRLEN + 8;
+        self.buffer[offset..offset + 4].copy_from_slice(&flags.to_ne_bytes());
+        self.buffer[offset + 4..offset + 8].copy_from_slice(&change.to_ne_bytes());
+        self
+    }
+
+    pub fn attr(&mut self, ty: u16, payload: &[u8]) -> &mut Self {
+        let len = RTA_HDRLEN + payload.len();
+        self.buffer.extend_from_slice(&(len as u16).to_ne_bytes());
+        self.buffer.extend_from_slice(&ty.to_ne_bytes());
+        self.buffer.extend_from_slice(payload);
+        self.buffer.resize(align(self.buffer.len()), 0);
+        self
+    }
+
+    #[inline]
+    pub fn attr_u32(&mut self, ty: u16, val: u32) -> &mut Self {
+        self.attr(ty, &val.to_ne_bytes())
+    }
+
+    /// Add the name attribute with the terminating NUL.
+    pub fn attr_name(&mut self, ty: u16, name: &str) -> &mut Self {
+        let mut payload = name.as_bytes().to_vec();
+        payload.push(0);
+        self.attr(ty, &payload)
+    }
+
+    /// Start a nested attribute, must be closed by [`LinkRequest::end_nested`].
+    pub fn begin_nested(&mut self, ty: u16) -> &mut Self {
+        self.nested.push(self.buffer.len());
+        self.attr(ty, &[])
+    }
+
+    /// Append the raw payload, e.g. a header inside a nested attribute.
+    pub fn raw(&mut self, payload: &[u8]) -> &mut Self {
+        self.buffer.extend_from_slice(payload);
+        self.buffer.resize(align(self.buffer.len()), 0);
+        self
+    }
+
+    pub fn end_nested(&mut self) -> &mut Self {
+        if let Some(start) = self.nested.pop() {
+            let len = (self.buffer.len() - start) as u16;
+            self.buffer[start..start + 2].copy_from_slice(&len.to_ne_bytes());
+        }
+        self
+    }
+
+    /// Send the request and return the payload of the reply message.
+    ///
+    /// The payload is empty when the kernel replies an acknowledgment only.
+    pub fn send(mut self) -> io::Result<Vec<u8>> {
+        debug_assert!(self.nested.is_empty());
+        let len = self.buffer.len() as u32;
+        self.buffer[..4].copy_from_slice(&len.to_ne_bytes());
+
+        let fd = unsafe { socket(AF_NETLINK, SOCK_RAW | SOCK_CLOEXEC, NETLINK_ROUTE) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut addr: sockaddr_nl = unsafe { mem::zeroed() };
+        addr.nl_family = AF_NETLINK as u16;
+        let ret = unsafe {
+            libc::sendto(
+                fd.as_raw_fd(),
+                self.buffer.as_ptr() as *const c_void,
+                self.buffer.len(),
+                0,
+                &addr as *const _ as *const sockaddr,
+                mem::size_of::<sockaddr_nl>() as u32,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut buffer = vec![0u8; RECV_BUFFER_SIZE];
+        loop {
+            let size = unsafe {
+                libc::recv(
+                    fd.as_raw_fd(),
+                    buffer.as_mut_ptr() as *mut c_void,
+                    buffer.len(),
+                    0,
+                )
+            };
+            if size < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut msgs = &buffer[..size as usize];
+            while msgs.len() >= NLMSG_HDRLEN {
+                let len = u32::from_ne_bytes(msgs[..4].try_into().unwrap()) as usize;
+                let ty = u16::from_ne_bytes(msgs[4..6].try_into().unwrap());
+                if len < NLMSG_HDRLEN || len > msgs.len() {
+                    return Err(io::Error::from(io::ErrorKind::InvalidData));
+                }
+
+                let payload = &msgs[NLMSG_HDRLEN..len];
+                match ty as i32 {
+                    NLMSG_ERROR => {
+                        // struct nlmsgerr, errno is negative or zero for acknowledgment.
+                        let errno = i32::from_ne_bytes(
+                            payload
+                                .get(..4)
+                                .ok_or(io::ErrorKind::InvalidData)?
+                                .try_into()
+                                .unwrap(),
+                        );
+                        return match errno {
+                            0 => Ok(Vec::new()),
+                            _ => Err(io::Error::from_raw_os_error(-errno)),
+                        };
+                    }
+                    NLMSG_DONE => return Ok(Vec::new()),
+                    _ if ty == RTM_NEWLINK => return Ok(payload.to_vec()),
+                    _ => {}
+                }
+
+                msgs = &msgs[align(len).min(msgs.len())..];
+            }
+        }
+    }
+}
+
+/// Iterate the `(type, payload)` of the attributes in buffer.
+pub(crate) fn attributes(buffer: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    let mut rest = buffer;
+    std::iter::from_fn(move || {
+        if rest.len() < RTA_HDRLEN {
+            return None;
+        }
+        let len = u16::from_ne_bytes([rest[0], rest[1]]) as usize;
+        let ty = u16::from_ne_bytes([rest[2], rest[3]]) & NLA_TYPE_MASK;
+        if len < RTA_HDRLEN || len > rest.len() {
+            return None;
+        }
+
+        let payload = &rest[RTA_HDRLEN..len];
+        rest = &rest[align(len).min(rest.len())..];
+        Some((ty, payload))
+    })
+}
+
+#[inline]
+pub(crate) fn find_attribute(buffer: &[u8], ty: u16) -> Option<&[u8]> {
+    attributes(buffer).find_map(|(t, payload)| (t == ty).then_some(payload))
+}
+
+#[inline]
+pub(crate) fn u32_attribute(buffer: &[u8], ty: u16) -> Option<u32> {
+    find_attribute(buffer, ty)
+        .and_then(|v| v.get(..4))
+        .map(|v| u32::from_ne_bytes(v.try_into().unwrap()))
+}
+
+/// Parse `struct can_bittiming_const` of the attribute.
+pub(crate) fn bittiming_const(buffer: &[u8], ty: u16) -> Option<BitTimingConst> {
+    // the name of controller is `char name[16]`
+    let values = find_attribute(buffer, ty)?
+        .get(16..48)?
+        .chunks_exact(4)
+        .map(|v| u32::from_ne_bytes(v.try_into().unwrap()))
+        .collect::<Vec<_>>();
+
+    Some(BitTimingConst {
+        tseg1_min: values[0],
+        tseg1_max: values[1],
+        tseg2_min: values[2],
+        tseg2_max: values[3],
+        sjw_max: values[4],
+        brp_min: values[5],
+        brp_max: values[6],
+        brp_inc: values[7],
+    })
+}
+
+/// `struct can_bittiming`
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Bittiming {
+    pub(crate) bitrate: u32,
+    pub(crate) sample_point: u32,
+    pub(crate) tq: u32,
+    pub(crate) prop_seg: u32,
+    pub(crate) phase_seg1: u32,
+    pub(crate) phase_seg2: u32,
+    pub(crate) sjw: u32,
+    pub(crate) brp: u32,
+}
+
+impl Bittiming {
+    /// The bit-timing is calculated by the kernel, the default sample point is used if it's 0.
+    #[inline]
+    pub fn with_bitrate(bitrate: u32, sample_point: u32) -> Self {
+        Self {
+            bitrate,
+            sample_point,
+            ..Default::default()
+        }
+    }
+
+    /// The explicit segments, the prescaler is derived from `tq` by the kernel.
+    pub fn with_timing(timing: &BitTiming, clock: u32) -> Self {
+        let tq = (timing.brp as u64 * 1_000_000_000 + clock as u64 / 2) / clock as u64;
+        let prop_seg = timing.tseg1 / 2;
+        Self {
+            tq: tq as u32,
+            prop_seg,
+            phase_seg1: timing.tseg1 - prop_seg,
+            phase_seg2: timing.tseg2,
+            sjw: timing.sjw,
+            brp: timing.brp,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_bytes(payload: &[u8]) -> Option<Self> {
+        let values = payload
+            .get(..32)?
+            .chunks_exact(4)
+            .map(|v| u32::from_ne_bytes(v.try_into().unwrap()))
+            .collect::<Vec<_>>();
+
+        Some(Self {
+            bitrate: values[0],
+            sample_point: values[1],
+            tq: values[2],
+            prop_seg: values[3],
+            phase_seg1: values[4],
+            phase_seg2: values[5],
+            sjw: values[6],
+            brp: values[7],
+        })
+    }
+
+    /// Whether the `current` bit-timing of link satisfies this one.
+    pub fn is_applied(&self, current: &Self) -> bool {
+        match self.bitrate {
+            // the explicit segments
+            0 => {
+                (
+                    self.prop_seg + self.phase_seg1,
+                    self.phase_seg2,
+                    self.sjw,
+                    self.brp,
+                ) == (
+                    current.prop_seg + current.phase_seg1,
+                    current.phase_seg2,
+                    current.sjw,
+                    current.brp,
+                )
+            }
+            bitrate => {
+                bitrate == current.bitrate
+                    && (self.sample_point == 0 || self.sample_point == current.sample_point)
+            }
+        }
+    }
+
+    pub fn to_bytes(self) -> Vec<u8> {
+        [
+            self.bitrate,
+            self.sample_point,
+            self.tq,
+            self.prop_seg,
+            self.phase_seg1,
+            self.phase_seg2,
+            self.sjw,
+            self.brp,
+        ]
+        .iter()
+        .flat_map(|v| v.to_ne_bytes())
+        .collect()
+    }
+}
+
+/// The `RTM_NEWLINK` reply of a link.
+pub(crate) struct Link(Vec<u8>);
+
+impl Link {
+    pub fn query(ifindex: u32) -> io::Result<Self> {
+        let reply = LinkRequest::new(RTM_GETLINK, 0, ifindex).send()?;
+        match reply.len() < IFINFOMSG_LEN {
+            true => Err(io::Error::from(io::ErrorKind::InvalidData)),
+            false => Ok(Self(reply)),
+        }
+    }
+
+    /// `ifi_flags` of `struct ifinfomsg`.
+    #[inline]
+    pub fn flags(&self) -> u32 {
+        u32::from_ne_bytes(self.0[8..12].try_into().unwrap())
+    }
+
+    #[inline]
+    pub fn attributes(&self) -> &[u8] {
+        &self.0[IFINFOMSG_LEN..]
+    }
+
+    /// The `IFLA_INFO_DATA`, `None` when the link has no type specific data, e.g. a `vcan` link.
+    pub fn info_data(&self) -> Option<&[u8]> {
+        find_attribute(self.attributes(), IFLA_LINKINFO)
+            .and_then(|info| find_attribute(info, IFLA_INFO_DATA))
+    }
+}
+
+/// Query the `IFLA_INFO_DATA` of a link.
+///
+/// Return `None` when the link has no type specific data, e.g. a `vcan` link.
+pub(crate) fn link_info_data(ifindex: u32) -> io::Result<Option<Vec<u8>>> {
+    Link::query(ifindex).map(|link| link.info_data().map(|data| data.to_vec()))
+}
+
+/// Bring the link up or down.
+pub(crate) fn set_link_up(ifindex: u32, up: bool) -> io::Result<()> {
+    let mut request = LinkRequest::new(RTM_NEWLINK, NLM_F_ACK as u16, ifindex);
+    request.flags(if up { IFF_UP as u32 } else { 0 }, IFF_UP as u32);
+
+    request.send().map(|_| ())
+}
+
+pub(crate) fn set_txqueuelen(ifindex: u32, len: u32) -> io::Result<()> {
+    let mut request = LinkRequest::new(RTM_NEWLINK, NLM_F_ACK as u16, ifindex);
+    request.attr_u32(IFLA_TXQLEN, len);
+
+    request.send().map(|_| ())
+}
+
+/// Create a virtual link of `kind`(`vcan` or `vxcan`), the `vxcan` is created with its `peer`.
+pub(crate) fn create_link(name: &str, kind: &str, peer: Option<&str>) -> io::Result<()> {
+    let flags = NLM_F_ACK | NLM_F_CREATE | NLM_F_EXCL;
+    let mut request = LinkRequest::new(RTM_NEWLINK, flags as u16, 0);
+    request
+        .attr_name(IFLA_IFNAME, name)
+        .begin_nested(IFLA_LINKINFO)
+        .attr(IFLA_INFO_KIND, kind.as_bytes());
+    if let Some(peer) = peer {
+        // the peer is a `struct ifinfomsg` followed by its attributes.
+        request
+            .begin_nested(IFLA_INFO_DATA)
+            .begin_nested(VXCAN_INFO_PEER)
+            .raw(&[0u8; IFINFOMSG_LEN])
+            .attr_name(IFLA_IFNAME, peer)
+            .end_nested()
+            .end_nested();
+    }
+    request.end_nested();
+
+    request.send().map(|_| ())
+}
+
+pub(crate) fn delete_link(ifindex: u32) -> io::Result<()> {
+    LinkRequest::new(RTM_DELLINK, NLM_F_ACK as u16, ifindex)
+        .send()
+        .map(|_| ())
+}
+
+/// Set the CAN specific attributes of a link.
+///
+/// `build` adds the attributes inside `IFLA_INFO_DATA`.
+pub(crate) fn set_can_info_data(
+    ifindex: u32,
+    build: impl FnOnce(&mut LinkRequest),
+) -> io::Result<()> {
+    let mut request = LinkRequest::new(RTM_NEWLINK, NLM_F_ACK as u16, ifindex);
+    request
+        .begin_nested(IFLA_LINKINFO)
+        .attr(IFLA_INFO_KIND, b"can")
+        .begin_nested(IFLA_INFO_DATA);
+    build(&mut request);
+    request.end_nested().end_nested();
+
+    request.send().map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_attributes_round_trip() {
+        let mut request = LinkRequest::new(RTM_NEWLINK, 0, 1);
+        request
+            .begin_nested(IFLA_LINKINFO)
+            .attr(IFLA_INFO_KIND, b"can")
+            .begin_nested(IFLA_INFO_DATA)
+            .attr_u32(IFLA_CAN_RESTART, 1)
+            .end_nested()
+            .end_nested();
+
+        let attrs = &request.buffer[NLMSG_HDRLEN + IFINFOMSG_LEN..];
+        let info = find_attribute(attrs, IFLA_LINKINFO).unwrap();
+        assert_eq!(find_attribute(info, IFLA_INFO_KIND), Some(&b"can"[..]));
+        let data = find_attribute(info, IFLA_INFO_DATA).unwrap();
+        assert_eq!(
+            find_attribute(data, IFLA_CAN_RESTART),
+            Some(&1u32.to_ne_bytes()[..])
+        );
+    }
+
+    #[test]
+    fn bittiming_attributes() {
+        let timing = BitTiming {
+            brp: 3,
+            tseg1: 13,
+            tseg2: 2,
+            sjw: 1,
+        };
+        let bittiming = Bittiming::with_timing(&timing, 80_000_000);
+        // the kernel derives brp = (tq * clock + 1e9 / 2 - 1) / 1e9
+        assert_eq!(
+            (bittiming.tq as u64 * 80_000_000 + 499_999_999) / 1_000_000_000,
+            3
+        );
+        assert_eq!(bittiming.prop_seg + bittiming.phase_seg1, 13);
+        let current = Bittiming::from_bytes(&bittiming.to_bytes()).unwrap();
+        assert!(bittiming.is_applied(&current));
+        assert!(!Bittiming::with_bitrate(500_000, 0).is_applied(&current));
+
+        let mut request = LinkRequest::new(RTM_NEWLINK, 0, 1);
+        let mut payload = b"m_can\0\0\0\0\0\0\0\0\0\0\0".to_vec();
+        [2u32, 256, 2, 128, 128, 1, 512, 1]
+            .iter()
+            .for_each(|v| payload.extend_from_slice(&v.to_ne_bytes()));
+        request
+            .attr(IFLA_CAN_BITTIMING, &bittiming.to_bytes())
+            .attr(IFLA_CAN_BITTIMING_CONST, &payload)
+            .attr_u32(IFLA_CAN_CLOCK, 80_000_000);
+
+        let attrs = &request.buffer[NLMSG_HDRLEN + IFINFOMSG_LEN..];
+        assert_eq!(find_attribute(attrs, IFLA_CAN_BITTIMING).unwrap().len(), 32);
+        assert_eq!(u32_attribute(attrs, IFLA_CAN_CLOCK), Some(80_000_000));
+        let limits = bittiming_const(attrs, IFLA_CAN_BITTIMING_CONST).unwrap();
+        assert_eq!((limits.tseg1_max, limits.brp_max), (256, 512));
+    }
+
+    #[test]
+    fn query_loopback_link() {
+        // `lo` is always the first link and has no CAN data.
+        assert!(matches!(link_info_data(1), Ok(None)));
+    }
+}
@@ -1,12 +1,25 @@
 //! from [socketcan](https://crates.io/crates/socketcan-rs)
 
+use crate::{
+    IsoTpFcOptions, IsoTpLlOptions, IsoTpOptions, CAN_ISOTP_LL_OPTS, CAN_ISOTP_OPTS,
+    CAN_ISOTP_RECV_FC, SOL_CAN_ISOTP,
+};
 use libc::*;
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
 use std::{
     ffi::CString,
     fmt, io, mem,
-    os::raw::{c_int, c_void},
+    os::{
+        fd::{AsFd, AsRawFd, OwnedFd, RawFd},
+        raw::{c_int, c_void},
+    },
     ptr,
-    time::Duration,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{unix::AsyncFd, Interest},
+    runtime::{self, Handle},
 };
 
 /// Tries to open the CAN socket by the interface number.
@@ -28,6 +41,62 @@ pub fn raw_open_socket(addr: &CanAddr) -> io::Result<c_int> {
     }
 }
 
+/// Tries to open the ISO-TP socket by the interface number and the tx/rx identifiers
+/// of `addr`, the options must be set before binding.
+pub fn isotp_open_socket(
+    addr: &CanAddr,
+    opts: &IsoTpOptions,
+    fc_opts: &IsoTpFcOptions,
+    ll_opts: Option<&IsoTpLlOptions>,
+) -> io::Result<c_int> {
+    let fd = unsafe { socket(PF_CAN, SOCK_DGRAM, CAN_ISOTP) };
+
+    if fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let ret = set_socket_option(fd, SOL_CAN_ISOTP, CAN_ISOTP_OPTS, opts)
+        .and_then(|_| set_socket_option(fd, SOL_CAN_ISOTP, CAN_ISOTP_RECV_FC, fc_opts))
+        .and_then(|_| match ll_opts {
+            Some(ll_opts) => set_socket_option(fd, SOL_CAN_ISOTP, CAN_ISOTP_LL_OPTS, ll_opts),
+            None => Ok(()),
+        })
+        .and_then(
+            |_| match unsafe { bind(fd, addr.as_sockaddr_ptr(), CanAddr::len() as u32) } {
+                -1 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            },
+        );
+
+    match ret {
+        Ok(()) => Ok(fd),
+        Err(err) => {
+            unsafe { close(fd) };
+            Err(err)
+        }
+    }
+}
+
+/// Tries to open the J1939 socket by the interface number and the name, PGN and
+/// address of `addr`.
+pub fn j1939_open_socket(addr: &CanAddr) -> io::Result<c_int> {
+    let fd = unsafe { socket(PF_CAN, SOCK_DGRAM, CAN_J1939) };
+
+    if fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let ret = unsafe { bind(fd, addr.as_sockaddr_ptr(), CanAddr::len() as u32) };
+
+    if ret == -1 {
+        let err = io::Error::last_os_error();
+        unsafe { close(fd) };
+        Err(err)
+    } else {
+        Ok(fd)
+    }
+}
+
 // Enable or disable FD mode on the socket, fd.
 pub fn set_fd_mode(fd: c_int, enable: bool) -> io::Result<c_int> {
     let enable = enable as c_int;
@@ -49,6 +118,119 @@ pub fn set_fd_mode(fd: c_int, enable: bool) -> io::Result<c_int> {
     }
 }
 
+/// Change the socket to non-blocking mode or back to blocking mode.
+pub(crate) fn set_nonblocking(fd: c_int, nonblocking: bool) -> io::Result<()> {
+    // retrieve current flags
+    let oldfl = unsafe { fcntl(fd, F_GETFL) };
+    if oldfl == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let newfl = if nonblocking {
+        oldfl | O_NONBLOCK
+    } else {
+        oldfl & !O_NONBLOCK
+    };
+    match unsafe { fcntl(fd, F_SETFL, newfl) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// A non-blocking socket for both of the blocking and async I/O.
+///
+/// It's registered with the tokio reactor on the first async I/O, so the blocking I/O
+/// works without a runtime. It's registered again when the async I/O is used inside
+/// another runtime, the registration of the previous runtime is dropped.
+#[derive(Debug)]
+pub(crate) struct NonBlockingSocket {
+    // it's dropped before the socket is closed
+    registration: Mutex<Option<(runtime::Id, Arc<AsyncFd<RawFd>>)>>,
+    fd: OwnedFd,
+}
+
+impl NonBlockingSocket {
+    pub fn new(fd: OwnedFd) -> io::Result<Self> {
+        set_nonblocking(fd.as_raw_fd(), true)?;
+        Ok(Self {
+            registration: Default::default(),
+            fd,
+        })
+    }
+
+    /// Retry `f` until the socket isn't `WouldBlock`, the thread is blocked by `poll` meanwhile.
+    pub fn poll_io<R>(
+        &self,
+        flags: PollFlags,
+        timeout: Option<Duration>,
+        mut f: impl FnMut(&OwnedFd) -> io::Result<R>,
+    ) -> io::Result<R> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            match f(&self.fd) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                ret => return ret,
+            }
+
+            let timeout = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    // round up to avoid spinning in the last millisecond
+                    Some(remaining) if !remaining.is_zero() => {
+                        PollTimeout::try_from(remaining.as_millis() + 1).unwrap_or(PollTimeout::MAX)
+                    }
+                    _ => return Err(io::ErrorKind::TimedOut.into()),
+                },
+                None => PollTimeout::NONE,
+            };
+            poll(&mut [PollFd::new(self.fd.as_fd(), flags)], timeout)?;
+        }
+    }
+
+    /// Retry `f` when the socket gets ready for `interest`,
+    /// the worker thread of runtime isn't blocked while waiting.
+    pub async fn async_io<R>(
+        &self,
+        interest: Interest,
+        mut f: impl FnMut(&OwnedFd) -> io::Result<R>,
+    ) -> io::Result<R> {
+        self.registration()?
+            .async_io(interest, |_| f(&self.fd))
+            .await
+    }
+
+    /// The registration with the reactor of current runtime.
+    fn registration(&self) -> io::Result<Arc<AsyncFd<RawFd>>> {
+        let id = Handle::try_current()
+            .map_err(|_| io::Error::other("the async I/O must be used inside a tokio runtime"))?
+            .id();
+
+        let mut registration = self
+            .registration
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some((current, registration)) = registration.as_ref() {
+            if *current == id {
+                return Ok(registration.clone());
+            }
+        }
+        // drop the previous one first, it's removed from the reactor of its runtime
+        *registration = None;
+        // SAFETY: the socket is closed only after the registration is dropped.
+        let new =
+            Arc::new(unsafe { AsyncFd::register(self.fd.as_raw_fd()) }.map_err(io::Error::from)?);
+        *registration = Some((id, new.clone()));
+
+        Ok(new)
+    }
+}
+
+impl AsRawFd for NonBlockingSocket {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
 // Write a single frame of any type to the socket, fd.
 pub fn raw_write_frame<T>(fd: c_int, frame_ptr: *const T, n: usize) -> io::Result<()> {
     let ret = unsafe { write(fd, frame_ptr.cast(), n) };
@@ -142,6 +324,13 @@ pub fn canfd_frame_default() -> canfd_frame {
     unsafe { mem::zeroed() }
 }
 
+/// Creates a default C `canxl_frame`.
+/// This initializes the entire structure to zeros.
+#[inline(always)]
+pub fn canxl_frame_default() -> canxl_frame {
+    unsafe { mem::zeroed() }
+}
+
 /// Check an error return value for timeouts.
 ///
 /// Due to the fact that timeouts are reported as errors, calling `read_frame`
@@ -204,6 +393,19 @@ impl CanAddr {
         addr
     }
 
+    /// Sets the tx/rx identifiers of ISO-TP socket, the extended identifiers
+    /// must be flagged by `CAN_EFF_FLAG`.
+    pub fn set_tp_ids(&mut self, tx_id: canid_t, rx_id: canid_t) -> &mut Self {
+        self.0.can_addr.tp = __c_anonymous_sockaddr_can_tp { rx_id, tx_id };
+        self
+    }
+
+    /// Sets the name, PGN and address of J1939 socket.
+    pub fn set_j1939(&mut self, name: u64, pgn: u32, addr: u8) -> &mut Self {
+        self.0.can_addr.j1939 = __c_anonymous_sockaddr_can_j1939 { name, pgn, addr };
+        self
+    }
+
     /// Try to create an address from an interface name.
     pub fn from_iface(ifname: &str) -> io::Result<Self> {
         let ifname = CString::new(ifname)?;
@@ -1,5 +1,7 @@
-use rs_can::{CanDevice, CanError, CanFrame, CanId, DeviceBuilder};
-use socketcan_rs::{SocketCan, SocketCanFrame};
+use rs_can::{
+    CanDevice, CanError, CanFrame, CanId, CanKind, CanXlParams, ChannelConfig, DeviceBuilder,
+};
+use socketcan_rs::{SocketCan, SocketCanFrame, CAN_XL};
 
 fn device_builder(iface: String) -> anyhow::Result<SocketCan, CanError> {
     let mut builder = DeviceBuilder::new();
@@ -41,3 +43,44 @@ async fn test_driver() -> anyhow::Result<(), CanError> {
 
     Ok(())
 }
+
+/// The interface must be CAN XL capable:
+/// `sudo ip link set dev vcan0 down && sudo ip link set dev vcan0 mtu 2060 && sudo ip link set dev vcan0 up`
+#[tokio::test]
+async fn test_driver_xl() -> anyhow::Result<(), CanError> {
+    let iface = "vcan0".to_string();
+    let xl_builder = || {
+        let mut cfg = ChannelConfig::new(500_000);
+        cfg.add_other(CAN_XL, Box::new(true));
+        let mut builder = DeviceBuilder::new();
+        builder.add_config(iface.clone(), cfg);
+        builder.build::<SocketCan>()
+    };
+
+    let mut device1 = xl_builder()?;
+    let mut device2 = xl_builder()?;
+
+    let data = (0..256).map(|v| v as u8).collect::<Vec<_>>();
+    let params = CanXlParams {
+        sdu_type: 0x03,
+        vcid: 0x12,
+        acceptance: 0xCAFE_BABE,
+        sec: true,
+    };
+    let mut message = SocketCanFrame::new_can_xl(0x123, &data, params)?;
+    message.set_channel(iface.clone());
+    device1.transmit(message, None).await?;
+
+    let frames = device2.receive(iface.clone(), Some(1000)).await?;
+    assert_eq!(frames.len(), 1);
+    let frame = &frames[0];
+    assert_eq!(frame.kind(), CanKind::XL);
+    assert_eq!(frame.id().as_raw(), 0x123);
+    assert_eq!(frame.data(), data.as_slice());
+    assert_eq!(frame.xl_params(), Some(params));
+
+    device1.shutdown();
+    device2.shutdown();
+
+    Ok(())
+}
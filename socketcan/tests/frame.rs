@@ -0,0 +1,109 @@
+use rs_can::{CanFrame, CanKind, CanXlParams};
+use socketcan_rs::{CanAnyFrame, SocketCanFrame};
+
+#[test]
+fn xl_frame_round_trip() -> anyhow::Result<()> {
+    let data = [0x55; 100];
+    let params = CanXlParams {
+        sdu_type: 0x05,
+        vcid: 0xA5,
+        acceptance: 0x1234_5678,
+        sec: false,
+    };
+    let frame = SocketCanFrame::new_can_xl(0x7FF, &data, params)?;
+    assert_eq!(frame.xl_dlc(), Some(99));
+    assert!(frame.dlc().is_err());
+
+    let raw: CanAnyFrame = frame.clone().into();
+    assert_eq!(raw.size(), 12 + data.len());
+    match &raw {
+        CanAnyFrame::XL(f) => {
+            assert_eq!(f.prio, 0xA5_07FF);
+            assert_eq!(f.flags, 0x80);
+            assert_eq!(f.sdt, 0x05);
+            assert_eq!(f.len, 100);
+            assert_eq!(f.af, 0x1234_5678);
+        }
+        _ => panic!("not a CAN XL frame"),
+    }
+
+    let frame2 = SocketCanFrame::try_from(raw)?;
+    assert_eq!(frame2.kind(), CanKind::XL);
+    assert_eq!(frame2.xl_params(), Some(params));
+    assert_eq!(frame, frame2);
+
+    Ok(())
+}
+
+#[test]
+fn xl_frame_rejects_invalid_arguments() {
+    let params = CanXlParams::default();
+    assert!(SocketCanFrame::new_can_xl(0x800, &[0x00], params).is_err());
+    assert!(SocketCanFrame::new_can_xl(0x100, &[], params).is_err());
+    assert!(SocketCanFrame::new_can_xl(0x100, &[0x00; 2049], params).is_err());
+}
+
+#[test]
+fn candump_round_trip() -> anyhow::Result<()> {
+    use rs_can::candump::{CandumpReader, CandumpWriter};
+
+    let content = "\
+(1697371234.123456) can0 123#DEADBEEF
+(1697371234.200000) can0 1ABCDEF0#R
+(1697371234.400000) can0 18DAF110##7000102030405060708090A0B
+(1697371234.500000) can0 20000004#0004000000000000
+";
+    let frames = CandumpReader::<_, SocketCanFrame>::new(content.as_bytes())
+        .collect::<Result<Vec<_>, _>>()?;
+    assert!(frames[2].is_bitrate_switch() && frames[2].is_esi());
+    assert!(frames[3].is_error_frame());
+    assert!(matches!(frames[3].clone().into(), CanAnyFrame::Error(_)));
+
+    let mut writer = CandumpWriter::new(Vec::new());
+    for frame in &frames {
+        writer.write_frame(frame)?;
+    }
+    assert_eq!(String::from_utf8(writer.finish()?)?, content);
+
+    Ok(())
+}
+
+#[test]
+fn any_frame_round_trip() -> anyhow::Result<()> {
+    use rs_can::{AnyFrame, CanDirection, CanFdFlags, CanId};
+
+    let mut frame = SocketCanFrame::new_can_fd(
+        CanId::from_bits(0x18DA_F110, Some(true))?,
+        &[0x55; 12],
+        CanFdFlags::BRS,
+    )?;
+    frame
+        .set_channel("can0".into())
+        .set_direction(CanDirection::Receive);
+
+    let any = AnyFrame::from(frame.clone());
+    assert_eq!(any.channel(), "can0");
+    assert_eq!(SocketCanFrame::try_from(any)?, frame);
+
+    Ok(())
+}
+
+#[test]
+fn generic_frame_round_trip() -> anyhow::Result<()> {
+    use rs_can::{CanDirection, CanFdFlags, CanId, GenericFrame};
+
+    let mut frame = SocketCanFrame::new_can_fd(
+        CanId::from_bits(0x18DA_F110, Some(true))?,
+        &[0x55; 12],
+        CanFdFlags::BRS,
+    )?;
+    frame
+        .set_channel("can0".into())
+        .set_direction(CanDirection::Receive);
+
+    let generic = GenericFrame::from(frame.clone());
+    assert_eq!(generic.channel(), "can0");
+    assert_eq!(SocketCanFrame::try_from(generic)?, frame);
+
+    Ok(())
+}
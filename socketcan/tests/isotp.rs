@@ -0,0 +1,49 @@
+use rs_can::{
+    isotp::{Address, AddressType, IsoTpConfig},
+    CanId,
+};
+use socketcan_rs::IsoTpSocket;
+
+fn address(tx_id: u32, rx_id: u32) -> anyhow::Result<Address> {
+    let mut address = Address::new(
+        CanId::from_bits(tx_id, None)?,
+        CanId::from_bits(rx_id, None)?,
+    );
+    address.set_functional_id(CanId::from_bits(0x7DF, None)?);
+    Ok(address)
+}
+
+#[tokio::test]
+#[ignore = "requires vcan0 and the can-isotp module"]
+async fn isotp_socket() -> anyhow::Result<()> {
+    let iface = "vcan0";
+    let config = IsoTpConfig {
+        block_size: 8,
+        ..Default::default()
+    };
+    let tester = IsoTpSocket::open(iface, &address(0x7E0, 0x7E8)?, &config)?;
+    let ecu = IsoTpSocket::open(iface, &address(0x7E8, 0x7E0)?, &config)?;
+
+    let data = (0..300).map(|v| v as u8).collect::<Vec<_>>();
+    let (sent, received) = tokio::join!(
+        tokio::task::spawn_blocking({
+            let data = data.clone();
+            move || tester.write(&data).map(|_| tester)
+        }),
+        tokio::task::spawn_blocking(move || ecu.read_timeout(std::time::Duration::from_secs(1)))
+    );
+    let tester = sent??;
+    assert_eq!(received??, data);
+
+    let functional = IsoTpSocket::open_to(
+        iface,
+        &address(0x7E0, 0x7E8)?,
+        &config,
+        AddressType::Functional,
+    )?;
+    functional.transmit(&[0x3E, 0x80]).await?;
+    assert!(functional.transmit(&[0x11; 20]).await.is_err());
+    drop(tester);
+
+    Ok(())
+}
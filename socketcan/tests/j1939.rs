@@ -0,0 +1,48 @@
+use rs_can::j1939::{Name, Pgn, GLOBAL_ADDRESS};
+use socketcan_rs::J1939Socket;
+use std::time::Duration;
+
+#[tokio::test]
+#[ignore = "requires vcan0 and the can-j1939 module"]
+async fn j1939_socket() -> anyhow::Result<()> {
+    let iface = "vcan0";
+    let sender = J1939Socket::open(iface, None, 0x20, None)?;
+    let receiver = J1939Socket::open(iface, None, 0x30, None)?;
+    sender.set_broadcast(true)?;
+    receiver.set_broadcast(true)?;
+
+    // transport protocol by RTS/CTS
+    let pgn = Pgn::new(0xEF00)?;
+    let data = (0..100).map(|v| v as u8).collect::<Vec<_>>();
+    let (sent, received) = tokio::join!(
+        tokio::task::spawn_blocking({
+            let data = data.clone();
+            move || sender.send_to(pgn, 0x30, &data).map(|_| sender)
+        }),
+        tokio::task::spawn_blocking(move || {
+            receiver
+                .recv_timeout(Duration::from_secs(1))
+                .map(|m| (m, receiver))
+        })
+    );
+    let sender = sent??;
+    let (message, receiver) = received??;
+    assert_eq!(
+        (message.pgn, message.source, message.destination),
+        (pgn, 0x20, 0x30)
+    );
+    assert_eq!(message.data, data);
+
+    let pgn = Pgn::new(0xFEF1)?;
+    sender.transmit(pgn, GLOBAL_ADDRESS, &[0x11; 8]).await?;
+    let message = receiver.receive(Some(1000)).await?;
+    assert_eq!((message.pgn, message.destination), (pgn, GLOBAL_ADDRESS));
+
+    let name = Name {
+        identity_number: 1,
+        ..Default::default()
+    };
+    assert!(J1939Socket::open(iface, Some(name), 0x40, None).is_ok());
+
+    Ok(())
+}
@@ -6,7 +6,7 @@ use crate::{
         api::{
             USBCANApi, USBCANFDApi, ZCanApi, ZChannelContext, ZDeviceApi, ZDeviceContext, ZLinApi,
         },
-        can::{ZCanChlError, ZCanChlStatus, ZCanFrame, ZCanFrameType},
+        can::{ZCanAutoSend, ZCanChlError, ZCanChlStatus, ZCanFrame, ZCanFrameType},
         device::{DeriveInfo, ZCanDeviceType, ZDeviceInfo},
         lin::{ZLinChlCfg, ZLinFrame, ZLinPublish, ZLinSubscribe},
         util::get_libpath,
@@ -393,6 +393,58 @@ impl ZCan for ZDriver {
         }
     }
 
+    fn restart_can_chl(&self, channel: u8) -> CanResult<()> {
+        if self.dev_type.is_usbcan() {
+            self.can_handler(channel, |context| {
+                self.usbcan_api.reset_can_chl(context)?;
+                self.usbcan_api.start_can_chl(context)
+            })
+        } else if self.dev_type.is_usbcan_4e_u() {
+            #[cfg(target_arch = "x86_64")]
+            {
+                self.can_handler(channel, |context| {
+                    self.usbcan_4e_api.reset_can_chl(context)?;
+                    self.usbcan_4e_api.start_can_chl(context)
+                })
+            }
+            #[cfg(not(target_arch = "x86_64"))]
+            {
+                Err(CanError::NotSupportedError)
+            }
+        } else if self.dev_type.is_usbcan_8e_u() {
+            #[cfg(target_arch = "x86_64")]
+            {
+                self.can_handler(channel, |context| {
+                    self.usbcan_8e_api.reset_can_chl(context)?;
+                    self.usbcan_8e_api.start_can_chl(context)
+                })
+            }
+            #[cfg(not(target_arch = "x86_64"))]
+            {
+                Err(CanError::NotSupportedError)
+            }
+        } else if self.dev_type.is_usbcanfd() {
+            self.can_handler(channel, |context| {
+                self.usbcanfd_api.reset_can_chl(context)?;
+                self.usbcanfd_api.start_can_chl(context)
+            })
+        } else if self.dev_type.is_usbcanfd_800u() {
+            #[cfg(target_arch = "x86_64")]
+            {
+                self.can_handler(channel, |context| {
+                    self.usbcanfd_800u_api.reset_can_chl(context)?;
+                    self.usbcanfd_800u_api.start_can_chl(context)
+                })
+            }
+            #[cfg(not(target_arch = "x86_64"))]
+            {
+                Err(CanError::NotSupportedError)
+            }
+        } else {
+            Err(CanError::NotSupportedError)
+        }
+    }
+
     fn read_can_chl_status(&self, channel: u8) -> CanResult<ZCanChlStatus> {
         if self.dev_type.is_usbcan() {
             self.can_handler(channel, |context| {
@@ -726,6 +778,102 @@ impl ZCan for ZDriver {
             Err(CanError::NotSupportedError)
         }
     }
+
+    fn add_auto_send(&self, channel: u8, cfg: &ZCanAutoSend) -> CanResult<()> {
+        if self.dev_type.is_usbcanfd() {
+            self.can_handler(channel, |context| {
+                self.usbcanfd_api.add_auto_send(context, cfg)
+            })
+        } else if self.dev_type.is_usbcanfd_800u() {
+            #[cfg(target_arch = "x86_64")]
+            {
+                self.can_handler(channel, |context| {
+                    self.usbcanfd_800u_api.add_auto_send(context, cfg)
+                })
+            }
+            #[cfg(not(target_arch = "x86_64"))]
+            {
+                Err(CanError::NotSupportedError)
+            }
+        } else if self.dev_type.is_usbcan_4e_u() || self.dev_type.is_usbcan_8e_u() {
+            // the linux library of USBCAN-E-U exports no auto-send interface.
+            Err(CanError::NotSupportedError)
+        } else {
+            Err(CanError::NotSupportedError)
+        }
+    }
+
+    fn remove_auto_send(&self, channel: u8, index: u16) -> CanResult<()> {
+        if self.dev_type.is_usbcanfd() {
+            self.can_handler(channel, |context| {
+                self.usbcanfd_api.remove_auto_send(context, index)
+            })
+        } else if self.dev_type.is_usbcanfd_800u() {
+            #[cfg(target_arch = "x86_64")]
+            {
+                self.can_handler(channel, |context| {
+                    self.usbcanfd_800u_api.remove_auto_send(context, index)
+                })
+            }
+            #[cfg(not(target_arch = "x86_64"))]
+            {
+                Err(CanError::NotSupportedError)
+            }
+        } else if self.dev_type.is_usbcan_4e_u() || self.dev_type.is_usbcan_8e_u() {
+            // the linux library of USBCAN-E-U exports no auto-send interface.
+            Err(CanError::NotSupportedError)
+        } else {
+            Err(CanError::NotSupportedError)
+        }
+    }
+
+    fn apply_auto_send(&self, channel: u8) -> CanResult<()> {
+        if self.dev_type.is_usbcanfd() {
+            self.can_handler(channel, |context| {
+                self.usbcanfd_api.apply_auto_send(context)
+            })
+        } else if self.dev_type.is_usbcanfd_800u() {
+            #[cfg(target_arch = "x86_64")]
+            {
+                self.can_handler(channel, |context| {
+                    self.usbcanfd_800u_api.apply_auto_send(context)
+                })
+            }
+            #[cfg(not(target_arch = "x86_64"))]
+            {
+                Err(CanError::NotSupportedError)
+            }
+        } else if self.dev_type.is_usbcan_4e_u() || self.dev_type.is_usbcan_8e_u() {
+            // the linux library of USBCAN-E-U exports no auto-send interface.
+            Err(CanError::NotSupportedError)
+        } else {
+            Err(CanError::NotSupportedError)
+        }
+    }
+
+    fn clear_auto_send(&self, channel: u8) -> CanResult<()> {
+        if self.dev_type.is_usbcanfd() {
+            self.can_handler(channel, |context| {
+                self.usbcanfd_api.clear_auto_send(context)
+            })
+        } else if self.dev_type.is_usbcanfd_800u() {
+            #[cfg(target_arch = "x86_64")]
+            {
+                self.can_handler(channel, |context| {
+                    self.usbcanfd_800u_api.clear_auto_send(context)
+                })
+            }
+            #[cfg(not(target_arch = "x86_64"))]
+            {
+                Err(CanError::NotSupportedError)
+            }
+        } else if self.dev_type.is_usbcan_4e_u() || self.dev_type.is_usbcan_8e_u() {
+            // the linux library of USBCAN-E-U exports no auto-send interface.
+            Err(CanError::NotSupportedError)
+        } else {
+            Err(CanError::NotSupportedError)
+        }
+    }
 }
 
 impl ZLin for ZDriver {
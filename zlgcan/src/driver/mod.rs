@@ -9,7 +9,7 @@ pub use win::ZDriver;
 
 use crate::native::{
     api::{ZChannelContext, ZDeviceContext},
-    can::{ZCanChlError, ZCanChlStatus, ZCanFrame, ZCanFrameType},
+    can::{ZCanAutoSend, ZCanChlError, ZCanChlStatus, ZCanFrame, ZCanFrameType},
     cloud::{ZCloudGpsFrame, ZCloudServerInfo, ZCloudUserData},
     device::{DeriveInfo, ZCanDeviceType, ZDeviceInfo},
     lin::{ZLinChlCfg, ZLinFrame, ZLinPublish, ZLinPublishEx, ZLinSubscribe},
@@ -141,6 +141,8 @@ pub trait ZDevice {
 pub trait ZCan {
     fn init_can_chl(&mut self, channel: u8, cfg: &ChannelConfig) -> CanResult<()>;
     fn reset_can_chl(&mut self, channel: u8) -> CanResult<()>;
+    /// Reset and start the channel again with the same configuration, e.g. recover from bus-off.
+    fn restart_can_chl(&self, channel: u8) -> CanResult<()>;
     // fn resistance_state(&self, dev_idx: u32, channel: u8) -> CanResult<()>;
     fn read_can_chl_status(&self, channel: u8) -> CanResult<ZCanChlStatus>;
     fn read_can_chl_error(&self, channel: u8) -> CanResult<ZCanChlError>;
@@ -164,6 +166,29 @@ pub trait ZCan {
     fn transmit_canfd(&self, channel: u8, frames: Vec<ZCanFrame>) -> CanResult<u32> {
         Err(CanError::NotSupportedError)
     }
+    /// Add(or replace) the frame transmitted by device periodically,
+    /// it takes effect after [`ZCan::apply_auto_send`].
+    ///
+    /// On linux only USBCANFD and USBCANFD-800U support it, the USBCAN-E-U family and the others
+    /// return [`CanError::NotSupportedError`], see [`ZCanAutoSend`] for the options of each device.
+    fn add_auto_send(&self, channel: u8, cfg: &ZCanAutoSend) -> CanResult<()> {
+        Err(CanError::NotSupportedError)
+    }
+    /// Disable the frame at `index`, it takes effect after [`ZCan::apply_auto_send`].
+    fn remove_auto_send(&self, channel: u8, index: u16) -> CanResult<()> {
+        Err(CanError::NotSupportedError)
+    }
+    /// Start transmitting the added frames.
+    fn apply_auto_send(&self, channel: u8) -> CanResult<()> {
+        Err(CanError::NotSupportedError)
+    }
+    /// Stop all frames transmitted by device.
+    ///
+    /// The frames are removed by USBCANFD-800U, but USBCANFD keeps them and restarts them
+    /// by [`ZCan::apply_auto_send`], remove them by [`ZCan::remove_auto_send`] if needed.
+    fn clear_auto_send(&self, channel: u8) -> CanResult<()> {
+        Err(CanError::NotSupportedError)
+    }
 }
 
 #[allow(unused_variables)]
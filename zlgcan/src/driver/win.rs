@@ -2,7 +2,7 @@ use crate::{
     driver::{Handler, ZCan, ZCloud, ZDevice, ZLin},
     native::{
         api::{WinApi, ZCanApi, ZChannelContext, ZCloudApi, ZDeviceApi, ZDeviceContext, ZLinApi},
-        can::{ZCanChlError, ZCanChlStatus, ZCanFrame, ZCanFrameType},
+        can::{ZCanAutoSend, ZCanChlError, ZCanChlStatus, ZCanFrame, ZCanFrameType},
         cloud::{ZCloudGpsFrame, ZCloudServerInfo, ZCloudUserData},
         device::{DeriveInfo, ZCanDeviceType, ZDeviceInfo},
         lin::{ZLinChlCfg, ZLinFrame, ZLinPublish, ZLinPublishEx, ZLinSubscribe},
@@ -155,6 +155,13 @@ impl ZCan for ZDriver {
         }
     }
 
+    fn restart_can_chl(&self, channel: u8) -> CanResult<()> {
+        self.can_handler(channel, |context| {
+            self.api.reset_can_chl(context)?;
+            self.api.start_can_chl(context)
+        })
+    }
+
     fn read_can_chl_status(&self, channel: u8) -> CanResult<ZCanChlStatus> {
         self.can_handler(channel, |context| self.api.read_can_chl_status(context))
     }
@@ -202,6 +209,22 @@ impl ZCan for ZDriver {
     fn transmit_canfd(&self, channel: u8, frames: Vec<ZCanFrame>) -> CanResult<u32> {
         self.can_handler(channel, |context| self.api.transmit_canfd(context, frames))
     }
+
+    fn add_auto_send(&self, channel: u8, cfg: &ZCanAutoSend) -> CanResult<()> {
+        self.can_handler(channel, |context| self.api.add_auto_send(context, cfg))
+    }
+
+    fn remove_auto_send(&self, channel: u8, index: u16) -> CanResult<()> {
+        self.can_handler(channel, |context| self.api.remove_auto_send(context, index))
+    }
+
+    fn apply_auto_send(&self, channel: u8) -> CanResult<()> {
+        self.can_handler(channel, |context| self.api.apply_auto_send(context))
+    }
+
+    fn clear_auto_send(&self, channel: u8) -> CanResult<()> {
+        self.can_handler(channel, |context| self.api.clear_auto_send(context))
+    }
 }
 
 impl ZLin for ZDriver {
@@ -5,11 +5,15 @@ mod native;
 pub use native::*;
 
 use crate::{
-    can::{ZCanFrame, ZCanFrameType},
+    can::{ZCanAutoSend, ZCanFrame, ZCanFrameType},
     device::{DeriveInfo, ZCanDeviceType},
     driver::{ZCan, ZDevice, ZDriver},
 };
-use rs_can::{CanDevice, CanError, CanFrame, CanKind, CanResult, DeviceBuilder};
+use rs_can::{
+    BusCapabilities, BusCapability, BusDiagnostic, BusState, CanDevice, CanError, CanFrame,
+    CanKind, CanResult, CyclicOffload, DeviceBuilder, ErrorCounters,
+};
+use std::time::Duration;
 
 #[async_trait::async_trait]
 impl CanDevice for ZDriver {
@@ -99,3 +103,50 @@ impl CanDevice for ZDriver {
         self.close()
     }
 }
+
+impl BusDiagnostic for ZDriver {
+    #[inline]
+    fn bus_state(&self, channel: Self::Channel) -> CanResult<BusState> {
+        self.read_can_chl_status(channel)
+            .map(|status| status.bus_state())
+    }
+
+    #[inline]
+    fn error_counters(&self, channel: Self::Channel) -> CanResult<ErrorCounters> {
+        self.read_can_chl_status(channel)
+            .map(|status| status.error_counters())
+    }
+
+    #[inline]
+    fn recover_bus_off(&self, channel: Self::Channel) -> CanResult<()> {
+        self.restart_can_chl(channel)
+    }
+}
+
+impl BusCapability for ZDriver {
+    fn capabilities(&self) -> BusCapabilities {
+        let canfd = self.device_type().canfd_support();
+        BusCapabilities {
+            can_fd: canfd,
+            bitrate_switch: canfd,
+            error_state_indicator: canfd,
+            listen_only: true,
+            loopback: false,
+            hardware_timestamp: true,
+            bus_diagnostics: true,
+        }
+    }
+}
+
+impl CyclicOffload for ZDriver {
+    fn start_cyclic(&self, index: u16, msg: &Self::Frame, period: Duration) -> CanResult<()> {
+        let channel = msg.channel();
+        self.add_auto_send(channel, &ZCanAutoSend::new(index, msg.clone(), period))?;
+        self.apply_auto_send(channel)
+    }
+
+    fn stop_cyclic(&self, channel: Self::Channel, index: u16) -> CanResult<()> {
+        self.remove_auto_send(channel, index)?;
+        self.apply_auto_send(channel)
+    }
+}
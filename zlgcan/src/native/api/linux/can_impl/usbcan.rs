@@ -69,6 +69,21 @@ impl ZCanApi for USBCANApi<'_> {
         }
     }
 
+    fn start_can_chl(&self, context: &ZChannelContext) -> CanResult<()> {
+        let (dev_type, dev_idx, channel) = (
+            context.device.dev_type,
+            context.device.dev_idx,
+            context.channel,
+        );
+        match unsafe { (self.VCI_StartCAN)(dev_type as u32, dev_idx, channel as u32) } {
+            Self::STATUS_OK => Ok(()),
+            code => Err(CanError::OperationError(format!(
+                "`VCI_StartCAN` ret: {}",
+                code
+            ))),
+        }
+    }
+
     fn read_can_chl_status(&self, context: &ZChannelContext) -> CanResult<ZCanChlStatus> {
         let (dev_type, dev_idx, channel) = (
             context.device.dev_type,
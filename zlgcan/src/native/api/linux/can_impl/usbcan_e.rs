@@ -75,6 +75,16 @@ impl ZCanApi for USBCANEApi<'_> {
         }
     }
 
+    fn start_can_chl(&self, context: &ZChannelContext) -> CanResult<()> {
+        match unsafe { (self.ZCAN_StartCAN)(context.channel_handler()?) } {
+            Self::STATUS_OK => Ok(()),
+            code => Err(CanError::OperationError(format!(
+                "`ZCAN_StartCAN` ret: {}",
+                code
+            ))),
+        }
+    }
+
     fn read_can_chl_status(&self, context: &ZChannelContext) -> CanResult<ZCanChlStatus> {
         let mut status = ZCanChlStatus::default();
         match unsafe { (self.ZCAN_ReadChannelStatus)(context.channel_handler()?, &mut status) }
@@ -5,13 +5,16 @@ use crate::{
         api::{USBCANFDApi, ZCanApi, ZChannelContext, ZDeviceApi},
         can::{
             common::CanChlCfgContext, constants::BITRATE_CFG_FILENAME, get_fd_cfg, Reference,
-            ZCanChlError, ZCanChlMode, ZCanChlStatus, ZCanChlType, ZCanFdFrameInner, ZCanFrame,
-            ZCanFrameInner, ZCanFrameType, ZCanFrameUnion,
+            ZCanAutoSend, ZCanChlError, ZCanChlMode, ZCanChlStatus, ZCanChlType, ZCanFdFrameInner,
+            ZCanFrame, ZCanFrameInner, ZCanFrameType, ZCanFrameUnion, ZCanTtx,
         },
     },
 };
-use rs_can::{CanError, CanResult, ChannelConfig};
-use std::ffi::{c_void, CString};
+use rs_can::{CanError, CanFrame, CanId, CanResult, ChannelConfig, StandardId};
+use std::{
+    ffi::{c_void, CString},
+    time::Duration,
+};
 
 impl ZCanApi for USBCANFDApi<'_> {
     fn init_can_chl(
@@ -49,8 +52,7 @@ impl ZCanApi for USBCANFDApi<'_> {
                     .unwrap_or(ZCanChlType::CANFD_ISO),
                 cfg.get_other::<ZCanChlMode>(constants::CHANNEL_MODE)?
                     .unwrap_or(ZCanChlMode::Normal),
-                cfg.nominal_bitrate,
-                cfg.data_bitrate,
+                cfg,
                 bc_ctx,
             )?;
             match (self.VCI_InitCAN)(dev_type as u32, dev_idx, channel as u32, &cfg) {
@@ -89,6 +91,21 @@ impl ZCanApi for USBCANFDApi<'_> {
         }
     }
 
+    fn start_can_chl(&self, context: &ZChannelContext) -> CanResult<()> {
+        let (dev_type, dev_idx, channel) = (
+            context.device.dev_type,
+            context.device.dev_idx,
+            context.channel,
+        );
+        match unsafe { (self.VCI_StartCAN)(dev_type as u32, dev_idx, channel as u32) } {
+            Self::STATUS_OK => Ok(()),
+            code => Err(CanError::OperationError(format!(
+                "`VCI_StartCAN` ret: {}",
+                code
+            ))),
+        }
+    }
+
     fn read_can_chl_status(&self, context: &ZChannelContext) -> CanResult<ZCanChlStatus> {
         let (dev_type, dev_idx, channel) = (
             context.device.dev_type,
@@ -327,4 +344,68 @@ impl ZCanApi for USBCANFDApi<'_> {
         }
         Ok(ret)
     }
+
+    fn add_auto_send(&self, context: &ZChannelContext, cfg: &ZCanAutoSend) -> CanResult<()> {
+        if cfg.delay.is_some() {
+            return Err(CanError::NotSupportedError);
+        }
+        let repeat = match cfg.count {
+            Some(0) => return Err(CanError::other_error("auto-send count: 0 is invalid")),
+            Some(count) => count,
+            None => 0,
+        };
+        let interval = cfg.interval(Duration::from_micros(100))?;
+        self.set_auto_send(
+            context,
+            cfg.index,
+            interval,
+            repeat,
+            true,
+            cfg.frame.clone(),
+        )
+    }
+
+    fn remove_auto_send(&self, context: &ZChannelContext, index: u16) -> CanResult<()> {
+        let frame = ZCanFrame::new_can(CanId::Standard(StandardId::new(0)?), &[])?;
+        self.set_auto_send(context, index, 0, 0, false, frame)
+    }
+
+    fn apply_auto_send(&self, context: &ZChannelContext) -> CanResult<()> {
+        self.set_auto_send_status(context, true)
+    }
+
+    fn clear_auto_send(&self, context: &ZChannelContext) -> CanResult<()> {
+        self.set_auto_send_status(context, false)
+    }
+}
+
+impl USBCANFDApi<'_> {
+    fn set_auto_send(
+        &self,
+        context: &ZChannelContext,
+        index: u16,
+        interval: u32,
+        repeat: u16,
+        enable: bool,
+        frame: ZCanFrame,
+    ) -> CanResult<()> {
+        let index = u8::try_from(index).map_err(|_| {
+            CanError::other_error(format!("auto-send index: {} is out of range", index))
+        })?;
+        let ttx = ZCanTtx::new(index, interval, repeat, enable, frame);
+        self.set_reference(
+            context,
+            &CmdPath::new_reference(Reference::SkdSend as u32),
+            &ttx as *const _ as *const c_void,
+        )
+    }
+
+    fn set_auto_send_status(&self, context: &ZChannelContext, enable: bool) -> CanResult<()> {
+        let status = enable as u32;
+        self.set_reference(
+            context,
+            &CmdPath::new_reference(Reference::SkdSendStatus as u32),
+            &status as *const _ as *const c_void,
+        )
+    }
 }
@@ -3,13 +3,17 @@ use crate::{
     native::{
         api::{USBCANFD800UApi, ZCanApi, ZChannelContext},
         can::{
-            common::CanChlCfgContext, constants::BITRATE_CFG_FILENAME, ZCanChlCfg, ZCanChlError,
-            ZCanChlStatus, ZCanChlType, ZCanFdFrameInner, ZCanFrame, ZCanFrameInner, ZCanFrameType,
-            ZCanFrameUnion,
+            common::CanChlCfgContext, constants::BITRATE_CFG_FILENAME, ZCanAutoSend,
+            ZCanAutoTransObj, ZCanChlCfg, ZCanChlError, ZCanChlStatus, ZCanChlType,
+            ZCanFdFrameInner, ZCanFrame, ZCanFrameInner, ZCanFrameType, ZCanFrameUnion,
         },
     },
 };
-use rs_can::{CanError, CanResult, ChannelConfig};
+use rs_can::{
+    CanError, CanFrame, CanId, CanKind, CanResult, ChannelConfig, StandardId, MAX_FD_FRAME_SIZE,
+    MAX_FRAME_SIZE,
+};
+use std::{ffi::c_void, time::Duration};
 
 impl ZCanApi for USBCANFD800UApi<'_> {
     fn init_can_chl(
@@ -67,6 +71,16 @@ impl ZCanApi for USBCANFD800UApi<'_> {
         }
     }
 
+    fn start_can_chl(&self, context: &ZChannelContext) -> CanResult<()> {
+        match unsafe { (self.ZCAN_StartCAN)(context.channel_handler()?) } {
+            Self::STATUS_OK => Ok(()),
+            code => Err(CanError::OperationError(format!(
+                "`ZCAN_StartCAN` ret: {}",
+                code
+            ))),
+        }
+    }
+
     fn read_can_chl_status(&self, context: &ZChannelContext) -> CanResult<ZCanChlStatus> {
         let mut status: ZCanChlStatus = Default::default();
         match unsafe { (self.ZCAN_ReadChannelStatus)(context.channel_handler()?, &mut status) } {
@@ -243,4 +257,92 @@ impl ZCanApi for USBCANFD800UApi<'_> {
         }
         Ok(ret)
     }
+
+    fn add_auto_send(&self, context: &ZChannelContext, cfg: &ZCanAutoSend) -> CanResult<()> {
+        if cfg.count.is_some() || cfg.delay.is_some() {
+            return Err(CanError::NotSupportedError);
+        }
+        Self::check_auto_send_index(cfg.index)?;
+        let interval = cfg.interval(Duration::from_millis(1))?;
+        self.set_auto_send(context, cfg.index, interval, true, cfg.frame.clone())
+    }
+
+    fn remove_auto_send(&self, context: &ZChannelContext, index: u16) -> CanResult<()> {
+        Self::check_auto_send_index(index)?;
+        let frame = ZCanFrame::new_can(CanId::Standard(StandardId::new(0)?), &[])?;
+        self.set_auto_send(context, index, 0, false, frame)
+    }
+
+    fn apply_auto_send(&self, context: &ZChannelContext) -> CanResult<()> {
+        self.self_set_reference(
+            context.device.dev_type,
+            context.device.dev_idx,
+            context.channel,
+            Self::REF_APPLY_TIMER_SEND,
+            std::ptr::null(),
+        )
+    }
+
+    fn clear_auto_send(&self, context: &ZChannelContext) -> CanResult<()> {
+        self.self_set_reference(
+            context.device.dev_type,
+            context.device.dev_idx,
+            context.channel,
+            Self::REF_APPLY_TIMER_SEND_FD,
+            std::ptr::null(),
+        )
+    }
+}
+
+impl USBCANFD800UApi<'_> {
+    #[inline]
+    fn check_auto_send_index(index: u16) -> CanResult<()> {
+        if index >= Self::DEV_AUTO_SEND_INDEX_MAX {
+            return Err(CanError::other_error(format!(
+                "auto-send index: {} is out of range",
+                index
+            )));
+        }
+        Ok(())
+    }
+
+    fn set_auto_send(
+        &self,
+        context: &ZChannelContext,
+        index: u16,
+        interval: u32,
+        enable: bool,
+        frame: ZCanFrame,
+    ) -> CanResult<()> {
+        let (dev_type, dev_idx, channel) = (
+            context.device.dev_type,
+            context.device.dev_idx,
+            context.channel,
+        );
+        match frame.kind {
+            CanKind::Classical => {
+                let obj =
+                    ZCanAutoTransObj::<{ MAX_FRAME_SIZE }>::new(index, interval, enable, frame);
+                self.self_set_reference(
+                    dev_type,
+                    dev_idx,
+                    channel,
+                    Self::REF_ADD_TIMER_SEND_CAN,
+                    &obj as *const _ as *const c_void,
+                )
+            }
+            CanKind::FD => {
+                let obj =
+                    ZCanAutoTransObj::<{ MAX_FD_FRAME_SIZE }>::new(index, interval, enable, frame);
+                self.self_set_reference(
+                    dev_type,
+                    dev_idx,
+                    channel,
+                    Self::REF_ADD_TIMER_SEND_CANFD,
+                    &obj as *const _ as *const c_void,
+                )
+            }
+            CanKind::XL => Err(CanError::NotSupportedError),
+        }
+    }
 }
@@ -75,6 +75,7 @@ impl USBCANFD800UApi<'_> {
     // #define DEVICE_TOTAL_CHNL_COUNT                 (DEVICE_CAN_CHNL_COUNT_MAX + DEVICE_LIN_CHNL_COUNT_MAX)
     // #define FILTER_RULE_COUNT_MAX                   64  //设备允许的过滤条数
     // #define DEV_AUTO_SEND_INDEX_MAX                 32  //定时发送索引最大值
+    pub(crate) const DEV_AUTO_SEND_INDEX_MAX: u16 = 32;
     pub(crate) const REF_CONTROLLER_TYPE: u32 = 1; // pData 指向uint32_t, 0:CAN; 1：ISO CANFD; 2:Non-ISO CANFD, 需要在StartCAN之前设置
     pub(crate) const REF_ADD_FILTER: u32 = 2; // 添加通道过滤条目，pData Pointer to RefFilterItem(12 Bytes)
     pub(crate) const REF_APPLY_FILTER: u32 = 3; // 应用通道过滤
@@ -136,6 +136,8 @@ pub trait ZCanApi {
         cfg: &ChannelConfig,
     ) -> CanResult<()>;
     fn reset_can_chl(&self, context: &ZChannelContext) -> CanResult<()>;
+    /// Start the channel again after [`ZCanApi::reset_can_chl`], the configuration is kept.
+    fn start_can_chl(&self, context: &ZChannelContext) -> CanResult<()>;
     fn read_can_chl_status(&self, context: &ZChannelContext) -> CanResult<ZCanChlStatus>;
     fn read_can_chl_error(&self, context: &ZChannelContext) -> CanResult<ZCanChlError>;
     fn clear_can_buffer(&self, context: &ZChannelContext) -> CanResult<()>;
@@ -158,6 +160,21 @@ pub trait ZCanApi {
     fn transmit_canfd(&self, context: &ZChannelContext, frames: Vec<ZCanFrame>) -> CanResult<u32> {
         Err(CanError::NotSupportedError)
     }
+    /// Add(or replace) the auto-send frame, it takes effect after [`ZCanApi::apply_auto_send`].
+    fn add_auto_send(&self, context: &ZChannelContext, cfg: &ZCanAutoSend) -> CanResult<()> {
+        Err(CanError::NotSupportedError)
+    }
+    /// Disable the auto-send frame at `index`, it takes effect after [`ZCanApi::apply_auto_send`].
+    fn remove_auto_send(&self, context: &ZChannelContext, index: u16) -> CanResult<()> {
+        Err(CanError::NotSupportedError)
+    }
+    fn apply_auto_send(&self, context: &ZChannelContext) -> CanResult<()> {
+        Err(CanError::NotSupportedError)
+    }
+    /// Stop all auto-send frames of channel.
+    fn clear_auto_send(&self, context: &ZChannelContext) -> CanResult<()> {
+        Err(CanError::NotSupportedError)
+    }
 }
 
 #[allow(unused_variables, dead_code)]
@@ -3,19 +3,23 @@ use crate::{
     native::{
         api::{WinApi, ZCanApi, ZChannelContext, ZDeviceApi},
         can::{
-            self, ZCanChlCfg, ZCanChlError, ZCanChlStatus, ZCanChlType, ZCanFrame, ZCanFrameRx,
-            ZCanFrameTx, ZCanFrameType,
+            self, ZCanAutoSend, ZCanAutoTransObj, ZCanAutoTransParam, ZCanChlCfg, ZCanChlError,
+            ZCanChlStatus, ZCanChlType, ZCanFrame, ZCanFrameRx, ZCanFrameTx, ZCanFrameType,
         },
         constants::{
-            BAUD_RATE, CANFD_ABIT_BAUD_RATE, CANFD_DBIT_BAUD_RATE, CLOCK, INTERNAL_RESISTANCE,
-            PROTOCOL,
+            APPLY_AUTO_SEND, AUTO_SEND, AUTO_SEND_CANFD, AUTO_SEND_PARAM, BAUD_RATE,
+            CANFD_ABIT_BAUD_RATE, CANFD_DBIT_BAUD_RATE, CLEAR_AUTO_SEND, CLOCK,
+            INTERNAL_RESISTANCE, PROTOCOL,
         },
         device::{CmdPath, ZCanDeviceType},
     },
 };
-use rs_can::{CanError, CanResult, ChannelConfig};
+use rs_can::{CanError, CanFrame, CanId, CanKind, CanResult, ChannelConfig, StandardId};
 use rs_can::{MAX_FD_FRAME_SIZE, MAX_FRAME_SIZE};
-use std::ffi::{c_void, CString};
+use std::{
+    ffi::{c_void, CString},
+    time::Duration,
+};
 
 impl ZCanApi for WinApi<'_> {
     fn init_can_chl(
@@ -127,6 +131,16 @@ impl ZCanApi for WinApi<'_> {
         }
     }
 
+    fn start_can_chl(&self, context: &ZChannelContext) -> CanResult<()> {
+        match unsafe { (self.ZCAN_StartCAN)(context.channel_handler()?) } {
+            Self::STATUS_OK => Ok(()),
+            code => Err(CanError::OperationError(format!(
+                "`ZCAN_StartCAN` ret = {}",
+                code
+            ))),
+        }
+    }
+
     fn read_can_chl_status(&self, context: &ZChannelContext) -> CanResult<ZCanChlStatus> {
         let mut status: ZCanChlStatus = Default::default();
         match unsafe { (self.ZCAN_ReadChannelStatus)(context.channel_handler()?, &mut status) } {
@@ -283,4 +297,82 @@ impl ZCanApi for WinApi<'_> {
         }
         Ok(ret)
     }
+
+    fn add_auto_send(&self, context: &ZChannelContext, cfg: &ZCanAutoSend) -> CanResult<()> {
+        if cfg.count.is_some() {
+            return Err(CanError::NotSupportedError);
+        }
+        let unit = Duration::from_millis(1);
+        let (interval, delay) = (cfg.interval(unit)?, cfg.delay_in(unit)?);
+        self.set_auto_send(context, cfg.index, interval, true, cfg.frame.clone())?;
+        if let Some(delay) = delay {
+            let path = format!("{}/{}", context.channel, AUTO_SEND_PARAM);
+            let param = ZCanAutoTransParam::delay(cfg.index, delay);
+            self.set_value(
+                context,
+                &CmdPath::new_path(path.as_str()),
+                &param as *const _ as *const c_void,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn remove_auto_send(&self, context: &ZChannelContext, index: u16) -> CanResult<()> {
+        let frame = ZCanFrame::new_can(CanId::Standard(StandardId::new(0)?), &[])?;
+        self.set_auto_send(context, index, 0, false, frame)
+    }
+
+    fn apply_auto_send(&self, context: &ZChannelContext) -> CanResult<()> {
+        self.set_auto_send_cmd(context, APPLY_AUTO_SEND)
+    }
+
+    fn clear_auto_send(&self, context: &ZChannelContext) -> CanResult<()> {
+        self.set_auto_send_cmd(context, CLEAR_AUTO_SEND)
+    }
+}
+
+impl WinApi<'_> {
+    fn set_auto_send(
+        &self,
+        context: &ZChannelContext,
+        index: u16,
+        interval: u32,
+        enable: bool,
+        frame: ZCanFrame,
+    ) -> CanResult<()> {
+        let channel = context.channel;
+        match frame.kind {
+            CanKind::Classical => {
+                let path = format!("{}/{}", channel, AUTO_SEND);
+                let obj =
+                    ZCanAutoTransObj::<{ MAX_FRAME_SIZE }>::new(index, interval, enable, frame);
+                self.set_value(
+                    context,
+                    &CmdPath::new_path(path.as_str()),
+                    &obj as *const _ as *const c_void,
+                )
+            }
+            CanKind::FD => {
+                let path = format!("{}/{}", channel, AUTO_SEND_CANFD);
+                let obj =
+                    ZCanAutoTransObj::<{ MAX_FD_FRAME_SIZE }>::new(index, interval, enable, frame);
+                self.set_value(
+                    context,
+                    &CmdPath::new_path(path.as_str()),
+                    &obj as *const _ as *const c_void,
+                )
+            }
+            CanKind::XL => Err(CanError::NotSupportedError),
+        }
+    }
+
+    fn set_auto_send_cmd(&self, context: &ZChannelContext, cmd: &str) -> CanResult<()> {
+        let path = format!("{}/{}", context.channel, cmd);
+        let value = CString::new("0").map_err(|e| CanError::OtherError(e.to_string()))?;
+        self.set_value(
+            context,
+            &CmdPath::new_path(path.as_str()),
+            value.as_ptr() as *const c_void,
+        )
+    }
 }
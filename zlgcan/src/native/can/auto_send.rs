@@ -0,0 +1,117 @@
+use crate::native::can::ZCanFrame;
+use rs_can::{CanError, CanResult};
+use std::time::Duration;
+
+/// The frame transmitted by device periodically at a slot of channel.
+#[derive(Debug, Clone)]
+pub struct ZCanAutoSend {
+    pub(crate) index: u16,
+    pub(crate) period: Duration,
+    pub(crate) count: Option<u16>,
+    pub(crate) delay: Option<Duration>,
+    pub(crate) frame: ZCanFrame,
+}
+
+impl ZCanAutoSend {
+    /// Transmit the `frame` every `period` forever at slot `index`.
+    pub fn new(index: u16, frame: ZCanFrame, period: Duration) -> Self {
+        Self {
+            index,
+            period,
+            count: None,
+            delay: None,
+            frame,
+        }
+    }
+
+    /// Stop after the `frame` is transmitted `count` times, only supported by USBCANFD on linux.
+    ///
+    /// USBCANFD-800U and the devices on windows reject it with [`CanError::NotSupportedError`].
+    #[inline]
+    pub fn set_count(&mut self, count: u16) -> &mut Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Delay the first transmission, only supported on windows.
+    ///
+    /// USBCANFD and USBCANFD-800U on linux reject it with [`CanError::NotSupportedError`].
+    #[inline]
+    pub fn set_delay(&mut self, delay: Duration) -> &mut Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    #[inline]
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    #[inline]
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    #[inline]
+    pub fn count(&self) -> Option<u16> {
+        self.count
+    }
+
+    #[inline]
+    pub fn delay(&self) -> Option<Duration> {
+        self.delay
+    }
+
+    #[inline]
+    pub fn frame(&self) -> &ZCanFrame {
+        &self.frame
+    }
+
+    /// The period in the `unit` of device.
+    pub(crate) fn interval(&self, unit: Duration) -> CanResult<u32> {
+        as_units(self.period, unit)
+            .filter(|&v| v > 0)
+            .ok_or(CanError::other_error(format!(
+                "auto-send period: {:?} is not a multiple of {:?}",
+                self.period, unit
+            )))
+    }
+
+    /// The delay in the `unit` of device.
+    #[cfg(target_os = "windows")]
+    pub(crate) fn delay_in(&self, unit: Duration) -> CanResult<Option<u32>> {
+        self.delay
+            .map(|delay| {
+                as_units(delay, unit).ok_or(CanError::other_error(format!(
+                    "auto-send delay: {:?} is not a multiple of {:?}",
+                    delay, unit
+                )))
+            })
+            .transpose()
+    }
+}
+
+fn as_units(value: Duration, unit: Duration) -> Option<u32> {
+    let (value, unit) = (value.as_nanos(), unit.as_nanos());
+    match unit > 0 && value % unit == 0 {
+        true => u32::try_from(value / unit).ok(),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rs_can::{CanFrame, CanId, StandardId};
+
+    #[test]
+    fn period_is_converted_to_device_unit() {
+        let frame =
+            ZCanFrame::new_can(CanId::Standard(StandardId::new(0x123).unwrap()), &[1, 2]).unwrap();
+        let cfg = ZCanAutoSend::new(0, frame, Duration::from_micros(1_500));
+
+        assert_eq!(cfg.interval(Duration::from_micros(100)).unwrap(), 15);
+        assert!(cfg.interval(Duration::from_millis(1)).is_err());
+        assert!(cfg.interval(Duration::ZERO).is_err());
+    }
+}
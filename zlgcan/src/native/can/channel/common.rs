@@ -5,7 +5,9 @@ use crate::{
         ZCanFilterType,
     },
 };
-use rs_can::{CanError, CanResult, ChannelConfig};
+use rs_can::{
+    BitTiming, BitTimingConst, BusState, CanError, CanResult, ChannelConfig, ErrorCounters,
+};
 use serde::Deserialize;
 use std::{
     collections::HashMap,
@@ -117,8 +119,11 @@ impl ZCanChlCfgInner {
 
     pub(crate) fn try_from_with(ctx: &BitrateCtx, cfg: &ChannelConfig) -> CanResult<Self> {
         let bitrate = cfg.nominal_bitrate;
-        match ctx.bitrate.get(&bitrate.to_string()) {
-            Some(v) => {
+        let (timing0, timing1) = match (cfg.nominal_timing, ctx.bitrate.get(&bitrate.to_string())) {
+            (Some(timing), _) => {
+                get_timing(&timing.resolve(Some(CAN_CLOCK), bitrate, &CAN_BIT_TIMING)?)
+            }
+            (None, Some(v)) => {
                 let &timing0 = v.get(TIMING0).ok_or(CanError::OtherError(format!(
                     "`{}` is not configured in file!",
                     TIMING0
@@ -127,26 +132,55 @@ impl ZCanChlCfgInner {
                     "`{}` is not configured in file!",
                     TIMING1
                 )))?;
-                let filter = cfg.get_other::<u8>(constants::FILTER_TYPE)?.unwrap_or(0xFF);
-
-                Ok(Self::new(
-                    cfg.get_other::<ZCanChlMode>(constants::CHANNEL_MODE)?
-                        .unwrap_or(ZCanChlMode::Normal),
-                    timing0,
-                    timing1,
-                    ZCanFilterType::try_from(filter)?,
-                    cfg.get_other::<u32>(constants::ACC_CODE)?,
-                    cfg.get_other::<u32>(constants::ACC_MASK)?,
-                ))
+                (timing0, timing1)
             }
-            None => Err(CanError::OtherError(format!(
-                "the bitrate: `{}` is not configured",
-                bitrate
-            ))),
-        }
+            (None, None) => {
+                let timing = BitTiming::calculate(CAN_CLOCK, bitrate, None, &CAN_BIT_TIMING)?;
+                rsutil::debug!(
+                    "ZLGCAN - the bitrate: `{}` is not configured, calculated: {:?}",
+                    bitrate,
+                    timing
+                );
+                get_timing(&timing)
+            }
+        };
+        let filter = cfg.get_other::<u8>(constants::FILTER_TYPE)?.unwrap_or(0xFF);
+
+        Ok(Self::new(
+            cfg.get_other::<ZCanChlMode>(constants::CHANNEL_MODE)?
+                .unwrap_or(ZCanChlMode::Normal),
+            timing0,
+            timing1,
+            ZCanFilterType::try_from(filter)?,
+            cfg.get_other::<u32>(constants::ACC_CODE)?,
+            cfg.get_other::<u32>(constants::ACC_MASK)?,
+        ))
     }
 }
 
+/// The clock of SJA1000 compatible controller(16MHz / 2).
+pub(crate) const CAN_CLOCK: u32 = 8_000_000;
+/// The limits of SJA1000 compatible `timing0`(BTR0) and `timing1`(BTR1).
+pub(crate) const CAN_BIT_TIMING: BitTimingConst = BitTimingConst {
+    tseg1_min: 1,
+    tseg1_max: 16,
+    tseg2_min: 1,
+    tseg2_max: 8,
+    sjw_max: 4,
+    brp_min: 1,
+    brp_max: 64,
+    brp_inc: 1,
+};
+
+/// Get `timing0`(BTR0) and `timing1`(BTR1) from bit-timing.
+#[inline]
+pub(crate) fn get_timing(timing: &BitTiming) -> (u32, u32) {
+    (
+        (timing.sjw - 1) << 6 | (timing.brp - 1),
+        (timing.tseg2 - 1) << 4 | (timing.tseg1 - 1),
+    )
+}
+
 /// Linux USBCAN_4E_8E USBCANFD_800U and windows
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -210,3 +244,41 @@ pub struct ZCanChlStatus {
     /**< TX errors */
     pub Reserved: c_uint,
 }
+
+impl ZCanChlStatus {
+    /// The bus status(BS) bit of `regStatus`, the status register of SJA1000.
+    pub const STATUS_BUS_OFF: u8 = 0x80;
+    /// The error status(ES) bit of `regStatus`, an error counter reaches the warning limit.
+    pub const STATUS_ERROR: u8 = 0x40;
+
+    #[inline]
+    pub fn error_counters(&self) -> ErrorCounters {
+        ErrorCounters {
+            tx: self.regTECounter as u32,
+            rx: self.regRECounter as u32,
+        }
+    }
+
+    /// The bus state decoded from `regStatus` and the error counters.
+    ///
+    /// The error counters reaching 128 are error-passive when the error status bit is set
+    /// or the status register isn't reported by device, and a saturated TX error counter
+    /// is treated as bus-off only if the status register isn't reported.
+    pub fn bus_state(&self) -> BusState {
+        let (status, tx, rx) = (self.regStatus, self.regTECounter, self.regRECounter);
+        if status & Self::STATUS_BUS_OFF != 0 {
+            return BusState::BusOff;
+        }
+
+        let reported = status != 0;
+        match (tx, rx) {
+            (u8::MAX, _) if !reported => BusState::BusOff,
+            (tx, rx)
+                if (tx >= 128 || rx >= 128) && (!reported || status & Self::STATUS_ERROR != 0) =>
+            {
+                BusState::ErrorPassive
+            }
+            _ => BusState::ErrorActive,
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::native::can::{ZCanChlMode, ZCanChlType};
-use rs_can::{CanError, CanResult};
+use rs_can::{CanError, CanResult, ChannelConfig};
 use std::ffi::c_uint;
 
 /// Linux USBCANFD
@@ -47,11 +47,10 @@ pub union ZCanChlCfgUnion {
 pub(crate) fn get_fd_cfg(
     can_type: ZCanChlType,
     mode: ZCanChlMode,
-    bitrate: u32,
-    dbitrate: Option<u32>,
+    cfg: &ChannelConfig,
     ctx: &super::BitrateCtx,
 ) -> CanResult<self::ZCanFdChlCfgInner> {
-    let (aset, dset) = super::get_fd_set(bitrate, dbitrate, ctx)?;
+    let (aset, dset) = super::get_fd_set(cfg, ctx)?;
     let clock = ctx
         .clock
         .ok_or(CanError::other_error("`clock` is not configured in file!"))?;
@@ -21,7 +21,7 @@ use crate::{
         device::ZCanDeviceType,
     },
 };
-use rs_can::{CanError, CanResult, ChannelConfig};
+use rs_can::{BitTiming, BitTimingConfig, BitTimingConst, CanError, CanResult, ChannelConfig};
 use std::{
     collections::HashMap,
     ffi::{c_uchar, c_uint, c_ushort},
@@ -65,6 +65,41 @@ impl TryFrom<&HashMap<String, u32>> for ZCanFdChlCfgSet {
     }
 }
 
+impl From<BitTiming> for ZCanFdChlCfgSet {
+    fn from(timing: BitTiming) -> Self {
+        Self::new(
+            timing.tseg1 - 1,
+            timing.tseg2 - 1,
+            timing.sjw - 1,
+            0,
+            timing.brp - 1,
+        )
+    }
+}
+
+/// The limits of nominal phase of CAN-FD devices.
+pub(crate) const CANFD_NOMINAL_BIT_TIMING: BitTimingConst = BitTimingConst {
+    tseg1_min: 2,
+    tseg1_max: 256,
+    tseg2_min: 2,
+    tseg2_max: 128,
+    sjw_max: 128,
+    brp_min: 1,
+    brp_max: 512,
+    brp_inc: 1,
+};
+/// The limits of data phase of CAN-FD devices.
+pub(crate) const CANFD_DATA_BIT_TIMING: BitTimingConst = BitTimingConst {
+    tseg1_min: 1,
+    tseg1_max: 32,
+    tseg2_min: 1,
+    tseg2_max: 16,
+    sjw_max: 16,
+    brp_min: 1,
+    brp_max: 32,
+    brp_inc: 1,
+};
+
 impl ZCanFdChlCfgSet {
     #[inline(always)]
     pub fn new(tseg1: u32, tseg2: u32, sjw: u32, smp: u32, brp: u32) -> Self {
@@ -103,11 +138,18 @@ impl ZCanChlCfg {
         if dev_type.canfd_support() {
             let (timing0, timing1) = match dev_type {
                 ZCanDeviceType::ZCAN_USBCANFD_800U => {
-                    let (aset, dset) = get_fd_set(cfg.nominal_bitrate, cfg.data_bitrate, ctx)?;
+                    let (aset, dset) = get_fd_set(cfg, ctx)?;
                     let timing0 = aset.get_timing(); // 4458527 = 0x44081f
                     let timing1 = dset.get_timing(); // 4260357 = 0x410205
                     (timing0, timing1)
                 }
+                // the bit-timing is determined by device
+                _ if cfg.nominal_timing.is_some()
+                    || cfg.data_timing.is_some()
+                    || cfg.tdc.is_some() =>
+                {
+                    return Err(CanError::NotSupportedError)
+                }
                 _ => (0, 0),
             };
             Ok(Self {
@@ -140,59 +182,183 @@ impl ZCanChlCfg {
     }
 }
 
+/// The bit-timings of both phases, the TDC is always handled by device,
+/// so `NotSupportedError` is returned if `tdc` is configured.
 pub(crate) fn get_fd_set(
-    bitrate: u32,
-    dbitrate: Option<u32>,
+    cfg: &ChannelConfig,
     ctx: &BitrateCtx,
 ) -> CanResult<(ZCanFdChlCfgSet, ZCanFdChlCfgSet)> {
+    if cfg.tdc.is_some() {
+        return Err(CanError::NotSupportedError);
+    }
+    let (bitrate, dbitrate) = (cfg.nominal_bitrate, cfg.data_bitrate);
     let bitrate_ctx = &ctx.bitrate;
     let dbitrate_ctx = &ctx.data_bitrate;
-    let aset = bitrate_ctx
-        .get(&bitrate.to_string())
-        .ok_or(CanError::OtherError(format!(
-            "bitrate `{}` is not configured in file!",
-            bitrate
-        )))?;
-    let dset = match dbitrate {
-        Some(v) => {
-            // dbitrate is not None
-            match dbitrate_ctx {
-                Some(ctx) => {
-                    // dbitrate context is not None
-                    match ctx.get(&v.to_string()) {
-                        Some(value) => Ok(value),
-                        None => Err(CanError::OtherError(format!(
-                            "data bitrate `{}` is not configured in file!",
-                            v
-                        ))),
-                    }
-                }
-                None => {
-                    // dbitrate context is None
-                    match bitrate_ctx.get(&v.to_string()) {
-                        Some(value) => Ok(value),
-                        None => Err(CanError::OtherError(format!(
-                            "data bitrate `{}` is not configured in file!",
-                            v
-                        ))),
-                    }
-                }
-            }
-        }
-        None => {
-            // dbitrate is None
-            match dbitrate_ctx {
-                Some(ctx) => match ctx.get(&bitrate.to_string()) {
-                    Some(value) => Ok(value),
-                    None => Ok(aset),
-                },
-                None => Ok(aset),
+    let resolve = |bitrate: u32,
+                   timing: Option<BitTimingConfig>,
+                   limits: &BitTimingConst|
+     -> CanResult<ZCanFdChlCfgSet> {
+        let timing = match timing {
+            Some(timing) => timing.resolve(ctx.clock, bitrate, limits)?,
+            None => {
+                let clock = ctx.clock.ok_or(CanError::other_error(format!(
+                    "bitrate `{}` is not configured and `clock` is not configured in file!",
+                    bitrate
+                )))?;
+                let timing = BitTiming::calculate(clock, bitrate, None, limits)?;
+                rsutil::debug!(
+                    "ZLGCAN - the bitrate: `{}` is not configured, calculated: {:?}",
+                    bitrate,
+                    timing
+                );
+                timing
             }
-        }
-    }?;
+        };
+        Ok(ZCanFdChlCfgSet::from(timing))
+    };
+
+    let aset = match (cfg.nominal_timing, bitrate_ctx.get(&bitrate.to_string())) {
+        (None, Some(value)) => ZCanFdChlCfgSet::try_from(value)?,
+        (timing, _) => resolve(bitrate, timing, &CANFD_NOMINAL_BIT_TIMING)?,
+    };
+    let dset = match (cfg.data_timing, dbitrate) {
+        (Some(timing), _) => resolve(
+            dbitrate.unwrap_or(bitrate),
+            Some(timing),
+            &CANFD_DATA_BIT_TIMING,
+        )?,
+        // dbitrate context is used if it is not None
+        (None, Some(v)) => match dbitrate_ctx
+            .as_ref()
+            .unwrap_or(bitrate_ctx)
+            .get(&v.to_string())
+        {
+            Some(value) => ZCanFdChlCfgSet::try_from(value)?,
+            None => resolve(v, None, &CANFD_DATA_BIT_TIMING)?,
+        },
+        (None, None) => match dbitrate_ctx
+            .as_ref()
+            .and_then(|ctx| ctx.get(&bitrate.to_string()))
+        {
+            Some(value) => ZCanFdChlCfgSet::try_from(value)?,
+            None => aset,
+        },
+    };
+
+    Ok((aset, dset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rs_can::{BusState, TdcConfig};
+
+    #[test]
+    fn bus_state_is_decoded_from_status() {
+        let status = |status: u8, tx: u8, rx: u8| common::ZCanChlStatus {
+            regStatus: status,
+            regTECounter: tx,
+            regRECounter: rx,
+            ..Default::default()
+        };
+        // transmit buffer status and transmission complete status
+        let idle = 0x0C;
+
+        assert_eq!(status(0x80 | idle, 0, 0).bus_state(), BusState::BusOff);
+        assert_eq!(
+            status(0x40 | idle, 130, 0).bus_state(),
+            BusState::ErrorPassive
+        );
+        assert_eq!(
+            status(0x40 | idle, 100, 0).bus_state(),
+            BusState::ErrorActive
+        );
+        assert_eq!(status(idle, 255, 0).bus_state(), BusState::ErrorActive);
+        assert_eq!(status(idle, 0, 0).bus_state(), BusState::ErrorActive);
+        // the status register isn't reported
+        assert_eq!(status(0, 255, 0).bus_state(), BusState::BusOff);
+        assert_eq!(status(0, 0, 128).bus_state(), BusState::ErrorPassive);
+        assert_eq!(status(0, 10, 10).bus_state(), BusState::ErrorActive);
+    }
 
-    Ok((
-        ZCanFdChlCfgSet::try_from(aset)?,
-        ZCanFdChlCfgSet::try_from(dset)?,
-    ))
+    #[test]
+    fn bitrate_not_configured_is_calculated() {
+        let ctx = BitrateCtx {
+            bitrate: HashMap::new(),
+            clock: Some(60_000_000),
+            data_bitrate: None,
+        };
+        let mut cfg = ChannelConfig::new(500_000);
+        cfg.set_data_bitrate(2_000_000);
+        let (aset, dset) = get_fd_set(&cfg, &ctx).unwrap();
+        // 60MHz / (1 + 104 + 15) = 500kbps, 60MHz / (1 + 22 + 7) = 2Mbps
+        assert_eq!((aset.brp, aset.tseg1, aset.tseg2), (0, 103, 14));
+        assert_eq!((dset.brp, dset.tseg1, dset.tseg2), (0, 21, 6));
+
+        let ctx = BitrateCtx { clock: None, ..ctx };
+        assert!(get_fd_set(&ChannelConfig::new(500_000), &ctx).is_err());
+
+        let mut cfg = ChannelConfig::new(500_000);
+        cfg.add_other(constants::FILTER_TYPE, Box::new(0u8));
+        let inner = common::ZCanChlCfgInner::try_from_with(&ctx, &cfg).unwrap();
+        // BRP 1, SJW 2, TSEG1 13, TSEG2 2
+        assert_eq!((inner.timing0, inner.timing1), (0x40, 0x1C));
+    }
+
+    #[test]
+    fn bit_timing_config_is_honored() {
+        let mut bitrate = HashMap::new();
+        bitrate.insert(
+            "500000".to_owned(),
+            HashMap::from([
+                (TSEG1.to_owned(), 46),
+                (TSEG2.to_owned(), 11),
+                (SJW.to_owned(), 3),
+                (SMP.to_owned(), 0),
+                (BRP.to_owned(), 1),
+            ]),
+        );
+        let ctx = BitrateCtx {
+            bitrate,
+            clock: Some(60_000_000),
+            data_bitrate: None,
+        };
+        let mut cfg = ChannelConfig::new(500_000);
+        cfg.set_nominal_timing(BitTimingConfig::SamplePoint {
+            sample_point: 800,
+            sjw: Some(4),
+        })
+        .set_data_bitrate(2_000_000)
+        .set_data_timing(BitTimingConfig::Segments(BitTiming {
+            brp: 2,
+            tseg1: 10,
+            tseg2: 4,
+            sjw: 4,
+        }));
+        let (aset, dset) = get_fd_set(&cfg, &ctx).unwrap();
+        // the configured sample point takes precedence over the file
+        assert_eq!((aset.brp, aset.tseg1, aset.tseg2, aset.sjw), (0, 94, 23, 3));
+        assert_eq!((dset.brp, dset.tseg1, dset.tseg2, dset.sjw), (1, 9, 3, 3));
+
+        cfg.set_data_timing(BitTimingConfig::Segments(BitTiming {
+            brp: 1,
+            tseg1: 10,
+            tseg2: 4,
+            sjw: 4,
+        }));
+        assert!(get_fd_set(&cfg, &ctx).is_err());
+
+        cfg.data_timing = None;
+        cfg.set_tdc(TdcConfig::Manual {
+            value: 0,
+            offset: 11,
+        });
+        assert!(get_fd_set(&cfg, &ctx).is_err());
+
+        cfg.set_tdc(TdcConfig::Auto);
+        assert!(matches!(
+            get_fd_set(&cfg, &ctx),
+            Err(CanError::NotSupportedError)
+        ));
+    }
 }
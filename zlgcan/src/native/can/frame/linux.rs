@@ -217,3 +217,29 @@ pub(crate) union ZCanFdFrameInner {
 
 /// only used usbcanfd on linux
 pub(crate) type ZCanChlErrInfo = ZCanMsg20<CANERR_FRAME_LENGTH>;
+
+/// `ZCAN_TTX`, only used usbcanfd on linux
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct ZCanTtx {
+    pub(crate) msg: ZCanMsg20<MAX_FD_FRAME_SIZE>,
+    /// the period in 0.1ms
+    pub(crate) interval: c_uint,
+    /// 0-forever
+    pub(crate) repeat: c_ushort,
+    pub(crate) index: c_uchar,
+    /// bit0: enable
+    pub(crate) flags: c_uchar,
+}
+
+impl ZCanTtx {
+    pub fn new(index: u8, interval: u32, repeat: u16, enable: bool, msg: ZCanFrame) -> Self {
+        Self {
+            msg: msg.into(),
+            interval,
+            repeat,
+            index,
+            flags: enable as u8,
+        }
+    }
+}
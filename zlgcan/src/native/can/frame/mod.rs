@@ -1,6 +1,9 @@
 mod common;
 pub use common::{ZCanFrameType, ZCanTxMode};
 
+use crate::native::can::ZCanFrame;
+use std::ffi::{c_uint, c_ushort};
+
 #[cfg(target_os = "linux")]
 mod linux;
 #[cfg(target_os = "linux")]
@@ -25,6 +28,31 @@ pub union ZCanChlError {
     pub(crate) v2: ZCanChlErrInfo,
 }
 
+/// `ZCAN_AUTO_TRANSMIT_OBJ` and `ZCANFD_AUTO_TRANSMIT_OBJ`
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct ZCanAutoTransObj<const S: usize> {
+    pub(crate) enable: c_ushort,
+    pub(crate) index: c_ushort,
+    /// the period in ms
+    pub(crate) interval: c_uint,
+    pub(crate) frame: common::ZCanMsg20<S>,
+    pub(crate) tx_mode: c_uint, // ZCanTxMode
+}
+
+impl<const S: usize> ZCanAutoTransObj<S> {
+    pub fn new(index: u16, interval: u32, enable: bool, msg: ZCanFrame) -> Self {
+        let tx_mode = msg.tx_mode() as u32;
+        Self {
+            enable: enable as u16,
+            index,
+            interval,
+            frame: msg.into(),
+            tx_mode,
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct USBCanEUAutoTransFrame {
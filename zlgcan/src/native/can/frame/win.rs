@@ -1,6 +1,6 @@
 use crate::native::can::{frame::common::ZCanMsg20, ZCanFrame};
 use rs_can::{Timestamp, TimestampSource, MAX_FD_FRAME_SIZE, MAX_FRAME_SIZE};
-use std::ffi::{c_uint, c_ulonglong};
+use std::ffi::{c_uint, c_ulonglong, c_ushort};
 
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone)]
@@ -49,3 +49,24 @@ pub(crate) union ZCanFdFrameInner {
     pub(crate) tx: ZCanFrameTx<MAX_FD_FRAME_SIZE>,
     pub(crate) rx: ZCanFrameRx<MAX_FD_FRAME_SIZE>,
 }
+
+/// `ZCAN_AUTO_TRANSMIT_OBJ_PARAM`
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct ZCanAutoTransParam {
+    pub(crate) index: c_ushort,
+    /// 1-delay of the first transmission
+    pub(crate) r#type: c_ushort,
+    /// the delay in ms
+    pub(crate) value: c_uint,
+}
+
+impl ZCanAutoTransParam {
+    pub fn delay(index: u16, delay: u32) -> Self {
+        Self {
+            index,
+            r#type: 1,
+            value: delay,
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use crate::can::ZCanTxMode;
 use rs_can::{
-    CanDirection, CanError, CanFdFlags, CanFrame, CanId, CanKind, CanResult, FrameFormat,
-    Timestamp, MAX_FD_FRAME_SIZE, MAX_FRAME_SIZE,
+    AnyFrame, CanDirection, CanError, CanFdFlags, CanFrame, CanId, CanKind, CanResult, FrameFormat,
+    GenericFrame, Timestamp, MAX_FD_FRAME_SIZE, MAX_FRAME_SIZE,
 };
 use std::fmt::{Display, Formatter};
 
@@ -95,6 +95,29 @@ impl CanFrame for ZCanFrame {
         })
     }
 
+    #[inline]
+    fn new_error(id: CanId, data: &[u8]) -> CanResult<Self> {
+        let length = data.len();
+        if length > MAX_FRAME_SIZE {
+            return Err(CanError::InvalidDLC(length));
+        }
+        Ok(Self {
+            timestamp: None,
+            arbitration_id: id.as_raw(),
+            is_extended_id: id.is_extended(),
+            is_remote_frame: false,
+            is_error_frame: true,
+            channel: Default::default(),
+            length,
+            data: data.to_vec(),
+            kind: CanKind::Classical,
+            direction: CanDirection::Receive,
+            bitrate_switch: false,
+            error_state_indicator: false,
+            tx_mode: Default::default(),
+        })
+    }
+
     #[inline]
     fn id(&self) -> CanId {
         CanId::from_bits(self.arbitration_id, Some(self.is_extended_id)).unwrap()
@@ -218,6 +241,34 @@ impl Display for ZCanFrame {
     }
 }
 
+impl From<ZCanFrame> for GenericFrame<u8> {
+    fn from(frame: ZCanFrame) -> Self {
+        GenericFrame::from_frame(&frame)
+    }
+}
+
+impl TryFrom<GenericFrame<u8>> for ZCanFrame {
+    type Error = CanError;
+
+    fn try_from(frame: GenericFrame<u8>) -> Result<Self, Self::Error> {
+        frame.into_frame()
+    }
+}
+
+impl From<ZCanFrame> for AnyFrame {
+    fn from(frame: ZCanFrame) -> Self {
+        AnyFrame::from_frame(&frame)
+    }
+}
+
+impl TryFrom<AnyFrame> for ZCanFrame {
+    type Error = CanError;
+
+    fn try_from(frame: AnyFrame) -> Result<Self, Self::Error> {
+        frame.to_frame()
+    }
+}
+
 #[cfg(feature = "pyo3")]
 impl ZCanFrame {
     const TIMESTAMP: &str = "timestamp";
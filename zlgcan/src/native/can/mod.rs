@@ -1,8 +1,10 @@
+mod auto_send;
 mod channel;
 pub(crate) mod constants;
 mod frame;
 mod message;
 
+pub use auto_send::*;
 pub use channel::*;
 pub use frame::*;
 pub use message::*;
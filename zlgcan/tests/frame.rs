@@ -0,0 +1,37 @@
+use rs_can::{
+    candump::{CandumpReader, CandumpWriter},
+    CanFrame, CanKind,
+};
+use zlgcan_rs::can::ZCanFrame;
+
+#[test]
+fn candump_round_trip() -> anyhow::Result<()> {
+    let content = "\
+(1697371234.123456) can0 123#DEADBEEF
+(1697371234.200000) can1 1ABCDEF0#R
+(1697371234.300000) can0 456#R3
+(1697371234.400000) can1 18DAF110##7000102030405060708090A0B
+(1697371234.500000) can0 20000004#0004000000000000
+";
+    let mut reader = CandumpReader::<_, ZCanFrame>::new(content.as_bytes());
+    reader.set_channel("can0", 0).set_channel("can1", 1);
+    let frames = reader.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(
+        frames.iter().map(|f| f.channel()).collect::<Vec<_>>(),
+        vec![0, 1, 0, 1, 0]
+    );
+    assert!(frames[1].is_remote() && frames[1].id().is_extended());
+    assert_eq!(frames[2].len(), 3);
+    assert_eq!(frames[3].kind(), CanKind::FD);
+    assert!(frames[3].is_bitrate_switch() && frames[3].is_esi());
+    assert!(frames[4].is_error_frame());
+
+    let mut writer = CandumpWriter::new(Vec::new());
+    writer.set_channel(0, "can0").set_channel(1, "can1");
+    for frame in &frames {
+        writer.write_frame(frame)?;
+    }
+    assert_eq!(String::from_utf8(writer.finish()?)?, content);
+
+    Ok(())
+}